@@ -0,0 +1,373 @@
+use crate::filter::FieldFilter;
+use crate::frontmatter::Note;
+use crate::output;
+use anyhow::{anyhow, Context, Result};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use yaml_rust2::{Yaml, YamlLoader};
+
+/// A query of a batch file, writing the notes it matches to its own file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Query {
+    pub name: String,
+    pub filters: Vec<FieldFilter>,
+    pub sort: Option<String>,
+    pub desc: bool,
+    /// `paths`, `json`, `ndjson` or `markdown`
+    pub format: String,
+    pub output: PathBuf,
+}
+
+impl Query {
+    /// Write the notes in the format of the query, creating missing directories
+    pub fn write(&self, notes: &[&Note]) -> Result<()> {
+        write_file(&self.output, notes, &self.format)
+    }
+}
+
+/// Replaced by the group in `--split-output` paths
+const GROUP_PLACEHOLDER: &str = "{group}";
+
+/// Where `--split-output` writes the notes of each group: a path with `{group}` in it,
+/// like `out/{group}.md`, and the format of the files
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplitOutput {
+    pub pattern: String,
+    /// `paths`, `json`, `ndjson` or `markdown`, by default the one of the file extension
+    pub format: String,
+}
+
+impl SplitOutput {
+    pub fn new(pattern: &str, format: Option<&str>) -> Result<Self> {
+        if !pattern.contains(GROUP_PLACEHOLDER) {
+            return Err(anyhow!(
+                "The output path '{}' needs a {{group}} placeholder, e.g. out/{{group}}.md",
+                pattern
+            ));
+        }
+        let format = format.map_or_else(
+            || format_for(Path::new(pattern)).to_string(),
+            str::to_lowercase,
+        );
+        check_format(&format)?;
+        Ok(Self {
+            pattern: pattern.to_string(),
+            format,
+        })
+    }
+
+    /// The file of a group, with characters that can't be in a file name replaced by `-`
+    pub fn path(&self, group: &str) -> PathBuf {
+        let mut name: String = group
+            .trim()
+            .chars()
+            .map(|c| {
+                if matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|')
+                    || c.is_control()
+                {
+                    '-'
+                } else {
+                    c
+                }
+            })
+            .collect();
+        // `.` and `..` would point to other directories
+        if name.trim_matches('.').is_empty() {
+            name = "-".repeat(name.len().max(1));
+        }
+        PathBuf::from(self.pattern.replace(GROUP_PLACEHOLDER, &name))
+    }
+
+    /// Write the notes of each group to its own file, returning the group names with their
+    /// files and the number of notes in them
+    pub fn write(
+        &self,
+        groups: &BTreeMap<Vec<String>, Vec<&Note>>,
+    ) -> Result<Vec<(String, PathBuf, usize)>> {
+        let mut written: Vec<(String, PathBuf, usize)> = Vec::new();
+        for (key, notes) in groups {
+            let group = key.join("-");
+            let path = self.path(&group);
+            if let Some((other, _, _)) = written.iter().find(|(_, other, _)| *other == path) {
+                return Err(anyhow!(
+                    "Groups '{}' and '{}' would both be written to {}",
+                    other,
+                    group,
+                    path.display()
+                ));
+            }
+            write_file(&path, notes, &self.format)?;
+            written.push((group, path, notes.len()));
+        }
+        Ok(written)
+    }
+}
+
+/// Write notes to a file in a format, creating missing directories
+fn write_file(path: &Path, notes: &[&Note], format: &str) -> Result<()> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create directory: {}", dir.display()))?;
+    }
+    let file =
+        File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut out = BufWriter::new(file);
+    output::write_notes(&mut out, notes, format)?;
+    out.flush()
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Several named queries run against a single scan, loaded from a YAML file:
+///
+/// ```yaml
+/// open-tasks:
+///   filter: [type=task, status=open]
+///   missing: [due]
+///   sort: created
+///   output: reports/open-tasks.json
+/// stale:
+///   filter: ["file.mtime<2024-01-01"]
+///   format: paths
+///   output: reports/stale.txt
+/// ```
+///
+/// Output paths are relative to the batch file.
+#[derive(Debug, Default)]
+pub struct Batch {
+    pub queries: Vec<Query>,
+}
+
+impl Batch {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read batch file: {}", path.display()))?;
+        let base = path.parent().unwrap_or_else(|| Path::new(""));
+        Self::from_yaml(&content, base)
+            .with_context(|| format!("Invalid batch file: {}", path.display()))
+    }
+
+    /// Parse the queries, resolving their output paths against `base`
+    pub fn from_yaml(content: &str, base: &Path) -> Result<Self> {
+        let docs =
+            YamlLoader::load_from_str(content).map_err(|e| anyhow!("YAML parsing error: {}", e))?;
+        let Some(Yaml::Hash(root)) = docs.into_iter().next() else {
+            return Err(anyhow!("Expected a mapping of query names to queries"));
+        };
+
+        let queries = root
+            .iter()
+            .map(|(name, query)| {
+                let name = name
+                    .as_str()
+                    .ok_or_else(|| anyhow!("Query names must be strings"))?;
+                parse_query(name, query, base).with_context(|| format!("Query '{}'", name))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if queries.is_empty() {
+            return Err(anyhow!("No queries found"));
+        }
+        Ok(Self { queries })
+    }
+}
+
+fn parse_query(name: &str, query: &Yaml, base: &Path) -> Result<Query> {
+    let Yaml::Hash(query) = query else {
+        return Err(anyhow!("Expected a mapping with filter, output, ..."));
+    };
+
+    let mut filters = Vec::new();
+    let mut sort = None;
+    let mut desc = false;
+    let mut format = None;
+    let mut output = None;
+    for (key, value) in query {
+        match key.as_str() {
+            Some("filter") => {
+                for filter in strings(value, "filter")? {
+                    filters.push(FieldFilter::parse(&filter)?);
+                }
+            }
+            Some("has") => {
+                filters.extend(
+                    strings(value, "has")?
+                        .iter()
+                        .map(|f| FieldFilter::exists(f)),
+                );
+            }
+            Some("missing") => filters.extend(
+                strings(value, "missing")?
+                    .iter()
+                    .map(|f| FieldFilter::missing(f)),
+            ),
+            Some("sort") => sort = Some(string(value, "sort")?),
+            Some("desc") => {
+                desc = value
+                    .as_bool()
+                    .ok_or_else(|| anyhow!("desc must be true or false"))?;
+            }
+            Some("format") => format = Some(string(value, "format")?),
+            Some("output") => output = Some(base.join(string(value, "output")?)),
+            Some(other) => return Err(anyhow!("Unknown query setting: {}", other)),
+            None => return Err(anyhow!("Query settings must be named")),
+        }
+    }
+
+    let output = output.ok_or_else(|| anyhow!("Missing output file"))?;
+    let format = format.unwrap_or_else(|| format_for(&output).to_string());
+    check_format(&format)?;
+    Ok(Query {
+        name: name.to_string(),
+        filters,
+        sort,
+        desc,
+        format,
+        output,
+    })
+}
+
+fn check_format(format: &str) -> Result<()> {
+    if !matches!(format, "paths" | "json" | "ndjson" | "markdown") {
+        return Err(anyhow!(
+            "Unknown format: '{}'. Use paths, json, ndjson or markdown",
+            format
+        ));
+    }
+    Ok(())
+}
+
+/// The format matching the extension of an output file, `paths` for anything but JSON and
+/// markdown
+fn format_for(output: &Path) -> &'static str {
+    match output.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => "json",
+        Some("ndjson" | "jsonl") => "ndjson",
+        Some("md") => "markdown",
+        _ => "paths",
+    }
+}
+
+fn string(value: &Yaml, key: &str) -> Result<String> {
+    value
+        .as_str()
+        .map(String::from)
+        .ok_or_else(|| anyhow!("{} must be a string", key))
+}
+
+/// A list of strings, or a single string as a list of one
+fn strings(value: &Yaml, key: &str) -> Result<Vec<String>> {
+    match value {
+        Yaml::Array(items) => items.iter().map(|item| string(item, key)).collect(),
+        value => Ok(vec![string(value, key)?]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_batch() {
+        let batch = Batch::from_yaml(
+            r#"
+open-tasks:
+  filter: [type=task, status=open]
+  missing: due
+  sort: created
+  desc: true
+  output: reports/open-tasks.json
+stale:
+  filter: "file.mtime<2024-01-01"
+  output: stale.txt
+"#,
+            Path::new("/vault"),
+        )
+        .unwrap();
+
+        assert_eq!(batch.queries.len(), 2);
+        let open_tasks = &batch.queries[0];
+        assert_eq!(open_tasks.name, "open-tasks");
+        assert_eq!(open_tasks.filters.len(), 3);
+        assert_eq!(open_tasks.filters[2], FieldFilter::missing("due"));
+        assert_eq!(open_tasks.sort.as_deref(), Some("created"));
+        assert!(open_tasks.desc);
+        assert_eq!(open_tasks.format, "json");
+        assert_eq!(
+            open_tasks.output,
+            PathBuf::from("/vault/reports/open-tasks.json")
+        );
+        assert_eq!(batch.queries[1].format, "paths");
+
+        assert!(Batch::from_yaml("q:\n  filter: [a=b]\n", Path::new("")).is_err());
+        assert!(Batch::from_yaml("q:\n  output: a.txt\n  format: table\n", Path::new("")).is_err());
+        assert!(Batch::from_yaml("q:\n  output: a.txt\n  limit: 3\n", Path::new("")).is_err());
+        assert!(Batch::from_yaml("{}", Path::new("")).is_err());
+    }
+
+    #[test]
+    fn test_write_query() {
+        let temp_dir = TempDir::new().unwrap();
+        let batch = Batch::from_yaml("all:\n  output: out/all.txt\n", temp_dir.path()).unwrap();
+        let notes = [
+            Note::new("a.md".to_string(), HashMap::new()),
+            Note::new("b.md".to_string(), HashMap::new()),
+        ];
+        let notes: Vec<&Note> = notes.iter().collect();
+
+        batch.queries[0].write(&notes).unwrap();
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("out/all.txt")).unwrap(),
+            "a.md\nb.md\n"
+        );
+    }
+
+    #[test]
+    fn test_split_output() {
+        let temp_dir = TempDir::new().unwrap();
+        let pattern = temp_dir.path().join("out/{group}.md");
+        let split = SplitOutput::new(pattern.to_str().unwrap(), None).unwrap();
+        assert_eq!(split.format, "markdown");
+        assert_eq!(
+            split.path("in review/blocked"),
+            temp_dir.path().join("out/in review-blocked.md")
+        );
+        assert_eq!(split.path(".."), temp_dir.path().join("out/--.md"));
+
+        let mut titled = HashMap::new();
+        titled.insert("title".to_string(), Yaml::String("Launch plan".to_string()));
+        let notes = [
+            Note::new("Projects/Site.md".to_string(), HashMap::new()),
+            Note::new("Projects/Launch.md".to_string(), titled),
+        ];
+        let groups = BTreeMap::from([
+            (vec!["done".to_string()], vec![&notes[0]]),
+            (vec!["open".to_string()], vec![&notes[1], &notes[0]]),
+        ]);
+        let written = split.write(&groups).unwrap();
+        assert_eq!(written[1], ("open".to_string(), split.path("open"), 2));
+        assert_eq!(
+            fs::read_to_string(split.path("open")).unwrap(),
+            "- [[Launch|Launch plan]]\n- [[Site]]\n"
+        );
+
+        // Groups must not overwrite each other's files
+        let clashing = BTreeMap::from([
+            (vec!["a/b".to_string()], vec![&notes[0]]),
+            (vec!["a:b".to_string()], vec![&notes[1]]),
+        ]);
+        assert!(split.write(&clashing).is_err());
+
+        assert!(SplitOutput::new("out.md", None).is_err());
+        assert!(SplitOutput::new("out/{group}.txt", Some("table")).is_err());
+        assert_eq!(
+            SplitOutput::new("out/{group}", Some("JSON"))
+                .unwrap()
+                .format,
+            "json"
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use crate::frontmatter::{extract_inline_tags, Note};
+use crate::links::extract_links;
+use crate::scanner::FileHandler;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use yaml_rust2::Yaml;
+
+/// Indexes Obsidian `.canvas` files as notes.
+///
+/// The text of their cards becomes the body, the notes they embed and the `[[links]]` in
+/// their cards become links, and `canvas.nodes` and `canvas.edges` count their cards and
+/// arrows.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CanvasHandler;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Canvas {
+    nodes: Vec<CanvasNode>,
+    edges: Vec<serde_json::Value>,
+}
+
+/// A card on the canvas: `text`, `file` (a note or attachment), `link` (a URL) or `group`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct CanvasNode {
+    #[serde(rename = "type")]
+    kind: String,
+    text: Option<String>,
+    file: Option<String>,
+    label: Option<String>,
+}
+
+impl FileHandler for CanvasHandler {
+    fn handles(&self, path: &Path) -> bool {
+        path.extension().is_some_and(|ext| ext == "canvas")
+    }
+
+    fn parse(&self, path: &Path, keep_body: bool) -> Result<Note> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read file: {}", path.display()))?;
+        let canvas: Canvas = serde_json::from_str(&content).context("Invalid canvas JSON")?;
+
+        let body = canvas
+            .nodes
+            .iter()
+            .filter_map(|node| match node.kind.as_str() {
+                "text" => node.text.as_deref(),
+                "group" => node.label.as_deref(),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n");
+
+        let mut note = Note::new(path.to_string_lossy().to_string(), HashMap::new());
+        note.inline_tags = extract_inline_tags(&body);
+        note.links = canvas
+            .nodes
+            .iter()
+            .filter(|node| node.kind == "file")
+            .filter_map(|node| node.file.clone())
+            .chain(extract_links(&body))
+            .fold(Vec::new(), |mut links, link| {
+                if !links.contains(&link) {
+                    links.push(link);
+                }
+                links
+            });
+        for (name, count) in [
+            ("canvas.nodes", canvas.nodes.len()),
+            ("canvas.edges", canvas.edges.len()),
+        ] {
+            note.set_virtual_field(
+                name,
+                Yaml::Integer(i64::try_from(count).unwrap_or(i64::MAX)),
+            );
+        }
+        if keep_body {
+            note.body = Some(body);
+        }
+        Ok(note)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_canvas() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("Roadmap.canvas");
+        fs::write(
+            &path,
+            r#"{
+  "nodes": [
+    {"id": "1", "type": "text", "text": "Launch #milestone, see [[Budget]]", "x": 0, "y": 0},
+    {"id": "2", "type": "file", "file": "Projects/Alpha.md"},
+    {"id": "3", "type": "group", "label": "Q3"},
+    {"id": "4", "type": "link", "url": "https://example.com"}
+  ],
+  "edges": [{"id": "e1", "fromNode": "1", "toNode": "2"}]
+}"#,
+        )
+        .unwrap();
+
+        let handler = CanvasHandler;
+        assert!(handler.handles(&path));
+        assert!(!handler.handles(Path::new("note.md")));
+
+        let note = handler.parse(&path, true).unwrap();
+        assert_eq!(note.title.as_deref(), Some("Roadmap"));
+        assert_eq!(note.links, vec!["Projects/Alpha.md", "Budget"]);
+        assert_eq!(note.inline_tags, vec!["milestone"]);
+        assert_eq!(
+            note.body.as_deref(),
+            Some("Launch #milestone, see [[Budget]]\n\nQ3")
+        );
+        assert_eq!(
+            note.get_frontmatter_value("canvas.edges"),
+            Some(&Yaml::Integer(1))
+        );
+
+        fs::write(&path, "not json").unwrap();
+        assert!(handler.parse(&path, false).is_err());
+    }
+}
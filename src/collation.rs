@@ -0,0 +1,141 @@
+use std::cmp::Ordering;
+
+/// Field holding the language of a note, like `lang: de`
+pub const LANG_FIELD: &str = "lang";
+
+/// How text values are ordered when sorting notes
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Collation {
+    /// By code point, so `Äpfel` comes after `Zebra`
+    #[default]
+    CodePoint,
+    /// By the rules of one language, like `de` or `sv`
+    Language(String),
+    /// By the rules of the language in each note's `lang` field
+    PerNote,
+}
+
+impl Collation {
+    /// `auto` for the language of each note, otherwise a language code
+    pub fn parse(s: &str) -> Self {
+        if s.eq_ignore_ascii_case("auto") {
+            Self::PerNote
+        } else {
+            Self::Language(s.to_string())
+        }
+    }
+
+    /// The key to sort `text` by, `None` to compare the text itself. `note_lang` is the
+    /// language of the note the text belongs to.
+    pub fn sort_key(&self, text: &str, note_lang: Option<&str>) -> Option<String> {
+        match self {
+            Self::CodePoint => None,
+            Self::Language(lang) => Some(collation_key(text, lang)),
+            Self::PerNote => Some(collation_key(text, note_lang.unwrap_or_default())),
+        }
+    }
+}
+
+/// Compare two texts by their collation keys, falling back to the texts for ties
+pub fn compare_collated(a: &str, key_a: &str, b: &str, key_b: &str) -> Ordering {
+    key_a.cmp(key_b).then_with(|| a.cmp(b))
+}
+
+/// A key that orders text like a dictionary of the language.
+///
+/// Case and accents are ignored, except for letters the language sorts on their own (`ä` after `z` in Swedish,
+/// `ñ` after `n` in Spanish). Unknown languages only fold case and accents.
+pub fn collation_key(text: &str, lang: &str) -> String {
+    let lang = lang
+        .split(['-', '_'])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+
+    let mut key = String::with_capacity(text.len());
+    for c in text.chars().flat_map(char::to_lowercase) {
+        // Letters after z use the ASCII characters following it
+        let tailored = match (lang.as_str(), c) {
+            ("sv" | "fi", 'å') | ("da" | "nb" | "nn" | "no", 'æ' | 'ä') => Some("{"),
+            ("sv" | "fi", 'ä' | 'æ') | ("da" | "nb" | "nn" | "no", 'ø' | 'ö') => Some("|"),
+            ("sv" | "fi", 'ö' | 'ø') | ("da" | "nb" | "nn" | "no", 'å') => Some("}"),
+            ("es", 'ñ') => Some("n\u{7f}"),
+            _ => None,
+        };
+        match tailored.or_else(|| fold_accent(c)) {
+            Some(letters) => key.push_str(letters),
+            None => key.push(c),
+        }
+    }
+    key
+}
+
+/// The base letters of an accented Latin letter
+fn fold_accent(c: char) -> Option<&'static str> {
+    let letters = match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+        'æ' => "ae",
+        'ç' | 'ć' | 'č' => "c",
+        'ď' | 'đ' => "d",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ė' | 'ę' | 'ě' => "e",
+        'ğ' => "g",
+        'ì' | 'í' | 'î' | 'ï' | 'ī' | 'į' | 'ı' => "i",
+        'ł' | 'ľ' => "l",
+        'ñ' | 'ń' | 'ň' => "n",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ő' => "o",
+        'œ' => "oe",
+        'ř' => "r",
+        'ß' => "ss",
+        'ś' | 'š' | 'ş' => "s",
+        'ť' | 'ţ' => "t",
+        'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' | 'ų' => "u",
+        'ý' | 'ÿ' => "y",
+        'ź' | 'ż' | 'ž' => "z",
+        _ => return None,
+    };
+    Some(letters)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(words: &[&str], lang: &str) -> Vec<String> {
+        let mut words: Vec<&str> = words.to_vec();
+        words.sort_by(|a, b| {
+            compare_collated(a, &collation_key(a, lang), b, &collation_key(b, lang))
+        });
+        words.into_iter().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_collation_key() {
+        let words = ["Zebra", "Äpfel", "apfel", "Öl", "Ober"];
+        assert_eq!(
+            sorted(&words, "de"),
+            vec!["apfel", "Äpfel", "Ober", "Öl", "Zebra"]
+        );
+        assert_eq!(
+            sorted(&words, "sv-SE"),
+            vec!["apfel", "Ober", "Zebra", "Äpfel", "Öl"]
+        );
+        assert_eq!(
+            sorted(&["ñu", "nube", "oso"], "es"),
+            vec!["nube", "ñu", "oso"]
+        );
+        assert_eq!(collation_key("Straße", "de"), "strasse");
+    }
+
+    #[test]
+    fn test_collation_sort_key() {
+        assert_eq!(Collation::default().sort_key("Äpfel", Some("de")), None);
+        assert_eq!(
+            Collation::parse("auto").sort_key("Öl", Some("sv")),
+            Some("}l".to_string())
+        );
+        assert_eq!(
+            Collation::parse("de").sort_key("Öl", Some("sv")),
+            Some("ol".to_string())
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use crate::filter::parse_date;
+use crate::frontmatter::Note;
+use crate::yaml_compat::yaml_as_str;
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use yaml_rust2::Yaml;
+
+/// A column of `--columns`: the path, the title, a field, or a number of days computed
+/// from a date field when the notes are shown
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Column {
+    Path,
+    Title,
+    /// A frontmatter or virtual field, like `status` or `file.mtime`
+    Field(String),
+    /// Days since the date in a field: `age` for `created.effective`, `age:<field>`
+    Age(String),
+    /// Days until the date in a field, negative once it passed: `due_in` for `due`,
+    /// `due_in:<field>`
+    DueIn(String),
+}
+
+/// The columns without `--columns`
+pub const DEFAULT_COLUMNS: [Column; 2] = [Column::Path, Column::Title];
+
+impl Column {
+    pub fn parse(spec: &str) -> Result<Self> {
+        let spec = spec.trim();
+        let column = match spec.split_once(':') {
+            Some(("age", field)) => Self::Age(field.to_string()),
+            Some(("due_in", field)) => Self::DueIn(field.to_string()),
+            _ => match spec {
+                "path" => Self::Path,
+                "title" => Self::Title,
+                "age" => Self::Age("created.effective".to_string()),
+                "due_in" => Self::DueIn("due".to_string()),
+                field => Self::Field(field.to_string()),
+            },
+        };
+        match &column {
+            Self::Field(field) | Self::Age(field) | Self::DueIn(field) if field.is_empty() => {
+                Err(anyhow!("Invalid column '{}', expected a field name", spec))
+            }
+            _ => Ok(column),
+        }
+    }
+
+    /// The column as it is given to `--columns`, its header and its key in JSON
+    pub fn name(&self) -> String {
+        match self {
+            Self::Path => "path".to_string(),
+            Self::Title => "title".to_string(),
+            Self::Field(field) => field.clone(),
+            Self::Age(field) if field == "created.effective" => "age".to_string(),
+            Self::Age(field) => format!("age:{}", field),
+            Self::DueIn(field) if field == "due" => "due_in".to_string(),
+            Self::DueIn(field) => format!("due_in:{}", field),
+        }
+    }
+
+    /// The value of the column for a note, `None` when the note doesn't have the field or
+    /// it holds no date. Days are counted from `today`.
+    pub fn value(&self, note: &Note, today: NaiveDate, case_sensitive: bool) -> Option<Yaml> {
+        let days_since = |field: &str| {
+            let date = parse_date(yaml_as_str(note.field(field, case_sensitive)?)?)?;
+            Some((today - date.date()).num_days())
+        };
+        match self {
+            Self::Path => Some(Yaml::String(note.path.clone())),
+            Self::Title => note.title.clone().map(Yaml::String),
+            Self::Field(field) => note.field(field, case_sensitive).cloned(),
+            Self::Age(field) => days_since(field).map(Yaml::Integer),
+            Self::DueIn(field) => days_since(field).map(|days| Yaml::Integer(-days)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_columns() {
+        let mut fm = HashMap::new();
+        fm.insert("Due".to_string(), Yaml::String("2024-06-10".to_string()));
+        fm.insert(
+            "review".to_string(),
+            Yaml::String("2024-05-01T10:30".to_string()),
+        );
+        let mut note = Note::new("Projects/Site.md".to_string(), fm);
+        note.set_virtual_field("created.effective", Yaml::String("2024-05-27".to_string()));
+        let today = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+
+        let columns: Vec<Column> = ["path", "title", "age", "due_in", "age:review", "Due"]
+            .iter()
+            .map(|spec| Column::parse(spec).unwrap())
+            .collect();
+        assert_eq!(
+            columns.iter().map(Column::name).collect::<Vec<_>>(),
+            vec!["path", "title", "age", "due_in", "age:review", "Due"]
+        );
+        let values: Vec<Option<Yaml>> = columns
+            .iter()
+            .map(|column| column.value(&note, today, false))
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                Some(Yaml::String("Projects/Site.md".to_string())),
+                Some(Yaml::String("Site".to_string())),
+                Some(Yaml::Integer(5)),
+                Some(Yaml::Integer(9)),
+                Some(Yaml::Integer(31)),
+                Some(Yaml::String("2024-06-10".to_string())),
+            ]
+        );
+
+        // Fields are matched like filters match them, dates that aren't dates are empty
+        assert_eq!(
+            Column::DueIn("due".to_string()).value(&note, today, true),
+            None
+        );
+        assert_eq!(
+            Column::Age("Due".to_string()).value(&note, today, true),
+            Some(Yaml::Integer(-9))
+        );
+        assert_eq!(
+            Column::Age("path".to_string()).value(&note, today, true),
+            None
+        );
+        assert!(Column::parse("age:").is_err());
+    }
+}
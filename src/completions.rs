@@ -0,0 +1,316 @@
+use aktenfux::filter::collect_all_fields;
+use aktenfux::Note;
+use anyhow::{Context, Result};
+use clap::Command;
+use clap_complete::{generate, Shell};
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Options whose values are field names, completed from the fields of the vault
+const FIELD_OPTIONS: &[&str] = &["--field", "--has", "--missing", "--sort", "--by"];
+
+/// Field names of the vault typed on the command line, or of the configured vault or the
+/// current directory, one per line
+const LIST_FIELDS: &str = "aktenfux fields --cached --silent";
+
+/// Field names of the vaults, remembered from their last full scan so completing them
+/// doesn't scan the vault on every key press
+#[derive(Debug)]
+pub struct FieldCache {
+    dir: PathBuf,
+}
+
+impl FieldCache {
+    /// The cache below `$XDG_CACHE_HOME/aktenfux/fields`, falling back to `~/.cache`
+    pub fn new() -> Option<Self> {
+        let cache_dir = std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+        Some(Self::in_dir(cache_dir.join("aktenfux").join("fields")))
+    }
+
+    pub fn in_dir(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Remember the field names of the notes of a vault
+    pub fn write(&self, vault_path: &Path, notes: &[Note]) -> Result<()> {
+        let path = self.path(vault_path)?;
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+        let mut names = collect_all_fields(notes).join("\n");
+        names.push('\n');
+        fs::write(&path, names).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// The field names of a vault as of its last full scan, `None` when it wasn't scanned yet
+    pub fn read(&self, vault_path: &Path) -> Option<Vec<String>> {
+        let content = fs::read_to_string(self.path(vault_path).ok()?).ok()?;
+        Some(content.lines().map(str::to_string).collect())
+    }
+
+    /// One file per vault, named after its full path
+    fn path(&self, vault_path: &Path) -> Result<PathBuf> {
+        let vault = fs::canonicalize(vault_path)
+            .with_context(|| format!("Vault not found: {}", vault_path.display()))?;
+        let name: String = vault
+            .to_string_lossy()
+            .chars()
+            .map(|c| {
+                if matches!(c, '/' | '\\' | ':') {
+                    '%'
+                } else {
+                    c
+                }
+            })
+            .collect();
+        Ok(self.dir.join(name))
+    }
+}
+
+/// The completion script for a shell, generated from the command line definition. In bash,
+/// zsh and fish field names for `--field`, `--has`, `--filter`, ... come from
+/// `aktenfux fields --cached` for the vault typed so far.
+pub fn script(command: &mut Command, shell: Shell) -> String {
+    let name = command.get_name().to_string();
+    match shell {
+        Shell::Bash => bash(command, &name),
+        // zsh runs the bash script through its bash compatibility layer, field hook included
+        Shell::Zsh => format!(
+            "autoload -U +X compinit && compinit\nautoload -U +X bashcompinit && bashcompinit\n\n{}",
+            bash(command, &name)
+        ),
+        Shell::Fish => {
+            let fields = fish_fields(command, &name);
+            generated(command, shell, &name) + &fields
+        }
+        _ => generated(command, shell, &name),
+    }
+}
+
+fn generated(command: &mut Command, shell: Shell, name: &str) -> String {
+    let mut script = Vec::new();
+    generate(shell, command, name, &mut script);
+    String::from_utf8_lossy(&script).into_owned()
+}
+
+/// Options that take a value, whose value is not the vault path
+fn value_options(command: &Command) -> Vec<String> {
+    let mut options = BTreeSet::new();
+    let mut commands = vec![command];
+    while let Some(command) = commands.pop() {
+        for arg in command.get_arguments() {
+            if arg.is_positional() || !arg.get_action().takes_values() {
+                continue;
+            }
+            options.extend(arg.get_long().map(|long| format!("--{}", long)));
+            options.extend(arg.get_short().map(|short| format!("-{}", short)));
+        }
+        commands.extend(command.get_subcommands());
+    }
+    options.into_iter().collect()
+}
+
+/// Names of the subcommands of subcommands, like `pre-commit` in `hook pre-commit`. The
+/// generated `help` subcommands are left out, they repeat the subcommands.
+fn nested_subcommands(command: &Command) -> Vec<String> {
+    let names: BTreeSet<String> = command
+        .get_subcommands()
+        .filter(|subcommand| subcommand.get_name() != "help")
+        .flat_map(Command::get_subcommands)
+        .map(|subcommand| subcommand.get_name().to_string())
+        .filter(|name| name != "help")
+        .collect();
+    names.into_iter().collect()
+}
+
+/// The generated script, with a completion function in front of it that completes field
+/// names and leaves everything else to the generated one
+fn bash(command: &mut Command, name: &str) -> String {
+    let mut script = generated(command, Shell::Bash, name);
+    let function = format!("_{}", name.replace('-', "__"));
+
+    // The vault is the first word after the subcommand that is no option or option value;
+    // bash splits --filter=x into --filter, = and x
+    let _ = writeln!(script, "\n{}_vault() {{", function);
+    script.push_str("    local i word\n");
+    script.push_str("    for ((i = 2; i < COMP_CWORD; i++)); do\n");
+    script.push_str("        word=\"${COMP_WORDS[i]}\"\n");
+    script.push_str("        case \"$word\" in\n");
+    let mut skipped_words = vec!["-*".to_string(), "=".to_string()];
+    skipped_words.extend(nested_subcommands(command));
+    let _ = writeln!(
+        script,
+        "            {}) continue ;;",
+        skipped_words.join("|")
+    );
+    script.push_str("        esac\n");
+    script.push_str("        case \"${COMP_WORDS[i-1]}\" in\n");
+    let mut value_words = vec!["=".to_string()];
+    value_words.extend(value_options(command));
+    let _ = writeln!(script, "            {}) continue ;;", value_words.join("|"));
+    script.push_str("        esac\n");
+    script.push_str("        printf '%s\\n' \"${word/#\\~/$HOME}\"\n");
+    script.push_str("        return\n");
+    script.push_str("    done\n");
+    script.push_str("}\n");
+
+    let _ = writeln!(script, "\n{}_field_names() {{", function);
+    let _ = writeln!(script, "    local vault=\"$({}_vault)\"", function);
+    let _ = writeln!(
+        script,
+        "    {} ${{vault:+\"$vault\"}} 2>/dev/null",
+        LIST_FIELDS
+    );
+    script.push_str("}\n");
+
+    let _ = writeln!(script, "\n{}_fields() {{", function);
+    script.push_str(
+        "    local cur=\"${COMP_WORDS[COMP_CWORD]}\" prev=\"${COMP_WORDS[COMP_CWORD-1]}\"\n",
+    );
+    script.push_str("    case \"$prev\" in\n");
+    let _ = writeln!(script, "        {})", FIELD_OPTIONS.join("|"));
+    let _ = writeln!(
+        script,
+        "            COMPREPLY=($(compgen -W \"$({}_field_names)\" -- \"$cur\"))\n            return ;;",
+        function
+    );
+    script.push_str("        --filter)\n");
+    script.push_str("            compopt -o nospace\n");
+    let _ = writeln!(
+        script,
+        "            COMPREPLY=($(compgen -S = -W \"$({}_field_names)\" -- \"$cur\"))\n            return ;;",
+        function
+    );
+    script.push_str("    esac\n");
+    let _ = writeln!(script, "    {} \"$@\"", function);
+    script.push_str("}\n");
+    let _ = writeln!(
+        script,
+        "complete -F {}_fields -o bashdefault -o default {}",
+        function, name
+    );
+    script
+}
+
+/// Field names as values of the field options, next to the generated completions
+fn fish_fields(command: &Command, name: &str) -> String {
+    // The vault is the first token after the subcommand that is no option or option value
+    let mut script = format!("\nfunction __{}_vault\n", name);
+    script.push_str("    set -l tokens (commandline -opc)\n");
+    script.push_str("    for i in (seq 3 (count $tokens))\n");
+    script.push_str("        set -l token $tokens[$i]\n");
+    script.push_str("        set -l previous $tokens[(math $i - 1)]\n");
+    let _ = writeln!(
+        script,
+        "        if not string match -q -- '-*' $token; and not contains -- $token {}; and not contains -- $previous {}",
+        nested_subcommands(command).join(" "),
+        value_options(command).join(" ")
+    );
+    script.push_str("            string replace -r '^~' $HOME $token\n");
+    script.push_str("            return\n");
+    script.push_str("        end\n");
+    script.push_str("    end\n");
+    script.push_str("end\n");
+    let _ = write!(
+        script,
+        "\nfunction __{}_fields\n    {} (__{}_vault) 2>/dev/null\nend\n",
+        name, LIST_FIELDS, name
+    );
+    for option in FIELD_OPTIONS {
+        let _ = writeln!(
+            script,
+            "complete -c {} -l {} -x -a '(__{}_fields)'",
+            name,
+            option.trim_start_matches('-'),
+            name
+        );
+    }
+    let _ = writeln!(
+        script,
+        "complete -c {} -l filter -x -a '(__{}_fields | string replace -r \\$ =)'",
+        name, name
+    );
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Arg;
+
+    fn command() -> Command {
+        Command::new("aktenfux")
+            .subcommand(
+                Command::new("filter")
+                    .about("Filter notes")
+                    .arg(Arg::new("filter").long("filter"))
+                    .arg(Arg::new("field").long("field"))
+                    .arg(Arg::new("vault")),
+            )
+            .subcommand(
+                Command::new("hook").subcommand(
+                    Command::new("pre-commit").arg(
+                        Arg::new("stdin")
+                            .long("stdin")
+                            .action(clap::ArgAction::SetTrue),
+                    ),
+                ),
+            )
+    }
+
+    #[test]
+    fn test_bash_script() {
+        let script = script(&mut command(), Shell::Bash);
+        assert!(script.contains("aktenfux__subcmd__hook,pre-commit)"));
+        assert!(script.contains("--filter --field"));
+        assert!(script.contains("--field|--has|--missing|--sort|--by)"));
+        assert!(script.contains("    _aktenfux \"$@\"\n"));
+        assert!(script.contains("            -*|=|pre-commit) continue ;;\n"));
+        assert!(script.contains("            =|--field|--filter) continue ;;\n"));
+        assert!(
+            script.contains("aktenfux fields --cached --silent ${vault:+\"$vault\"} 2>/dev/null")
+        );
+        assert!(
+            script.ends_with("complete -F _aktenfux_fields -o bashdefault -o default aktenfux\n")
+        );
+    }
+
+    #[test]
+    fn test_fish_script() {
+        let script = script(&mut command(), Shell::Fish);
+        assert!(script.contains("-a \"filter\" -d 'Filter notes'"));
+        assert!(script.contains("-l stdin"));
+        assert!(script.contains("complete -c aktenfux -l field -x -a '(__aktenfux_fields)'"));
+        assert!(script.contains(
+            "not contains -- $token pre-commit; and not contains -- $previous --field --filter"
+        ));
+        assert!(script.contains("aktenfux fields --cached --silent (__aktenfux_vault) 2>/dev/null"));
+        assert!(script.contains(
+            "complete -c aktenfux -l filter -x -a '(__aktenfux_fields | string replace -r \\$ =)'"
+        ));
+    }
+
+    #[test]
+    fn test_field_cache() {
+        let cache_dir = tempfile::TempDir::new().unwrap();
+        let vault = tempfile::TempDir::new().unwrap();
+        let cache = FieldCache::in_dir(cache_dir.path().join("fields"));
+        assert_eq!(cache.read(vault.path()), None);
+
+        let frontmatter = [("title", "Plan"), ("status", "open")]
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), yaml_rust2::Yaml::String(value.to_string())))
+            .collect();
+        let note = Note::new("note.md".to_string(), frontmatter);
+        cache.write(vault.path(), &[note]).unwrap();
+        assert_eq!(
+            cache.read(vault.path()),
+            Some(vec!["status".to_string(), "title".to_string()])
+        );
+        assert_eq!(cache.read(cache_dir.path()), None);
+    }
+}
@@ -0,0 +1,649 @@
+use crate::query::SavedQuery;
+use aktenfux::filter::FieldFilter;
+use aktenfux::lint::KeyCase;
+use aktenfux::matcher::{OrderMatcher, QuantityMatcher, ValueMatcher};
+use aktenfux::policy::{older_than, Policy};
+use aktenfux::scanner::vault_name;
+use aktenfux::writer::FrontmatterEdit;
+use aktenfux::yaml_compat::MatchMode;
+use anyhow::{anyhow, Context, Result};
+use chrono::{Local, NaiveDateTime};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use yaml_rust2::yaml::Hash;
+use yaml_rust2::Yaml;
+
+/// File name of the configuration, both in the vault root and in `~/.config/aktenfux/`
+pub const CONFIG_FILE_NAME: &str = "aktenfux.toml";
+
+/// Defaults read from `aktenfux.toml`. Command line flags take precedence.
+///
+/// ```toml
+/// vault = "~/Documents/ObsidianVault"
+/// format = "table"
+/// exclude = ["templates", "archive/old"]
+/// lenient = true
+/// ignore_case = false
+/// exact = false
+/// tolerant_fences = false
+/// include_canvas = true
+/// follow_symlinks = true
+/// max_depth = 5
+/// one_file_system = false
+/// namespace_separator = "."
+/// created_from = ["filename", "git", "ctime"]
+/// threads = 4
+/// hints = 5
+/// summary = "terse"
+/// schema = "schema.yaml"
+/// redact = ["email", "salary"]
+/// download_hook = "brctl download {}"
+/// skip_placeholders = false
+/// confirm_threshold = 50
+///
+/// [vaults]
+/// work = "~/work-vault"
+///
+/// [joins]
+/// project = "data/projects.csv"
+///
+/// [canonical_keys]
+/// Tag = "tags"
+/// Created = "created"
+///
+/// [presets]
+/// open-tasks = ["type=task", "status=open"]
+///
+/// [queries.due-this-week]
+/// filter = ["type=task", "due<=2024-06-07"]
+/// sort = "due"
+/// format = "paths"
+///
+/// [rules.stale]
+/// filter = ["status!=done", "file.mtime<2024-01-01"]
+///
+/// [rules.missing-owner]
+/// missing = ["owner"]
+///
+/// [matchers.duration]
+/// pattern = '^(?:(?P<h>\d+)h)?\s*(?:(?P<m>\d+)m)?$'
+/// units = { h = 60, m = 1 }
+///
+/// [matchers.priority]
+/// order = ["low", "medium", "high"]
+///
+/// [policies.archive-done]
+/// filter = ["status=done"]
+/// older_than = "180d"
+/// set = { archived = true }
+/// move_to = "Archive"
+///
+/// [lint]
+/// key_order = ["title", "date", "tags"]
+/// key_case = "snake_case"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Vault to scan when no path is given
+    pub vault: Option<PathBuf>,
+    /// Default output format for note lists
+    pub format: Option<String>,
+    /// Directories to skip, by name or by path relative to the vault root
+    pub exclude: Vec<String>,
+    /// Quote values with colons instead of rejecting the frontmatter
+    pub lenient: Option<bool>,
+    /// Match field names and values ignoring case
+    pub ignore_case: Option<bool>,
+    /// Match `field=value` filters against whole values instead of substrings
+    pub exact: Option<bool>,
+    /// Also accept `----` and `...` as closing frontmatter fences
+    pub tolerant_fences: Option<bool>,
+    /// Also index `.canvas` files
+    pub include_canvas: Option<bool>,
+    /// Descend into symlinked folders
+    pub follow_symlinks: Option<bool>,
+    /// Only scan this many folders deep
+    pub max_depth: Option<usize>,
+    /// Stay on the file system of the vault root
+    pub one_file_system: Option<bool>,
+    /// Where to look for the creation date of notes without `created`, in this order
+    pub created_from: Vec<String>,
+    /// Number of threads to parse notes on
+    pub threads: Option<NonZeroUsize>,
+    /// Print hints to speed up scans that take longer than this many seconds, like `--hints`
+    pub hints: Option<u64>,
+    /// Scan summary as terse, text or json, like `--summary`
+    pub summary: Option<String>,
+    /// Split field names into namespaces in the `fields` output
+    pub namespace_separator: Option<String>,
+    /// Schema for `aktenfux validate`, relative to the vault root
+    pub schema: Option<PathBuf>,
+    /// Fields whose values are masked in every output, like `--redact`
+    pub redact: Vec<String>,
+    /// Command run for notes that are only in the cloud, like `--download-hook`
+    pub download_hook: Option<String>,
+    /// Leave out notes that are only in the cloud without reporting them
+    pub skip_placeholders: Option<bool>,
+    /// Ask before write commands change more than this many files, like `--confirm-threshold`
+    pub confirm_threshold: Option<usize>,
+    /// Vaults that can be passed by name instead of by path
+    pub vaults: BTreeMap<String, PathBuf>,
+    /// Lookup tables joined onto notes by a field, relative to the vault root
+    pub joins: BTreeMap<String, PathBuf>,
+    /// Frontmatter keys read under another name when scanning, like `Tag` as `tags`
+    pub canonical_keys: BTreeMap<String, String>,
+    /// Named sets of field=value filters, used with `--preset`
+    pub presets: BTreeMap<String, Vec<String>>,
+    /// Saved queries, run with `aktenfux query run`
+    pub queries: BTreeMap<String, SavedQuery>,
+    /// Named filters each note is checked against with `--annotate`
+    pub rules: BTreeMap<String, RuleConfig>,
+    /// Custom comparisons for the values of fields, by field name
+    pub matchers: BTreeMap<String, MatcherConfig>,
+    /// What `aktenfux policy run` does with the notes matching filters
+    pub policies: BTreeMap<String, PolicyConfig>,
+    /// Rules for `aktenfux lint`
+    pub lint: LintConfig,
+}
+
+/// A rule for `--annotate`, matching the notes that pass all of its filters
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RuleConfig {
+    /// Filters like `--filter`, e.g. `status!=done`
+    pub filter: Vec<String>,
+    /// Fields that must be set, like `--has`
+    pub has: Vec<String>,
+    /// Fields that must be missing or empty, like `--missing`
+    pub missing: Vec<String>,
+}
+
+/// How the values of a field are compared: as quantities read with a pattern, or by their
+/// position in a list
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MatcherConfig {
+    /// Regular expression with a named group per unit
+    pub pattern: Option<String>,
+    /// Weight of each unit, by the name of its group in the pattern
+    pub units: BTreeMap<String, f64>,
+    /// Values from lowest to highest
+    pub order: Vec<String>,
+}
+
+impl MatcherConfig {
+    pub fn matcher(&self, field: &str) -> Result<Arc<dyn ValueMatcher>> {
+        let matcher: Result<Arc<dyn ValueMatcher>> = match (&self.pattern, self.order.is_empty()) {
+            (Some(pattern), true) => QuantityMatcher::new(pattern, self.units.clone())
+                .map(|matcher| Arc::new(matcher) as Arc<dyn ValueMatcher>),
+            (None, false) => Ok(Arc::new(OrderMatcher::new(self.order.clone()))),
+            _ => Err(anyhow!("set either pattern and units or order")),
+        };
+        matcher.map_err(|e| anyhow!("Matcher for '{}': {}", field, e))
+    }
+}
+
+/// A policy for `aktenfux policy run`: filters like a rule, and what to do with the notes
+/// matching them
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PolicyConfig {
+    /// Filters like `--filter`, e.g. `status=done`, but `=` and `!=` compare whole values
+    /// like `==`; `~=` still matches part of a value
+    pub filter: Vec<String>,
+    /// Fields that must be set, like `--has`
+    pub has: Vec<String>,
+    /// Fields that must be missing or empty, like `--missing`
+    pub missing: Vec<String>,
+    /// Only notes whose `date` is older than this, like `180d`, `6w` or `1y`
+    pub older_than: Option<String>,
+    /// Date field `older_than` looks at, `file.mtime` by default
+    pub date: Option<String>,
+    /// Fields to set, like `edit --set`
+    pub set: BTreeMap<String, toml::Value>,
+    /// Values to add to list fields, like `edit --add`
+    pub add: BTreeMap<String, toml::Value>,
+    /// Fields to remove, like `edit --remove`
+    pub remove: Vec<String>,
+    /// Folder to move the notes to, relative to the vault root
+    pub move_to: Option<PathBuf>,
+}
+
+impl PolicyConfig {
+    /// The policy, with `older_than` counted back from `now`
+    pub fn policy(&self, name: &str, now: NaiveDateTime) -> Result<Policy> {
+        let mut filters = self
+            .filter
+            .iter()
+            .map(|filter| FieldFilter::parse(filter).map(exact))
+            .chain(self.has.iter().map(|field| Ok(FieldFilter::exists(field))))
+            .chain(
+                self.missing
+                    .iter()
+                    .map(|field| Ok(FieldFilter::missing(field))),
+            )
+            .collect::<Result<Vec<_>>>()
+            .with_context(|| format!("Policy '{}'", name))?;
+        if let Some(age) = &self.older_than {
+            let date = self.date.as_deref().unwrap_or("file.mtime");
+            filters.push(older_than(date, age, now).with_context(|| format!("Policy '{}'", name))?);
+        }
+
+        let edits = self
+            .set
+            .iter()
+            .map(|(field, value)| FrontmatterEdit::Set(field.clone(), toml_to_yaml(value)))
+            .chain(
+                self.add
+                    .iter()
+                    .map(|(field, value)| FrontmatterEdit::Add(field.clone(), toml_to_yaml(value))),
+            )
+            .chain(self.remove.iter().cloned().map(FrontmatterEdit::Remove))
+            .collect();
+        Policy::new(name, filters, edits, self.move_to.clone())
+    }
+}
+
+/// A policy filter without an explicit match mode compares whole values, so that
+/// `status=done` never archives a note with `status: undone`
+fn exact(mut filter: FieldFilter) -> FieldFilter {
+    filter.mode.get_or_insert(MatchMode::Exact);
+    filter
+}
+
+/// A TOML value as the YAML value written to the frontmatter
+fn toml_to_yaml(value: &toml::Value) -> Yaml {
+    match value {
+        toml::Value::String(s) => Yaml::String(s.clone()),
+        toml::Value::Integer(n) => Yaml::Integer(*n),
+        toml::Value::Float(f) => Yaml::Real(f.to_string()),
+        toml::Value::Boolean(b) => Yaml::Boolean(*b),
+        toml::Value::Datetime(datetime) => Yaml::String(datetime.to_string()),
+        toml::Value::Array(items) => Yaml::Array(items.iter().map(toml_to_yaml).collect()),
+        toml::Value::Table(table) => Yaml::Hash(
+            table
+                .iter()
+                .map(|(key, value)| (Yaml::String(key.clone()), toml_to_yaml(value)))
+                .collect::<Hash>(),
+        ),
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LintConfig {
+    /// Keys that must come first in the frontmatter, in this order
+    pub key_order: Vec<String>,
+    /// Casing convention for keys: kebab-case, snake_case or lowercase
+    pub key_case: Option<String>,
+}
+
+impl LintConfig {
+    pub fn key_case(&self) -> Result<Option<KeyCase>> {
+        self.key_case.as_deref().map(KeyCase::parse).transpose()
+    }
+}
+
+impl Config {
+    pub fn from_toml(content: &str) -> Result<Self> {
+        toml::from_str(content).map_err(|e| anyhow!("{}", e))
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read config: {}", path.as_ref().display()))?;
+        Self::from_toml(&content)
+            .with_context(|| format!("Invalid config: {}", path.as_ref().display()))
+    }
+
+    /// Load the user config and the config of the vault, which takes precedence.
+    ///
+    /// Returns the merged config and the vault path: `vault_path` when given, otherwise the
+    /// vault from the user config or the current directory.
+    pub fn load(vault_path: Option<&Path>) -> Result<(Self, PathBuf)> {
+        let global = match global_config_path() {
+            Some(path) if path.is_file() => Self::from_file(path)?,
+            _ => Self::default(),
+        };
+
+        let vault_path = vault_path
+            .map(|path| global.resolve_vault(path).1)
+            .or_else(|| global.vault.as_deref().map(expand_home))
+            .unwrap_or_else(|| PathBuf::from("."));
+
+        let vault_config_path = vault_path.join(CONFIG_FILE_NAME);
+        let config = if vault_config_path.is_file() {
+            global.merge(Self::from_file(vault_config_path)?)
+        } else {
+            global
+        };
+        Ok((config, vault_path))
+    }
+
+    /// Combine two configs, values from `other` override the ones in `self`
+    pub fn merge(mut self, other: Self) -> Self {
+        self.vault = other.vault.or(self.vault);
+        self.format = other.format.or(self.format);
+        self.exclude.extend(other.exclude);
+        self.lenient = other.lenient.or(self.lenient);
+        self.ignore_case = other.ignore_case.or(self.ignore_case);
+        self.exact = other.exact.or(self.exact);
+        self.tolerant_fences = other.tolerant_fences.or(self.tolerant_fences);
+        self.include_canvas = other.include_canvas.or(self.include_canvas);
+        self.follow_symlinks = other.follow_symlinks.or(self.follow_symlinks);
+        self.max_depth = other.max_depth.or(self.max_depth);
+        self.one_file_system = other.one_file_system.or(self.one_file_system);
+        self.namespace_separator = other.namespace_separator.or(self.namespace_separator);
+        if !other.created_from.is_empty() {
+            self.created_from = other.created_from;
+        }
+        self.threads = other.threads.or(self.threads);
+        self.hints = other.hints.or(self.hints);
+        self.summary = other.summary.or(self.summary);
+        self.schema = other.schema.or(self.schema);
+        self.redact.extend(other.redact);
+        self.download_hook = other.download_hook.or(self.download_hook);
+        self.skip_placeholders = other.skip_placeholders.or(self.skip_placeholders);
+        self.confirm_threshold = other.confirm_threshold.or(self.confirm_threshold);
+        self.vaults.extend(other.vaults);
+        self.joins.extend(other.joins);
+        self.canonical_keys.extend(other.canonical_keys);
+        self.presets.extend(other.presets);
+        self.queries.extend(other.queries);
+        self.rules.extend(other.rules);
+        self.matchers.extend(other.matchers);
+        self.policies.extend(other.policies);
+        if !other.lint.key_order.is_empty() {
+            self.lint.key_order = other.lint.key_order;
+        }
+        if other.lint.key_case.is_some() {
+            self.lint.key_case = other.lint.key_case;
+        }
+        self
+    }
+
+    /// Look up a vault argument: the name and path of a configured vault, or the path
+    /// itself, named after its directory. Existing paths win over vault names.
+    pub fn resolve_vault(&self, path_or_name: &Path) -> (String, PathBuf) {
+        let configured = path_or_name
+            .to_str()
+            .filter(|_| !path_or_name.exists())
+            .and_then(|name| Some((name, self.vaults.get(name)?)));
+        match configured {
+            Some((name, path)) => (name.to_string(), expand_home(path)),
+            None => (vault_name(path_or_name), path_or_name.to_path_buf()),
+        }
+    }
+
+    pub fn preset(&self, name: &str) -> Result<&[String]> {
+        self.presets.get(name).map(Vec::as_slice).ok_or_else(|| {
+            let known: Vec<&str> = self.presets.keys().map(String::as_str).collect();
+            if known.is_empty() {
+                anyhow!("Unknown preset '{}': no presets configured", name)
+            } else {
+                anyhow!(
+                    "Unknown preset '{}', known presets: {}",
+                    name,
+                    known.join(", ")
+                )
+            }
+        })
+    }
+
+    pub fn query(&self, name: &str) -> Result<&SavedQuery> {
+        self.queries.get(name).ok_or_else(|| {
+            if self.queries.is_empty() {
+                anyhow!(
+                    "Unknown query '{}': no queries saved, add one with `aktenfux query save`",
+                    name
+                )
+            } else {
+                let known: Vec<&str> = self.queries.keys().map(String::as_str).collect();
+                anyhow!(
+                    "Unknown query '{}', saved queries: {}",
+                    name,
+                    known.join(", ")
+                )
+            }
+        })
+    }
+
+    /// The rules with the given names, or all rules when no names are given
+    pub fn rules(&self, names: &[String]) -> Result<Vec<(&str, &RuleConfig)>> {
+        if self.rules.is_empty() {
+            return Err(anyhow!(
+                "No rules configured, add them as [rules.<name>] to {}",
+                CONFIG_FILE_NAME
+            ));
+        }
+        if names.is_empty() {
+            return Ok(self
+                .rules
+                .iter()
+                .map(|(name, rule)| (name.as_str(), rule))
+                .collect());
+        }
+        names
+            .iter()
+            .map(|name| {
+                let (name, rule) = self.rules.get_key_value(name).ok_or_else(|| {
+                    let known: Vec<&str> = self.rules.keys().map(String::as_str).collect();
+                    anyhow!("Unknown rule '{}', known rules: {}", name, known.join(", "))
+                })?;
+                Ok((name.as_str(), rule))
+            })
+            .collect()
+    }
+
+    /// The policies with the given names, or all policies when no names are given
+    pub fn policies(&self, names: &[String]) -> Result<Vec<Policy>> {
+        if self.policies.is_empty() {
+            return Err(anyhow!(
+                "No policies configured, add them as [policies.<name>] to {}",
+                CONFIG_FILE_NAME
+            ));
+        }
+        let now = Local::now().naive_local();
+        if names.is_empty() {
+            return self
+                .policies
+                .iter()
+                .map(|(name, policy)| policy.policy(name, now))
+                .collect();
+        }
+        names
+            .iter()
+            .map(|name| {
+                let policy = self.policies.get(name).ok_or_else(|| {
+                    let known: Vec<&str> = self.policies.keys().map(String::as_str).collect();
+                    anyhow!(
+                        "Unknown policy '{}', known policies: {}",
+                        name,
+                        known.join(", ")
+                    )
+                })?;
+                policy.policy(name, now)
+            })
+            .collect()
+    }
+}
+
+/// `$XDG_CONFIG_HOME/aktenfux/aktenfux.toml`, falling back to `~/.config`
+fn global_config_path() -> Option<PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_dir.join("aktenfux").join(CONFIG_FILE_NAME))
+}
+
+fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), std::env::var_os("HOME")) {
+        (Ok(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aktenfux::{FilterCriteria, Note};
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_config() {
+        let config = Config::from_toml(
+            r#"
+format = "paths"
+exclude = ["templates"]
+ignore_case = true
+redact = ["email"]
+confirm_threshold = 50
+
+[joins]
+project = "projects.csv"
+
+[canonical_keys]
+Tag = "tags"
+
+[presets]
+open-tasks = ["type=task", "status=open"]
+
+[rules.stale]
+filter = ["status!=done"]
+
+[rules.missing-owner]
+missing = ["owner"]
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.format.as_deref(), Some("paths"));
+        assert_eq!(config.exclude, vec!["templates".to_string()]);
+        assert_eq!(config.ignore_case, Some(true));
+        assert_eq!(config.lenient, None);
+        assert_eq!(config.preset("open-tasks").unwrap().len(), 2);
+        assert!(config.preset("missing").is_err());
+        assert_eq!(config.joins["project"], PathBuf::from("projects.csv"));
+        assert_eq!(config.canonical_keys["Tag"], "tags");
+        assert_eq!(config.redact, vec!["email".to_string()]);
+        assert_eq!(config.confirm_threshold, Some(50));
+        let rules = config.rules(&[]).unwrap();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].0, "missing-owner");
+        assert_eq!(rules[0].1.missing, vec!["owner".to_string()]);
+        assert_eq!(config.rules(&["stale".to_string()]).unwrap().len(), 1);
+        assert!(config.rules(&["fresh".to_string()]).is_err());
+        assert!(Config::default().rules(&[]).is_err());
+        assert!(Config::from_toml("colour = \"red\"").is_err());
+        assert!(Config::from_toml("threads = 0").is_err());
+    }
+
+    #[test]
+    fn test_vault_config_overrides_global() {
+        let global = Config::from_toml(
+            "format = \"json\"\nexclude = [\"a\"]\nlenient = false\n[lint]\nkey_order = [\"title\"]",
+        )
+        .unwrap();
+        let vault = Config::from_toml(
+            "format = \"paths\"\nexclude = [\"b\"]\n[lint]\nkey_case = \"kebab-case\"",
+        )
+        .unwrap();
+        let merged = global.merge(vault);
+
+        assert_eq!(merged.format.as_deref(), Some("paths"));
+        assert_eq!(merged.exclude, vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(merged.lenient, Some(false));
+        assert_eq!(merged.lint.key_order, vec!["title".to_string()]);
+        assert_eq!(merged.lint.key_case().unwrap(), Some(KeyCase::Kebab));
+    }
+
+    #[test]
+    fn test_matchers() {
+        let config = Config::from_toml(
+            "[matchers.duration]\npattern = '(?P<h>\\d+)h'\nunits = { h = 60 }\n\n[matchers.priority]\norder = [\"low\", \"high\"]\n[matchers.broken]\norder = [\"a\"]\nunits = { h = 1 }\npattern = '(?P<h>x)'",
+        )
+        .unwrap();
+        let duration = config.matchers["duration"].matcher("duration").unwrap();
+        assert_eq!(
+            duration.compare("2h", "1h"),
+            Some(std::cmp::Ordering::Greater)
+        );
+        assert!(config.matchers["priority"].matcher("priority").is_ok());
+        assert!(config.matchers["broken"].matcher("broken").is_err());
+    }
+
+    #[test]
+    fn test_policies() {
+        let config = Config::from_toml(
+            r#"
+[policies.archive-done]
+filter = ["status=done"]
+older_than = "180d"
+set = { archived = true, archived_by = "policy" }
+add = { tags = "archive" }
+move_to = "Archive"
+
+[policies.noop]
+missing = ["owner"]
+
+[policies.escape]
+move_to = "../elsewhere"
+"#,
+        )
+        .unwrap();
+        let now =
+            NaiveDateTime::parse_from_str("2024-07-01 00:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+
+        let policy = config.policies["archive-done"]
+            .policy("archive-done", now)
+            .unwrap();
+        assert_eq!(policy.filters.len(), 2);
+        assert_eq!(policy.filters[1].field, "file.mtime");
+        assert_eq!(policy.filters[1].value, "2024-01-03T00:00:00");
+        assert_eq!(policy.edits.len(), 3);
+        assert!(matches!(
+            &policy.edits[0],
+            FrontmatterEdit::Set(field, Yaml::Boolean(true)) if field == "archived"
+        ));
+        assert_eq!(policy.move_to, Some(PathBuf::from("Archive")));
+
+        // `=` compares whole values, so a near miss is not archived
+        let criteria = FilterCriteria::new(policy.filters[..1].to_vec());
+        let done = |status: &str| {
+            let mut frontmatter = HashMap::new();
+            frontmatter.insert("status".to_string(), Yaml::String(status.to_string()));
+            criteria.matches(&Note::new("note.md".to_string(), frontmatter))
+        };
+        assert!(done("done"));
+        assert!(!done("undone"));
+
+        assert!(config.policies(&["noop".to_string()]).is_err());
+        assert!(config.policies(&["escape".to_string()]).is_err());
+        assert!(config.policies(&["missing".to_string()]).is_err());
+        assert!(Config::default().policies(&[]).is_err());
+    }
+
+    #[test]
+    fn test_load_vault_config() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join(CONFIG_FILE_NAME),
+            "[presets]\ndrafts = [\"status=draft\"]\n",
+        )
+        .unwrap();
+
+        let (config, vault_path) = Config::load(Some(temp_dir.path())).unwrap();
+        assert_eq!(vault_path, temp_dir.path());
+        assert_eq!(
+            config.preset("drafts").unwrap(),
+            &["status=draft".to_string()]
+        );
+    }
+}
@@ -0,0 +1,244 @@
+use crate::frontmatter::Note;
+use crate::yaml_compat::yaml_to_string;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local, NaiveDate};
+use regex::Regex;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+use yaml_rust2::Yaml;
+
+/// Virtual field with the `created` date, or the inferred one for notes without it
+pub const EFFECTIVE_FIELD: &str = "created.effective";
+/// Virtual field naming where `created.effective` comes from
+pub const SOURCE_FIELD: &str = "created.source";
+
+/// Where the creation date of a note without a `created` field is taken from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CreatedSource {
+    /// A date in the file name, like `2024-06-01 Standup.md` or `202406011530 Idea.md`
+    FileName,
+    /// The commit that added the file to git
+    Git,
+    /// The creation time of the file
+    Ctime,
+}
+
+impl CreatedSource {
+    /// File name first, then the creation time. Git is left out as it needs a `git log`
+    /// over the whole history.
+    pub const DEFAULT: [Self; 2] = [Self::FileName, Self::Ctime];
+
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "filename" | "file-name" => Ok(Self::FileName),
+            "git" => Ok(Self::Git),
+            "ctime" => Ok(Self::Ctime),
+            _ => Err(anyhow!(
+                "Unknown created date source: '{}'. Use filename, git or ctime",
+                s
+            )),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::FileName => "filename",
+            Self::Git => "git",
+            Self::Ctime => "ctime",
+        }
+    }
+}
+
+fn file_name_date_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"(?:^|[^0-9])(\d{4})-(\d{2})-(\d{2})(?:[^0-9]|$)|^(\d{4})(\d{2})(\d{2})")
+            .unwrap()
+    })
+}
+
+/// The date in a file name: `YYYY-MM-DD` anywhere, or `YYYYMMDD` at the start as in
+/// Zettelkasten ids
+pub fn date_from_file_name(name: &str) -> Option<String> {
+    let captures = file_name_date_regex().captures(name)?;
+    let part = |a: usize, b: usize| captures.get(a).or_else(|| captures.get(b));
+    let date = NaiveDate::from_ymd_opt(
+        part(1, 4)?.as_str().parse().ok()?,
+        part(2, 5)?.as_str().parse().ok()?,
+        part(3, 6)?.as_str().parse().ok()?,
+    )?;
+    Some(date.format("%Y-%m-%d").to_string())
+}
+
+/// When each file in a git repository was first committed, by path relative to `dir`.
+/// Empty when `dir` isn't in a git repository.
+pub fn first_commit_dates(dir: &Path) -> HashMap<PathBuf, String> {
+    let Ok(output) = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args([
+            "log",
+            "--diff-filter=A",
+            "--name-only",
+            "--relative",
+            "--format=@%aI",
+        ])
+        .output()
+    else {
+        return HashMap::new();
+    };
+    if !output.status.success() {
+        return HashMap::new();
+    }
+    parse_git_log(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Read `git log --format=@%aI --name-only` output, newest commit first, keeping the
+/// oldest date of every file
+fn parse_git_log(log: &str) -> HashMap<PathBuf, String> {
+    let mut dates = HashMap::new();
+    let mut date = None;
+    for line in log.lines().filter(|line| !line.is_empty()) {
+        if let Some(commit_date) = line.strip_prefix('@') {
+            date = DateTime::parse_from_rfc3339(commit_date).ok().map(|date| {
+                date.with_timezone(&Local)
+                    .format("%Y-%m-%dT%H:%M:%S")
+                    .to_string()
+            });
+        } else if let Some(date) = &date {
+            dates.insert(PathBuf::from(line), date.clone());
+        }
+    }
+    dates
+}
+
+/// Set `created.effective` and `created.source` of a note.
+///
+/// The date is the `created` field when the note has one, otherwise the first source that
+/// knows a date. `git_date` looks up the first commit of the note and is only called when
+/// git comes before a source with a date.
+pub fn set_effective_created<F>(note: &mut Note, sources: &[CreatedSource], git_date: F)
+where
+    F: FnOnce(&Path) -> Option<String>,
+{
+    let created = note
+        .frontmatter
+        .get("created")
+        .map(yaml_to_string)
+        .filter(|created| !created.trim().is_empty());
+    let mut git_date = Some(git_date);
+
+    let found = match created {
+        Some(created) => Some((created, "frontmatter")),
+        None => sources.iter().find_map(|source| {
+            let date = match source {
+                CreatedSource::FileName => Path::new(&note.path)
+                    .file_stem()
+                    .and_then(|stem| date_from_file_name(&stem.to_string_lossy())),
+                CreatedSource::Git => git_date
+                    .take()
+                    .and_then(|git_date| git_date(Path::new(&note.path))),
+                CreatedSource::Ctime => {
+                    note.get_frontmatter_value("file.ctime").map(yaml_to_string)
+                }
+            };
+            date.map(|date| (date, source.name()))
+        }),
+    };
+
+    if let Some((date, source)) = found {
+        note.set_virtual_field(EFFECTIVE_FIELD, Yaml::String(date));
+        note.set_virtual_field(SOURCE_FIELD, Yaml::String(source.to_string()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_date_from_file_name() {
+        assert_eq!(
+            date_from_file_name("2024-06-01 Standup").as_deref(),
+            Some("2024-06-01")
+        );
+        assert_eq!(
+            date_from_file_name("Standup 2024-06-01").as_deref(),
+            Some("2024-06-01")
+        );
+        assert_eq!(
+            date_from_file_name("202406011530 Idea").as_deref(),
+            Some("2024-06-01")
+        );
+        assert_eq!(date_from_file_name("2024-13-01 Nonsense"), None);
+        assert_eq!(date_from_file_name("Meeting notes"), None);
+        assert_eq!(date_from_file_name("Invoice 12024-06-015"), None);
+    }
+
+    #[test]
+    fn test_parse_git_log() {
+        let log = "@2024-06-03T10:00:00+00:00\n\nnotes/b.md\n\n@2024-06-01T09:00:00+00:00\n\nnotes/a.md\nnotes/b.md\n";
+        let dates = parse_git_log(log);
+        assert_eq!(dates.len(), 2);
+        assert!(dates[Path::new("notes/b.md")].starts_with("2024-06-0"));
+        assert_eq!(
+            dates[Path::new("notes/a.md")],
+            dates[Path::new("notes/b.md")]
+        );
+    }
+
+    #[test]
+    fn test_set_effective_created() {
+        let effective = |path: &str, created: Option<&str>, sources: &[CreatedSource]| {
+            let mut fm = HashMap::new();
+            if let Some(created) = created {
+                fm.insert("created".to_string(), Yaml::String(created.to_string()));
+            }
+            let mut note = Note::new(path.to_string(), fm);
+            note.set_virtual_field(
+                "file.ctime",
+                Yaml::String("2025-01-01T08:00:00".to_string()),
+            );
+            set_effective_created(&mut note, sources, |_| {
+                Some("2023-03-03T12:00:00".to_string())
+            });
+            (
+                note.get_frontmatter_value(EFFECTIVE_FIELD)
+                    .map(yaml_to_string),
+                note.get_frontmatter_value(SOURCE_FIELD).map(yaml_to_string),
+            )
+        };
+        let pair = |date: &str, source: &str| (Some(date.to_string()), Some(source.to_string()));
+
+        assert_eq!(
+            effective(
+                "2024-06-01 Standup.md",
+                Some("2020-01-01"),
+                &CreatedSource::DEFAULT
+            ),
+            pair("2020-01-01", "frontmatter")
+        );
+        assert_eq!(
+            effective("2024-06-01 Standup.md", None, &CreatedSource::DEFAULT),
+            pair("2024-06-01", "filename")
+        );
+        assert_eq!(
+            effective("Standup.md", None, &CreatedSource::DEFAULT),
+            pair("2025-01-01T08:00:00", "ctime")
+        );
+        assert_eq!(
+            effective(
+                "2024-06-01 Standup.md",
+                None,
+                &[CreatedSource::Git, CreatedSource::FileName]
+            ),
+            pair("2023-03-03T12:00:00", "git")
+        );
+        assert_eq!(
+            effective("Standup.md", None, &[CreatedSource::FileName]),
+            (None, None)
+        );
+    }
+}
@@ -0,0 +1,299 @@
+use crate::filter::{get_field_statistics, is_tag_field};
+use crate::frontmatter::Note;
+use crate::yaml_compat::{
+    collect_yaml_strings, yaml_nested_fields, yaml_to_json_value, yaml_to_string,
+};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use yaml_rust2::Yaml;
+
+/// Fields with more distinct values than this get no value distribution
+const MAX_DISTINCT_VALUES: usize = 30;
+
+/// Number of value distributions shown, for the fields set in the most notes
+const MAX_DISTRIBUTIONS: usize = 12;
+
+/// Values per distribution, the rest are counted as other values
+const VALUES_PER_DISTRIBUTION: usize = 10;
+
+/// Fields shown as columns of the note table besides path and title
+const TABLE_COLUMNS: usize = 6;
+
+/// Number of tags in the tag cloud
+const MAX_TAGS: usize = 150;
+
+/// The page, with the title and the data of the dashboard filled in by `render`
+const TEMPLATE: &str = include_str!("dashboard.html");
+
+/// What the dashboard shows about a set of notes, embedded into the page as JSON
+#[derive(Debug, Serialize)]
+pub struct Dashboard {
+    pub title: String,
+    pub generated: String,
+    pub notes: usize,
+    pub fields: Vec<FieldSummary>,
+    /// Tags from the frontmatter and the note bodies with the number of notes having them,
+    /// most used first
+    pub tags: Vec<(String, usize)>,
+    pub distributions: Vec<Distribution>,
+    /// Fields shown as columns of the note table
+    pub columns: Vec<String>,
+    pub rows: Vec<Row>,
+}
+
+/// How a field is used across the notes
+#[derive(Debug, Serialize)]
+pub struct FieldSummary {
+    pub name: String,
+    /// Notes with the field
+    pub notes: usize,
+    pub kind: &'static str,
+    pub distinct_values: usize,
+}
+
+/// How often the values of a field occur, most common first
+#[derive(Debug, Serialize)]
+pub struct Distribution {
+    pub field: String,
+    pub values: Vec<(String, usize)>,
+    /// Occurrences of the values left out
+    pub other: usize,
+}
+
+/// A note in the note table, with its fields as text
+#[derive(Debug, Serialize)]
+pub struct Row {
+    pub path: String,
+    pub title: String,
+    pub tags: Vec<String>,
+    pub values: BTreeMap<String, String>,
+}
+
+impl Dashboard {
+    pub fn new(notes: &[&Note], title: &str, generated: &str) -> Self {
+        let stats = get_field_statistics(notes.iter().copied());
+        let mut fields: Vec<FieldSummary> = stats
+            .iter()
+            .map(|(name, stats)| FieldSummary {
+                name: name.clone(),
+                notes: stats.total_count,
+                kind: stats.inferred_type(),
+                distinct_values: stats.unique_values.len(),
+            })
+            .collect();
+        fields.sort_by(|a, b| b.notes.cmp(&a.notes).then_with(|| a.name.cmp(&b.name)));
+
+        // Fields whose values repeat, like status or priority, not ids or titles
+        let distributions = fields
+            .iter()
+            .filter(|field| {
+                !is_tag_field(&field.name)
+                    && !matches!(field.kind, "date" | "map")
+                    && field.notes >= 2
+                    && (1..=MAX_DISTINCT_VALUES).contains(&field.distinct_values)
+                    && field.distinct_values < field.notes
+            })
+            .take(MAX_DISTRIBUTIONS)
+            .map(|field| {
+                let mut values: Vec<(String, usize)> = stats[&field.name]
+                    .value_counts
+                    .iter()
+                    .map(|(value, count)| (value.clone(), *count))
+                    .collect();
+                values.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+                let other = values
+                    .iter()
+                    .skip(VALUES_PER_DISTRIBUTION)
+                    .map(|(_, count)| count)
+                    .sum();
+                values.truncate(VALUES_PER_DISTRIBUTION);
+                Distribution {
+                    field: field.name.clone(),
+                    values,
+                    other,
+                }
+            })
+            .collect();
+
+        let rows: Vec<Row> = notes.iter().map(|note| row(note)).collect();
+
+        let mut tag_counts: HashMap<&str, usize> = HashMap::new();
+        for row in &rows {
+            for tag in &row.tags {
+                *tag_counts.entry(tag).or_insert(0) += 1;
+            }
+        }
+        let mut tags: Vec<(String, usize)> = tag_counts
+            .into_iter()
+            .map(|(tag, count)| (tag.to_string(), count))
+            .collect();
+        tags.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        tags.truncate(MAX_TAGS);
+
+        // The most used top-level fields, leaving out the ones shown elsewhere
+        let mut column_counts: HashMap<&str, usize> = HashMap::new();
+        for row in &rows {
+            for field in row.values.keys() {
+                *column_counts.entry(field).or_insert(0) += 1;
+            }
+        }
+        let mut columns: Vec<(&str, usize)> = column_counts
+            .into_iter()
+            .filter(|(field, _)| !field.eq_ignore_ascii_case("title"))
+            .collect();
+        columns.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        let columns = columns
+            .into_iter()
+            .take(TABLE_COLUMNS)
+            .map(|(field, _)| field.to_string())
+            .collect();
+
+        Self {
+            title: title.to_string(),
+            generated: generated.to_string(),
+            notes: notes.len(),
+            fields,
+            tags,
+            distributions,
+            columns,
+            rows,
+        }
+    }
+
+    /// The page: styles, script and data in one file that opens in a browser without a
+    /// network connection
+    pub fn render(&self) -> Result<String> {
+        // `<` is escaped so no value can close the script element holding the data
+        let data = serde_json::to_string(self)?.replace('<', "\\u003c");
+        let title = escape_html(&self.title);
+
+        // One pass over the template, so placeholders in the title or data stay as they are
+        let mut page = String::with_capacity(TEMPLATE.len() + data.len());
+        let mut rest = TEMPLATE;
+        while let Some(start) = rest.find("{{") {
+            page.push_str(&rest[..start]);
+            rest = &rest[start..];
+            if let Some(after) = rest.strip_prefix("{{title}}") {
+                page.push_str(&title);
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix("{{data}}") {
+                page.push_str(&data);
+                rest = after;
+            } else {
+                page.push_str("{{");
+                rest = &rest[2..];
+            }
+        }
+        page.push_str(rest);
+        Ok(page)
+    }
+
+    /// Write the page to `path`, replacing an existing file
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.render()?)
+            .with_context(|| format!("Failed to write dashboard: {}", path.display()))
+    }
+}
+
+fn row(note: &Note) -> Row {
+    // Nested fields like `project.name` too, so they can be filtered on like in `filter`
+    let mut values = BTreeMap::new();
+    for (key, value) in &note.frontmatter {
+        values.insert(key.clone(), cell(value));
+        for (path, value) in yaml_nested_fields(key, value) {
+            values.insert(path, cell(value));
+        }
+    }
+
+    Row {
+        path: note.path.clone(),
+        title: note.title.clone().unwrap_or_default(),
+        tags: note.tags(true),
+        values,
+    }
+}
+
+/// A value as the text of a table cell: lists joined by commas, maps as JSON
+fn cell(value: &Yaml) -> String {
+    match value {
+        Yaml::Array(_) => collect_yaml_strings(value).join(", "),
+        Yaml::Hash(_) => yaml_to_json_value(value).to_string(),
+        Yaml::Null => String::new(),
+        _ => yaml_to_string(value),
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yaml_rust2::YamlLoader;
+
+    fn note(path: &str, frontmatter: &str) -> Note {
+        let Yaml::Hash(hash) = YamlLoader::load_from_str(frontmatter).unwrap().remove(0) else {
+            panic!("frontmatter must be a mapping");
+        };
+        let frontmatter = hash
+            .into_iter()
+            .map(|(key, value)| (key.into_string().unwrap(), value))
+            .collect();
+        Note::new(path.to_string(), frontmatter)
+    }
+
+    #[test]
+    fn test_dashboard() {
+        let mut notes = vec![
+            note(
+                "a.md",
+                "status: open\ntags: [work, '#urgent']\nid: 1\nproject: {name: Alpha}",
+            ),
+            note("b.md", "status: open\ntags: work, idea\nid: 2"),
+            note("c.md", "status: done\nid: 3\nnote: </script><b>"),
+        ];
+        notes[2].inline_tags = vec!["work".to_string(), "idea".to_string()];
+        let notes: Vec<&Note> = notes.iter().collect();
+        let dashboard = Dashboard::new(&notes, "Vault <1> {{data}}", "2024-06-01 10:00");
+
+        assert_eq!(dashboard.notes, 3);
+        assert_eq!(dashboard.fields[0].notes, 3);
+        assert_eq!(
+            dashboard.tags,
+            vec![
+                ("work".to_string(), 3),
+                ("idea".to_string(), 2),
+                ("urgent".to_string(), 1)
+            ]
+        );
+        // Ids are all distinct and tags are in the cloud
+        let distributions: Vec<&str> = dashboard
+            .distributions
+            .iter()
+            .map(|distribution| distribution.field.as_str())
+            .collect();
+        assert_eq!(distributions, vec!["status"]);
+        assert_eq!(
+            dashboard.distributions[0].values,
+            vec![("open".to_string(), 2), ("done".to_string(), 1)]
+        );
+        assert_eq!(&dashboard.columns[..2], ["id", "status"]);
+        assert_eq!(dashboard.rows[0].values["tags"], "work, #urgent");
+        assert_eq!(dashboard.rows[1].tags, ["work", "idea"]);
+        assert_eq!(dashboard.rows[0].values["project"], r#"{"name":"Alpha"}"#);
+        assert_eq!(dashboard.rows[0].values["project.name"], "Alpha");
+
+        let page = dashboard.render().unwrap();
+        assert!(page.contains("<title>Vault &lt;1&gt; {{data}} · aktenfux</title>"));
+        assert!(!page.contains("</script><b>"));
+        assert!(page.contains(r"\u003c/script>\u003cb>"));
+        assert!(!page.contains("http://") && !page.contains("https://"));
+    }
+}
@@ -0,0 +1,141 @@
+use anyhow::{Context, Result};
+use std::process::Command;
+
+/// Longest command line run at once by `command_lines`. Linux limits a single argument,
+/// and `sh -c` gets the whole line as one, to 128 KiB.
+const MAX_COMMAND_LINE: usize = 100_000;
+
+/// A shell command run for matching notes, like `find -exec`: `{}` stands for the quoted
+/// path, or the paths are appended when the command has no `{}`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecCommand {
+    template: String,
+}
+
+impl ExecCommand {
+    pub fn new(template: &str) -> Self {
+        Self {
+            template: template.to_string(),
+        }
+    }
+
+    /// The command line for one or more paths
+    pub fn command_line(&self, paths: &[&str]) -> String {
+        let quoted = paths
+            .iter()
+            .map(|path| shell_quote(path))
+            .collect::<Vec<_>>()
+            .join(" ");
+        if self.template.contains("{}") {
+            self.template.replace("{}", &quoted)
+        } else {
+            format!("{} {}", self.template, quoted)
+        }
+    }
+
+    /// Command lines passing all paths at once, split into several commands when the line
+    /// would get too long for the shell
+    pub fn command_lines(&self, paths: &[&str]) -> Vec<String> {
+        let mut lines = Vec::new();
+        let mut batch: Vec<&str> = Vec::new();
+        let mut length = self.template.len();
+        for path in paths {
+            let path_length = shell_quote(path).len() + 1;
+            if !batch.is_empty() && length + path_length > MAX_COMMAND_LINE {
+                lines.push(self.command_line(&batch));
+                batch.clear();
+                length = self.template.len();
+            }
+            batch.push(path);
+            length += path_length;
+        }
+        if !batch.is_empty() {
+            lines.push(self.command_line(&batch));
+        }
+        lines
+    }
+}
+
+/// Run a command line with `sh -c`, returning whether it exited successfully
+pub fn run(command_line: &str) -> Result<bool> {
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(command_line)
+        .status()
+        .with_context(|| format!("Failed to run: {}", command_line))?;
+    Ok(status.success())
+}
+
+/// Like `run`, but the output of the command goes to stderr, so hooks run during a scan
+/// don't mix with the output of aktenfux
+pub fn run_hook(command_line: &str) -> Result<bool> {
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(command_line)
+        .stdout(std::io::stderr())
+        .status()
+        .with_context(|| format!("Failed to run: {}", command_line))?;
+    Ok(status.success())
+}
+
+/// Quote a path for `sh` unless it only has characters that are safe unquoted
+pub fn shell_quote(text: &str) -> String {
+    let safe = !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "/._-+,:@%".contains(c));
+    if safe {
+        text.to_string()
+    } else {
+        format!("'{}'", text.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("notes/a-1.md"), "notes/a-1.md");
+        assert_eq!(shell_quote("My Notes/it's.md"), r"'My Notes/it'\''s.md'");
+        assert_eq!(shell_quote(""), "''");
+    }
+
+    #[test]
+    fn test_command_line() {
+        let exec = ExecCommand::new("mv {} archive/");
+        assert_eq!(exec.command_line(&["a b.md"]), "mv 'a b.md' archive/");
+        assert_eq!(
+            ExecCommand::new("touch").command_line(&["a.md", "b.md"]),
+            "touch a.md b.md"
+        );
+    }
+
+    #[test]
+    fn test_command_lines_split_long_batches() {
+        let path = "x".repeat(MAX_COMMAND_LINE / 3);
+        let paths = vec![path.as_str(); 5];
+        let lines = ExecCommand::new("touch").command_lines(&paths);
+        assert_eq!(lines.len(), 3);
+        assert!(lines.iter().all(|line| line.len() <= MAX_COMMAND_LINE));
+        assert_eq!(
+            lines
+                .iter()
+                .map(|line| line.matches(path.as_str()).count())
+                .sum::<usize>(),
+            5
+        );
+        assert_eq!(
+            ExecCommand::new("touch").command_lines(&[]),
+            Vec::<String>::new()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_reports_exit_status() {
+        assert!(run("true").unwrap());
+        assert!(!run("exit 3").unwrap());
+    }
+}
@@ -0,0 +1,195 @@
+use crate::frontmatter::Note;
+use crate::links::LinkIndex;
+use crate::yaml_compat::{yaml_to_json_value, yaml_to_string, yaml_type_name};
+use anyhow::{anyhow, Context, Result};
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use yaml_rust2::Yaml;
+
+const SCHEMA: &str = r#"
+CREATE TABLE notes (
+    id INTEGER PRIMARY KEY,
+    path TEXT NOT NULL UNIQUE,
+    title TEXT
+);
+CREATE TABLE frontmatter (
+    note_id INTEGER NOT NULL REFERENCES notes(id),
+    key TEXT NOT NULL,
+    -- Position in the list for list values, NULL for single values
+    position INTEGER,
+    value TEXT,
+    type TEXT NOT NULL
+);
+CREATE TABLE inline_tags (
+    note_id INTEGER NOT NULL REFERENCES notes(id),
+    tag TEXT NOT NULL
+);
+CREATE TABLE links (
+    note_id INTEGER NOT NULL REFERENCES notes(id),
+    target TEXT NOT NULL,
+    -- NULL when the link doesn't resolve to a note in the vault
+    target_note_id INTEGER REFERENCES notes(id)
+);
+CREATE INDEX frontmatter_key ON frontmatter(key, value);
+CREATE INDEX links_target ON links(target_note_id);
+"#;
+
+/// Number of rows written by an export
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ExportSummary {
+    pub notes: usize,
+    pub values: usize,
+    pub links: usize,
+}
+
+/// Write notes, their frontmatter values, inline tags and links to a new SQLite database.
+/// An existing file at `path` is replaced.
+pub fn export_sqlite(notes: &[&Note], all_notes: &[Note], path: &Path) -> Result<ExportSummary> {
+    if path.is_dir() {
+        return Err(anyhow!("Output path is a directory: {}", path.display()));
+    }
+    if path.exists() {
+        fs::remove_file(path)
+            .with_context(|| format!("Failed to replace existing file: {}", path.display()))?;
+    }
+
+    let mut conn = Connection::open(path)
+        .with_context(|| format!("Failed to create database: {}", path.display()))?;
+    conn.execute_batch(SCHEMA)?;
+
+    let tx = conn.transaction()?;
+    let mut summary = ExportSummary::default();
+    {
+        let mut insert_note =
+            tx.prepare("INSERT INTO notes (id, path, title) VALUES (?1, ?2, ?3)")?;
+        let mut insert_value = tx.prepare(
+            "INSERT INTO frontmatter (note_id, key, position, value, type) VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        let mut insert_tag =
+            tx.prepare("INSERT INTO inline_tags (note_id, tag) VALUES (?1, ?2)")?;
+        let mut insert_link =
+            tx.prepare("INSERT INTO links (note_id, target, target_note_id) VALUES (?1, ?2, ?3)")?;
+
+        let ids: HashMap<&str, i64> = notes
+            .iter()
+            .zip(1..)
+            .map(|(note, id)| (note.path.as_str(), id))
+            .collect();
+        // Resolve against the whole vault so filtered exports keep correct link targets
+        let index = LinkIndex::new(all_notes);
+
+        // All notes first, links may point at notes later in the list
+        for note in notes {
+            insert_note.execute(params![ids[note.path.as_str()], note.path, note.title])?;
+            summary.notes += 1;
+        }
+
+        for note in notes {
+            let id = ids[note.path.as_str()];
+            let mut keys: Vec<&String> = note.frontmatter.keys().collect();
+            keys.sort();
+            for key in keys {
+                let value = &note.frontmatter[key];
+                if let Yaml::Array(items) = value {
+                    for (position, item) in items.iter().enumerate() {
+                        insert_value.execute(params![
+                            id,
+                            key,
+                            i64::try_from(position)?,
+                            sql_value(item),
+                            yaml_type_name(item)
+                        ])?;
+                        summary.values += 1;
+                    }
+                } else {
+                    insert_value.execute(params![
+                        id,
+                        key,
+                        None::<i64>,
+                        sql_value(value),
+                        yaml_type_name(value)
+                    ])?;
+                    summary.values += 1;
+                }
+            }
+
+            for tag in &note.inline_tags {
+                insert_tag.execute(params![id, tag])?;
+            }
+
+            for (target, resolved) in index.outgoing(note) {
+                let target_id = resolved.and_then(|n| ids.get(n.path.as_str()).copied());
+                insert_link.execute(params![id, target, target_id])?;
+                summary.links += 1;
+            }
+        }
+    }
+    tx.commit()?;
+
+    Ok(summary)
+}
+
+fn sql_value(value: &Yaml) -> Option<String> {
+    match value {
+        Yaml::Null => None,
+        Yaml::Hash(_) | Yaml::Array(_) => Some(yaml_to_json_value(value).to_string()),
+        other => Some(yaml_to_string(other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_export_sqlite() {
+        let mut fm = HashMap::new();
+        fm.insert("status".to_string(), Yaml::String("open".to_string()));
+        fm.insert(
+            "tags".to_string(),
+            Yaml::Array(vec![
+                Yaml::String("work".to_string()),
+                Yaml::String("urgent".to_string()),
+            ]),
+        );
+        let mut a = Note::new("vault/a.md".to_string(), fm);
+        a.links = vec!["b".to_string(), "missing".to_string()];
+        let b = Note::new("vault/b.md".to_string(), HashMap::new());
+        let notes = vec![a, b];
+        let refs: Vec<&Note> = notes.iter().collect();
+
+        let temp_dir = TempDir::new().unwrap();
+        let db = temp_dir.path().join("vault.db");
+        fs::write(&db, "stale").unwrap();
+        let summary = export_sqlite(&refs, &notes, &db).unwrap();
+        assert_eq!(
+            summary,
+            ExportSummary {
+                notes: 2,
+                values: 3,
+                links: 2
+            }
+        );
+
+        let conn = Connection::open(&db).unwrap();
+        let tag: String = conn
+            .query_row(
+                "SELECT value FROM frontmatter WHERE key = 'tags' AND position = 1",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(tag, "urgent");
+        let resolved: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM links l JOIN notes n ON n.id = l.target_note_id WHERE n.path = 'vault/b.md'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(resolved, 1);
+    }
+}
@@ -1,52 +1,713 @@
-use crate::frontmatter::Note;
-use crate::yaml_compat::{collect_yaml_strings, yaml_to_string};
-use std::collections::HashMap;
+use crate::collation::{compare_collated, Collation, LANG_FIELD};
+use crate::frontmatter::{normalize_tags, tag_matches, Note};
+use crate::matcher::ValueMatcher;
+use crate::yaml_compat::{
+    collect_yaml_strings, trim_block, yaml_nested_fields, yaml_to_string, MatchMode,
+};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime};
+use regex::{Regex, RegexBuilder};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
 use yaml_rust2::Yaml;
 
+/// How a filter compares a field with its value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterOp {
+    /// `field=value`: the field contains the value (`field==value` for the whole value,
+    /// `field~=value` for a substring regardless of `--exact`)
+    Contains,
+    /// `field!=value`: the field doesn't contain the value (or is missing)
+    NotContains,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// `--has field`: the field is set to a non-empty value
+    Exists,
+    /// `--missing field`: the field is absent or empty
+    Missing,
+}
+
+impl FilterOp {
+    /// Whether the ordering of a field value against the filter value passes, only
+    /// meaningful for the comparison operators
+    fn accepts(self, ordering: Ordering) -> bool {
+        match self {
+            Self::Contains => ordering == Ordering::Equal,
+            Self::NotContains => ordering != Ordering::Equal,
+            Self::Exists | Self::Missing => false,
+            Self::Lt => ordering == Ordering::Less,
+            Self::Le => ordering != Ordering::Greater,
+            Self::Gt => ordering == Ordering::Greater,
+            Self::Ge => ordering != Ordering::Less,
+        }
+    }
+}
+
+/// A single filter like `status=open` or `file.mtime>=2024-06-01`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldFilter {
+    pub field: String,
+    pub op: FilterOp,
+    pub value: String,
+    /// Match mode forced by the operator (`==`, `~=`), otherwise the one of the criteria
+    pub mode: Option<MatchMode>,
+}
+
+impl FieldFilter {
+    pub fn parse(s: &str) -> Result<Self> {
+        const OPERATORS: [(&str, FilterOp, Option<MatchMode>); 8] = [
+            ("<=", FilterOp::Le, None),
+            (">=", FilterOp::Ge, None),
+            ("!=", FilterOp::NotContains, None),
+            ("==", FilterOp::Contains, Some(MatchMode::Exact)),
+            ("~=", FilterOp::Contains, Some(MatchMode::Substring)),
+            ("=", FilterOp::Contains, None),
+            ("<", FilterOp::Lt, None),
+            (">", FilterOp::Gt, None),
+        ];
+
+        // The first operator in the string wins, longer ones first at the same position
+        let (index, operator, op, mode) = OPERATORS
+            .iter()
+            .filter_map(|(operator, op, mode)| {
+                s.find(operator)
+                    .map(|index| (index, *operator, *op, *mode))
+            })
+            .min_by_key(|(index, operator, _, _)| (*index, std::cmp::Reverse(operator.len())))
+            .ok_or_else(|| {
+                anyhow!(
+                    "Invalid filter format: '{}'. Use field=value, field==value, field~=value, field!=value, or <, <=, >, >= to compare",
+                    s
+                )
+            })?;
+
+        Ok(Self {
+            field: s[..index].to_string(),
+            op,
+            value: s[index + operator.len()..].to_string(),
+            mode,
+        })
+    }
+
+    /// Match notes where the field is set to a non-empty value
+    pub fn exists(field: &str) -> Self {
+        Self {
+            field: field.to_string(),
+            op: FilterOp::Exists,
+            value: String::new(),
+            mode: None,
+        }
+    }
+
+    /// Match notes where the field is absent or empty
+    pub fn missing(field: &str) -> Self {
+        Self {
+            field: field.to_string(),
+            op: FilterOp::Missing,
+            value: String::new(),
+            mode: None,
+        }
+    }
+
+    /// Whether a field value satisfies a comparison filter. Dates are compared as dates
+    /// (by day when the filter has no time), numbers as numbers and anything else as text.
+    fn compares(&self, candidate: &str) -> bool {
+        let ordering = match (parse_date(candidate), parse_date(&self.value)) {
+            (Some(a), Some(b)) if is_date_only(&self.value) => a.date().cmp(&b.date()),
+            (Some(a), Some(b)) => a.cmp(&b),
+            _ => match (candidate.parse::<f64>(), self.value.parse::<f64>()) {
+                (Ok(a), Ok(b)) => a.total_cmp(&b),
+                _ => candidate.cmp(self.value.as_str()),
+            },
+        };
+        self.op.accepts(ordering)
+    }
+}
+
+/// The filter as `FieldFilter::parse` reads it. `--has` and `--missing` filters have no
+/// such form and show as their field.
+impl fmt::Display for FieldFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let operator = match (self.op, self.mode) {
+            (FilterOp::Contains, None) => "=",
+            (FilterOp::Contains, Some(MatchMode::Exact)) => "==",
+            (FilterOp::Contains, Some(MatchMode::Substring)) => "~=",
+            (FilterOp::NotContains, _) => "!=",
+            (FilterOp::Lt, _) => "<",
+            (FilterOp::Le, _) => "<=",
+            (FilterOp::Gt, _) => ">",
+            (FilterOp::Ge, _) => ">=",
+            (FilterOp::Exists | FilterOp::Missing, _) => return write!(f, "{}", self.field),
+        };
+        write!(f, "{}{}{}", self.field, operator, self.value)
+    }
+}
+
+impl From<(String, String)> for FieldFilter {
+    fn from((field, value): (String, String)) -> Self {
+        Self {
+            field,
+            op: FilterOp::Contains,
+            value,
+            mode: None,
+        }
+    }
+}
+
+/// Parse dates like `2024-06-01`, `2024-06-01T10:30:00` or RFC 3339 timestamps
+pub fn parse_date(s: &str) -> Option<NaiveDateTime> {
+    let s = s.trim();
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return date.and_hms_opt(0, 0, 0);
+    }
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(s) {
+        return Some(datetime.with_timezone(&Local).naive_local());
+    }
+    [
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(s, format).ok())
+}
+
+fn is_date_only(s: &str) -> bool {
+    NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d").is_ok()
+}
+
+/// Order two field values: as dates when both are dates, as numbers when both are
+/// numbers, as text otherwise
+pub fn compare_values(a: &str, b: &str) -> Ordering {
+    compare_sort_values((a, None), (b, None))
+}
+
+/// Like [`compare_values`], comparing text by the collation keys when there are any
+fn compare_sort_values(
+    (a, key_a): (&str, Option<&str>),
+    (b, key_b): (&str, Option<&str>),
+) -> Ordering {
+    match (parse_date(a), parse_date(b)) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        _ => match (a.parse::<f64>(), b.parse::<f64>()) {
+            (Ok(a), Ok(b)) => a.total_cmp(&b),
+            _ => match (key_a, key_b) {
+                (Some(key_a), Some(key_b)) => compare_collated(a, key_a, b, key_b),
+                _ => a.cmp(b),
+            },
+        },
+    }
+}
+
+/// Sort notes by a field, notes without it last
+pub fn sort_notes(notes: &mut [&Note], field: &str, descending: bool, case_sensitive: bool) {
+    sort_notes_collated(
+        notes,
+        field,
+        descending,
+        case_sensitive,
+        &Collation::default(),
+    );
+}
+
+/// A note with the value it is sorted by and the collation key of that value
+type SortEntry<'a> = (Option<(String, Option<String>)>, &'a Note);
+
+/// Sort notes by a field, ordering text by the rules of a language
+pub fn sort_notes_collated<'a>(
+    notes: &mut [&'a Note],
+    field: &str,
+    descending: bool,
+    case_sensitive: bool,
+    collation: &Collation,
+) {
+    let sort_value = |note: &Note| {
+        let value = if case_sensitive {
+            note.get_frontmatter_value(field)
+        } else {
+            note.get_frontmatter_value_case_insensitive(field)
+        };
+        let value = value.and_then(|v| collect_yaml_strings(v).into_iter().next())?;
+        let lang = note
+            .get_frontmatter_value_case_insensitive(LANG_FIELD)
+            .map(yaml_to_string);
+        let key = collation.sort_key(&value, lang.as_deref());
+        Some((value, key))
+    };
+
+    let mut keyed: Vec<SortEntry<'a>> =
+        notes.iter().map(|note| (sort_value(note), *note)).collect();
+    keyed.sort_by(|(a, _), (b, _)| match (a, b) {
+        (Some((a, key_a)), Some((b, key_b))) => {
+            let (a, b) = (
+                (a.as_str(), key_a.as_deref()),
+                (b.as_str(), key_b.as_deref()),
+            );
+            if descending {
+                compare_sort_values(b, a)
+            } else {
+                compare_sort_values(a, b)
+            }
+        }
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    });
+    for (slot, (_, note)) in notes.iter_mut().zip(keyed) {
+        *slot = note;
+    }
+}
+
+/// Text or a regular expression to find in note bodies
+#[derive(Debug, Clone)]
+pub struct ContentQuery(Regex);
+
+impl ContentQuery {
+    pub fn new(query: &str, is_regex: bool, case_sensitive: bool) -> Result<Self> {
+        let pattern = if is_regex {
+            query.to_string()
+        } else {
+            regex::escape(query)
+        };
+        RegexBuilder::new(&pattern)
+            .case_insensitive(!case_sensitive)
+            .build()
+            .map(Self)
+            .map_err(|e| anyhow!("Invalid content pattern '{}': {}", query, e))
+    }
+
+    pub fn matches(&self, body: &str) -> bool {
+        self.0.is_match(body)
+    }
+}
+
 pub struct FilterCriteria {
-    filters: Vec<(String, String)>,
+    filters: Vec<FieldFilter>,
     case_sensitive: bool,
+    match_mode: MatchMode,
+    include_inline_tags: bool,
+    content: Option<ContentQuery>,
+    /// Custom comparisons by lowercase field name
+    matchers: HashMap<String, Arc<dyn ValueMatcher>>,
 }
 
 impl FilterCriteria {
-    pub fn new(filters: Vec<(String, String)>) -> Self {
+    pub fn new<F: Into<FieldFilter>>(filters: Vec<F>) -> Self {
         Self {
-            filters,
+            filters: filters.into_iter().map(Into::into).collect(),
             case_sensitive: true,
+            match_mode: MatchMode::default(),
+            include_inline_tags: false,
+            content: None,
+            matchers: HashMap::new(),
         }
     }
 
-    pub fn new_case_insensitive(filters: Vec<(String, String)>) -> Self {
+    pub fn new_case_insensitive<F: Into<FieldFilter>>(filters: Vec<F>) -> Self {
         Self {
-            filters,
+            filters: filters.into_iter().map(Into::into).collect(),
             case_sensitive: false,
+            match_mode: MatchMode::default(),
+            include_inline_tags: false,
+            content: None,
+            matchers: HashMap::new(),
         }
     }
 
+    /// Compare `field=value` filters with whole values instead of substrings.
+    /// `field==value` and `field~=value` filters keep their own mode.
+    #[must_use]
+    pub fn with_match_mode(mut self, match_mode: MatchMode) -> Self {
+        self.match_mode = match_mode;
+        self
+    }
+
+    /// Let `tag`/`tags` filters also match inline `#tags` from the note body
+    #[must_use]
+    pub fn with_inline_tags(mut self, include_inline_tags: bool) -> Self {
+        self.include_inline_tags = include_inline_tags;
+        self
+    }
+
+    /// Compare the values of a field with a matcher, see [`ValueMatcher`]. The field name is
+    /// matched regardless of case.
+    #[must_use]
+    pub fn with_matcher(mut self, field: &str, matcher: Arc<dyn ValueMatcher>) -> Self {
+        self.matchers.insert(field.to_lowercase(), matcher);
+        self
+    }
+
+    /// Only match notes whose body contains the query. Notes must have been scanned
+    /// with their bodies.
+    #[must_use]
+    pub fn with_content(mut self, content: ContentQuery) -> Self {
+        self.content = Some(content);
+        self
+    }
+
     pub fn apply_filters<'a>(&self, notes: &'a [Note]) -> Vec<&'a Note> {
-        if self.filters.is_empty() {
+        if self.filters.is_empty() && self.content.is_none() {
             return notes.iter().collect();
         }
 
-        notes
+        notes.iter().filter(|note| self.matches(note)).collect()
+    }
+
+    /// Whether a note satisfies all filters
+    pub fn matches(&self, note: &Note) -> bool {
+        let content_matches = self.content.as_ref().is_none_or(|content| {
+            note.body
+                .as_deref()
+                .is_some_and(|body| content.matches(body))
+        });
+        content_matches
+            && self
+                .filters
+                .iter()
+                .all(|filter| self.matches_filter(note, filter))
+    }
+
+    /// Whether a note can match judging by its frontmatter alone, before its body was read:
+    /// the content query and tag filters that include inline tags are left out
+    pub fn may_match(&self, note: &Note) -> bool {
+        self.filters
+            .iter()
+            .filter(|filter| !(self.include_inline_tags && is_tag_field(&filter.field)))
+            .all(|filter| self.matches_filter(note, filter))
+    }
+
+    fn matches_filter(&self, note: &Note, filter: &FieldFilter) -> bool {
+        match filter.op {
+            FilterOp::Contains => self.contains(note, filter),
+            FilterOp::NotContains => !self.contains(note, filter),
+            FilterOp::Exists => self.has_field(note, &filter.field),
+            FilterOp::Missing => !self.has_field(note, &filter.field),
+            FilterOp::Lt | FilterOp::Le | FilterOp::Gt | FilterOp::Ge => self
+                .candidates(note, &filter.field)
+                .iter()
+                .any(|candidate| self.compares(filter, candidate)),
+        }
+    }
+
+    fn contains(&self, note: &Note, filter: &FieldFilter) -> bool {
+        let mode = self.mode_of(filter);
+        let matches = if is_tag_field(&filter.field) {
+            self.matches_tags(note, &filter.value, mode)
+        } else {
+            note.matches_filter_with_mode(&filter.field, &filter.value, self.case_sensitive, mode)
+        };
+        matches
+            || (self.matchers.contains_key(&filter.field.to_lowercase())
+                && self
+                    .candidates(note, &filter.field)
+                    .iter()
+                    .any(|candidate| self.matcher_equals(filter, candidate)))
+    }
+
+    /// Whether a value satisfies a comparison filter, with the matcher of the field when it
+    /// understands the value
+    fn compares(&self, filter: &FieldFilter, candidate: &str) -> bool {
+        self.matchers
+            .get(&filter.field.to_lowercase())
+            .and_then(|matcher| matcher.compare(candidate, &filter.value))
+            .map_or_else(
+                || filter.compares(candidate),
+                |ordering| filter.op.accepts(ordering),
+            )
+    }
+
+    /// Whether the matcher of the field takes a value for the one of the filter
+    fn matcher_equals(&self, filter: &FieldFilter, candidate: &str) -> bool {
+        self.matchers
+            .get(&filter.field.to_lowercase())
+            .and_then(|matcher| matcher.compare(candidate, &filter.value))
+            == Some(Ordering::Equal)
+    }
+
+    fn mode_of(&self, filter: &FieldFilter) -> MatchMode {
+        filter.mode.unwrap_or(self.match_mode)
+    }
+
+    fn has_field(&self, note: &Note, field: &str) -> bool {
+        self.candidates(note, field)
+            .iter()
+            .any(|value| !value.trim().is_empty())
+    }
+
+    pub fn filters(&self) -> &[FieldFilter] {
+        &self.filters
+    }
+
+    pub fn is_case_sensitive(&self) -> bool {
+        self.case_sensitive
+    }
+
+    /// The values of a note that satisfied each filter, as `(field, values)` pairs.
+    /// Used to show why a note matched.
+    pub fn matched_values(&self, note: &Note) -> Vec<(String, Vec<String>)> {
+        self.filters
+            .iter()
+            .map(|filter| {
+                let matched = self
+                    .candidates(note, &filter.field)
+                    .into_iter()
+                    .filter(|candidate| match filter.op {
+                        FilterOp::Contains | FilterOp::NotContains => {
+                            let mode = self.mode_of(filter);
+                            let matches = if is_tag_field(&filter.field) {
+                                tag_matches(candidate, &filter.value, mode, self.case_sensitive)
+                            } else {
+                                mode.matches(candidate, &filter.value, self.case_sensitive)
+                            } || self.matcher_equals(filter, candidate);
+                            matches == (filter.op == FilterOp::Contains)
+                        }
+                        FilterOp::Exists => !candidate.trim().is_empty(),
+                        FilterOp::Missing => false,
+                        FilterOp::Lt | FilterOp::Le | FilterOp::Gt | FilterOp::Ge => {
+                            self.compares(filter, candidate)
+                        }
+                    })
+                    .collect();
+                (filter.field.clone(), matched)
+            })
+            .collect()
+    }
+
+    /// The values of a field as strings, list fields give one value per item. `tag` and
+    /// `tags` give the tags of both fields.
+    fn candidates(&self, note: &Note, field: &str) -> Vec<String> {
+        if is_tag_field(field) {
+            self.tags(note)
+        } else {
+            note.field(field, self.case_sensitive)
+                .map(collect_yaml_strings)
+                .unwrap_or_default()
+        }
+    }
+
+    /// Values of a field starting with `prefix` and the number of notes holding each, most
+    /// used first, for autocompletion in editors. A `#` before a tag is ignored.
+    pub fn completions(&self, notes: &[&Note], field: &str, prefix: &str) -> Vec<(String, usize)> {
+        let prefix = if is_tag_field(field) {
+            prefix.trim_start_matches('#')
+        } else {
+            prefix
+        };
+        let prefix_lower = prefix.to_lowercase();
+
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for note in notes {
+            let mut values = self.candidates(note, field);
+            values.sort();
+            values.dedup();
+            for value in values {
+                let matches = if self.case_sensitive {
+                    value.starts_with(prefix)
+                } else {
+                    value.to_lowercase().starts_with(&prefix_lower)
+                };
+                if matches {
+                    *counts.entry(value).or_insert(0) += 1;
+                }
+            }
+        }
+
+        let mut completions: Vec<(String, usize)> = counts.into_iter().collect();
+        completions.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        completions
+    }
+
+    /// The tags `tag`/`tags` filters see. Case-sensitive filters only read the fields named
+    /// exactly `tag` and `tags`, unless inline tags are merged in.
+    fn tags(&self, note: &Note) -> Vec<String> {
+        if !self.case_sensitive || self.include_inline_tags {
+            return note.tags(self.include_inline_tags);
+        }
+        let mut tags: Vec<String> = Vec::new();
+        for tag in ["tags", "tag"]
+            .iter()
+            .filter_map(|key| note.get_frontmatter_value(key))
+            .flat_map(normalize_tags)
+        {
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        tags
+    }
+
+    fn matches_tags(&self, note: &Note, value: &str, mode: MatchMode) -> bool {
+        self.tags(note)
             .iter()
-            .filter(|note| self.matches_all_filters(note))
+            .any(|tag| tag_matches(tag, value, mode, self.case_sensitive))
+    }
+}
+
+/// Whether a field holds tags, which also match the inline `#tags` of the body
+pub fn is_tag_field(key: &str) -> bool {
+    key.eq_ignore_ascii_case("tags") || key.eq_ignore_ascii_case("tag")
+}
+
+/// Parse the output of a previous run: one path per line, NDJSON objects or a JSON array
+/// of objects with a `path` field
+pub fn parse_path_list(input: &str) -> Result<Vec<String>> {
+    let trimmed = input.trim();
+    if trimmed.starts_with('[') {
+        let items: Vec<serde_json::Value> =
+            serde_json::from_str(trimmed).context("Invalid JSON input")?;
+        return items.iter().map(json_path).collect();
+    }
+
+    trimmed
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            if line.starts_with('{') {
+                let item: serde_json::Value =
+                    serde_json::from_str(line).context("Invalid NDJSON line")?;
+                json_path(&item)
+            } else {
+                Ok(line.to_string())
+            }
+        })
+        .collect()
+}
+
+fn json_path(item: &serde_json::Value) -> Result<String> {
+    item.get("path")
+        .and_then(serde_json::Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("JSON input without a \"path\" field: {}", item))
+}
+
+/// The path a file resolves to, or the path without `./` parts when it doesn't exist
+fn canonical_path(path: &str) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| {
+        Path::new(path)
+            .components()
+            .filter(|c| !matches!(c, Component::CurDir))
             .collect()
+    })
+}
+
+/// A named set of filters from the config, reported on every note with `--annotate`
+pub struct Rule {
+    pub name: String,
+    pub criteria: FilterCriteria,
+}
+
+/// Names of the rules a note matches, in the order of the rules
+pub fn matching_rules(note: &Note, rules: &[Rule]) -> Vec<String> {
+    rules
+        .iter()
+        .filter(|rule| rule.criteria.matches(note))
+        .map(|rule| rule.name.clone())
+        .collect()
+}
+
+/// Record on every note which of the rules it matches
+pub fn annotate_rules(notes: &mut [Note], rules: &[Rule]) {
+    for note in notes {
+        note.rules = Some(matching_rules(note, rules));
+    }
+}
+
+/// Keep only the notes whose file is in `paths`, comparing canonical paths
+pub fn restrict_to_paths(notes: Vec<Note>, paths: &[String]) -> Vec<Note> {
+    let wanted: HashSet<PathBuf> = paths.iter().map(|p| canonical_path(p)).collect();
+
+    notes
+        .into_iter()
+        .filter(|note| wanted.contains(&canonical_path(&note.path)))
+        .collect()
+}
+
+/// Notes that entered and left a result set since a previous run, sorted by path
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ResultDiff {
+    /// Matching notes that weren't in the previous results
+    pub entered: Vec<String>,
+    /// Paths in the previous results that no longer match or no longer exist
+    pub left: Vec<String>,
+}
+
+/// Compare the current matches to the paths of a previous run, comparing canonical paths
+pub fn diff_results(previous: &[String], current: &[&Note]) -> ResultDiff {
+    let before: HashSet<PathBuf> = previous.iter().map(|p| canonical_path(p)).collect();
+    let now: HashSet<PathBuf> = current
+        .iter()
+        .map(|note| canonical_path(&note.path))
+        .collect();
+
+    let mut entered: Vec<String> = current
+        .iter()
+        .filter(|note| !before.contains(&canonical_path(&note.path)))
+        .map(|note| note.path.clone())
+        .collect();
+    let mut left: Vec<String> = previous
+        .iter()
+        .filter(|path| !now.contains(&canonical_path(path)))
+        .cloned()
+        .collect();
+    entered.sort();
+    entered.dedup();
+    left.sort();
+    left.dedup();
+    ResultDiff { entered, left }
+}
+
+/// Find notes by path (or path suffix), title or file name, ignoring case for names
+pub fn find_notes<'a>(notes: &'a [Note], query: &str) -> Vec<&'a Note> {
+    let exact: Vec<&Note> = notes
+        .iter()
+        .filter(|note| note.path == query || Path::new(&note.path) == Path::new(query))
+        .collect();
+    if !exact.is_empty() {
+        return exact;
     }
 
-    fn matches_all_filters(&self, note: &Note) -> bool {
-        self.filters.iter().all(|(key, value)| {
-            note.matches_filter_with_case_sensitivity(key, value, self.case_sensitive)
+    let query_lower = query.to_lowercase();
+    let query_stem = query_lower.strip_suffix(".md").unwrap_or(&query_lower);
+    notes
+        .iter()
+        .filter(|note| {
+            let path_lower = note.path.to_lowercase();
+            let stem_matches = Path::new(&note.path)
+                .file_stem()
+                .is_some_and(|stem| stem.to_string_lossy().to_lowercase() == query_stem);
+            let title_matches = note
+                .title
+                .as_ref()
+                .is_some_and(|title| title.to_lowercase() == query_lower);
+            path_lower.ends_with(&format!("/{}", query_lower)) || stem_matches || title_matches
         })
+        .collect()
+}
+
+/// Every field of a note with its value, including the dotted paths inside map fields
+fn note_fields(note: &Note) -> Vec<(String, &Yaml)> {
+    let mut fields = Vec::new();
+    for (key, value) in &note.frontmatter {
+        fields.push((key.clone(), value));
+        fields.extend(yaml_nested_fields(key, value));
     }
+    fields
 }
 
 pub fn collect_all_fields(notes: &[Note]) -> Vec<String> {
     let mut all_fields = std::collections::HashSet::new();
 
     for note in notes {
-        for key in note.frontmatter.keys() {
-            all_fields.insert(key.clone());
+        for (key, _) in note_fields(note) {
+            all_fields.insert(key);
         }
     }
 
@@ -55,6 +716,66 @@ pub fn collect_all_fields(notes: &[Note]) -> Vec<String> {
     fields
 }
 
+/// A line of the `fields` tree: either a namespace like `review` or a field
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldTreeRow {
+    pub depth: usize,
+    /// Last segment of the name, shown indented by `depth`
+    pub label: String,
+    /// Full field name, or the namespace prefix for namespaces
+    pub name: String,
+    pub is_namespace: bool,
+}
+
+/// Arrange sorted field names into a tree by splitting them at `separator`, so
+/// `review.date` and `review.status` appear below a `review` namespace
+pub fn field_tree(fields: &[String], separator: &str) -> Vec<FieldTreeRow> {
+    let mut split: Vec<Vec<&str>> = fields
+        .iter()
+        .map(|f| f.split(separator).collect())
+        .collect();
+    split.sort();
+
+    let mut rows = Vec::new();
+    // A map field like `project` already heads the rows of its nested fields
+    let mut namespaces: HashSet<Vec<&str>> = HashSet::new();
+    for segments in split {
+        let (label, parents) = segments.split_last().unwrap_or((&"", &[]));
+        for depth in 0..parents.len() {
+            let prefix = parents[..=depth].to_vec();
+            if namespaces.insert(prefix.clone()) {
+                rows.push(FieldTreeRow {
+                    depth,
+                    label: parents[depth].to_string(),
+                    name: prefix.join(separator),
+                    is_namespace: true,
+                });
+            }
+        }
+        rows.push(FieldTreeRow {
+            depth: parents.len(),
+            label: (*label).to_string(),
+            name: segments.join(separator),
+            is_namespace: false,
+        });
+        namespaces.insert(segments);
+    }
+    rows
+}
+
+/// Number of notes with at least one field in a namespace
+pub fn count_notes_in_namespace(notes: &[Note], namespace: &str, separator: &str) -> usize {
+    let prefix = format!("{}{}", namespace, separator);
+    notes
+        .iter()
+        .filter(|note| {
+            note_fields(note)
+                .iter()
+                .any(|(key, _)| key.starts_with(&prefix))
+        })
+        .count()
+}
+
 pub fn collect_field_values(notes: &[Note], field: &str) -> Vec<String> {
     let mut all_values = std::collections::HashSet::new();
 
@@ -81,13 +802,9 @@ pub fn collect_field_values_case_insensitive(notes: &[Note], field: &str) -> (Ve
         if let Some(value) = note.get_frontmatter_value_case_insensitive(field) {
             if !found_field {
                 // Find the actual field name (with original casing) from the first match
-                let field_lower = field.to_lowercase();
-                for key in note.frontmatter.keys() {
-                    if key.to_lowercase() == field_lower {
-                        actual_field_name = key.clone();
-                        found_field = true;
-                        break;
-                    }
+                if let Some(name) = note.field_name(field, false) {
+                    actual_field_name = name.to_string();
+                    found_field = true;
                 }
             }
 
@@ -103,12 +820,268 @@ pub fn collect_field_values_case_insensitive(notes: &[Note], field: &str) -> (Ve
     (values, actual_field_name)
 }
 
-pub fn get_field_statistics(notes: &[Note]) -> HashMap<String, FieldStats> {
+/// The fields to list values for, with fields that hold maps replaced by their nested fields.
+///
+/// `project` becomes `project.name` and `project.phase`, for example. Fields that hold maps
+/// in some notes and other values in others are kept as well. Returns the fields and the
+/// names of the map fields that were expanded.
+pub fn expand_map_fields<S: std::hash::BuildHasher>(
+    fields: &[String],
+    stats: &HashMap<String, FieldStats, S>,
+    case_sensitive: bool,
+) -> (Vec<String>, Vec<String>) {
+    let holds_maps = |field: &FieldStats| field.kinds.contains("map");
+    let mut expanded: Vec<String> = Vec::new();
+    let mut maps: Vec<String> = Vec::new();
+    for field in fields {
+        let found = stats.iter().find(|(name, _)| {
+            if case_sensitive {
+                *name == field
+            } else {
+                name.to_lowercase() == field.to_lowercase()
+            }
+        });
+        let Some((name, field_stats)) = found.filter(|(_, field_stats)| holds_maps(field_stats))
+        else {
+            if !expanded.contains(field) {
+                expanded.push(field.clone());
+            }
+            continue;
+        };
+        if field_stats.kinds.len() > 1 && !expanded.contains(field) {
+            expanded.push(field.clone());
+        }
+        let prefix = format!("{}.", name);
+        let mut nested: Vec<&String> = stats
+            .iter()
+            .filter(|(nested, nested_stats)| {
+                nested.starts_with(&prefix)
+                    && (!holds_maps(nested_stats) || nested_stats.kinds.len() > 1)
+            })
+            .map(|(nested, _)| nested)
+            .collect();
+        nested.sort();
+        for nested in nested {
+            if !expanded.contains(nested) {
+                expanded.push(nested.clone());
+            }
+        }
+        maps.push(name.clone());
+    }
+    (expanded, maps)
+}
+
+/// Folder label for notes that live directly in the vault root
+pub const ROOT_FOLDER: &str = "(root)";
+
+/// The first path component of a note below the vault root, or `(root)`
+pub fn top_level_folder(note_path: &str, vault_root: &Path) -> String {
+    let path = Path::new(note_path);
+    let relative = path.strip_prefix(vault_root).unwrap_or(path);
+    let mut components = relative.components();
+    match (components.next(), components.next()) {
+        (Some(first), Some(_)) => first.as_os_str().to_string_lossy().to_string(),
+        _ => ROOT_FOLDER.to_string(),
+    }
+}
+
+/// Value counts of a field by top-level folder
+pub type FolderValueCounts = BTreeMap<String, BTreeMap<String, usize>>;
+
+/// Count the values of a field separately for each top-level folder of the vault
+pub fn collect_field_values_by_folder(
+    notes: &[&Note],
+    field: &str,
+    vault_root: &Path,
+    case_sensitive: bool,
+) -> FolderValueCounts {
+    let mut folders = FolderValueCounts::new();
+
+    for note in notes {
+        let value = if case_sensitive {
+            note.get_frontmatter_value(field)
+        } else {
+            note.get_frontmatter_value_case_insensitive(field)
+        };
+        if let Some(value) = value {
+            let counts = folders
+                .entry(top_level_folder(&note.path, vault_root))
+                .or_default();
+            for s in collect_yaml_strings(value) {
+                *counts.entry(s).or_insert(0) += 1;
+            }
+        }
+    }
+
+    folders
+}
+
+/// Where the values of a field come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ValueSource {
+    /// The YAML frontmatter
+    Frontmatter,
+    /// The note body: `#tags`, listed under the `tags` and `tag` fields
+    Inline,
+    /// Computed by aktenfux, like `file.*` and the derived `title`, or joined with `--join`
+    Virtual,
+}
+
+impl ValueSource {
+    pub const ALL: [Self; 3] = [Self::Frontmatter, Self::Inline, Self::Virtual];
+
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "frontmatter" => Ok(Self::Frontmatter),
+            "inline" => Ok(Self::Inline),
+            "virtual" => Ok(Self::Virtual),
+            _ => Err(anyhow!(
+                "Unknown value source: '{}'. Use frontmatter, inline or virtual",
+                s
+            )),
+        }
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Frontmatter => "frontmatter",
+            Self::Inline => "inline",
+            Self::Virtual => "virtual",
+        }
+    }
+}
+
+/// Count the values of a field separately for each source they come from, most used first.
+/// Only the given sources are counted.
+pub fn collect_field_values_by_source(
+    notes: &[&Note],
+    field: &str,
+    case_sensitive: bool,
+    sources: &[ValueSource],
+) -> Vec<(String, ValueSource, usize)> {
+    let mut counts: HashMap<(String, ValueSource), usize> = HashMap::new();
+    for note in notes {
+        if let Some(value) = note.field(field, case_sensitive) {
+            // Values nested in a map field have no name of their own, they are in the
+            // frontmatter like the map
+            let source = match note.field_name(field, case_sensitive) {
+                Some(name) if !note.frontmatter.contains_key(name) => ValueSource::Virtual,
+                _ => ValueSource::Frontmatter,
+            };
+            if sources.contains(&source) {
+                for s in collect_yaml_strings(value) {
+                    *counts.entry((s, source)).or_insert(0) += 1;
+                }
+            }
+        }
+        if is_tag_field(field) && sources.contains(&ValueSource::Inline) {
+            for tag in &note.inline_tags {
+                *counts
+                    .entry((tag.clone(), ValueSource::Inline))
+                    .or_insert(0) += 1;
+            }
+        }
+    }
+
+    let mut counts: Vec<(String, ValueSource, usize)> = counts
+        .into_iter()
+        .map(|((value, source), count)| (value, source, count))
+        .collect();
+    counts.sort_by(|a, b| {
+        b.2.cmp(&a.2)
+            .then_with(|| a.0.cmp(&b.0))
+            .then_with(|| a.1.cmp(&b.1))
+    });
+    counts
+}
+
+/// Label used for notes that lack a grouped field
+pub const MISSING_GROUP: &str = "(none)";
+
+/// Group notes by the values of one or more fields.
+///
+/// Notes with list values appear in one group per list item, so group counts can add
+/// up to more than the number of notes.
+pub fn group_notes<'a>(
+    notes: &[&'a Note],
+    fields: &[String],
+    case_sensitive: bool,
+) -> BTreeMap<Vec<String>, Vec<&'a Note>> {
+    let mut groups: BTreeMap<Vec<String>, Vec<&'a Note>> = BTreeMap::new();
+
+    for note in notes {
+        let mut keys: Vec<Vec<String>> = vec![Vec::new()];
+        for field in fields {
+            let value = if case_sensitive {
+                note.get_frontmatter_value(field)
+            } else {
+                note.get_frontmatter_value_case_insensitive(field)
+            };
+            let mut values = value.map(collect_yaml_strings).unwrap_or_default();
+            if values.is_empty() {
+                values.push(MISSING_GROUP.to_string());
+            }
+            values.sort();
+            values.dedup();
+
+            keys = keys
+                .into_iter()
+                .flat_map(|prefix| {
+                    values.iter().map(move |v| {
+                        let mut key = prefix.clone();
+                        key.push(v.clone());
+                        key
+                    })
+                })
+                .collect();
+        }
+
+        for key in keys {
+            groups.entry(key).or_default().push(note);
+        }
+    }
+
+    groups
+}
+
+/// Values of `field` shared by more than one note, with the notes sorted by path. Notes
+/// with list values are compared item by item, notes without the field are ignored.
+pub fn find_duplicates<'a>(
+    notes: &[&'a Note],
+    field: &str,
+    case_sensitive: bool,
+) -> BTreeMap<String, Vec<&'a Note>> {
+    let notes: Vec<&Note> = notes
+        .iter()
+        .copied()
+        .filter(|note| {
+            let value = if case_sensitive {
+                note.get_frontmatter_value(field)
+            } else {
+                note.get_frontmatter_value_case_insensitive(field)
+            };
+            value.is_some_and(|v| !collect_yaml_strings(v).is_empty())
+        })
+        .collect();
+
+    group_notes(&notes, &[field.to_string()], case_sensitive)
+        .into_iter()
+        .filter(|(_, notes)| notes.len() > 1)
+        .map(|(mut key, mut notes)| {
+            notes.sort_by(|a, b| a.path.cmp(&b.path));
+            (key.remove(0), notes)
+        })
+        .collect()
+}
+
+pub fn get_field_statistics<'a>(
+    notes: impl IntoIterator<Item = &'a Note>,
+) -> HashMap<String, FieldStats> {
     let mut stats = HashMap::new();
 
     for note in notes {
-        for (key, value) in &note.frontmatter {
-            let field_stats = stats.entry(key.clone()).or_insert_with(FieldStats::new);
+        for (key, value) in note_fields(note) {
+            let field_stats = stats.entry(key).or_insert_with(FieldStats::new);
             field_stats.increment(value);
         }
     }
@@ -116,11 +1089,26 @@ pub fn get_field_statistics(notes: &[Note]) -> HashMap<String, FieldStats> {
     stats
 }
 
+/// The kind of a value as reported by `fields`, `None` for empty values
+fn value_kind(value: &Yaml) -> Option<&'static str> {
+    match value {
+        Yaml::String(s) if parse_date(s).is_some() => Some("date"),
+        Yaml::Array(_) => Some("list"),
+        Yaml::Integer(_) | Yaml::Real(_) => Some("number"),
+        Yaml::Boolean(_) => Some("bool"),
+        Yaml::Hash(_) => Some("map"),
+        Yaml::Null | Yaml::BadValue => None,
+        _ => Some("string"),
+    }
+}
+
 #[derive(Debug)]
 pub struct FieldStats {
     pub total_count: usize,
     pub unique_values: std::collections::HashSet<String>,
     pub value_counts: HashMap<String, usize>,
+    /// Kinds of values seen, like `string` and `list`
+    pub kinds: BTreeSet<&'static str>,
 }
 
 impl FieldStats {
@@ -129,22 +1117,39 @@ impl FieldStats {
             total_count: 0,
             unique_values: std::collections::HashSet::new(),
             value_counts: HashMap::new(),
+            kinds: BTreeSet::new(),
+        }
+    }
+
+    /// The kind of all values of the field (string, list, number, bool, date or map),
+    /// `mixed` when they differ and `null` when the field is always empty
+    pub fn inferred_type(&self) -> &'static str {
+        let mut kinds = self.kinds.iter();
+        match (kinds.next(), kinds.next()) {
+            (None, _) => "null",
+            (Some(kind), None) => kind,
+            _ => "mixed",
         }
     }
 
     fn increment(&mut self, value: &Yaml) {
         self.total_count += 1;
+        if let Some(kind) = value_kind(value) {
+            self.kinds.insert(kind);
+        }
 
         match value {
             Yaml::String(s) => {
-                self.unique_values.insert(s.clone());
-                *self.value_counts.entry(s.clone()).or_insert(0) += 1;
+                let s = trim_block(s);
+                self.unique_values.insert(s.to_string());
+                *self.value_counts.entry(s.to_string()).or_insert(0) += 1;
             }
             Yaml::Array(arr) => {
                 for item in arr {
                     if let Yaml::String(s) = item {
-                        self.unique_values.insert(s.clone());
-                        *self.value_counts.entry(s.clone()).or_insert(0) += 1;
+                        let s = trim_block(s);
+                        self.unique_values.insert(s.to_string());
+                        *self.value_counts.entry(s.to_string()).or_insert(0) += 1;
                     }
                 }
             }
@@ -154,7 +1159,7 @@ impl FieldStats {
                 *self.value_counts.entry(s).or_insert(0) += 1;
             }
             Yaml::Real(f) => {
-                let s = f.to_string();
+                let s = f.clone();
                 self.unique_values.insert(s.clone());
                 *self.value_counts.entry(s).or_insert(0) += 1;
             }
@@ -163,6 +1168,8 @@ impl FieldStats {
                 self.unique_values.insert(s.clone());
                 *self.value_counts.entry(s).or_insert(0) += 1;
             }
+            // The values inside a map are counted for the fields nested in it
+            Yaml::Hash(_) => {}
             _ => {
                 let s = yaml_to_string(value);
                 self.unique_values.insert(s.clone());
@@ -181,6 +1188,81 @@ mod tests {
         Note::new(path.to_string(), frontmatter)
     }
 
+    #[test]
+    fn test_matched_values() {
+        let mut fm = HashMap::new();
+        fm.insert(
+            "tags".to_string(),
+            Yaml::Array(vec![
+                Yaml::String("project".to_string()),
+                Yaml::String("projection".to_string()),
+                Yaml::String("work".to_string()),
+            ]),
+        );
+        fm.insert("Status".to_string(), Yaml::String("Active".to_string()));
+        let note = create_test_note("note.md", fm);
+
+        let criteria = FilterCriteria::new_case_insensitive(vec![
+            ("tags".to_string(), "proj".to_string()),
+            ("status".to_string(), "act".to_string()),
+        ]);
+        assert_eq!(
+            criteria.matched_values(&note),
+            vec![
+                (
+                    "tags".to_string(),
+                    vec!["project".to_string(), "projection".to_string()]
+                ),
+                ("status".to_string(), vec!["Active".to_string()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tag_filters() {
+        let note = create_test_note(
+            "note.md",
+            HashMap::from([
+                (
+                    "tags".to_string(),
+                    Yaml::String("project/alpha, #work".to_string()),
+                ),
+                ("tag".to_string(), Yaml::String("review".to_string())),
+            ]),
+        );
+        let matches = |filter: &str| {
+            FilterCriteria::new(vec![FieldFilter::parse(filter).unwrap()]).matches(&note)
+        };
+        assert!(matches("tags==project"));
+        assert!(matches("tags==work"));
+        assert!(matches("tags=#review"));
+        assert!(matches("tag==project/alpha"));
+        assert!(!matches("tags==alpha"));
+        assert!(!matches("tags!=project"));
+    }
+
+    #[test]
+    fn test_may_match() {
+        let note = create_test_note(
+            "note.md",
+            HashMap::from([("status".to_string(), Yaml::String("open".to_string()))]),
+        );
+        let criteria = |filters: &[&str]| {
+            FilterCriteria::new(
+                filters
+                    .iter()
+                    .map(|filter| FieldFilter::parse(filter).unwrap())
+                    .collect(),
+            )
+            .with_inline_tags(true)
+            .with_content(ContentQuery::new("launch", false, true).unwrap())
+        };
+        // The body wasn't read, so only the frontmatter filters can rule the note out
+        assert!(criteria(&["status=open", "tags=work"]).may_match(&note));
+        assert!(!criteria(&["status=open", "tags=work"]).matches(&note));
+        assert!(!criteria(&["status=done"]).may_match(&note));
+    }
+
     #[test]
     fn test_filter_criteria() {
         let mut fm1 = HashMap::new();
@@ -308,4 +1390,619 @@ mod tests {
         assert!(values.contains(&"Work".to_string()));
         assert!(values.contains(&"Important".to_string()));
     }
+
+    #[test]
+    fn test_find_duplicates() {
+        let note = |path: &str, uid: Option<&str>| {
+            let mut fm = HashMap::new();
+            if let Some(uid) = uid {
+                fm.insert("uid".to_string(), Yaml::String(uid.to_string()));
+            }
+            create_test_note(path, fm)
+        };
+        let notes = vec![
+            note("c.md", Some("1234")),
+            note("a.md", Some("1234")),
+            note("b.md", Some("5678")),
+            note("d.md", None),
+            note("e.md", None),
+        ];
+        let note_refs: Vec<&Note> = notes.iter().collect();
+
+        let duplicates = find_duplicates(&note_refs, "uid", true);
+        assert_eq!(duplicates.len(), 1);
+        let paths: Vec<&str> = duplicates["1234"].iter().map(|n| n.path.as_str()).collect();
+        assert_eq!(paths, vec!["a.md", "c.md"]);
+        assert!(find_duplicates(&note_refs, "UID", true).is_empty());
+        assert_eq!(find_duplicates(&note_refs, "UID", false).len(), 1);
+    }
+
+    #[test]
+    fn test_group_notes() {
+        let mut fm1 = HashMap::new();
+        fm1.insert("status".to_string(), Yaml::String("active".to_string()));
+        fm1.insert(
+            "tags".to_string(),
+            Yaml::Array(vec![
+                Yaml::String("work".to_string()),
+                Yaml::String("urgent".to_string()),
+            ]),
+        );
+
+        let mut fm2 = HashMap::new();
+        fm2.insert("status".to_string(), Yaml::String("active".to_string()));
+
+        let notes = vec![
+            create_test_note("note1.md", fm1),
+            create_test_note("note2.md", fm2),
+        ];
+        let note_refs: Vec<&Note> = notes.iter().collect();
+
+        let groups = group_notes(&note_refs, &["status".to_string()], true);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[&vec!["active".to_string()]].len(), 2);
+
+        let groups = group_notes(
+            &note_refs,
+            &["status".to_string(), "tags".to_string()],
+            true,
+        );
+        assert_eq!(groups.len(), 3);
+        assert_eq!(
+            groups[&vec!["active".to_string(), MISSING_GROUP.to_string()]][0].path,
+            "note2.md"
+        );
+        assert!(groups.contains_key(&vec!["active".to_string(), "urgent".to_string()]));
+    }
+
+    #[test]
+    fn test_collect_field_values_by_folder() {
+        let mut fm = HashMap::new();
+        fm.insert("status".to_string(), Yaml::String("stalled".to_string()));
+
+        let notes = vec![
+            create_test_note("vault/projects/a/note1.md", fm.clone()),
+            create_test_note("vault/projects/note2.md", fm.clone()),
+            create_test_note("vault/note3.md", fm),
+        ];
+        let note_refs: Vec<&Note> = notes.iter().collect();
+
+        let folders =
+            collect_field_values_by_folder(&note_refs, "status", Path::new("vault"), true);
+        assert_eq!(folders.len(), 2);
+        assert_eq!(folders["projects"]["stalled"], 2);
+        assert_eq!(folders[ROOT_FOLDER]["stalled"], 1);
+    }
+
+    #[test]
+    fn test_collect_field_values_by_source() {
+        let mut fm = HashMap::new();
+        fm.insert("tags".to_string(), Yaml::String("work".to_string()));
+        let mut note1 = create_test_note("vault/note1.md", fm);
+        note1.inline_tags = vec!["work".to_string(), "meeting".to_string()];
+        let mut note2 = create_test_note("vault/note2.md", HashMap::new());
+        note2.inline_tags = vec!["meeting".to_string()];
+        let notes = vec![note1, note2];
+        let note_refs: Vec<&Note> = notes.iter().collect();
+
+        let counts = collect_field_values_by_source(&note_refs, "Tags", false, &ValueSource::ALL);
+        assert_eq!(
+            counts,
+            vec![
+                ("meeting".to_string(), ValueSource::Inline, 2),
+                ("work".to_string(), ValueSource::Frontmatter, 1),
+                ("work".to_string(), ValueSource::Inline, 1),
+            ]
+        );
+
+        // The derived title is virtual
+        let counts = collect_field_values_by_source(&note_refs, "title", true, &ValueSource::ALL);
+        assert_eq!(counts.len(), 2);
+        assert!(counts
+            .iter()
+            .all(|(_, source, _)| *source == ValueSource::Virtual));
+        assert!(
+            collect_field_values_by_source(&note_refs, "title", true, &[ValueSource::Inline])
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_inline_tag_filtering() {
+        let mut fm = HashMap::new();
+        fm.insert("tags".to_string(), Yaml::String("work".to_string()));
+        let mut note1 = create_test_note("note1.md", fm);
+        note1.inline_tags = vec!["meeting".to_string()];
+        let mut note2 = create_test_note("note2.md", HashMap::new());
+        note2.inline_tags = vec!["Meeting".to_string()];
+        let notes = vec![note1, note2];
+
+        let filters = vec![("tag".to_string(), "meeting".to_string())];
+        assert_eq!(
+            FilterCriteria::new(filters.clone())
+                .apply_filters(&notes)
+                .len(),
+            0
+        );
+
+        let criteria = FilterCriteria::new(filters.clone()).with_inline_tags(true);
+        let filtered = criteria.apply_filters(&notes);
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].path, "note1.md");
+
+        // The merged view also covers frontmatter tags, whichever tag field name is used
+        let criteria = FilterCriteria::new(vec![("tag".to_string(), "work".to_string())])
+            .with_inline_tags(true);
+        assert_eq!(criteria.apply_filters(&notes).len(), 1);
+
+        let criteria = FilterCriteria::new_case_insensitive(filters).with_inline_tags(true);
+        assert_eq!(criteria.apply_filters(&notes).len(), 2);
+    }
+
+    #[test]
+    fn test_find_notes() {
+        let mut fm = HashMap::new();
+        fm.insert(
+            "title".to_string(),
+            Yaml::String("Project Alpha".to_string()),
+        );
+        let notes = vec![
+            create_test_note("vault/projects/alpha.md", fm),
+            create_test_note("vault/daily/2024-01-01.md", HashMap::new()),
+        ];
+
+        assert_eq!(find_notes(&notes, "vault/projects/alpha.md").len(), 1);
+        assert_eq!(find_notes(&notes, "projects/alpha.md").len(), 1);
+        assert_eq!(find_notes(&notes, "project alpha").len(), 1);
+        assert_eq!(find_notes(&notes, "2024-01-01").len(), 1);
+        assert!(find_notes(&notes, "beta").is_empty());
+    }
+
+    #[test]
+    fn test_parse_path_list() {
+        let paths = parse_path_list("a.md\n\n  b/c.md\n").unwrap();
+        assert_eq!(paths, vec!["a.md", "b/c.md"]);
+
+        let ndjson = "{\"path\": \"a.md\", \"title\": \"A\"}\n{\"path\": \"b.md\"}\n";
+        assert_eq!(parse_path_list(ndjson).unwrap(), vec!["a.md", "b.md"]);
+
+        let json = "[{\"path\": \"a.md\"}, {\"path\": \"b.md\"}]";
+        assert_eq!(parse_path_list(json).unwrap(), vec!["a.md", "b.md"]);
+
+        assert!(parse_path_list("{\"title\": \"A\"}").is_err());
+    }
+
+    #[test]
+    fn test_restrict_to_paths() {
+        let notes = vec![
+            create_test_note("note1.md", HashMap::new()),
+            create_test_note("note2.md", HashMap::new()),
+        ];
+        let restricted = restrict_to_paths(notes, &["./note2.md".to_string()]);
+        assert_eq!(restricted.len(), 1);
+        assert_eq!(restricted[0].path, "note2.md");
+    }
+
+    #[test]
+    fn test_diff_results() {
+        let notes = [
+            create_test_note("b.md", HashMap::new()),
+            create_test_note("a.md", HashMap::new()),
+            create_test_note("stays.md", HashMap::new()),
+        ];
+        let current: Vec<&Note> = notes.iter().collect();
+        let previous = ["./stays.md".to_string(), "gone.md".to_string()];
+
+        let diff = diff_results(&previous, &current);
+        assert_eq!(diff.entered, vec!["a.md", "b.md"]);
+        assert_eq!(diff.left, vec!["gone.md"]);
+        assert_eq!(diff_results(&[], &[]), ResultDiff::default());
+    }
+
+    #[test]
+    fn test_parse_field_filter() {
+        let filter = FieldFilter::parse("file.mtime>=2024-06-01").unwrap();
+        assert_eq!(filter.field, "file.mtime");
+        assert_eq!(filter.op, FilterOp::Ge);
+        assert_eq!(filter.value, "2024-06-01");
+
+        let filter = FieldFilter::parse("url=https://a.b/?x<y").unwrap();
+        assert_eq!(filter.op, FilterOp::Contains);
+        assert_eq!(filter.value, "https://a.b/?x<y");
+
+        assert_eq!(FieldFilter::parse("size<10").unwrap().op, FilterOp::Lt);
+        assert!(FieldFilter::parse("status").is_err());
+
+        for text in ["a=b", "a==b", "a~=b", "a!=b", "due<=2024-06-01", "url=x<y"] {
+            assert_eq!(FieldFilter::parse(text).unwrap().to_string(), text);
+        }
+    }
+
+    #[test]
+    fn test_comparison_filters() {
+        let note_due = |path: &str, due: &str, priority: i64| {
+            let mut fm = HashMap::new();
+            fm.insert("due".to_string(), Yaml::String(due.to_string()));
+            fm.insert("priority".to_string(), Yaml::Integer(priority));
+            create_test_note(path, fm)
+        };
+        let notes = vec![
+            note_due("a.md", "2024-05-31", 9),
+            note_due("b.md", "2024-06-01T18:00:00", 12),
+            note_due("c.md", "2024-07-15", 10),
+        ];
+        let matching = |filters: &[&str]| {
+            let filters: Vec<FieldFilter> = filters
+                .iter()
+                .map(|f| FieldFilter::parse(f).unwrap())
+                .collect();
+            FilterCriteria::new(filters)
+                .apply_filters(&notes)
+                .iter()
+                .map(|n| n.path.clone())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(matching(&["due>=2024-06-01"]), vec!["b.md", "c.md"]);
+        // Date-only values compare by day, so the evening of June 1st is included
+        assert_eq!(matching(&["due<=2024-06-01"]), vec!["a.md", "b.md"]);
+        assert_eq!(
+            matching(&["due>2024-06-01", "due<2024-08-01"]),
+            vec!["c.md"]
+        );
+        assert_eq!(matching(&["priority>10"]), vec!["b.md"]);
+    }
+
+    #[test]
+    fn test_negation_and_missing_filters() {
+        let note = |path: &str, tags: &[&str], due: Option<&str>| {
+            let mut fm = HashMap::new();
+            fm.insert(
+                "tags".to_string(),
+                Yaml::Array(tags.iter().map(|t| Yaml::String(t.to_string())).collect()),
+            );
+            if let Some(due) = due {
+                fm.insert("due".to_string(), Yaml::String(due.to_string()));
+            }
+            create_test_note(path, fm)
+        };
+        let notes = vec![
+            note("a.md", &["work", "archive"], Some("2024-06-01")),
+            note("b.md", &["work"], Some("")),
+            note("c.md", &[], None),
+        ];
+        let matching = |filters: Vec<FieldFilter>| {
+            FilterCriteria::new(filters)
+                .apply_filters(&notes)
+                .iter()
+                .map(|n| n.path.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let not_archived = FieldFilter::parse("tags!=archive").unwrap();
+        assert_eq!(not_archived.op, FilterOp::NotContains);
+        assert_eq!(not_archived.value, "archive");
+        assert_eq!(matching(vec![not_archived]), vec!["b.md", "c.md"]);
+        assert_eq!(matching(vec![FieldFilter::exists("due")]), vec!["a.md"]);
+        assert_eq!(
+            matching(vec![FieldFilter::missing("due")]),
+            vec!["b.md", "c.md"]
+        );
+    }
+
+    #[test]
+    fn test_exact_and_substring_filters() {
+        let note = |path: &str, status: &str| {
+            let mut fm = HashMap::new();
+            fm.insert("status".to_string(), Yaml::String(status.to_string()));
+            create_test_note(path, fm)
+        };
+        let notes = vec![note("a.md", "active"), note("b.md", "inactive")];
+        let matching = |criteria: FilterCriteria| {
+            criteria
+                .apply_filters(&notes)
+                .iter()
+                .map(|n| n.path.clone())
+                .collect::<Vec<_>>()
+        };
+        let parse = |s: &str| FieldFilter::parse(s).unwrap();
+
+        assert_eq!(parse("status==active").mode, Some(MatchMode::Exact));
+        assert_eq!(parse("status~=active").mode, Some(MatchMode::Substring));
+        assert_eq!(parse("status=active").mode, None);
+        assert_eq!(parse("status==active").value, "active");
+
+        assert_eq!(
+            matching(FilterCriteria::new(vec![parse("status=active")])),
+            vec!["a.md", "b.md"]
+        );
+        assert_eq!(
+            matching(FilterCriteria::new(vec![parse("status==active")])),
+            vec!["a.md"]
+        );
+        let exact = |filter: &str| {
+            FilterCriteria::new_case_insensitive(vec![parse(filter)])
+                .with_match_mode(MatchMode::Exact)
+        };
+        assert_eq!(matching(exact("status=ACTIVE")), vec!["a.md"]);
+        assert_eq!(matching(exact("status!=active")), vec!["b.md"]);
+        assert_eq!(matching(exact("status~=active")), vec!["a.md", "b.md"]);
+    }
+
+    #[test]
+    fn test_sort_notes() {
+        let mut fm = HashMap::new();
+        fm.insert("priority".to_string(), Yaml::Integer(10));
+        let high = create_test_note("high.md", fm);
+        let mut fm = HashMap::new();
+        fm.insert("priority".to_string(), Yaml::Integer(9));
+        let low = create_test_note("low.md", fm);
+        let none = create_test_note("none.md", HashMap::new());
+
+        let mut notes = vec![&none, &high, &low];
+        sort_notes(&mut notes, "priority", false, true);
+        let paths: Vec<&str> = notes.iter().map(|n| n.path.as_str()).collect();
+        assert_eq!(paths, vec!["low.md", "high.md", "none.md"]);
+
+        sort_notes(&mut notes, "priority", true, true);
+        assert_eq!(notes[0].path, "high.md");
+        assert_eq!(notes[2].path, "none.md");
+
+        // Notes without a title in their frontmatter sort and filter by their file name
+        let mut fm = HashMap::new();
+        fm.insert("title".to_string(), Yaml::String("kickoff".to_string()));
+        let titled = create_test_note("meeting.md", fm);
+        let mut notes = vec![&none, &titled, &high];
+        sort_notes(&mut notes, "title", false, true);
+        let paths: Vec<&str> = notes.iter().map(|n| n.path.as_str()).collect();
+        assert_eq!(paths, vec!["high.md", "meeting.md", "none.md"]);
+        assert!(none.matches_filter("title", "none"));
+        assert!(!titled.matches_filter("title", "meeting"));
+    }
+
+    #[test]
+    fn test_field_tree() {
+        let fields: Vec<String> = ["title", "review.status", "review.date", "review.by.name"]
+            .iter()
+            .map(|f| f.to_string())
+            .collect();
+        let tree = field_tree(&fields, ".");
+        let rows: Vec<(usize, &str, bool)> = tree
+            .iter()
+            .map(|row| (row.depth, row.name.as_str(), row.is_namespace))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                (0, "review", true),
+                (1, "review.by", true),
+                (2, "review.by.name", false),
+                (1, "review.date", false),
+                (1, "review.status", false),
+                (0, "title", false),
+            ]
+        );
+
+        let mut fm = HashMap::new();
+        fm.insert(
+            "review.date".to_string(),
+            Yaml::String("2024-01-01".to_string()),
+        );
+        let notes = vec![
+            create_test_note("a.md", fm),
+            create_test_note("b.md", HashMap::new()),
+        ];
+        assert_eq!(count_notes_in_namespace(&notes, "review", "."), 1);
+        assert_eq!(count_notes_in_namespace(&notes, "rev", "."), 0);
+    }
+
+    #[test]
+    fn test_annotate_rules() {
+        let note = |path: &str, status: &str| {
+            let mut fm = HashMap::new();
+            fm.insert("status".to_string(), Yaml::String(status.to_string()));
+            create_test_note(path, fm)
+        };
+        let mut notes = vec![note("a.md", "stalled"), note("b.md", "done")];
+        let rules = [
+            Rule {
+                name: "stale".to_string(),
+                criteria: FilterCriteria::new(vec![FieldFilter::parse("status=stalled").unwrap()]),
+            },
+            Rule {
+                name: "missing-owner".to_string(),
+                criteria: FilterCriteria::new(vec![FieldFilter::missing("owner")]),
+            },
+        ];
+
+        annotate_rules(&mut notes, &rules);
+        assert_eq!(
+            notes[0].rules,
+            Some(vec!["stale".to_string(), "missing-owner".to_string()])
+        );
+        assert_eq!(notes[1].rules, Some(vec!["missing-owner".to_string()]));
+    }
+
+    #[test]
+    fn test_completions() {
+        let note = |path: &str, tags: &[&str]| {
+            let mut fm = HashMap::new();
+            fm.insert(
+                "tags".to_string(),
+                Yaml::Array(tags.iter().map(|t| Yaml::String(t.to_string())).collect()),
+            );
+            create_test_note(path, fm)
+        };
+        let notes = [
+            note("a.md", &["project", "Programming", "work"]),
+            note("b.md", &["project", "project"]),
+            note("c.md", &["promo"]),
+        ];
+        let notes: Vec<&Note> = notes.iter().collect();
+
+        let criteria = FilterCriteria::new_case_insensitive(Vec::<FieldFilter>::new());
+        assert_eq!(
+            criteria.completions(&notes, "tags", "#pro"),
+            vec![
+                ("project".to_string(), 2),
+                ("Programming".to_string(), 1),
+                ("promo".to_string(), 1)
+            ]
+        );
+        let criteria = FilterCriteria::new(Vec::<FieldFilter>::new());
+        assert_eq!(
+            criteria.completions(&notes, "tags", "Pro"),
+            vec![("Programming".to_string(), 1)]
+        );
+        assert_eq!(criteria.completions(&notes, "status", "").len(), 0);
+    }
+
+    #[test]
+    fn test_field_types() {
+        let note = |pairs: &[(&str, Yaml)]| {
+            let fm: HashMap<String, Yaml> = pairs
+                .iter()
+                .map(|(key, value)| ((*key).to_string(), value.clone()))
+                .collect();
+            create_test_note("note.md", fm)
+        };
+        let text = |s: &str| Yaml::String(s.to_string());
+        let notes = vec![
+            note(&[
+                ("title", text("A")),
+                ("due", text("2024-06-01")),
+                ("tags", Yaml::Array(vec![text("work")])),
+                ("priority", Yaml::Integer(1)),
+                ("done", Yaml::Boolean(true)),
+                ("empty", Yaml::Null),
+            ]),
+            note(&[
+                ("title", text("B")),
+                ("due", text("2024-06-02T10:30")),
+                ("tags", text("home")),
+                ("priority", Yaml::Real("2.5".to_string())),
+            ]),
+        ];
+
+        let stats = get_field_statistics(&notes);
+        let kind = |field: &str| stats[field].inferred_type();
+        assert_eq!(kind("title"), "string");
+        assert_eq!(kind("due"), "date");
+        assert_eq!(kind("tags"), "mixed");
+        assert_eq!(kind("priority"), "number");
+        assert_eq!(kind("done"), "bool");
+        assert_eq!(kind("empty"), "null");
+    }
+
+    #[test]
+    fn test_nested_field_collection() {
+        let mut fm = HashMap::new();
+        let mut project = yaml_rust2::yaml::Hash::new();
+        project.insert(
+            Yaml::String("name".to_string()),
+            Yaml::String("Website".to_string()),
+        );
+        project.insert(Yaml::String("phase".to_string()), Yaml::Integer(2));
+        fm.insert("project".to_string(), Yaml::Hash(project));
+        let notes = vec![create_test_note("a.md", fm)];
+
+        let fields = collect_all_fields(&notes);
+        assert_eq!(fields, vec!["project", "project.name", "project.phase"]);
+        assert_eq!(collect_field_values(&notes, "project.phase"), vec!["2"]);
+        assert_eq!(count_notes_in_namespace(&notes, "project", "."), 1);
+
+        // The map field heads its nested fields instead of a separate namespace row
+        let rows: Vec<(usize, bool)> = field_tree(&fields, ".")
+            .iter()
+            .map(|row| (row.depth, row.is_namespace))
+            .collect();
+        assert_eq!(rows, vec![(0, false), (1, false), (1, false)]);
+
+        let criteria =
+            FilterCriteria::new(vec![FieldFilter::parse("project.name==Website").unwrap()]);
+        assert_eq!(criteria.apply_filters(&notes).len(), 1);
+
+        // Maps have no values of their own, `values` lists the fields nested in them
+        let stats = get_field_statistics(&notes);
+        assert!(stats["project"].value_counts.is_empty());
+        let (expanded, maps) = expand_map_fields(
+            &["Project".to_string(), "status".to_string()],
+            &stats,
+            false,
+        );
+        assert_eq!(expanded, vec!["project.name", "project.phase", "status"]);
+        assert_eq!(maps, vec!["project"]);
+        let (expanded, maps) = expand_map_fields(&["Project".to_string()], &stats, true);
+        assert_eq!(expanded, vec!["Project"]);
+        assert!(maps.is_empty());
+    }
+
+    #[test]
+    fn test_content_query() {
+        let with_body = |path: &str, body: &str| {
+            let mut note = create_test_note(path, HashMap::new());
+            note.body = Some(body.to_string());
+            note
+        };
+        let notes = vec![
+            with_body("a.md", "We discussed the Budget for 2025."),
+            with_body("b.md", "Nothing to see here (budget?)"),
+            create_test_note("c.md", HashMap::new()),
+        ];
+
+        let plain = FilterCriteria::new_case_insensitive(Vec::<FieldFilter>::new())
+            .with_content(ContentQuery::new("budget", false, false).unwrap());
+        assert_eq!(plain.apply_filters(&notes).len(), 2);
+
+        let sensitive = FilterCriteria::new(Vec::<FieldFilter>::new())
+            .with_content(ContentQuery::new("budget?", false, true).unwrap());
+        assert_eq!(sensitive.apply_filters(&notes)[0].path, "b.md");
+
+        let regex = FilterCriteria::new(Vec::<FieldFilter>::new())
+            .with_content(ContentQuery::new(r"Budget for \d{4}", true, true).unwrap());
+        assert_eq!(regex.apply_filters(&notes)[0].path, "a.md");
+        assert!(ContentQuery::new("(", true, true).is_err());
+    }
+
+    #[test]
+    fn test_matchers() {
+        use crate::matcher::{OrderMatcher, QuantityMatcher};
+        use std::collections::BTreeMap;
+
+        let mut fm = HashMap::new();
+        fm.insert("duration".to_string(), Yaml::String("1h30m".to_string()));
+        fm.insert("priority".to_string(), Yaml::String("High".to_string()));
+        let note = create_test_note("task.md", fm);
+
+        let duration: Arc<dyn ValueMatcher> = Arc::new(
+            QuantityMatcher::new(
+                r"^(?:(?P<h>\d+)h)?\s*(?:(?P<m>\d+)m)?$",
+                BTreeMap::from([("h".to_string(), 60.0), ("m".to_string(), 1.0)]),
+            )
+            .unwrap(),
+        );
+        let priority: Arc<dyn ValueMatcher> = Arc::new(OrderMatcher::new(vec![
+            "low".to_string(),
+            "medium".to_string(),
+            "high".to_string(),
+        ]));
+        let matches = |filter: &str| {
+            FilterCriteria::new(vec![FieldFilter::parse(filter).unwrap()])
+                .with_matcher("Duration", Arc::clone(&duration))
+                .with_matcher("priority", Arc::clone(&priority))
+                .matches(&note)
+        };
+        assert!(matches("duration=90m"));
+        assert!(matches("duration=1h30m"));
+        assert!(!matches("duration!=90m"));
+        assert!(matches("duration>1h"));
+        assert!(!matches("duration<=1h"));
+        assert!(matches("priority>medium"));
+        assert!(!matches("priority<medium"));
+        // Values the matcher doesn't understand use the built-in comparison
+        assert!(!matches("priority=urgent"));
+        assert!(
+            !FilterCriteria::new(vec![FieldFilter::parse("duration=90m").unwrap()]).matches(&note)
+        );
+    }
 }
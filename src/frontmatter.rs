@@ -1,26 +1,150 @@
+use crate::links::extract_links;
 use crate::yaml_compat::{
-    parse_yaml_frontmatter, yaml_as_str, yaml_contains_str, yaml_contains_str_case_insensitive,
+    collect_yaml_strings, parse_yaml_frontmatter, yaml_as_str, yaml_get_path, yaml_matches_str,
+    MatchMode,
 };
 use anyhow::{Context, Result};
+use regex::Regex;
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fs;
+use std::io::{BufRead, BufReader};
 use std::path::Path;
+use std::sync::OnceLock;
 use yaml_rust2::Yaml;
 
 // Type alias for complex frontmatter extraction result
-type FrontmatterResult = Result<(Option<HashMap<String, Yaml>>, Option<String>)>;
+type FrontmatterResult = Result<(Option<HashMap<String, Yaml>>, Option<String>, ParseStatus)>;
 
 #[derive(Debug)]
 pub struct ParseResult {
     pub note: Option<Note>,
     pub frontmatter_warning: Option<String>,
+    pub status: ParseStatus,
 }
 
+/// How the frontmatter was read, the `parse.status` field of the note
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParseStatus {
+    /// Parsed as written, or the note has none
+    #[default]
+    Ok,
+    /// Parsed after the lenient fixes
+    Lenient,
+    /// Could not be parsed and is left empty
+    Failed,
+}
+
+impl ParseStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Ok => "ok",
+            Self::Lenient => "lenient",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+/// Which lines close a frontmatter block. Any line starting with `---` opens one, like
+/// `----` from some importers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Fences {
+    /// `---` to close
+    #[default]
+    Strict,
+    /// Three or more dashes or `...` to close, as written by some importers
+    Tolerant,
+}
+
+impl Fences {
+    fn closes(self, line: &str) -> bool {
+        let line = line.trim();
+        match self {
+            Self::Strict => line == "---",
+            Self::Tolerant => line == "..." || (line.len() >= 3 && line.chars().all(|c| c == '-')),
+        }
+    }
+}
+
+fn opens_frontmatter(line: &str) -> bool {
+    line.starts_with("---")
+}
+
+/// A frontmatter block split out of a note, with its fence lines as written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrontmatterBlock<'a> {
+    /// The opening fence line, with its line break
+    pub opener: &'a str,
+    /// The frontmatter text between the fences
+    pub text: &'a str,
+    /// The closing fence line, with its line break if there is one
+    pub closer: &'a str,
+    /// Everything after the closing fence
+    pub body: &'a str,
+}
+
+/// The value of fields masked with `--redact`
+pub const REDACTED: &str = "[redacted]";
+
 #[derive(Debug, Clone)]
 pub struct Note {
     pub path: String,
+    /// The parsed frontmatter. Look fields up with `field`, which also finds them by
+    /// another case; fields inserted after such a lookup are only found by their exact name.
     pub frontmatter: HashMap<String, Yaml>,
     pub title: Option<String>,
+    /// Tags written inline as `#tag` in the note body
+    pub inline_tags: Vec<String>,
+    /// Link targets (`[[wikilinks]]` and markdown links) found in the note body
+    pub links: Vec<String>,
+    /// Size and nesting of the frontmatter block
+    pub metrics: FrontmatterMetrics,
+    /// The note text after the frontmatter, only kept when searching note contents
+    pub body: Option<String>,
+    /// Name of the vault the note belongs to, set when scanning several vaults
+    pub vault: Option<String>,
+    /// Names of the rules the note matches, set with `--annotate`
+    pub rules: Option<Vec<String>>,
+    /// The start of the body, set with `--excerpt`
+    pub excerpt: Option<String>,
+    /// Virtual fields added from lookup tables with `--join`, in the order they were added
+    pub joined_fields: Vec<String>,
+    /// The fences the note was scanned with, so edits find the same frontmatter block
+    pub fences: Fences,
+    /// Computed fields like `fm.lines`, looked up after the frontmatter fields
+    virtual_fields: HashMap<String, Yaml>,
+    /// Names of the frontmatter and virtual fields by their folded name, see `fold_key`.
+    /// Built on the first lookup that doesn't find a field by its exact name.
+    field_index: OnceLock<HashMap<String, Vec<String>>>,
+}
+
+/// Size and nesting of a frontmatter block
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrontmatterMetrics {
+    /// Lines between the `---` delimiters
+    pub lines: usize,
+    /// Top-level keys
+    pub keys: usize,
+    /// Deepest nesting of lists and maps, 1 for flat frontmatter, 0 when empty
+    pub depth: usize,
+}
+
+impl FrontmatterMetrics {
+    pub fn new(frontmatter_text: &str, frontmatter: &HashMap<String, Yaml>) -> Self {
+        Self {
+            lines: frontmatter_text.lines().count(),
+            keys: frontmatter.len(),
+            depth: frontmatter.values().map(yaml_depth).max().unwrap_or(0),
+        }
+    }
+}
+
+fn yaml_depth(value: &Yaml) -> usize {
+    match value {
+        Yaml::Array(items) => 1 + items.iter().map(yaml_depth).max().unwrap_or(0),
+        Yaml::Hash(map) => 1 + map.values().map(yaml_depth).max().unwrap_or(0),
+        _ => 1,
+    }
 }
 
 impl Note {
@@ -37,23 +161,275 @@ impl Note {
                     .map(|s| s.to_string())
             });
 
-        Self {
+        let mut note = Self {
             path,
             frontmatter,
-            title,
+            title: None,
+            inline_tags: Vec::new(),
+            links: Vec::new(),
+            metrics: FrontmatterMetrics::default(),
+            body: None,
+            vault: None,
+            rules: None,
+            excerpt: None,
+            joined_fields: Vec::new(),
+            fences: Fences::default(),
+            virtual_fields: HashMap::new(),
+            field_index: OnceLock::new(),
+        };
+        note.set_title(title);
+        note
+    }
+
+    /// Set the title and the `title` virtual field, so notes without a title in their
+    /// frontmatter are filtered and sorted by the title they are shown with. A `Title`
+    /// field in another case is left to be found as before.
+    fn set_title(&mut self, title: Option<String>) {
+        let own_field = self.frontmatter.keys().any(|key| fold_key(key) == "title");
+        match &title {
+            Some(title) if !own_field => {
+                self.set_virtual_field("title", Yaml::String(title.clone()));
+            }
+            _ => {
+                self.virtual_fields.remove("title");
+                self.field_index.take();
+            }
+        }
+        self.title = title;
+    }
+
+    /// Set the frontmatter metrics and expose them as the `fm.lines`, `fm.keys` and
+    /// `fm.depth` virtual fields
+    pub fn set_metrics(&mut self, metrics: FrontmatterMetrics) {
+        self.metrics = metrics;
+        for (name, value) in [
+            ("fm.lines", metrics.lines),
+            ("fm.keys", metrics.keys),
+            ("fm.depth", metrics.depth),
+        ] {
+            self.set_virtual_field(
+                name,
+                Yaml::Integer(i64::try_from(value).unwrap_or(i64::MAX)),
+            );
+        }
+    }
+
+    /// Add a computed field that filters, templates and grouping see like a frontmatter
+    /// field. Real frontmatter fields with the same name take precedence.
+    pub fn set_virtual_field(&mut self, name: &str, value: Yaml) {
+        self.virtual_fields.insert(name.to_string(), value);
+        self.field_index.take();
+    }
+
+    /// Read frontmatter keys under their canonical name, like `Tag` as `tags`, without
+    /// touching the file.
+    ///
+    /// Notes that already have the canonical key keep both fields as they are.
+    pub fn canonicalize_keys<S: std::hash::BuildHasher>(
+        &mut self,
+        canonical_keys: &HashMap<String, String, S>,
+    ) {
+        for (key, canonical) in canonical_keys {
+            if key == canonical || self.frontmatter.contains_key(canonical) {
+                continue;
+            }
+            if let Some(value) = self.frontmatter.remove(key) {
+                let title = yaml_as_str(&value).map(str::to_string);
+                self.frontmatter.insert(canonical.clone(), value);
+                self.field_index.take();
+                if canonical == "title" && title.is_some() {
+                    self.set_title(title);
+                }
+            }
         }
     }
 
+    /// Replace the values of fields with `[redacted]`, so results can be shared without them.
+    ///
+    /// Field names match as in `is_redacted`. Redacting `title` masks the title the note is
+    /// shown with.
+    pub fn redact(&mut self, fields: &[String]) {
+        if fields.is_empty() {
+            return;
+        }
+        for (name, value) in self
+            .frontmatter
+            .iter_mut()
+            .chain(self.virtual_fields.iter_mut())
+        {
+            if is_redacted(name, fields) {
+                *value = Yaml::String(REDACTED.to_string());
+            }
+        }
+        if is_redacted("title", fields) {
+            self.set_title(Some(REDACTED.to_string()));
+        }
+    }
+
+    /// Add a virtual field from a lookup table, also listed in `joined_fields`
+    pub fn set_joined_field(&mut self, name: &str, value: Yaml) {
+        if !self.joined_fields.iter().any(|field| field == name) {
+            self.joined_fields.push(name.to_string());
+        }
+        self.set_virtual_field(name, value);
+    }
+
+    /// Read the body of a note parsed with `parse_frontmatter_head` for its inline tags and
+    /// links, keeping the body itself when asked to. Files of other types are left as they
+    /// are, their handler already read them.
+    pub fn load_body(&mut self, keep_body: bool) -> Result<()> {
+        if !self.is_markdown() {
+            return Ok(());
+        }
+        let content = fs::read_to_string(&self.path)
+            .with_context(|| format!("Failed to read file: {}", self.path))?;
+        let body =
+            split_frontmatter(&content, self.fences).map_or(content.as_str(), |(_, body)| body);
+        self.scan_body(body, keep_body);
+        Ok(())
+    }
+
+    /// Set the inline tags and links of the body, and the body itself when asked to
+    fn scan_body(&mut self, body: &str, keep_body: bool) {
+        self.inline_tags = extract_inline_tags(body);
+        self.links = extract_links(body);
+        if keep_body {
+            self.body = Some(body.to_string());
+        }
+    }
+
+    /// Whether the note is a markdown file, as opposed to a file indexed by a handler like
+    /// `.canvas` files
+    pub fn is_markdown(&self) -> bool {
+        Path::new(&self.path)
+            .extension()
+            .is_some_and(|ext| ext == "md")
+    }
+
+    /// Set `excerpt` to the first paragraph of the body, cut at `max_chars` characters. The
+    /// body is read from the file unless the scan kept it.
+    pub fn load_excerpt(&mut self, max_chars: usize) {
+        let excerpt = match &self.body {
+            Some(body) => excerpt(body, max_chars),
+            None if !self.is_markdown() => return,
+            None => {
+                let Ok(content) = fs::read_to_string(&self.path) else {
+                    return;
+                };
+                let body = split_frontmatter(&content, self.fences)
+                    .map_or(content.as_str(), |(_, body)| body);
+                excerpt(body, max_chars)
+            }
+        };
+        self.excerpt = Some(excerpt);
+    }
+
+    /// Tags from the `tags`/`tag` frontmatter fields, optionally merged with inline tags.
+    ///
+    /// Frontmatter tags are read like Obsidian does, see `normalize_tags`.
+    pub fn tags(&self, include_inline: bool) -> Vec<String> {
+        let frontmatter_tags = ["tags", "tag"]
+            .iter()
+            .filter_map(|key| self.get_frontmatter_value_case_insensitive(key))
+            .flat_map(normalize_tags);
+        let inline_tags = self.inline_tags.iter().filter(|_| include_inline).cloned();
+
+        let mut tags: Vec<String> = Vec::new();
+        for tag in frontmatter_tags.chain(inline_tags) {
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        tags
+    }
+
+    /// Aliases from the `aliases`/`alias` frontmatter fields, as a list or a comma separated
+    /// string
+    pub fn aliases(&self) -> Vec<String> {
+        let mut aliases: Vec<String> = Vec::new();
+        for alias in ["aliases", "alias"]
+            .iter()
+            .filter_map(|key| self.get_frontmatter_value_case_insensitive(key))
+            .flat_map(|value| split_list(value, |c| c == ','))
+        {
+            if !aliases.contains(&alias) {
+                aliases.push(alias);
+            }
+        }
+        aliases
+    }
+
+    /// The value of a frontmatter or virtual field, or of a value nested in a map field by
+    /// its dotted path like `project.name`.
+    ///
+    /// Emoji in field names match with and without a variation selector (`📅` vs `📅️`),
+    /// which look the same but are different keys. Unless `case_sensitive`, `Status` also
+    /// finds `status`; a field with the exact name comes first.
+    pub fn field(&self, key: &str, case_sensitive: bool) -> Option<&Yaml> {
+        self.find_field(key, case_sensitive)
+            .map(|(_, value)| value)
+            .or_else(|| self.get_nested_value(key, case_sensitive))
+    }
+
+    /// The name a frontmatter or virtual field has in this note, for a name that may
+    /// differ in case or emoji variation selectors
+    pub fn field_name(&self, key: &str, case_sensitive: bool) -> Option<&str> {
+        self.find_field(key, case_sensitive).map(|(name, _)| name)
+    }
+
+    fn find_field(&self, key: &str, case_sensitive: bool) -> Option<(&str, &Yaml)> {
+        let exact = |name: &str| {
+            self.frontmatter
+                .get_key_value(name)
+                .or_else(|| self.virtual_fields.get_key_value(name))
+                .map(|(name, value)| (name.as_str(), value))
+        };
+        if let Some(found) = exact(key) {
+            return Some(found);
+        }
+        if case_sensitive && key.is_ascii() {
+            return None;
+        }
+        let normalized = normalize_key(key);
+        self.field_index()
+            .get(&fold_key(key))?
+            .iter()
+            .filter(|name| !case_sensitive || normalize_key(name) == normalized)
+            .find_map(|name| exact(name))
+    }
+
+    fn field_index(&self) -> &HashMap<String, Vec<String>> {
+        self.field_index.get_or_init(|| {
+            let mut index: HashMap<String, Vec<String>> = HashMap::new();
+            for name in self.frontmatter.keys().chain(self.virtual_fields.keys()) {
+                index.entry(fold_key(name)).or_default().push(name.clone());
+            }
+            index
+        })
+    }
+
+    /// A value inside a map field by its dotted path, like `project.name`
+    fn get_nested_value(&self, key: &str, case_sensitive: bool) -> Option<&Yaml> {
+        key.match_indices('.').find_map(|(i, _)| {
+            let (field, path) = (&key[..i], &key[i + 1..]);
+            let value = self
+                .find_field(field, case_sensitive)
+                .filter(|(name, _)| self.frontmatter.contains_key(*name))?
+                .1;
+            yaml_get_path(value, path, case_sensitive)
+        })
+    }
+
     pub fn get_frontmatter_value(&self, key: &str) -> Option<&Yaml> {
-        self.frontmatter.get(key)
+        self.field(key, true)
+    }
+
+    pub fn get_frontmatter_value_case_insensitive(&self, key: &str) -> Option<&Yaml> {
+        self.field(key, false)
     }
 
     pub fn matches_filter(&self, key: &str, value: &str) -> bool {
-        if let Some(fm_value) = self.get_frontmatter_value(key) {
-            yaml_contains_str(fm_value, value)
-        } else {
-            false
-        }
+        self.matches_filter_with_mode(key, value, true, MatchMode::Substring)
     }
 
     pub fn matches_filter_with_case_sensitivity(
@@ -62,72 +438,139 @@ impl Note {
         value: &str,
         case_sensitive: bool,
     ) -> bool {
-        if case_sensitive {
-            self.matches_filter(key, value)
-        } else {
-            // For case-insensitive matching, we need to check both field name and value
-            let matching_key = if case_sensitive {
-                self.get_frontmatter_value(key)
-            } else {
-                // Find field with case-insensitive key matching
-                self.frontmatter
-                    .iter()
-                    .find(|(k, _)| k.to_lowercase() == key.to_lowercase())
-                    .map(|(_, v)| v)
-            };
+        self.matches_filter_with_mode(key, value, case_sensitive, MatchMode::Substring)
+    }
 
-            if let Some(fm_value) = matching_key {
-                yaml_contains_str_case_insensitive(fm_value, value)
-            } else {
-                false
-            }
-        }
+    /// Whether a field matches a value, comparing either substrings or whole values. Field
+    /// names and values are compared with the same case sensitivity.
+    pub fn matches_filter_with_mode(
+        &self,
+        key: &str,
+        value: &str,
+        case_sensitive: bool,
+        mode: MatchMode,
+    ) -> bool {
+        self.field(key, case_sensitive)
+            .is_some_and(|fm_value| yaml_matches_str(fm_value, value, mode, case_sensitive))
     }
+}
 
-    pub fn get_frontmatter_value_case_insensitive(&self, key: &str) -> Option<&Yaml> {
-        // First try exact match
-        if let Some(value) = self.frontmatter.get(key) {
-            return Some(value);
-        }
+/// A field name in lowercase without emoji variation selectors, for case-insensitive
+/// lookups
+fn fold_key(key: &str) -> String {
+    normalize_key(key).to_lowercase()
+}
 
-        // Then try case-insensitive match
-        let key_lower = key.to_lowercase();
-        self.frontmatter
-            .iter()
-            .find(|(k, _)| k.to_lowercase() == key_lower)
-            .map(|(_, v)| v)
-    }
+/// A field name without emoji variation selectors, for lookups
+fn normalize_key(key: &str) -> String {
+    key.chars()
+        .filter(|c| !matches!(c, '\u{FE0E}' | '\u{FE0F}'))
+        .collect()
 }
 
 pub fn parse_frontmatter_from_file<P: AsRef<Path>>(
     path: P,
     verbose: bool,
     lenient: bool,
+    keep_body: bool,
+    fences: Fences,
 ) -> Result<ParseResult> {
     let content = fs::read_to_string(&path)
         .with_context(|| format!("Failed to read file: {}", path.as_ref().display()))?;
+    parse_frontmatter_from_str(&content, path.as_ref(), verbose, lenient, keep_body, fences)
+}
 
-    let path_str = path.as_ref().to_string_lossy().to_string();
+/// Like `parse_frontmatter_from_file`, for the content of a note read from elsewhere, like
+/// a git revision
+pub fn parse_frontmatter_from_str(
+    content: &str,
+    path: &Path,
+    verbose: bool,
+    lenient: bool,
+    keep_body: bool,
+    fences: Fences,
+) -> Result<ParseResult> {
+    let (mut note, warning, status) = parse_content(content, path, verbose, lenient, fences)?;
+    let body = split_frontmatter(content, fences).map_or(content, |(_, body)| body);
+    note.scan_body(body, keep_body);
 
-    let (frontmatter_opt, warning) =
-        extract_frontmatter_with_options(&content, &path_str, verbose, lenient)?;
+    Ok(ParseResult {
+        note: Some(note),
+        frontmatter_warning: warning,
+        status,
+    })
+}
 
-    let note = if let Some(frontmatter) = frontmatter_opt {
-        Some(Note::new(path_str.clone(), frontmatter))
-    } else {
-        // Create note with empty frontmatter if no frontmatter found
-        Some(Note::new(path_str, HashMap::new()))
-    };
+/// Like `parse_frontmatter_from_file`, but only reads the file up to the end of the
+/// frontmatter. Inline tags, links and body are left empty until `Note::load_body`.
+pub fn parse_frontmatter_head<P: AsRef<Path>>(
+    path: P,
+    verbose: bool,
+    lenient: bool,
+    fences: Fences,
+) -> Result<ParseResult> {
+    let head = read_head(path.as_ref(), fences)
+        .with_context(|| format!("Failed to read file: {}", path.as_ref().display()))?;
+    let (note, warning, status) = parse_content(&head, path.as_ref(), verbose, lenient, fences)?;
 
     Ok(ParseResult {
-        note,
+        note: Some(note),
         frontmatter_warning: warning,
+        status,
     })
 }
 
+/// The note for the content of a file, with its frontmatter but nothing from the body
+fn parse_content(
+    content: &str,
+    path: &Path,
+    verbose: bool,
+    lenient: bool,
+    fences: Fences,
+) -> Result<(Note, Option<String>, ParseStatus)> {
+    let path_str = path.to_string_lossy().to_string();
+
+    let (frontmatter_opt, warning, status) =
+        extract_frontmatter_with_options(content, &path_str, verbose, lenient, fences)?;
+
+    // Notes without frontmatter get an empty one
+    let mut note = Note::new(path_str, frontmatter_opt.unwrap_or_default());
+
+    let frontmatter_text = split_frontmatter(content, fences).map_or("", |(text, _)| text);
+    note.fences = fences;
+    note.set_metrics(FrontmatterMetrics::new(frontmatter_text, &note.frontmatter));
+    Ok((note, warning, status))
+}
+
+/// The start of a file up to the line closing its frontmatter, or just the first line that
+/// isn't blank when it doesn't open a frontmatter block
+fn read_head(path: &Path, fences: Fences) -> std::io::Result<String> {
+    let mut reader = BufReader::new(fs::File::open(path)?);
+    let mut head = String::new();
+    let mut opened = false;
+    loop {
+        let start = head.len();
+        if reader.read_line(&mut head)? == 0 {
+            break;
+        }
+        let line = &head[start..];
+        if opened {
+            if fences.closes(line) {
+                break;
+            }
+        } else if !line.trim().is_empty() {
+            if !opens_frontmatter(line.trim_start()) {
+                break;
+            }
+            opened = true;
+        }
+    }
+    Ok(head)
+}
+
 #[cfg(test)]
 fn extract_frontmatter(content: &str, file_path: &str, _verbose: bool) -> FrontmatterResult {
-    extract_frontmatter_with_options(content, file_path, _verbose, true)
+    extract_frontmatter_with_options(content, file_path, _verbose, true, Fences::Strict)
 }
 
 fn extract_frontmatter_with_options(
@@ -135,54 +578,37 @@ fn extract_frontmatter_with_options(
     file_path: &str,
     _verbose: bool,
     lenient: bool,
+    fences: Fences,
 ) -> FrontmatterResult {
-    let content = content.trim();
-
-    // Check if content starts with frontmatter delimiter
-    if !content.starts_with("---") {
-        return Ok((None, None));
-    }
-
-    // Find the end of frontmatter
-    let lines: Vec<&str> = content.lines().collect();
-    if lines.len() < 3 {
-        return Ok((None, None));
-    }
-
-    let mut end_index = None;
-    for (i, line) in lines.iter().enumerate().skip(1) {
-        if line.trim() == "---" {
-            end_index = Some(i);
-            break;
+    let frontmatter_content = match find_frontmatter(content, fences) {
+        None => return Ok((None, None, ParseStatus::Ok)),
+        Some(Err(reason)) => {
+            let warning = format!(
+                "Leading '---' in file {} not treated as frontmatter: {}",
+                file_path, reason
+            );
+            return Ok((None, Some(warning), ParseStatus::Ok));
         }
-    }
-
-    let end_index = match end_index {
-        Some(idx) => idx,
-        None => return Ok((None, None)),
+        Some(Ok(block)) => block.text,
     };
 
-    // Extract frontmatter content
-    let frontmatter_lines = &lines[1..end_index];
-    let frontmatter_content = frontmatter_lines.join("\n");
-
     if frontmatter_content.trim().is_empty() {
-        return Ok((Some(HashMap::new()), None));
+        return Ok((Some(HashMap::new()), None, ParseStatus::Ok));
     }
 
     // Parse YAML frontmatter
-    match parse_yaml_frontmatter(&frontmatter_content) {
-        Ok(parsed) => Ok((Some(parsed), None)),
+    match parse_yaml_frontmatter(frontmatter_content) {
+        Ok(parsed) => Ok((Some(parsed), None, ParseStatus::Ok)),
         Err(e) => {
             if lenient {
                 // Try lenient parsing by fixing common YAML issues
-                match try_lenient_parse(&frontmatter_content) {
+                match try_lenient_parse(frontmatter_content) {
                     Ok(parsed) => {
                         let warning = format!(
                             "Used lenient parsing for frontmatter in file {} due to: {}",
                             file_path, e
                         );
-                        Ok((Some(parsed), Some(warning)))
+                        Ok((Some(parsed), Some(warning), ParseStatus::Lenient))
                     }
                     Err(_) => {
                         // If lenient parsing also fails, return warning message and empty frontmatter
@@ -190,16 +616,202 @@ fn extract_frontmatter_with_options(
                             "Failed to parse frontmatter in file {} even with lenient parsing: {}",
                             file_path, e
                         );
-                        Ok((Some(HashMap::new()), Some(warning)))
+                        Ok((Some(HashMap::new()), Some(warning), ParseStatus::Failed))
                     }
                 }
             } else {
                 // If YAML parsing fails, return warning message and empty frontmatter
                 let warning = format!("Failed to parse frontmatter in file {}: {}", file_path, e);
-                Ok((Some(HashMap::new()), Some(warning)))
+                Ok((Some(HashMap::new()), Some(warning), ParseStatus::Failed))
+            }
+        }
+    }
+}
+
+/// Frontmatter blocks longer than this are taken for a horizontal rule followed by text
+const MAX_FRONTMATTER_LINES: usize = 300;
+
+/// Split content into the frontmatter text (without fences) and the body after the closing fence
+pub fn split_frontmatter(content: &str, fences: Fences) -> Option<(&str, &str)> {
+    frontmatter_block(content, fences).map(|block| (block.text, block.body))
+}
+
+/// Like `split_frontmatter`, keeping the fence lines so they can be written back as they were
+pub fn frontmatter_block(content: &str, fences: Fences) -> Option<FrontmatterBlock<'_>> {
+    find_frontmatter(content, fences).and_then(Result::ok)
+}
+
+/// Like `split_frontmatter`, but tells apart notes without fences (`None`) from notes
+/// whose fenced block doesn't look like frontmatter (`Some(Err(reason))`): a note that
+/// starts with a `---` horizontal rule would otherwise swallow the text up to the next rule.
+fn find_frontmatter(content: &str, fences: Fences) -> Option<Result<FrontmatterBlock<'_>, String>> {
+    let start = content.len() - content.trim_start().len();
+    let mut lines = content[start..].split_inclusive('\n');
+    let first = lines.next()?;
+    if !opens_frontmatter(first) {
+        return None;
+    }
+
+    let fm_start = start + first.len();
+    let mut offset = fm_start;
+    for (count, line) in lines.enumerate() {
+        if fences.closes(line) {
+            let text = &content[fm_start..offset];
+            if count > MAX_FRONTMATTER_LINES {
+                return Some(Err(format!(
+                    "the block has {} lines, more than {}",
+                    count, MAX_FRONTMATTER_LINES
+                )));
             }
+            if !looks_like_yaml_mapping(text) {
+                return Some(Err("the lines after it are not YAML keys".to_string()));
+            }
+            return Some(Ok(FrontmatterBlock {
+                opener: first,
+                text,
+                closer: line,
+                body: &content[offset + line.len()..],
+            }));
         }
+        offset += line.len();
     }
+    None
+}
+
+/// Whether the first line that isn't blank or a comment starts a `key: value` pair
+fn looks_like_yaml_mapping(text: &str) -> bool {
+    static KEY_REGEX: OnceLock<Regex> = OnceLock::new();
+    let key_regex = KEY_REGEX.get_or_init(|| {
+        Regex::new(r#"^(?:[^\s#\-?:\[{"'][^:]*|"[^"]*"|'[^']*'):(?:\s|$)"#).unwrap()
+    });
+
+    text.lines()
+        .map(str::trim_end)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .is_none_or(|line| key_regex.is_match(line))
+}
+
+/// The first paragraph of a note body as one line, skipping headings, cut at `max_chars`
+/// characters with `…`
+pub fn excerpt(body: &str, max_chars: usize) -> String {
+    let is_heading = |line: &str| {
+        line.starts_with('#')
+            && matches!(
+                line.trim_start_matches('#').chars().next(),
+                None | Some(' ')
+            )
+    };
+    let paragraph: Vec<&str> = body
+        .lines()
+        .map(str::trim)
+        .skip_while(|line| line.is_empty() || is_heading(line))
+        .take_while(|line| !line.is_empty() && !is_heading(line))
+        .collect();
+    let text = paragraph.join(" ");
+    if text.chars().count() <= max_chars {
+        return text;
+    }
+    let cut: String = text.chars().take(max_chars).collect();
+    format!("{}…", cut.trim_end())
+}
+
+/// Whether `--redact` masks a field. Names match in any case, and also mask the fields under
+/// them: `created` masks `created.effective`, `project` the virtual fields joined as
+/// `project.*`.
+pub fn is_redacted(name: &str, fields: &[String]) -> bool {
+    let name = fold_key(name);
+    fields.iter().any(|field| {
+        name.strip_prefix(fold_key(field).as_str())
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+    })
+}
+
+/// The notes as they are shown: filters and checks read the real values, output gets
+/// copies with the redacted fields masked. Notes are only copied when there is something to
+/// mask.
+pub fn redact_notes<'a>(notes: &[&'a Note], fields: &[String]) -> Vec<Cow<'a, Note>> {
+    notes
+        .iter()
+        .map(|note| {
+            if fields.is_empty() {
+                Cow::Borrowed(*note)
+            } else {
+                let mut note = (*note).clone();
+                note.redact(fields);
+                Cow::Owned(note)
+            }
+        })
+        .collect()
+}
+
+/// The tags of a `tags`/`tag` value the way Obsidian reads them: list items or a string of
+/// tags separated by commas or spaces (`work, project/alpha`), without a leading `#`
+pub fn normalize_tags(value: &Yaml) -> Vec<String> {
+    split_list(value, |c| c == ',' || c.is_whitespace())
+        .into_iter()
+        .map(|tag| tag.trim_start_matches('#').to_string())
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+/// The strings of a list value, or the parts of a string value split at `separator`
+fn split_list(value: &Yaml, separator: fn(char) -> bool) -> Vec<String> {
+    let items = collect_yaml_strings(value);
+    let parts: Vec<&str> = if matches!(value, Yaml::Array(_)) {
+        items.iter().map(String::as_str).collect()
+    } else {
+        items
+            .iter()
+            .flat_map(|item| item.split(separator))
+            .collect()
+    };
+    parts
+        .into_iter()
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .map(ToString::to_string)
+        .collect()
+}
+
+/// Whether a tag matches a tag filter value. A `#` before the value is ignored, and nested
+/// tags match their parents: `project` matches `project/alpha` even for whole-value matches.
+pub fn tag_matches(tag: &str, search: &str, mode: MatchMode, case_sensitive: bool) -> bool {
+    let search = search.trim_start_matches('#');
+    mode.matches(tag, search, case_sensitive)
+        || tag
+            .match_indices('/')
+            .any(|(i, _)| MatchMode::Exact.matches(&tag[..i], search, case_sensitive))
+}
+
+/// Extract inline `#tags` from a note body, skipping fenced code blocks and inline code
+pub fn extract_inline_tags(body: &str) -> Vec<String> {
+    static TAG_RE: OnceLock<Regex> = OnceLock::new();
+    let tag_re = TAG_RE.get_or_init(|| {
+        // Obsidian tags need at least one non-numeric character
+        Regex::new(r"(?:^|\s)#([\p{L}\p{N}_/-]*[\p{L}_/-][\p{L}\p{N}_/-]*)").unwrap()
+    });
+
+    let mut tags = Vec::new();
+    let mut in_code_block = false;
+    for line in body.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block {
+            continue;
+        }
+
+        // Drop inline code spans before looking for tags
+        let text: String = line.split('`').step_by(2).collect::<Vec<_>>().join(" ");
+        for capture in tag_re.captures_iter(&text) {
+            let tag = capture[1].to_string();
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+    }
+    tags
 }
 
 fn try_lenient_parse(frontmatter_content: &str) -> Result<HashMap<String, Yaml>> {
@@ -208,15 +820,70 @@ fn try_lenient_parse(frontmatter_content: &str) -> Result<HashMap<String, Yaml>>
     parse_yaml_frontmatter(&fixed_content)
 }
 
-fn fix_yaml_issues(content: &str) -> String {
-    let mut fixed_lines = Vec::new();
+/// A correction lenient parsing makes to frontmatter that isn't valid YAML
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LenientFix {
+    /// Tabs in the indentation, which YAML doesn't allow, replaced by two spaces each
+    Tabs,
+    /// A `[[wikilink]]` followed by more text, quoted as one string
+    Wikilinks,
+    /// A value containing a colon, quoted
+    Colons,
+}
+
+impl LenientFix {
+    pub const ALL: [Self; 3] = [Self::Tabs, Self::Wikilinks, Self::Colons];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Tabs => "tabs",
+            Self::Wikilinks => "wikilinks",
+            Self::Colons => "colons",
+        }
+    }
+
+    /// How much the fix has to guess: `high` for tabs, whose width decides the nesting,
+    /// `medium` for wikilinks, which YAML would read as nested lists, and `low` for colons,
+    /// where quoting keeps the value as written
+    pub const fn severity(self) -> &'static str {
+        match self {
+            Self::Tabs => "high",
+            Self::Wikilinks => "medium",
+            Self::Colons => "low",
+        }
+    }
+
+    pub const fn description(self) -> &'static str {
+        match self {
+            Self::Tabs => "tabs in the indentation replaced by spaces",
+            Self::Wikilinks => "[[wikilinks]] followed by more text quoted",
+            Self::Colons => "values with colons quoted",
+        }
+    }
+}
+
+pub fn fix_yaml_issues(content: &str) -> String {
+    lenient_fixes(content).0
+}
 
-    for line in content.lines() {
+/// The frontmatter with the lenient fixes applied, and the fixes it needed with their
+/// line numbers, counting from 1 for the line after the opening fence
+pub fn lenient_fixes(content: &str) -> (String, Vec<(usize, LenientFix)>) {
+    let mut fixed_lines = Vec::new();
+    let mut fixes = Vec::new();
+
+    for (index, original) in content.lines().enumerate() {
+        let mut line = original.to_string();
+        let indent = &original[..original.len() - original.trim_start().len()];
+        if indent.contains('\t') {
+            line = format!("{}{}", indent.replace('\t', "  "), original.trim_start());
+            fixes.push((index + 1, LenientFix::Tabs));
+        }
         let trimmed = line.trim();
 
         // Skip empty lines and comments
         if trimmed.is_empty() || trimmed.starts_with('#') {
-            fixed_lines.push(line.to_string());
+            fixed_lines.push(line);
             continue;
         }
 
@@ -225,35 +892,109 @@ fn fix_yaml_issues(content: &str) -> String {
             let key_part = &trimmed[..colon_pos];
             let value_part = &trimmed[colon_pos + 1..].trim_start();
 
-            // Skip if this is already a properly formatted YAML (like arrays, objects, etc.)
-            if value_part.starts_with('[')
+            let already_yaml = value_part.starts_with('[')
                 || value_part.starts_with('{')
                 || value_part.starts_with('"')
                 || value_part.starts_with('\'')
-                || value_part.is_empty()
+                || value_part.is_empty();
+            let fix = if value_part.starts_with("[[")
+                && yaml_rust2::YamlLoader::load_from_str(value_part).is_err()
             {
-                fixed_lines.push(line.to_string());
-                continue;
-            }
+                // `[[a]]` alone is a valid (nested) list, `[[a]] and [[b]]` isn't YAML at all
+                Some(LenientFix::Wikilinks)
+            } else if !already_yaml && value_part.contains(':') {
+                // The value contains additional colons and isn't quoted yet
+                Some(LenientFix::Colons)
+            } else {
+                None
+            };
 
-            // Check if the value contains additional colons and isn't already quoted
-            if value_part.contains(':')
-                && !value_part.starts_with('"')
-                && !value_part.starts_with('\'')
-            {
+            if let Some(fix) = fix {
                 // Quote the value to make it valid YAML
                 let leading_spaces = line.len() - line.trim_start().len();
                 let spaces = " ".repeat(leading_spaces);
-                fixed_lines.push(format!("{}{}: \"{}\"", spaces, key_part, value_part));
-            } else {
-                fixed_lines.push(line.to_string());
+                fixed_lines.push(format!(
+                    "{}{}: \"{}\"",
+                    spaces,
+                    key_part,
+                    value_part.replace('\\', "\\\\").replace('"', "\\\"")
+                ));
+                fixes.push((index + 1, fix));
+                continue;
+            }
+        }
+        fixed_lines.push(line);
+    }
+
+    (fixed_lines.join("\n"), fixes)
+}
+
+/// Rewrite tags in syntax YAML reads differently than Obsidian: `tags: #a #b` (a comment
+/// in YAML) and `tags: a, b` become `tags: [a, b]`, and `#` is dropped from list items
+pub fn fix_tag_syntax(content: &str) -> String {
+    use std::fmt::Write;
+
+    let mut fixed = String::new();
+    let mut in_tags = false;
+
+    for line in content.split_inclusive('\n') {
+        let text = line.trim_end_matches(['\n', '\r']);
+        let ending = &line[text.len()..];
+
+        if !text.starts_with([' ', '\t', '#', '-']) && text.contains(':') {
+            let (key, value) = text.split_once(':').unwrap_or((text, ""));
+            in_tags = matches!(key.trim().to_lowercase().as_str(), "tags" | "tag");
+            if in_tags {
+                if let Some(items) = tag_items(value.trim()) {
+                    let _ = write!(fixed, "{}: [{}]{}", key, items.join(", "), ending);
+                    continue;
+                }
+            }
+        } else if in_tags {
+            let indent = &text[..text.len() - text.trim_start().len()];
+            if let Some(tag) = text.trim_start().strip_prefix("- #") {
+                let _ = write!(fixed, "{}- {}{}", indent, tag, ending);
+                continue;
             }
-        } else {
-            fixed_lines.push(line.to_string());
         }
+        fixed.push_str(line);
     }
+    fixed
+}
+
+/// The tags of an inline tag value that needs rewriting as a flow list
+fn tag_items(value: &str) -> Option<Vec<String>> {
+    let items: Vec<&str> = if let Some(inner) = value
+        .strip_prefix('[')
+        .and_then(|value| value.strip_suffix(']'))
+    {
+        let items: Vec<&str> = inner.split(',').map(str::trim).collect();
+        if !items.iter().any(|item| item.starts_with('#')) {
+            return None;
+        }
+        items
+    } else if value.starts_with('#') {
+        value.split([',', ' ', '\t']).collect()
+    } else if value.contains(',') && !value.starts_with(['"', '\'', '{', '|', '>']) {
+        value.split(',').collect()
+    } else {
+        return None;
+    };
 
-    fixed_lines.join("\n")
+    Some(
+        items
+            .iter()
+            .map(|item| item.trim().trim_start_matches('#'))
+            .filter(|item| !item.is_empty())
+            .map(|item| {
+                if item.contains([':', ',', '[', ']', '{', '}', '"', '\'', '#']) {
+                    format!("\"{}\"", item.replace('\\', "\\\\").replace('"', "\\\""))
+                } else {
+                    item.to_string()
+                }
+            })
+            .collect(),
+    )
 }
 
 #[cfg(test)]
@@ -272,7 +1013,7 @@ status: active
 
 This is the content of the note."#;
 
-        let (result, warning) = extract_frontmatter(content, "test.md", false).unwrap();
+        let (result, warning, _) = extract_frontmatter(content, "test.md", false).unwrap();
         let result = result.unwrap();
         assert_eq!(
             yaml_as_str(result.get("title").unwrap()).unwrap(),
@@ -288,7 +1029,7 @@ This is the content of the note."#;
     #[test]
     fn test_no_frontmatter() {
         let content = "# Just a regular markdown file\n\nWith some content.";
-        let (result, warning) = extract_frontmatter(content, "test.md", false).unwrap();
+        let (result, warning, _) = extract_frontmatter(content, "test.md", false).unwrap();
         assert!(result.is_none());
         assert!(warning.is_none());
     }
@@ -296,12 +1037,57 @@ This is the content of the note."#;
     #[test]
     fn test_empty_frontmatter() {
         let content = "---\n---\n\n# Note with empty frontmatter";
-        let (result, warning) = extract_frontmatter(content, "test.md", false).unwrap();
+        let (result, warning, _) = extract_frontmatter(content, "test.md", false).unwrap();
         let result = result.unwrap();
         assert!(result.is_empty());
         assert!(warning.is_none());
     }
 
+    #[test]
+    fn test_tolerant_fences() {
+        let dotted = "---\ntitle: Imported\n...\nBody\n";
+        let dashed = "-----\ntitle: Imported\n----\nBody\n";
+        for content in [dotted, dashed] {
+            assert_eq!(split_frontmatter(content, Fences::Strict), None);
+            assert_eq!(
+                split_frontmatter(content, Fences::Tolerant),
+                Some(("title: Imported\n", "Body\n"))
+            );
+        }
+        assert_eq!(
+            split_frontmatter("--\ntitle: x\n--\n", Fences::Tolerant),
+            None
+        );
+    }
+
+    #[test]
+    fn test_longer_opening_fence_in_strict_mode() {
+        // Any `---` prefix has always opened frontmatter, only the closing line is strict
+        let content = "----\ntitle: Imported\n---\nBody\n";
+        let block = frontmatter_block(content, Fences::Strict).unwrap();
+        assert_eq!(block.opener, "----\n");
+        assert_eq!(block.text, "title: Imported\n");
+        assert_eq!(block.closer, "---\n");
+        assert_eq!(block.body, "Body\n");
+    }
+
+    #[test]
+    fn test_horizontal_rule_is_not_frontmatter() {
+        let content = "---\nSome text after a rule.\n\n---\nMore text\n";
+        let (result, warning, _) = extract_frontmatter(content, "test.md", false).unwrap();
+        assert!(result.is_none());
+        assert!(warning.unwrap().contains("not treated as frontmatter"));
+
+        let long = format!("---\ntitle: x\n{}---\n", "text: more\n".repeat(400));
+        let (result, warning, _) = extract_frontmatter(&long, "test.md", false).unwrap();
+        assert!(result.is_none());
+        assert!(warning.unwrap().contains("has 401 lines"));
+
+        let commented = "---\n# Generated\n\"quoted key\": 1\n---\n";
+        assert!(split_frontmatter(commented, Fences::Strict).is_some());
+        assert_eq!(split_frontmatter("---\n- a\n---\n", Fences::Strict), None);
+    }
+
     #[test]
     fn test_frontmatter_with_colons_in_values() {
         let content = r#"---
@@ -315,7 +1101,7 @@ url: https://example.com/path
 
 This note has colons in frontmatter values."#;
 
-        let (result, warning) = extract_frontmatter(content, "test.md", false).unwrap();
+        let (result, warning, _) = extract_frontmatter(content, "test.md", false).unwrap();
         let result = result.unwrap();
 
         assert_eq!(
@@ -360,6 +1146,36 @@ number: 42"#;
         assert!(fixed.contains("tags: [work, important]")); // Array, shouldn't be quoted
         assert!(fixed.contains("quoted: \"Already: quoted\"")); // Already quoted, shouldn't be double-quoted
         assert!(fixed.contains("number: 42")); // Number, shouldn't be quoted
+
+        let (fixed, fixes) =
+            lenient_fixes("project:\n\tname: Site\nup: [[Home]] and [[Index]]\nparent: [[Home]]");
+        assert_eq!(
+            fixed,
+            "project:\n  name: Site\nup: \"[[Home]] and [[Index]]\"\nparent: [[Home]]"
+        );
+        assert_eq!(
+            fixes,
+            vec![(2, LenientFix::Tabs), (3, LenientFix::Wikilinks)]
+        );
+    }
+
+    #[test]
+    fn test_fix_tag_syntax() {
+        let fixed = fix_tag_syntax(
+            "title: A, B\ntags: #work #home\nTag: one, two/three\naliases:\n  - #not-a-tag\n",
+        );
+        assert_eq!(
+            fixed,
+            "title: A, B\ntags: [work, home]\nTag: [one, two/three]\naliases:\n  - #not-a-tag\n"
+        );
+
+        let fixed = fix_tag_syntax("tags:\n  - #work\n  - home\ncategories: [#x]\n");
+        assert_eq!(fixed, "tags:\n  - work\n  - home\ncategories: [#x]\n");
+        assert_eq!(fix_tag_syntax("tags: [#a, b]"), "tags: [a, b]");
+        assert_eq!(
+            fix_tag_syntax("tags: [a, b]\ntags: work"),
+            "tags: [a, b]\ntags: work"
+        );
     }
 
     #[test]
@@ -372,8 +1188,10 @@ source: Eberron: Rising from the Last War p. 277
 # Test Note"#;
 
         // Test strict parsing (should fail and return empty frontmatter)
-        let (result_strict, warning_strict) =
-            extract_frontmatter_with_options(content, "test.md", false, false).unwrap();
+        let (result_strict, warning_strict, status_strict) =
+            extract_frontmatter_with_options(content, "test.md", false, false, Fences::Strict)
+                .unwrap();
+        assert_eq!(status_strict, ParseStatus::Failed);
         let result_strict = result_strict.unwrap();
         assert!(result_strict.is_empty()); // Should be empty due to parsing failure
         assert!(warning_strict.is_some());
@@ -382,8 +1200,10 @@ source: Eberron: Rising from the Last War p. 277
             .contains("Failed to parse frontmatter"));
 
         // Test lenient parsing (should succeed)
-        let (result_lenient, warning_lenient) =
-            extract_frontmatter_with_options(content, "test.md", false, true).unwrap();
+        let (result_lenient, warning_lenient, status_lenient) =
+            extract_frontmatter_with_options(content, "test.md", false, true, Fences::Strict)
+                .unwrap();
+        assert_eq!(status_lenient, ParseStatus::Lenient);
         let result_lenient = result_lenient.unwrap();
         assert!(!result_lenient.is_empty()); // Should have parsed content
         assert_eq!(
@@ -429,6 +1249,38 @@ source: Eberron: Rising from the Last War p. 277
         // field name case mismatch
     }
 
+    #[test]
+    fn test_emoji_keys() {
+        let content = "---\n📅 date: 2024-06-01\n\"✅ done\": true\n⏰ time: 10:30\n---\n";
+        let (frontmatter, _, _) =
+            extract_frontmatter_with_options(content, "emoji.md", false, true, Fences::Strict)
+                .unwrap();
+        let note = Note::new("emoji.md".to_string(), frontmatter.unwrap());
+
+        assert!(note.matches_filter("📅 date", "2024"));
+        assert!(note.matches_filter("✅ done", "true"));
+        assert!(note.matches_filter("⏰ time", "10:30"));
+        // With a variation selector after the emoji
+        assert!(note.matches_filter("📅\u{FE0F} date", "2024"));
+        assert!(note.matches_filter_with_case_sensitivity("📅 DATE", "2024", false));
+    }
+
+    #[test]
+    fn test_nested_fields() {
+        let content = "---\nproject:\n  name: Website\n  phase: 2\nreview.date: 2024-06-01\n---\n";
+        let (frontmatter, _, _) =
+            extract_frontmatter_with_options(content, "nested.md", false, true, Fences::Strict)
+                .unwrap();
+        let note = Note::new("nested.md".to_string(), frontmatter.unwrap());
+
+        assert!(note.matches_filter("project.name", "Website"));
+        assert!(note.matches_filter("project.phase", "2"));
+        assert!(note.matches_filter("review.date", "2024"));
+        assert!(!note.matches_filter("project.owner", "Website"));
+        assert!(note.get_frontmatter_value("Project.Name").is_none());
+        assert!(note.matches_filter_with_case_sensitivity("Project.Name", "website", false));
+    }
+
     #[test]
     fn test_case_insensitive_field_lookup() {
         let mut fm = HashMap::new();
@@ -471,4 +1323,281 @@ source: Eberron: Rising from the Last War p. 277
             panic!("Expected string value for title");
         }
     }
+
+    #[test]
+    fn test_field_index() {
+        let mut fm = HashMap::new();
+        fm.insert("Status".to_string(), Yaml::String("open".to_string()));
+        fm.insert("status".to_string(), Yaml::String("done".to_string()));
+        fm.insert(
+            "📅\u{FE0F} Due".to_string(),
+            Yaml::String("2024-05-01".to_string()),
+        );
+        let mut note = Note::new("test.md".to_string(), fm);
+
+        // The field with the exact name wins over the one that differs in case
+        assert_eq!(
+            note.field("Status", false),
+            Some(&Yaml::String("open".to_string()))
+        );
+        assert_eq!(
+            note.field("status", false),
+            Some(&Yaml::String("done".to_string()))
+        );
+        assert!(note.field("STATUS", false).is_some());
+        assert!(note.field("STATUS", true).is_none());
+        assert_eq!(note.field_name("📅 due", false), Some("📅\u{FE0F} Due"));
+        assert!(note.field("📅 Due", true).is_some());
+        assert!(note.field("📅 due", true).is_none());
+
+        // Virtual fields added after a lookup are found as well
+        assert!(note.field("FM.LINES", false).is_none());
+        note.set_metrics(FrontmatterMetrics::default());
+        assert_eq!(note.field_name("FM.LINES", false), Some("fm.lines"));
+        assert!(note.matches_filter_with_case_sensitivity("fm.LINES", "0", false));
+    }
+
+    #[test]
+    fn test_canonicalize_keys() {
+        let mut fm = HashMap::new();
+        fm.insert("Tag".to_string(), Yaml::String("work".to_string()));
+        fm.insert("Title".to_string(), Yaml::String("Old Note".to_string()));
+        fm.insert(
+            "Created".to_string(),
+            Yaml::String("2023-01-01".to_string()),
+        );
+        fm.insert(
+            "created".to_string(),
+            Yaml::String("2024-01-01".to_string()),
+        );
+        let mut note = Note::new("test.md".to_string(), fm);
+        assert_eq!(note.title.as_deref(), Some("test"));
+        assert!(note.field("tags", false).is_none());
+        assert!(note.matches_filter_with_case_sensitivity("title", "Old Note", false));
+
+        let canonical_keys = HashMap::from([
+            ("Tag".to_string(), "tags".to_string()),
+            ("Title".to_string(), "title".to_string()),
+            ("Created".to_string(), "created".to_string()),
+        ]);
+        note.canonicalize_keys(&canonical_keys);
+        assert_eq!(note.tags(false), vec!["work"]);
+        assert!(note.get_frontmatter_value("Tag").is_none());
+        assert_eq!(note.title.as_deref(), Some("Old Note"));
+
+        // A note that already has the canonical key keeps both
+        assert!(note.matches_filter("created", "2024-01-01"));
+        assert!(note.matches_filter("Created", "2023-01-01"));
+    }
+
+    #[test]
+    fn test_redact() {
+        let mut fm = HashMap::new();
+        fm.insert(
+            "Email".to_string(),
+            Yaml::String("a@example.com".to_string()),
+        );
+        fm.insert("status".to_string(), Yaml::String("open".to_string()));
+        fm.insert(
+            "created".to_string(),
+            Yaml::String("2024-01-01".to_string()),
+        );
+        let mut note = Note::new("Contacts/Anna.md".to_string(), fm);
+        note.set_virtual_field("created.effective", Yaml::String("2024-01-01".to_string()));
+        note.set_virtual_field("createdby", Yaml::String("ben".to_string()));
+
+        note.redact(&[
+            "email".to_string(),
+            "created".to_string(),
+            "title".to_string(),
+        ]);
+        let redacted = Some(Yaml::String(REDACTED.to_string()));
+        assert_eq!(note.field("email", false).cloned(), redacted);
+        assert_eq!(note.field("created.effective", true).cloned(), redacted);
+        assert_eq!(note.title.as_deref(), Some(REDACTED));
+        assert_eq!(note.field("title", true).cloned(), redacted);
+        assert!(note.matches_filter("status", "open"));
+        assert!(note.matches_filter("createdby", "ben"));
+
+        // Shown notes are masked copies, the scanned note keeps its values
+        let original = Note::new(
+            "a.md".to_string(),
+            HashMap::from([("Email".to_string(), Yaml::String("a@b.c".to_string()))]),
+        );
+        let shown = redact_notes(&[&original], &["email".to_string()]);
+        assert_eq!(shown[0].field("email", false).cloned(), redacted);
+        assert!(original.matches_filter("Email", "a@b.c"));
+        assert!(matches!(
+            redact_notes(&[&original], &[])[0],
+            Cow::Borrowed(_)
+        ));
+        assert!(!is_redacted("createdby", &["created".to_string()]));
+    }
+
+    #[test]
+    fn test_excerpt() {
+        let body = "\n# Title\n\n#idea First line\nsecond line.\n\nMore\n";
+        assert_eq!(excerpt(body, 100), "#idea First line second line.");
+        assert_eq!(excerpt(body, 10), "#idea Firs…");
+        assert_eq!(excerpt("Ünïcödé text", 6), "Ünïcöd…");
+        assert_eq!(excerpt("## Only a heading\n", 10), "");
+    }
+
+    #[test]
+    fn test_extract_inline_tags() {
+        let body = r#"# Heading
+
+Working on #project/alpha with #review-needed today.
+Issue #123 is not a tag, but #v2 is.
+Inline `#code` is ignored, as is a [link](http://example.com/#anchor).
+
+```
+#not-a-tag
+```
+Another #review-needed mention."#;
+
+        let tags = extract_inline_tags(body);
+        assert_eq!(tags, vec!["project/alpha", "review-needed", "v2"]);
+    }
+
+    #[test]
+    fn test_parse_frontmatter_head() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("note.md");
+        fs::write(
+            &path,
+            "\n---\nstatus: open\n---\nSee [[Plan]] #work\n---\nnot: frontmatter\n",
+        )
+        .unwrap();
+
+        let mut note = parse_frontmatter_head(&path, false, true, Fences::Strict)
+            .unwrap()
+            .note
+            .unwrap();
+        assert_eq!(
+            note.get_frontmatter_value("status"),
+            Some(&Yaml::String("open".to_string()))
+        );
+        assert_eq!(note.metrics.keys, 1);
+        assert!(note.links.is_empty() && note.inline_tags.is_empty());
+
+        note.load_body(true).unwrap();
+        assert_eq!(note.links, vec!["Plan"]);
+        assert_eq!(note.inline_tags, vec!["work"]);
+        assert_eq!(
+            note.body.as_deref(),
+            Some("See [[Plan]] #work\n---\nnot: frontmatter\n")
+        );
+
+        fs::write(&path, "# Title\n---\nkey: value\n---\n").unwrap();
+        let note = parse_frontmatter_head(&path, false, true, Fences::Strict)
+            .unwrap()
+            .note
+            .unwrap();
+        assert!(note.frontmatter.is_empty());
+    }
+
+    #[test]
+    fn test_merged_tags_view() {
+        let mut fm = HashMap::new();
+        fm.insert(
+            "tags".to_string(),
+            Yaml::Array(vec![Yaml::String("work".to_string())]),
+        );
+        let mut note = Note::new("test.md".to_string(), fm);
+        note.inline_tags = vec!["meeting".to_string()];
+
+        assert_eq!(note.tags(false), vec!["work"]);
+        assert_eq!(note.tags(true), vec!["work", "meeting"]);
+    }
+
+    #[test]
+    fn test_normalize_tags() {
+        let note = Note::new(
+            "test.md".to_string(),
+            HashMap::from([
+                (
+                    "tags".to_string(),
+                    Yaml::String("#work, project/alpha  home".to_string()),
+                ),
+                (
+                    "Tag".to_string(),
+                    Yaml::Array(vec![
+                        Yaml::String("#work".to_string()),
+                        Yaml::String("review".to_string()),
+                    ]),
+                ),
+                (
+                    "aliases".to_string(),
+                    Yaml::String("Alpha Project, AP".to_string()),
+                ),
+            ]),
+        );
+        assert_eq!(
+            note.tags(false),
+            vec!["work", "project/alpha", "home", "review"]
+        );
+        assert_eq!(note.aliases(), vec!["Alpha Project", "AP"]);
+    }
+
+    #[test]
+    fn test_tag_matches() {
+        assert!(tag_matches(
+            "project/alpha",
+            "project",
+            MatchMode::Exact,
+            true
+        ));
+        assert!(tag_matches(
+            "project/alpha/x",
+            "#Project/Alpha",
+            MatchMode::Exact,
+            false
+        ));
+        assert!(!tag_matches(
+            "projection",
+            "project",
+            MatchMode::Exact,
+            true
+        ));
+        assert!(!tag_matches(
+            "project/alpha",
+            "alpha",
+            MatchMode::Exact,
+            true
+        ));
+        assert!(tag_matches(
+            "project/alpha",
+            "alpha",
+            MatchMode::Substring,
+            true
+        ));
+    }
+
+    #[test]
+    fn test_frontmatter_metrics() {
+        let content = "---\ntitle: Test\nproject:\n  name: Aktenfux\n  owners:\n    - anna\n    - ben\n---\nBody";
+        let (text, _) = split_frontmatter(content, Fences::Strict).unwrap();
+        let (frontmatter, _, _) = extract_frontmatter(content, "test.md", false).unwrap();
+        let frontmatter = frontmatter.unwrap();
+
+        let metrics = FrontmatterMetrics::new(text, &frontmatter);
+        assert_eq!(
+            metrics,
+            FrontmatterMetrics {
+                lines: 6,
+                keys: 2,
+                depth: 3
+            }
+        );
+
+        let mut note = Note::new("test.md".to_string(), frontmatter);
+        note.set_metrics(metrics);
+        assert_eq!(
+            note.get_frontmatter_value("fm.depth"),
+            Some(&Yaml::Integer(3))
+        );
+        assert!(note.matches_filter_with_case_sensitivity("FM.Keys", "2", false));
+        assert_eq!(FrontmatterMetrics::new("", &HashMap::new()).depth, 0);
+    }
 }
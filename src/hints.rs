@@ -0,0 +1,146 @@
+use std::time::Duration;
+
+/// How long the phases of a scan took, and the settings that shaped them
+#[derive(Debug, Clone, Default)]
+pub struct ScanPhases {
+    /// Walking the vaults to find the notes
+    pub walk: Duration,
+    /// Reading and parsing the notes
+    pub parse: Duration,
+    /// Reading first commit dates from git, part of `parse`
+    pub git: Duration,
+    /// Number of notes parsed
+    pub files: usize,
+    /// Whether the notes came from a list (`--stdin`) instead of a walk of the vaults
+    pub listed: bool,
+    /// Whether notes passing the frontmatter filters were read past their frontmatter, for
+    /// `--content` or inline tags
+    pub filtered_bodies: bool,
+    /// Threads the notes were parsed on, and the cores available
+    pub threads: usize,
+    pub cores: usize,
+}
+
+impl ScanPhases {
+    pub fn total(&self) -> Duration {
+        self.walk + self.parse
+    }
+
+    /// Ways to speed up the phases that took a large share of a scan longer than
+    /// `threshold`; none for faster scans
+    pub fn hints(&self, threshold: Duration) -> Vec<String> {
+        let total = self.total();
+        if total < threshold || total.is_zero() {
+            return Vec::new();
+        }
+        let large = |phase: Duration| phase * 3 >= total;
+        let mut hints = Vec::new();
+
+        if large(self.walk) && !self.listed {
+            hints.push(format!(
+                "Finding the notes took {}: skip folders without notes with --exclude or \
+                 max_depth, or pass the notes to read with --stdin \
+                 (e.g. rg -l 'status:' | aktenfux filter --stdin ...)",
+                seconds(self.walk)
+            ));
+        }
+        if large(self.git) {
+            hints.push(format!(
+                "Reading creation dates from git took {}: put filename before git in \
+                 --created-from, or add a created field to the notes",
+                seconds(self.git)
+            ));
+        }
+        if large(self.parse) {
+            let before = hints.len();
+            if self.filtered_bodies {
+                hints.push(format!(
+                    "Parsing {} notes took {}, reading bodies for --content or inline tags: \
+                     add --filter conditions so fewer bodies are read",
+                    self.files,
+                    seconds(self.parse)
+                ));
+            }
+            if self.threads < self.cores {
+                hints.push(format!(
+                    "Notes were parsed on {} of {} cores: raise --threads (or threads in the \
+                     config) to use more",
+                    self.threads, self.cores
+                ));
+            }
+            if hints.len() == before && !self.listed {
+                hints.push(format!(
+                    "Parsing {} notes took {}: to query a part of the vault, pass its notes \
+                     with --stdin or --exclude the rest",
+                    self.files,
+                    seconds(self.parse)
+                ));
+            }
+        }
+        hints
+    }
+
+    /// Print the hints for a scan longer than `threshold` to stderr, so they never mix with
+    /// the command output
+    pub fn print_hints(&self, threshold: Duration) {
+        let hints = self.hints(threshold);
+        if hints.is_empty() {
+            return;
+        }
+        eprintln!(
+            "Scan took {} (finding notes {}, parsing {}). To speed it up:",
+            seconds(self.total()),
+            seconds(self.walk),
+            seconds(self.parse)
+        );
+        for hint in hints {
+            eprintln!("  - {}", hint);
+        }
+    }
+}
+
+fn seconds(duration: Duration) -> String {
+    format!("{:.1} s", duration.as_secs_f64())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hints() {
+        let phases = ScanPhases {
+            walk: Duration::from_secs(6),
+            parse: Duration::from_secs(4),
+            git: Duration::from_secs(4),
+            files: 50_000,
+            threads: 8,
+            cores: 8,
+            ..ScanPhases::default()
+        };
+        assert!(phases.hints(Duration::from_secs(11)).is_empty());
+
+        let hints = phases.hints(Duration::from_secs(2));
+        assert_eq!(hints.len(), 3);
+        assert!(hints[0].starts_with("Finding the notes took 6.0 s"));
+        assert!(hints[1].starts_with("Reading creation dates from git took 4.0 s"));
+        assert!(hints[2].starts_with("Parsing 50000 notes took 4.0 s: to query a part"));
+
+        // A listed set of notes on too few threads
+        let phases = ScanPhases {
+            walk: Duration::ZERO,
+            git: Duration::ZERO,
+            listed: true,
+            threads: 2,
+            ..phases
+        };
+        assert_eq!(
+            phases.hints(Duration::ZERO),
+            vec![
+                "Notes were parsed on 2 of 8 cores: raise --threads (or threads in the config) \
+                 to use more"
+                    .to_string()
+            ]
+        );
+    }
+}
@@ -0,0 +1,216 @@
+use crate::frontmatter::{parse_frontmatter_from_str, Fences, ParseStatus};
+use crate::lint::{LintIssue, Linter};
+use crate::revision;
+use crate::schema::Schema;
+use crate::writer::FrontmatterDocument;
+use anyhow::{anyhow, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Rule name for notes whose frontmatter can't be parsed at all
+pub const PARSE_RULE: &str = "parse";
+
+/// The markdown files staged in the git repository containing `vault_path`, limited to
+/// the vault. Deleted files are left out.
+pub fn staged_files(vault_path: &Path) -> Result<Vec<PathBuf>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(vault_path)
+        .args([
+            "diff",
+            "--cached",
+            "--name-only",
+            "--diff-filter=ACMR",
+            "--relative",
+            "-z",
+        ])
+        .output()
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git diff --cached failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .split('\0')
+        .filter(|path| !path.is_empty())
+        .map(|path| vault_path.join(path))
+        .filter(|path| is_markdown(path))
+        .collect())
+}
+
+/// The content of `files` as staged in the git repository containing `vault_path`, which
+/// is what a commit will contain. Outside a repository, as with `--stdin`, the files are
+/// read from disk instead.
+pub fn read_staged(vault_path: &Path, files: &[PathBuf]) -> Result<Vec<Result<String>>> {
+    if !revision::in_work_tree(vault_path) {
+        return Ok(files
+            .iter()
+            .map(|path| {
+                fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read file: {}", path.display()))
+            })
+            .collect());
+    }
+
+    let relative: Vec<PathBuf> = files
+        .iter()
+        .map(|path| relative_to(vault_path, path))
+        .collect::<Result<_>>()?;
+    let contents = revision::read_index(vault_path, &relative)?;
+    Ok(files
+        .iter()
+        .zip(contents)
+        .map(|(path, content)| {
+            content.ok_or_else(|| anyhow!("Failed to read file: {} is not staged", path.display()))
+        })
+        .collect())
+}
+
+/// The path of a file relative to the vault, for looking it up in the index
+fn relative_to(vault_path: &Path, path: &Path) -> Result<PathBuf> {
+    if let Ok(relative) = path.strip_prefix(vault_path) {
+        return Ok(relative.to_path_buf());
+    }
+    let vault = fs::canonicalize(vault_path)?;
+    let file = fs::canonicalize(path)
+        .with_context(|| format!("Failed to read file: {}", path.display()))?;
+    file.strip_prefix(&vault)
+        .map(Path::to_path_buf)
+        .map_err(|_| anyhow!("{} is outside the vault", path.display()))
+}
+
+pub fn is_markdown(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "md")
+}
+
+/// The schema and lint rules a hook checks files against
+#[derive(Debug)]
+pub struct HookChecks {
+    schema: Option<Schema>,
+    linter: Option<Linter>,
+}
+
+impl HookChecks {
+    pub fn new(schema: Option<Schema>, linter: Option<Linter>) -> Result<Self> {
+        if schema.is_none() && linter.is_none() {
+            return Err(anyhow!(
+                "Nothing to check: configure a schema, key_order or key_case in aktenfux.toml, or use --schema, --key-order or --key-case"
+            ));
+        }
+        Ok(Self { schema, linter })
+    }
+
+    /// Check the frontmatter of a single file, reporting unparseable frontmatter as an issue
+    pub fn check_content(
+        &self,
+        path: &Path,
+        content: &str,
+        lenient: bool,
+        fences: Fences,
+    ) -> Result<Vec<LintIssue>> {
+        let parsed = parse_frontmatter_from_str(content, path, false, lenient, false, fences)?;
+        let Some(note) = parsed.note else {
+            return Ok(Vec::new());
+        };
+
+        if let Some(warning) = parsed
+            .frontmatter_warning
+            .filter(|_| parsed.status == ParseStatus::Failed)
+        {
+            return Ok(vec![LintIssue {
+                path: note.path,
+                rule: PARSE_RULE,
+                message: warning,
+            }]);
+        }
+
+        let mut issues = Vec::new();
+        if let Some(schema) = &self.schema {
+            issues.extend(schema.validate(&note));
+        }
+        if let Some(linter) = &self.linter {
+            // The key order comes from the text, the note only keeps the values
+            let doc = FrontmatterDocument::parse(content, fences)?;
+            issues.extend(linter.check(&note.path, &doc.frontmatter));
+        }
+        Ok(issues)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn git(dir: &Path, args: &[&str]) {
+        assert!(Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .status()
+            .unwrap()
+            .success());
+    }
+
+    #[test]
+    fn test_check_content() {
+        let schema = Schema::from_yaml("fields:\n  status:\n    allowed: [open, done]\n").unwrap();
+        let linter = Linter::new(vec!["title".to_string()], None).unwrap();
+        let checks = HookChecks::new(Some(schema), Some(linter)).unwrap();
+
+        let rules = |content: &str| -> Vec<&str> {
+            checks
+                .check_content(Path::new("note.md"), content, true, Fences::Strict)
+                .unwrap()
+                .iter()
+                .map(|issue| issue.rule)
+                .collect()
+        };
+        assert!(rules("---\ntitle: Plan\nstatus: open\n---\n").is_empty());
+        assert_eq!(
+            rules("---\nstatus: wip\ntitle: Plan\n---\n"),
+            vec!["allowed", "key-order"]
+        );
+        assert_eq!(rules("---\ntitle: [unclosed\n---\n"), vec![PARSE_RULE]);
+        assert!(HookChecks::new(None, None).is_err());
+    }
+
+    #[test]
+    fn test_read_staged_content_of_partially_staged_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = temp_dir.path();
+        git(repo, &["init", "-q"]);
+        let vault = repo.join("vault");
+        fs::create_dir(&vault).unwrap();
+        let note = vault.join("note.md");
+        fs::write(&note, "---\nstatus: wip\ntitle: A\n---\n").unwrap();
+        git(repo, &["add", "-A"]);
+        fs::write(&note, "---\ntitle: A\nstatus: wip\n---\n").unwrap();
+
+        let files = staged_files(&vault).unwrap();
+        assert_eq!(files, vec![note.clone()]);
+        let contents = read_staged(&vault, &files).unwrap();
+        assert_eq!(
+            contents[0].as_ref().unwrap(),
+            "---\nstatus: wip\ntitle: A\n---\n"
+        );
+        let untracked = vault.join("new.md");
+        fs::write(&untracked, "---\ntitle: B\n---\n").unwrap();
+        assert!(read_staged(&vault, &[untracked]).unwrap()[0].is_err());
+
+        // Outside a repository the files are read from disk
+        let plain = TempDir::new().unwrap();
+        let loose = plain.path().join("loose.md");
+        fs::write(&loose, "---\ntitle: C\n---\n").unwrap();
+        assert_eq!(
+            read_staged(plain.path(), &[loose]).unwrap()[0]
+                .as_ref()
+                .unwrap(),
+            "---\ntitle: C\n---\n"
+        );
+    }
+}
@@ -0,0 +1,243 @@
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+
+/// Ignore file of git, read from the vault root
+pub const GITIGNORE_FILE: &str = ".gitignore";
+/// Obsidian settings holding the "Excluded files" (`userIgnoreFilters`)
+pub const OBSIDIAN_APP_CONFIG: &str = ".obsidian/app.json";
+
+/// One line of a `.gitignore` or an `--exclude` glob
+#[derive(Debug, Clone)]
+pub struct IgnorePattern {
+    regex: Regex,
+    /// Re-include paths an earlier pattern ignored (`!keep.md`)
+    negated: bool,
+    /// Only match directories (`templates/`)
+    dir_only: bool,
+}
+
+impl IgnorePattern {
+    /// A glob in `.gitignore` syntax: without a `/` it matches names at any depth, otherwise
+    /// paths relative to the vault. `None` for blank lines and comments.
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, glob) = match line.strip_prefix('!') {
+            Some(glob) => (true, glob),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+        let (dir_only, glob) = match glob.strip_suffix('/') {
+            Some(glob) => (true, glob),
+            None => (false, glob),
+        };
+        if glob.is_empty() {
+            return None;
+        }
+
+        let pattern = match glob.strip_prefix('/') {
+            Some(glob) => format!("^{}$", glob_to_regex(glob)),
+            None if glob.contains('/') => format!("^{}$", glob_to_regex(glob)),
+            None => format!("(?:^|/){}$", glob_to_regex(glob)),
+        };
+        let regex = Regex::new(&pattern)
+            .or_else(|_| Regex::new(&format!("^{}$", regex::escape(glob))))
+            .ok()?;
+        Some(Self {
+            regex,
+            negated,
+            dir_only,
+        })
+    }
+
+    /// An Obsidian excluded files entry: a path prefix, or a regular expression between
+    /// slashes (`/\.excalidraw$/`)
+    pub fn obsidian_filter(filter: &str) -> Option<Self> {
+        let pattern = match filter
+            .strip_prefix('/')
+            .and_then(|filter| filter.strip_suffix('/'))
+        {
+            Some(regex) if !regex.is_empty() => regex.to_string(),
+            _ if filter.is_empty() => return None,
+            _ => format!("^{}", regex::escape(filter)),
+        };
+        Some(Self {
+            regex: Regex::new(&pattern).ok()?,
+            negated: false,
+            dir_only: false,
+        })
+    }
+
+    fn matches(&self, relative: &str, is_dir: bool) -> bool {
+        (is_dir || !self.dir_only) && self.regex.is_match(relative)
+    }
+}
+
+/// Translate a glob to a regular expression: `*` and `?` stay within a directory, `**`
+/// crosses them
+fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::new();
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    regex.push_str("(?:.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '[' => {
+                let class: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                let class = class
+                    .strip_prefix('!')
+                    .map_or_else(|| class.clone(), |rest| format!("^{}", rest));
+                regex.push('[');
+                regex.push_str(&class.replace('\\', "\\\\"));
+                regex.push(']');
+            }
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    regex.push_str(&regex::escape(&escaped.to_string()));
+                }
+            }
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex
+}
+
+/// Patterns deciding which files and directories a scan skips. The last matching pattern
+/// wins, so `!` patterns can take back earlier ones.
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreRules {
+    patterns: Vec<IgnorePattern>,
+}
+
+impl IgnoreRules {
+    /// Rules from `--exclude` globs
+    pub fn from_globs<S: AsRef<str>>(globs: &[S]) -> Self {
+        Self {
+            patterns: globs
+                .iter()
+                .filter_map(|glob| IgnorePattern::parse(glob.as_ref()))
+                .collect(),
+        }
+    }
+
+    /// Rules from the `.gitignore` and the Obsidian excluded files of a vault, empty when it
+    /// has neither
+    pub fn from_vault(vault_path: &Path) -> Self {
+        let mut patterns: Vec<IgnorePattern> = fs::read_to_string(vault_path.join(GITIGNORE_FILE))
+            .map(|gitignore| gitignore.lines().filter_map(IgnorePattern::parse).collect())
+            .unwrap_or_default();
+        patterns.extend(
+            obsidian_filters(vault_path)
+                .iter()
+                .filter_map(|filter| IgnorePattern::obsidian_filter(filter)),
+        );
+        Self { patterns }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Whether a path relative to the vault is ignored
+    pub fn is_ignored(&self, relative: &Path, is_dir: bool) -> bool {
+        let relative = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        if relative.is_empty() {
+            return false;
+        }
+        self.patterns
+            .iter()
+            .rev()
+            .find(|pattern| pattern.matches(&relative, is_dir))
+            .is_some_and(|pattern| !pattern.negated)
+    }
+}
+
+/// The `userIgnoreFilters` in `.obsidian/app.json`, empty when there are none
+fn obsidian_filters(vault_path: &Path) -> Vec<String> {
+    let Ok(content) = fs::read_to_string(vault_path.join(OBSIDIAN_APP_CONFIG)) else {
+        return Vec::new();
+    };
+    serde_json::from_str::<serde_json::Value>(&content)
+        .ok()
+        .and_then(|config| {
+            config.get("userIgnoreFilters")?.as_array().map(|filters| {
+                filters
+                    .iter()
+                    .filter_map(|filter| filter.as_str().map(String::from))
+                    .collect()
+            })
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn ignored(rules: &IgnoreRules, path: &str, is_dir: bool) -> bool {
+        rules.is_ignored(Path::new(path), is_dir)
+    }
+
+    #[test]
+    fn test_ignore_globs() {
+        let rules = IgnoreRules::from_globs(&[
+            "templates",
+            "archive/old/",
+            "*.excalidraw.md",
+            "journal/**/draft-?.md",
+            "# comment",
+            "!keep.excalidraw.md",
+        ]);
+
+        assert!(ignored(&rules, "templates", true));
+        assert!(ignored(&rules, "projects/templates", true));
+        assert!(!ignored(&rules, "templates.md", false));
+        assert!(ignored(&rules, "archive/old", true));
+        assert!(!ignored(&rules, "archive/old", false));
+        assert!(!ignored(&rules, "notes/archive/old", true));
+        assert!(ignored(&rules, "drawings/plan.excalidraw.md", false));
+        assert!(!ignored(&rules, "drawings/keep.excalidraw.md", false));
+        assert!(ignored(&rules, "journal/2024/06/draft-1.md", false));
+        assert!(ignored(&rules, "journal/draft-2.md", false));
+        assert!(!ignored(&rules, "journal/draft-10.md", false));
+        assert!(!ignored(&rules, "", true));
+    }
+
+    #[test]
+    fn test_ignore_rules_from_vault() {
+        let temp_dir = TempDir::new().unwrap();
+        assert!(IgnoreRules::from_vault(temp_dir.path()).is_empty());
+
+        fs::write(temp_dir.path().join(".gitignore"), "/private/\n*.tmp.md\n").unwrap();
+        fs::create_dir(temp_dir.path().join(".obsidian")).unwrap();
+        fs::write(
+            temp_dir.path().join(".obsidian/app.json"),
+            r#"{"userIgnoreFilters": ["Templates/", "/\\.canvas\\.md$/"], "alwaysUpdateLinks": true}"#,
+        )
+        .unwrap();
+        let rules = IgnoreRules::from_vault(temp_dir.path());
+
+        assert!(ignored(&rules, "private", true));
+        assert!(!ignored(&rules, "notes/private", true));
+        assert!(ignored(&rules, "notes/scratch.tmp.md", false));
+        assert!(ignored(&rules, "Templates/Daily.md", false));
+        assert!(ignored(&rules, "boards/plan.canvas.md", false));
+        assert!(!ignored(&rules, "notes/Templates.md", false));
+    }
+}
@@ -0,0 +1,319 @@
+use crate::frontmatter::Note;
+use crate::links::extract_links;
+use crate::yaml_compat::collect_yaml_strings;
+use anyhow::{anyhow, Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use yaml_rust2::Yaml;
+
+/// A lookup table from a CSV or JSON file, joined onto notes by the value of a field.
+///
+/// The columns of the row whose key matches the field become virtual fields named
+/// `<field>.<column>`: with `--join project=projects.csv`, a note with `project: Alpha`
+/// gets `project.owner` from the `Alpha` row. Keys are matched ignoring case, and
+/// `[[wikilinks]]` match by their target.
+#[derive(Debug, Clone, Default)]
+pub struct Join {
+    field: String,
+    /// Columns in the order of the file, without the key column
+    columns: Vec<String>,
+    /// Rows by lowercased key, one value per column
+    rows: HashMap<String, Vec<Yaml>>,
+}
+
+impl Join {
+    /// Load a table, as JSON for `.json` files and as CSV otherwise
+    pub fn from_file<P: AsRef<Path>>(field: &str, path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read lookup table: {}", path.display()))?;
+        let join = if path.extension().is_some_and(|ext| ext == "json") {
+            Self::from_json(field, &content)
+        } else {
+            Self::from_csv(field, &content)
+        };
+        join.with_context(|| format!("Invalid lookup table: {}", path.display()))
+    }
+
+    /// A CSV table with a header row. The key is the column named like the field, or the
+    /// first column.
+    pub fn from_csv(field: &str, content: &str) -> Result<Self> {
+        let mut records = parse_csv(content).into_iter();
+        let header = records
+            .next()
+            .ok_or_else(|| anyhow!("Missing header row"))?;
+        let key_index = header
+            .iter()
+            .position(|column| column.trim().eq_ignore_ascii_case(field))
+            .unwrap_or(0);
+
+        let mut join = Self::new(field);
+        join.columns = header
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != key_index)
+            .map(|(_, column)| column.trim().to_string())
+            .collect();
+        for record in records {
+            let Some(key) = record.get(key_index) else {
+                continue;
+            };
+            let values = (0..header.len())
+                .filter(|i| *i != key_index)
+                .map(|i| match record.get(i).map(|value| value.trim()) {
+                    Some("") | None => Yaml::Null,
+                    Some(value) => Yaml::from_str(value),
+                })
+                .collect();
+            join.insert(key, values);
+        }
+        Ok(join)
+    }
+
+    /// Either an object of rows by key, or an array of rows with a key named like the field
+    pub fn from_json(field: &str, content: &str) -> Result<Self> {
+        let value: serde_json::Value = serde_json::from_str(content).context("Invalid JSON")?;
+        let rows: Vec<(String, &serde_json::Map<String, serde_json::Value>)> = match &value {
+            serde_json::Value::Object(rows) => rows
+                .iter()
+                .map(|(key, row)| Ok((key.clone(), json_row(row)?)))
+                .collect::<Result<_>>()?,
+            serde_json::Value::Array(rows) => rows
+                .iter()
+                .map(|row| {
+                    let row = json_row(row)?;
+                    let key = row
+                        .iter()
+                        .find(|(column, _)| column.eq_ignore_ascii_case(field))
+                        .map(|(_, key)| json_key(key))
+                        .ok_or_else(|| anyhow!("Row without a \"{}\" key: {:?}", field, row))?;
+                    Ok((key, row))
+                })
+                .collect::<Result<_>>()?,
+            _ => return Err(anyhow!("Expected an object or an array of objects")),
+        };
+
+        let mut join = Self::new(field);
+        for (_, row) in &rows {
+            for column in row.keys() {
+                if !column.eq_ignore_ascii_case(field) && !join.columns.contains(column) {
+                    join.columns.push(column.clone());
+                }
+            }
+        }
+        for (key, row) in rows {
+            let values = join
+                .columns
+                .iter()
+                .map(|column| row.get(column).map_or(Yaml::Null, json_to_yaml))
+                .collect();
+            join.insert(&key, values);
+        }
+        Ok(join)
+    }
+
+    fn new(field: &str) -> Self {
+        Self {
+            field: field.to_string(),
+            ..Self::default()
+        }
+    }
+
+    fn insert(&mut self, key: &str, values: Vec<Yaml>) {
+        self.rows.insert(key.trim().to_lowercase(), values);
+    }
+
+    /// Names of the fields the join adds, `<field>.<column>`
+    pub fn fields(&self) -> Vec<String> {
+        self.columns
+            .iter()
+            .map(|column| format!("{}.{}", self.field, column))
+            .collect()
+    }
+
+    /// Add the columns of the matching row to a note. For list fields the values of all
+    /// matching rows are collected into lists.
+    pub fn apply(&self, note: &mut Note) {
+        let Some(value) = note.get_frontmatter_value_case_insensitive(&self.field) else {
+            return;
+        };
+        let is_list = matches!(value, Yaml::Array(_));
+        let rows: Vec<&Vec<Yaml>> = collect_yaml_strings(value)
+            .iter()
+            .filter_map(|key| self.rows.get(&lookup_key(key)))
+            .collect();
+        if rows.is_empty() {
+            return;
+        }
+
+        for (i, name) in self.fields().iter().enumerate() {
+            let value = if is_list {
+                Yaml::Array(
+                    rows.iter()
+                        .map(|row| row[i].clone())
+                        .filter(|value| !value.is_null())
+                        .collect(),
+                )
+            } else {
+                rows[0][i].clone()
+            };
+            note.set_joined_field(name, value);
+        }
+    }
+}
+
+/// The key a note value is looked up by: the target of a wikilink, or the value itself
+fn lookup_key(value: &str) -> String {
+    let key = if value.contains("[[") {
+        extract_links(value).into_iter().next()
+    } else {
+        None
+    };
+    key.as_deref().unwrap_or(value).trim().to_lowercase()
+}
+
+fn json_row(row: &serde_json::Value) -> Result<&serde_json::Map<String, serde_json::Value>> {
+    row.as_object()
+        .ok_or_else(|| anyhow!("Expected an object per row, got {}", row))
+}
+
+fn json_key(key: &serde_json::Value) -> String {
+    match key {
+        serde_json::Value::String(key) => key.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn json_to_yaml(value: &serde_json::Value) -> Yaml {
+    match value {
+        serde_json::Value::Null => Yaml::Null,
+        serde_json::Value::Bool(b) => Yaml::Boolean(*b),
+        serde_json::Value::Number(n) => n
+            .as_i64()
+            .map_or_else(|| Yaml::Real(n.to_string()), Yaml::Integer),
+        serde_json::Value::String(s) => Yaml::String(s.clone()),
+        serde_json::Value::Array(items) => Yaml::Array(items.iter().map(json_to_yaml).collect()),
+        serde_json::Value::Object(map) => Yaml::Hash(
+            map.iter()
+                .map(|(k, v)| (Yaml::String(k.clone()), json_to_yaml(v)))
+                .collect(),
+        ),
+    }
+}
+
+/// Split CSV into records: fields separated by commas, quoted with `"` when they contain
+/// commas, quotes (doubled) or line breaks. Empty lines are skipped.
+fn parse_csv(content: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = content.trim_start_matches('\u{feff}').chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => record.push(std::mem::take(&mut field)),
+            '\r' if !in_quotes => {}
+            '\n' if !in_quotes => {
+                record.push(std::mem::take(&mut field));
+                if record.iter().any(|field| !field.is_empty()) {
+                    records.push(std::mem::take(&mut record));
+                }
+                record.clear();
+            }
+            c => field.push(c),
+        }
+    }
+    record.push(field);
+    if record.iter().any(|field| !field.is_empty()) {
+        records.push(record);
+    }
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(field: &str, value: Yaml) -> Note {
+        Note::new(
+            "note.md".to_string(),
+            HashMap::from([(field.to_string(), value)]),
+        )
+    }
+
+    #[test]
+    fn test_parse_csv() {
+        assert_eq!(
+            parse_csv("a,b\n\"x, y\",\"say \"\"hi\"\"\"\r\n\n1,\n"),
+            vec![
+                vec!["a".to_string(), "b".to_string()],
+                vec!["x, y".to_string(), "say \"hi\"".to_string()],
+                vec!["1".to_string(), String::new()],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_join_csv() {
+        let join = Join::from_csv(
+            "project",
+            "owner,project,budget\nalice,Alpha,1000\nbob,Beta,\n",
+        )
+        .unwrap();
+        assert_eq!(join.fields(), vec!["project.owner", "project.budget"]);
+
+        let mut alpha = note("project", Yaml::String("[[alpha|The Alpha]]".to_string()));
+        join.apply(&mut alpha);
+        assert_eq!(
+            alpha.get_frontmatter_value("project.owner"),
+            Some(&Yaml::String("alice".to_string()))
+        );
+        assert_eq!(
+            alpha.get_frontmatter_value("project.budget"),
+            Some(&Yaml::Integer(1000))
+        );
+        assert_eq!(alpha.joined_fields, join.fields());
+
+        let mut both = note(
+            "project",
+            Yaml::Array(vec![
+                Yaml::String("Alpha".to_string()),
+                Yaml::String("Beta".to_string()),
+            ]),
+        );
+        join.apply(&mut both);
+        assert_eq!(
+            both.get_frontmatter_value("project.budget"),
+            Some(&Yaml::Array(vec![Yaml::Integer(1000)]))
+        );
+
+        let mut unknown = note("project", Yaml::String("Gamma".to_string()));
+        join.apply(&mut unknown);
+        assert!(unknown.get_frontmatter_value("project.owner").is_none());
+    }
+
+    #[test]
+    fn test_join_json() {
+        let by_key = Join::from_json("project", r#"{"Alpha": {"owner": "alice"}}"#).unwrap();
+        let rows =
+            Join::from_json("project", r#"[{"project": "Alpha", "owner": "alice"}]"#).unwrap();
+        for join in [by_key, rows] {
+            let mut alpha = note("project", Yaml::String("Alpha".to_string()));
+            join.apply(&mut alpha);
+            assert_eq!(
+                alpha.get_frontmatter_value("project.owner"),
+                Some(&Yaml::String("alice".to_string()))
+            );
+        }
+
+        assert!(Join::from_json("project", r#"[{"owner": "alice"}]"#).is_err());
+        assert!(Join::from_json("project", "3").is_err());
+    }
+}
@@ -0,0 +1,58 @@
+//! Index and filter the notes of an Obsidian vault by their frontmatter.
+//!
+//! This is the library behind the `aktenfux` command line tool. Scan a vault into
+//! [`Note`]s with a [`VaultScanner`], select notes with [`FilterCriteria`] and serialize
+//! them with the functions in [`output`]:
+//!
+//! ```no_run
+//! use aktenfux::{output, FieldFilter, FilterCriteria, VaultScanner};
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let scan = VaultScanner::new("/path/to/vault")?.scan_vault(false, true, true, None)?;
+//! let criteria = FilterCriteria::new(vec![FieldFilter::parse("status=open")?]);
+//! let open_notes = criteria.apply_filters(&scan.notes);
+//!
+//! println!("{}", output::notes_to_json(&open_notes)?);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The editing side ([`writer`], [`plan`], [`migrate`]) rewrites frontmatter in place and
+//! keeps the formatting of every field it doesn't change.
+
+pub mod batch;
+pub mod canvas;
+pub mod collation;
+pub mod columns;
+pub mod created;
+pub mod dashboard;
+pub mod exec;
+pub mod export;
+pub mod filter;
+pub mod frontmatter;
+pub mod hints;
+pub mod hook;
+pub mod ignore;
+pub mod join;
+pub mod links;
+pub mod lint;
+pub mod logger;
+pub mod matcher;
+pub mod migrate;
+pub mod normalize;
+pub mod output;
+pub mod plan;
+pub mod policy;
+pub mod progress;
+pub mod revision;
+pub mod scanner;
+pub mod schema;
+pub mod stats;
+pub mod titles;
+pub mod writer;
+pub mod yaml_compat;
+
+pub use filter::{FieldFilter, FilterCriteria};
+pub use frontmatter::Note;
+pub use output::SerializableNote;
+pub use scanner::{ScanOutcome, VaultScanner};
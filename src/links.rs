@@ -0,0 +1,308 @@
+use crate::frontmatter::Note;
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Extract link targets from a note body: `[[wikilinks]]`, `![[embeds]]` and markdown links
+/// to local files. Aliases (`|text`), headings (`#section`) and block references are dropped.
+pub fn extract_links(body: &str) -> Vec<String> {
+    static WIKILINK_RE: OnceLock<Regex> = OnceLock::new();
+    static MARKDOWN_LINK_RE: OnceLock<Regex> = OnceLock::new();
+    let wikilink_re = WIKILINK_RE.get_or_init(|| Regex::new(r"\[\[([^\[\]]+?)\]\]").unwrap());
+    let markdown_link_re =
+        MARKDOWN_LINK_RE.get_or_init(|| Regex::new(r"\[[^\]]*\]\(([^)\s]+)\)").unwrap());
+
+    let mut links = Vec::new();
+    let mut in_code_block = false;
+    for line in body.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block {
+            continue;
+        }
+
+        let wikilinks = wikilink_re.captures_iter(line).map(|c| {
+            let target = c[1].split('|').next().unwrap_or_default();
+            target
+                .split('#')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_string()
+        });
+        let markdown_links = markdown_link_re
+            .captures_iter(line)
+            .map(|c| c[1].to_string())
+            .filter(|target| !target.contains("://") && !target.starts_with("mailto:"))
+            .map(|target| {
+                let target = target.split('#').next().unwrap_or_default();
+                target.replace("%20", " ")
+            });
+
+        for link in wikilinks.chain(markdown_links) {
+            if !link.is_empty() && !links.contains(&link) {
+                links.push(link);
+            }
+        }
+    }
+    links
+}
+
+/// Resolves link targets to note paths the way Obsidian does: by file name anywhere in the
+/// vault, or by (partial) path when the link contains a folder
+pub struct LinkIndex<'a> {
+    notes: &'a [Note],
+    by_name: HashMap<String, Vec<usize>>,
+    attachments: HashMap<String, Vec<String>>,
+}
+
+/// A link whose target is neither a note nor an attachment in the vault
+#[derive(Debug, Clone)]
+pub struct BrokenLink<'a> {
+    pub source: &'a Note,
+    pub target: String,
+}
+
+impl<'a> LinkIndex<'a> {
+    pub fn new(notes: &'a [Note]) -> Self {
+        let mut by_name: HashMap<String, Vec<usize>> = HashMap::new();
+        for (i, note) in notes.iter().enumerate() {
+            if let Some(stem) = Path::new(&note.path).file_stem() {
+                by_name
+                    .entry(stem.to_string_lossy().to_lowercase())
+                    .or_default()
+                    .push(i);
+            }
+        }
+        Self {
+            notes,
+            by_name,
+            attachments: HashMap::new(),
+        }
+    }
+
+    /// Also accept links to these non-note files (images, PDFs, ...)
+    #[must_use]
+    pub fn with_attachments(mut self, paths: Vec<String>) -> Self {
+        for path in paths {
+            if let Some(name) = Path::new(&path).file_name() {
+                self.attachments
+                    .entry(name.to_string_lossy().to_lowercase())
+                    .or_default()
+                    .push(path);
+            }
+        }
+        self
+    }
+
+    /// Resolve a link found in the note at `from_path`
+    pub fn resolve(&self, target: &str, from_path: &str) -> Option<&'a Note> {
+        // Wikilinks usually omit the extension, and note names may contain dots
+        let target_path = Path::new(target.strip_suffix(".md").unwrap_or(target));
+        let name = target_path.file_name()?.to_string_lossy().to_lowercase();
+        let candidates = self.by_name.get(&name)?;
+
+        let has_folder = target_path.components().count() > 1;
+        let target_lower = normalize(target_path).to_string_lossy().to_lowercase();
+        let from_dir = Path::new(from_path)
+            .parent()
+            .unwrap_or_else(|| Path::new(""));
+        let relative = normalize(&from_dir.join(target_path))
+            .to_string_lossy()
+            .to_lowercase();
+
+        let mut best: Option<&'a Note> = None;
+        for &i in candidates {
+            let note = &self.notes[i];
+            let note_path = Path::new(&note.path).with_extension("");
+            let note_lower = normalize(&note_path).to_string_lossy().to_lowercase();
+
+            if has_folder {
+                // Relative markdown links first, then vault-relative path suffixes
+                if note_lower == relative || note_lower.ends_with(&format!("/{}", target_lower)) {
+                    return Some(note);
+                }
+            } else if Path::new(&note.path).parent() == Some(from_dir) {
+                // Obsidian prefers a same-named note in the same folder
+                return Some(note);
+            } else if best.is_none_or(|b| note.path < b.path) {
+                best = Some(note);
+            }
+        }
+        best
+    }
+
+    /// Whether a link points at a known attachment
+    fn is_attachment(&self, target: &str) -> bool {
+        let target_path = Path::new(target);
+        let Some(name) = target_path.file_name() else {
+            return false;
+        };
+        let target_lower = normalize(target_path).to_string_lossy().to_lowercase();
+        self.attachments
+            .get(&name.to_string_lossy().to_lowercase())
+            .is_some_and(|paths| {
+                target_path.components().count() == 1
+                    || paths
+                        .iter()
+                        .any(|path| path.to_lowercase().ends_with(&target_lower))
+            })
+    }
+
+    /// Outgoing links of a note, each with the note it resolves to (if any)
+    pub fn outgoing(&self, note: &Note) -> Vec<(String, Option<&'a Note>)> {
+        note.links
+            .iter()
+            .map(|target| (target.clone(), self.resolve(target, &note.path)))
+            .collect()
+    }
+
+    /// Notes that link to the note at `path`
+    pub fn backlinks(&self, path: &str) -> Vec<&'a Note> {
+        self.notes
+            .iter()
+            .filter(|note| {
+                note.path != path
+                    && note.links.iter().any(|target| {
+                        self.resolve(target, &note.path)
+                            .is_some_and(|n| n.path == path)
+                    })
+            })
+            .collect()
+    }
+
+    /// Notes that no other note links to
+    pub fn orphans(&self) -> Vec<&'a Note> {
+        let mut linked = HashSet::new();
+        for note in self.notes {
+            for target in &note.links {
+                if let Some(resolved) = self.resolve(target, &note.path) {
+                    if resolved.path != note.path {
+                        linked.insert(resolved.path.as_str());
+                    }
+                }
+            }
+        }
+        self.notes
+            .iter()
+            .filter(|note| !linked.contains(note.path.as_str()))
+            .collect()
+    }
+
+    /// Links that resolve neither to a note nor to an attachment, in note order
+    pub fn broken_links(&self) -> Vec<BrokenLink<'a>> {
+        self.notes
+            .iter()
+            .flat_map(|note| {
+                note.links
+                    .iter()
+                    .filter(|target| {
+                        self.resolve(target, &note.path).is_none() && !self.is_attachment(target)
+                    })
+                    .map(move |target| BrokenLink {
+                        source: note,
+                        target: target.clone(),
+                    })
+            })
+            .collect()
+    }
+}
+
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn note_with_links(path: &str, links: &[&str]) -> Note {
+        let mut note = Note::new(path.to_string(), HashMap::new());
+        note.links = links.iter().map(|l| (*l).to_string()).collect();
+        note
+    }
+
+    #[test]
+    fn test_extract_links() {
+        let body = r#"See [[Project X]] and [[Project X|the project]].
+Embedded: ![[diagram.png]], heading link [[Meeting Notes#Agenda]].
+A [markdown link](notes/Other%20Note.md) and an [external](https://example.com).
+```
+[[Not A Link]]
+```"#;
+        assert_eq!(
+            extract_links(body),
+            vec![
+                "Project X",
+                "diagram.png",
+                "Meeting Notes",
+                "notes/Other Note.md"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_and_backlinks() {
+        let notes = vec![
+            note_with_links("vault/projects/Project X.md", &[]),
+            note_with_links("vault/daily/today.md", &["Project X", "Missing"]),
+            note_with_links("vault/projects/plan.md", &["./Project X.md"]),
+            note_with_links("vault/other.md", &["projects/plan"]),
+        ];
+        let index = LinkIndex::new(&notes);
+
+        let outgoing = index.outgoing(&notes[1]);
+        assert_eq!(
+            outgoing[0].1.map(|n| n.path.as_str()),
+            Some("vault/projects/Project X.md")
+        );
+        assert!(outgoing[1].1.is_none());
+        assert_eq!(
+            index
+                .resolve("projects/plan", "vault/other.md")
+                .map(|n| n.path.as_str()),
+            Some("vault/projects/plan.md")
+        );
+
+        let backlinks = index.backlinks("vault/projects/Project X.md");
+        let paths: Vec<&str> = backlinks.iter().map(|n| n.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec!["vault/daily/today.md", "vault/projects/plan.md"]
+        );
+    }
+
+    #[test]
+    fn test_orphans_and_broken_links() {
+        let notes = vec![
+            note_with_links("vault/a.md", &["b", "diagram.png", "Meeting 2024.01.05"]),
+            note_with_links("vault/b.md", &["a", "b", "missing.pdf"]),
+            note_with_links("vault/Meeting 2024.01.05.md", &[]),
+            note_with_links("vault/lonely.md", &["lonely"]),
+        ];
+        let index =
+            LinkIndex::new(&notes).with_attachments(vec!["vault/assets/diagram.png".to_string()]);
+
+        let orphans: Vec<&str> = index.orphans().iter().map(|n| n.path.as_str()).collect();
+        assert_eq!(orphans, vec!["vault/lonely.md"]);
+
+        let broken = index.broken_links();
+        assert_eq!(broken.len(), 1);
+        assert_eq!(broken[0].source.path, "vault/b.md");
+        assert_eq!(broken[0].target, "missing.pdf");
+    }
+}
@@ -0,0 +1,324 @@
+use crate::frontmatter::Note;
+use crate::writer::{read_document, FrontmatterEdit};
+use anyhow::{anyhow, Result};
+use yaml_rust2::yaml::Hash;
+use yaml_rust2::Yaml;
+
+/// A problem a lint rule or the schema found in a single note
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintIssue {
+    pub path: String,
+    pub rule: &'static str,
+    pub message: String,
+}
+
+/// Requires the configured keys to come first, in the configured order. Other keys may
+/// follow in any order.
+#[derive(Debug, Clone)]
+pub struct KeyOrder {
+    order: Vec<String>,
+}
+
+impl KeyOrder {
+    pub const RULE: &'static str = "key-order";
+
+    pub fn new(order: Vec<String>) -> Self {
+        Self { order }
+    }
+
+    /// The keys in canonical order, keys without a configured position keep their
+    /// relative order after the configured ones
+    pub fn sorted<'a>(&self, keys: &[&'a str]) -> Vec<&'a str> {
+        let mut sorted = keys.to_vec();
+        sorted.sort_by_key(|key| {
+            self.order
+                .iter()
+                .position(|k| k == key)
+                .unwrap_or(self.order.len())
+        });
+        sorted
+    }
+
+    pub fn check(&self, keys: &[&str]) -> Option<String> {
+        let sorted = self.sorted(keys);
+        let (expected, found) = sorted.iter().zip(keys).find(|(a, b)| a != b)?;
+        Some(format!("'{}' should come before '{}'", expected, found))
+    }
+
+    /// Reorder the frontmatter, returning whether anything moved
+    pub fn apply(&self, frontmatter: &mut Hash) -> bool {
+        let keys: Vec<&str> = frontmatter.keys().filter_map(|k| k.as_str()).collect();
+        // Leave mappings with non-string keys alone rather than guess where they belong
+        if keys.len() != frontmatter.len() || self.check(&keys).is_none() {
+            return false;
+        }
+
+        let sorted: Vec<String> = self.sorted(&keys).into_iter().map(str::to_string).collect();
+        let mut entries = std::mem::take(frontmatter);
+        for key in sorted {
+            let key = Yaml::String(key);
+            if let Some(value) = entries.remove(&key) {
+                frontmatter.insert(key, value);
+            }
+        }
+        true
+    }
+}
+
+/// A casing convention for frontmatter keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyCase {
+    /// `due-date`
+    Kebab,
+    /// `due_date`
+    Snake,
+    /// `duedate`, or `due date` and `due-date` with their separators kept
+    Lower,
+}
+
+impl KeyCase {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "kebab-case" | "kebab" => Ok(Self::Kebab),
+            "snake_case" | "snake" => Ok(Self::Snake),
+            "lowercase" | "lower" => Ok(Self::Lower),
+            _ => Err(anyhow!(
+                "Unknown key case: '{}'. Use kebab-case, snake_case or lowercase",
+                name
+            )),
+        }
+    }
+
+    /// The key written in this case. Words are split at spaces, `-`, `_` and where camelCase
+    /// starts a new word; dots of namespaced keys like `project.name` stay.
+    pub fn convert(self, key: &str) -> String {
+        match self {
+            Self::Kebab => words(key).join("-"),
+            Self::Snake => words(key).join("_"),
+            Self::Lower => key.to_lowercase(),
+        }
+    }
+}
+
+/// The lowercase words of a key
+fn words(key: &str) -> Vec<String> {
+    let chars: Vec<char> = key.chars().collect();
+    let mut words: Vec<String> = Vec::new();
+    let mut word = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_whitespace() || c == '-' || c == '_' {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+        // `dueDate` and `HTTPServer` start a word at the capital of the next word
+        let previous = i.checked_sub(1).map(|i| chars[i]);
+        let next = chars.get(i + 1);
+        let starts_word = c.is_uppercase()
+            && previous.is_some_and(|p| {
+                p.is_lowercase()
+                    || p.is_ascii_digit()
+                    || (p.is_uppercase() && next.is_some_and(|n| n.is_lowercase()))
+            });
+        if starts_word && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        word.extend(c.to_lowercase());
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// Requires top-level keys to follow a casing convention
+#[derive(Debug, Clone)]
+pub struct KeyCasing {
+    case: KeyCase,
+}
+
+impl KeyCasing {
+    pub const RULE: &'static str = "key-case";
+
+    pub const fn new(case: KeyCase) -> Self {
+        Self { case }
+    }
+
+    /// A message per key that doesn't follow the convention
+    pub fn check(&self, keys: &[&str]) -> Vec<String> {
+        keys.iter()
+            .filter_map(|key| {
+                let converted = self.case.convert(key);
+                (converted != *key).then(|| format!("'{}' should be '{}'", key, converted))
+            })
+            .collect()
+    }
+
+    /// Rename the keys to the convention, keeping their position. Keys whose new name is
+    /// already taken are left for a person to merge. Returns whether anything was renamed.
+    pub fn apply(&self, frontmatter: &mut Hash) -> bool {
+        let keys: Vec<String> = frontmatter
+            .keys()
+            .filter_map(Yaml::as_str)
+            .map(str::to_string)
+            .collect();
+        let mut changed = false;
+        for key in keys {
+            let converted = self.case.convert(&key);
+            if converted != key {
+                changed |= FrontmatterEdit::Rename(key, converted).apply(frontmatter);
+            }
+        }
+        changed
+    }
+}
+
+/// The lint rules configured for a vault
+#[derive(Debug, Clone)]
+pub struct Linter {
+    key_order: Option<KeyOrder>,
+    key_casing: Option<KeyCasing>,
+}
+
+impl Linter {
+    pub fn new(key_order: Vec<String>, key_case: Option<KeyCase>) -> Result<Self> {
+        if key_order.is_empty() && key_case.is_none() {
+            return Err(anyhow!(
+                "No lint rules configured: set key_order or key_case in the [lint] section of aktenfux.toml or use --key-order or --key-case"
+            ));
+        }
+        Ok(Self {
+            key_order: (!key_order.is_empty()).then(|| KeyOrder::new(key_order)),
+            key_casing: key_case.map(KeyCasing::new),
+        })
+    }
+
+    /// Check the frontmatter of a note as written in the file
+    pub fn check_note(&self, note: &Note) -> Result<Vec<LintIssue>> {
+        let doc = read_document(&note.path, note.fences)?;
+        Ok(self.check(&note.path, &doc.frontmatter))
+    }
+
+    pub fn check(&self, path: &str, frontmatter: &Hash) -> Vec<LintIssue> {
+        let keys: Vec<&str> = frontmatter.keys().filter_map(|k| k.as_str()).collect();
+        let issue = |rule: &'static str, message: String| LintIssue {
+            path: path.to_string(),
+            rule,
+            message,
+        };
+        let casing = self
+            .key_casing
+            .iter()
+            .flat_map(|rule| rule.check(&keys))
+            .map(|message| issue(KeyCasing::RULE, message));
+        self.key_order
+            .iter()
+            .filter_map(|rule| rule.check(&keys))
+            .map(|message| issue(KeyOrder::RULE, message))
+            .chain(casing)
+            .collect()
+    }
+
+    /// Apply the fixes of all rules, returning whether the frontmatter changed
+    pub fn fix(&self, frontmatter: &mut Hash) -> bool {
+        let mut changed = false;
+        // Keys are renamed first, so the order applies to their new names
+        if let Some(rule) = &self.key_casing {
+            changed |= rule.apply(frontmatter);
+        }
+        if let Some(rule) = &self.key_order {
+            changed |= rule.apply(frontmatter);
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontmatter::Fences;
+    use crate::writer::FrontmatterDocument;
+
+    #[test]
+    fn test_key_order_check() {
+        let rule = KeyOrder::new(vec!["title".to_string(), "date".to_string()]);
+
+        assert_eq!(rule.check(&["title", "date", "status"]), None);
+        assert_eq!(rule.check(&["title", "status"]), None);
+        assert_eq!(
+            rule.check(&["date", "title"]),
+            Some("'title' should come before 'date'".to_string())
+        );
+        assert_eq!(
+            rule.sorted(&["status", "date", "tags", "title"]),
+            vec!["title", "date", "status", "tags"]
+        );
+    }
+
+    #[test]
+    fn test_fix_key_order() {
+        let mut doc = FrontmatterDocument::parse(
+            "---\nstatus: open # keep me\ntags:\n  - work\ntitle: Plan\n---\nBody\n",
+            Fences::Strict,
+        )
+        .unwrap();
+        let linter = Linter::new(vec!["title".to_string(), "tags".to_string()], None).unwrap();
+
+        assert_eq!(linter.check("a.md", &doc.frontmatter).len(), 1);
+        assert!(linter.fix(&mut doc.frontmatter));
+        assert!(linter.check("a.md", &doc.frontmatter).is_empty());
+        assert_eq!(
+            doc.render().unwrap(),
+            "---\ntitle: Plan\ntags:\n  - work\nstatus: open # keep me\n---\nBody\n"
+        );
+        assert!(Linter::new(Vec::new(), None).is_err());
+    }
+
+    #[test]
+    fn test_key_case() {
+        let cases = [
+            ("dueDate", "due-date", "due_date", "duedate"),
+            ("Due Date", "due-date", "due_date", "due date"),
+            (
+                "HTTPServer_url",
+                "http-server-url",
+                "http_server_url",
+                "httpserver_url",
+            ),
+            (
+                "project.ownerName",
+                "project.owner-name",
+                "project.owner_name",
+                "project.ownername",
+            ),
+            ("📅 due", "📅-due", "📅_due", "📅 due"),
+        ];
+        for (key, kebab, snake, lower) in cases {
+            assert_eq!(KeyCase::Kebab.convert(key), kebab);
+            assert_eq!(KeyCase::Snake.convert(key), snake);
+            assert_eq!(KeyCase::Lower.convert(key), lower);
+        }
+        assert_eq!(KeyCase::parse("snake_case").unwrap(), KeyCase::Snake);
+        assert!(KeyCase::parse("camelCase").is_err());
+
+        let mut doc = FrontmatterDocument::parse(
+            "---\ntitle: Plan\ndueDate: 2024-06-01\nDue Date: 2024-07-01\nStatus: open\n---\n",
+            Fences::Strict,
+        )
+        .unwrap();
+        let linter = Linter::new(Vec::new(), Some(KeyCase::Snake)).unwrap();
+        let issues = linter.check("a.md", &doc.frontmatter);
+        assert_eq!(issues.len(), 3);
+        assert_eq!(issues[0].rule, KeyCasing::RULE);
+        assert_eq!(issues[0].message, "'dueDate' should be 'due_date'");
+
+        // The second key that would become due_date stays for a person to merge
+        assert!(linter.fix(&mut doc.frontmatter));
+        assert_eq!(
+            doc.render().unwrap(),
+            "---\ntitle: Plan\ndue_date: 2024-06-01\nDue Date: 2024-07-01\nstatus: open\n---\n"
+        );
+        assert_eq!(linter.check("a.md", &doc.frontmatter).len(), 1);
+    }
+}
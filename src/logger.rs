@@ -1,5 +1,10 @@
-use std::collections::HashMap;
-use std::path::Path;
+use anyhow::{anyhow, Context, Result};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::Duration;
 
 #[derive(Debug, Clone)]
 pub enum ErrorLevel {
@@ -8,23 +13,150 @@ pub enum ErrorLevel {
     Info,
 }
 
+impl ErrorLevel {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Critical => "critical",
+            Self::Warning => "warning",
+            Self::Info => "info",
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct LogEntry {
-    #[allow(dead_code)]
     pub level: ErrorLevel,
-    #[allow(dead_code)]
     pub message: String,
-    #[allow(dead_code)]
     pub file_path: Option<String>,
 }
 
+impl LogEntry {
+    /// The summary category of a warning or error, `None` for info messages
+    pub fn category(&self) -> Option<String> {
+        match self.level {
+            ErrorLevel::Info => None,
+            _ if self.message.contains("Used lenient parsing") => Some(LENIENT_PARSING.to_string()),
+            _ if self.message.contains(NOT_FRONTMATTER) => Some(HORIZONTAL_RULE.to_string()),
+            _ if self.message.contains(NOT_LOCAL) => Some(NOT_LOCAL_CATEGORY.to_string()),
+            _ => Some(extract_warning_type(&self.message)),
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "level": self.level.name(),
+            "category": self.category(),
+            "message": self.message,
+            "file_path": self.file_path,
+        })
+    }
+}
+
+/// How log messages are written to stderr
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Readable lines, warnings and info only with `--verbose`
+    #[default]
+    Text,
+    /// Every message as a JSON object on its own line
+    Json,
+}
+
+impl LogFormat {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(anyhow!("Unknown log format: '{}'. Use text or json", s)),
+        }
+    }
+}
+
+/// How much the scan summary says, and in which form
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SummaryFormat {
+    /// A single line with the counts
+    Terse,
+    /// A line per kind of problem, with a hint how to find out more
+    #[default]
+    Text,
+    /// A JSON object on stderr, shown with every output format
+    Json,
+}
+
+impl SummaryFormat {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "terse" => Ok(Self::Terse),
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(anyhow!(
+                "Unknown summary format: '{}'. Use terse, text or json",
+                s
+            )),
+        }
+    }
+}
+
+/// A message reported by a parsing worker, recorded by the logger that collects it
+#[derive(Debug)]
+pub enum LogEvent {
+    Message {
+        level: ErrorLevel,
+        message: String,
+        file_path: Option<PathBuf>,
+    },
+    /// How long a file took to read and parse
+    Timing {
+        file_path: PathBuf,
+        duration: Duration,
+    },
+}
+
+/// Handle the parallel workers use to report to a collecting `Logger` without sharing it
+#[derive(Debug, Clone)]
+pub struct LogSender(Sender<LogEvent>);
+
+impl LogSender {
+    pub fn critical<P: AsRef<Path>>(&self, message: String, file_path: Option<P>) {
+        self.send(ErrorLevel::Critical, message, file_path);
+    }
+
+    pub fn warning<P: AsRef<Path>>(&self, message: String, file_path: Option<P>) {
+        self.send(ErrorLevel::Warning, message, file_path);
+    }
+
+    pub fn timing<P: AsRef<Path>>(&self, file_path: P, duration: Duration) {
+        let _ = self.0.send(LogEvent::Timing {
+            file_path: file_path.as_ref().to_path_buf(),
+            duration,
+        });
+    }
+
+    fn send<P: AsRef<Path>>(&self, level: ErrorLevel, message: String, file_path: Option<P>) {
+        // The collector only stops once every sender is gone, so this can't fail
+        let _ = self.0.send(LogEvent::Message {
+            level,
+            message,
+            file_path: file_path.map(|p| p.as_ref().to_path_buf()),
+        });
+    }
+}
+
 #[derive(Debug)]
 pub struct Logger {
     verbose: bool,
     silent: bool,
+    list_files: bool,
+    log_format: LogFormat,
+    summary_format: SummaryFormat,
     entries: Vec<LogEntry>,
     error_counts: HashMap<String, usize>,
     lenient_parsing_count: usize,
+    horizontal_rule_count: usize,
+    not_local_count: usize,
+    timings: Vec<(String, Duration)>,
+    slowest_shown: Option<usize>,
 }
 
 impl Logger {
@@ -32,9 +164,91 @@ impl Logger {
         Self {
             verbose,
             silent,
+            list_files: false,
+            log_format: LogFormat::default(),
+            summary_format: SummaryFormat::default(),
             entries: Vec::new(),
             error_counts: HashMap::new(),
             lenient_parsing_count: 0,
+            horizontal_rule_count: 0,
+            not_local_count: 0,
+            timings: Vec::new(),
+            slowest_shown: None,
+        }
+    }
+
+    /// List the affected files under each warning category in the summary
+    #[must_use]
+    pub fn with_file_lists(mut self, list_files: bool) -> Self {
+        self.list_files = list_files;
+        self
+    }
+
+    /// Write messages to stderr as JSON lines instead of text
+    #[must_use]
+    pub fn with_log_format(mut self, log_format: LogFormat) -> Self {
+        self.log_format = log_format;
+        self
+    }
+
+    /// Print the summary after the scan as a single line, as text or as JSON
+    #[must_use]
+    pub fn with_summary_format(mut self, summary_format: SummaryFormat) -> Self {
+        self.summary_format = summary_format;
+        self
+    }
+
+    /// Report the given number of slowest files to parse after the scan
+    #[must_use]
+    pub fn with_timings(mut self, slowest_shown: Option<usize>) -> Self {
+        self.slowest_shown = slowest_shown;
+        self
+    }
+
+    /// Run `work` while a single collector thread records everything sent through the
+    /// given `LogSender`. Workers never wait on each other, and messages are printed whole,
+    /// in the order they arrive.
+    pub fn collect<R, F>(self, work: F) -> (Self, R)
+    where
+        F: FnOnce(&LogSender) -> R,
+    {
+        let (sender, receiver) = mpsc::channel::<LogEvent>();
+        let sender = LogSender(sender);
+        thread::scope(|scope| {
+            let collector = scope.spawn(move || {
+                let mut logger = self;
+                for event in receiver {
+                    logger.record(event);
+                }
+                logger
+            });
+
+            let result = work(&sender);
+            drop(sender);
+            let logger = collector
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+            (logger, result)
+        })
+    }
+
+    pub fn record(&mut self, event: LogEvent) {
+        match event {
+            LogEvent::Message {
+                level,
+                message,
+                file_path,
+            } => match level {
+                ErrorLevel::Critical => self.log_critical(message, file_path),
+                ErrorLevel::Warning => self.log_warning(message, file_path),
+                ErrorLevel::Info => self.log_info(message, file_path),
+            },
+            LogEvent::Timing {
+                file_path,
+                duration,
+            } => self
+                .timings
+                .push((file_path.to_string_lossy().to_string(), duration)),
         }
     }
 
@@ -47,7 +261,9 @@ impl Logger {
         };
 
         // Critical errors are always shown
-        if let Some(path) = &file_path_str {
+        if self.log_format == LogFormat::Json {
+            eprintln!("{}", entry.to_json());
+        } else if let Some(path) = &file_path_str {
             eprintln!("Error: {} ({})", message, path);
         } else {
             eprintln!("Error: {}", message);
@@ -64,16 +280,22 @@ impl Logger {
             file_path: file_path_str.clone(),
         };
 
-        // Count warnings by type, but handle lenient parsing separately
+        // Count warnings by type, but handle lenient parsing and horizontal rules separately
         if message.contains("Used lenient parsing") {
             self.lenient_parsing_count += 1;
+        } else if message.contains(NOT_FRONTMATTER) {
+            self.horizontal_rule_count += 1;
+        } else if message.contains(NOT_LOCAL) {
+            self.not_local_count += 1;
         } else {
             let warning_type = extract_warning_type(&message);
             *self.error_counts.entry(warning_type).or_insert(0) += 1;
         }
 
-        // Show warnings only in verbose mode
-        if self.verbose {
+        // Show warnings only in verbose mode, unless they are logged for machines
+        if self.log_format == LogFormat::Json {
+            eprintln!("{}", entry.to_json());
+        } else if self.verbose {
             if let Some(path) = &file_path_str {
                 eprintln!("Warning: {} ({})", message, path);
             } else {
@@ -93,39 +315,100 @@ impl Logger {
         };
 
         // Show info only in verbose mode AND not in silent mode
-        if self.verbose && !self.silent {
+        if self.log_format == LogFormat::Json {
+            eprintln!("{}", entry.to_json());
+        } else if self.verbose && !self.silent {
             println!("{}", message);
         }
 
         self.entries.push(entry);
     }
 
-    pub fn print_summary(
-        &self,
-        _total_files: usize,
-        successful_files: usize,
-        format: Option<&str>,
-    ) {
-        // Don't print summary for JSON format to keep output clean
-        if let Some(fmt) = format {
-            if fmt.to_lowercase() == "json" {
+    pub fn print_summary(&self, total_files: usize, successful_files: usize, format: Option<&str>) {
+        // Don't print summary for JSON, CSV or template output to keep output clean, and not
+        // if silent flag is set. Skipped files are still reported, as the results are
+        // incomplete without them.
+        let machine_format = format.is_some_and(|fmt| {
+            matches!(
+                fmt.to_lowercase().as_str(),
+                "json" | "ndjson" | "csv" | "template"
+            )
+        });
+        // The JSON summary goes to stderr, so it never mixes with the output
+        if self.summary_format == SummaryFormat::Json && !self.silent {
+            eprintln!("{}", self.summary_json(total_files, successful_files));
+            return;
+        }
+        if machine_format || self.silent {
+            // JSON logs already hold every skipped file
+            if self.log_format == LogFormat::Json {
                 return;
             }
+            if let Some(notice) = self.skipped_notice(total_files, successful_files) {
+                eprintln!("{}", notice);
+            }
+            return;
         }
 
-        // Don't print summary if silent flag is set
-        if self.silent {
+        if self.summary_format == SummaryFormat::Terse {
+            println!("{}", self.terse_summary(total_files, successful_files));
             return;
         }
 
         println!("Successfully parsed {} notes", successful_files);
 
+        let files = self.files_by_warning_type();
+
         // Show lenient parsing info if any files were fixed
         if self.lenient_parsing_count > 0 {
             println!(
                 "Fixed {} files with lenient parsing (frontmatter with colons in values)",
                 self.lenient_parsing_count
             );
+            self.print_file_list(files.get(LENIENT_PARSING), "  ");
+        }
+
+        // Notes that start with a horizontal rule still count as parsed
+        if self.horizontal_rule_count > 0 {
+            println!(
+                "Read {} files starting with '---' as notes without frontmatter (horizontal rule)",
+                self.horizontal_rule_count
+            );
+            self.print_file_list(files.get(HORIZONTAL_RULE), "  ");
+        }
+
+        // Cloud placeholders aren't broken, they only need downloading
+        if self.not_local_count > 0 {
+            println!(
+                "Skipped {} files that are not locally available (cloud placeholders not downloaded)",
+                self.not_local_count
+            );
+            self.print_file_list(files.get(NOT_LOCAL_CATEGORY), "  ");
+            if !self.list_files {
+                println!("Use --download-hook to download them before parsing");
+            }
+        }
+
+        // Files that could not be read at all, by cause
+        let unreadable = self.unreadable_files();
+        if !unreadable.is_empty() {
+            let total: usize = unreadable.values().map(Vec::len).sum();
+            println!("Could not read {} files:", total);
+            for (cause, paths) in &unreadable {
+                println!("  - {}: {} files", cause, paths.len());
+                self.print_file_list(Some(paths), "      ");
+            }
+        }
+
+        // Files that were read, but not understood
+        let unparsable = self.unparsable_files();
+        if !unparsable.is_empty() {
+            let total: usize = unparsable.values().map(Vec::len).sum();
+            println!("Could not parse {} files:", total);
+            for (cause, paths) in &unparsable {
+                println!("  - {}: {} files", cause, paths.len());
+                self.print_file_list(Some(paths), "      ");
+            }
         }
 
         // Show actual parsing errors (files that were skipped)
@@ -135,14 +418,198 @@ impl Logger {
                 println!("Skipped {} files due to parsing errors:", total_errors);
                 for (error_type, count) in &self.error_counts {
                     println!("  - {}: {} files", error_type, count);
+                    self.print_file_list(files.get(error_type), "      ");
                 }
-                if !self.verbose {
-                    println!("Use --verbose/-v to see detailed error messages");
+                if !self.verbose && !self.list_files {
+                    println!(
+                        "Use --verbose/-v to see detailed error messages or --warning-files to list the files"
+                    );
                 }
             }
         }
     }
 
+    /// The summary on one line, with only the counts that aren't zero
+    fn terse_summary(&self, total_files: usize, successful_files: usize) -> String {
+        let unreadable: usize = self.unreadable_files().values().map(Vec::len).sum();
+        let unparsable: usize = self.unparsable_files().values().map(Vec::len).sum();
+        let broken: usize = self.error_counts.values().sum();
+        let counts: Vec<String> = [
+            (self.lenient_parsing_count, "fixed"),
+            (self.horizontal_rule_count, "horizontal rule"),
+            (self.not_local_count, "not local"),
+            (unreadable, "unreadable"),
+            (unparsable, "unparsable"),
+            (broken, "skipped"),
+        ]
+        .iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, label)| format!("{} {}", count, label))
+        .collect();
+        let parsed = if successful_files == total_files {
+            format!("Parsed {} notes", successful_files)
+        } else {
+            format!("Parsed {} of {} files", successful_files, total_files)
+        };
+        if counts.is_empty() {
+            parsed
+        } else {
+            format!("{}: {}", parsed, counts.join(", "))
+        }
+    }
+
+    /// The summary as a JSON object, with the files of every category for `--warning-files`
+    fn summary_json(&self, total_files: usize, successful_files: usize) -> serde_json::Value {
+        let unreadable = self.unreadable_files();
+        let unparsable = self.unparsable_files();
+        let mut summary = serde_json::json!({
+            "total_files": total_files,
+            "parsed": successful_files,
+            "lenient_parsing": self.lenient_parsing_count,
+            "horizontal_rule": self.horizontal_rule_count,
+            "not_local": self.not_local_count,
+            "unreadable": unreadable
+                .iter()
+                .map(|(cause, paths)| (cause.clone(), paths.len()))
+                .collect::<BTreeMap<_, _>>(),
+            "unparsable": unparsable
+                .iter()
+                .map(|(cause, paths)| (cause.clone(), paths.len()))
+                .collect::<BTreeMap<_, _>>(),
+            "parse_errors": self.error_counts.iter().collect::<BTreeMap<_, _>>(),
+        });
+        if self.list_files {
+            summary["warning_files"] = serde_json::json!(self.files_by_warning_type());
+            summary["unreadable_files"] = serde_json::json!(unreadable);
+            summary["unparsable_files"] = serde_json::json!(unparsable);
+        }
+        summary
+    }
+
+    /// One line about the files that were skipped or lost their frontmatter, for when the
+    /// summary isn't shown
+    fn skipped_notice(&self, total_files: usize, successful_files: usize) -> Option<String> {
+        let skipped = total_files.saturating_sub(successful_files);
+        let broken: usize = self.error_counts.values().sum();
+        let mut problems = Vec::new();
+        if skipped > 0 {
+            problems.push(format!("skipped {} of {} files", skipped, total_files));
+        }
+        if broken > 0 {
+            problems.push(format!(
+                "could not parse the frontmatter of {} files",
+                broken
+            ));
+        }
+        if self.not_local_count > 0 {
+            problems.push(format!(
+                "{} files are not locally available",
+                self.not_local_count
+            ));
+        }
+        if problems.is_empty() {
+            return None;
+        }
+        Some(format!(
+            "Warning: {}, results may be incomplete (use --verbose for details)",
+            problems.join(" and ")
+        ))
+    }
+
+    /// Write every message to a file as a JSON array, sorted by file so runs can be compared
+    pub fn write_entries(&self, path: &Path) -> Result<()> {
+        let mut entries: Vec<&LogEntry> = self.entries.iter().collect();
+        entries.sort_by(|a, b| {
+            a.file_path
+                .cmp(&b.file_path)
+                .then_with(|| a.message.cmp(&b.message))
+        });
+        let entries: Vec<serde_json::Value> = entries.iter().map(|entry| entry.to_json()).collect();
+        fs::write(path, serde_json::to_string_pretty(&entries)? + "\n")
+            .with_context(|| format!("Failed to write warnings to {}", path.display()))
+    }
+
+    /// Print the slowest files to stderr, so the report never mixes with the command output
+    pub fn print_timings(&self) {
+        let Some(shown) = self.slowest_shown else {
+            return;
+        };
+        let total: Duration = self.timings.iter().map(|(_, duration)| *duration).sum();
+        eprintln!(
+            "Timed {} files ({:.1} ms of parsing in total), slowest:",
+            self.timings.len(),
+            total.as_secs_f64() * 1000.0
+        );
+        for (path, duration) in self.slowest_files(shown) {
+            eprintln!("  {:>9.1} ms  {}", duration.as_secs_f64() * 1000.0, path);
+        }
+    }
+
+    fn slowest_files(&self, count: usize) -> Vec<(&str, Duration)> {
+        let mut timings: Vec<(&str, Duration)> = self
+            .timings
+            .iter()
+            .map(|(path, duration)| (path.as_str(), *duration))
+            .collect();
+        timings.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        timings.truncate(count);
+        timings
+    }
+
+    fn print_file_list(&self, files: Option<&Vec<String>>, indent: &str) {
+        if !self.list_files {
+            return;
+        }
+        for file in files.into_iter().flatten() {
+            println!("{}{}", indent, file);
+        }
+    }
+
+    /// Sorted paths of the files and folders that could not be read, by cause
+    fn unreadable_files(&self) -> BTreeMap<String, Vec<String>> {
+        let mut files = self.files_by_category(true);
+        files.retain(|category, _| is_read_failure(category));
+        files
+    }
+
+    /// Sorted paths of the files that were read but could not be parsed, by cause
+    fn unparsable_files(&self) -> BTreeMap<String, Vec<String>> {
+        let mut files = self.files_by_category(true);
+        files.retain(|category, _| !is_read_failure(category));
+        files
+    }
+
+    /// Number of files that triggered each warning category
+    pub fn warning_counts(&self) -> BTreeMap<String, usize> {
+        self.files_by_warning_type()
+            .into_iter()
+            .map(|(category, files)| (category, files.len()))
+            .collect()
+    }
+
+    /// Sorted paths of the files that triggered each warning category
+    fn files_by_warning_type(&self) -> BTreeMap<String, Vec<String>> {
+        self.files_by_category(false)
+    }
+
+    /// Sorted paths of the files behind each category of critical errors or of warnings
+    fn files_by_category(&self, critical: bool) -> BTreeMap<String, Vec<String>> {
+        let mut files: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for entry in &self.entries {
+            if matches!(entry.level, ErrorLevel::Critical) != critical {
+                continue;
+            }
+            if let (Some(path), Some(category)) = (&entry.file_path, entry.category()) {
+                files.entry(category).or_default().push(path.clone());
+            }
+        }
+        for paths in files.values_mut() {
+            paths.sort();
+            paths.dedup();
+        }
+        files
+    }
+
     #[cfg(test)]
     pub fn get_warning_count(&self) -> usize {
         self.entries
@@ -151,7 +618,6 @@ impl Logger {
             .count()
     }
 
-    #[cfg(test)]
     pub fn get_critical_count(&self) -> usize {
         self.entries
             .iter()
@@ -160,13 +626,38 @@ impl Logger {
     }
 }
 
+/// Category under which lenient parsing fixes are listed
+const LENIENT_PARSING: &str = "Lenient parsing";
+/// Category under which notes starting with a horizontal rule are listed
+const HORIZONTAL_RULE: &str = "Horizontal rule";
+/// Marker of the warning for a leading `---` that doesn't start frontmatter
+const NOT_FRONTMATTER: &str = "not treated as frontmatter";
+
+/// Marker of the warning for files that are cloud placeholders not downloaded yet
+pub const NOT_LOCAL: &str = "not locally available";
+/// Category under which files that aren't locally available are listed
+const NOT_LOCAL_CATEGORY: &str = "Not locally available";
+/// Marker of the errors for files and folders aktenfux may not read
+pub const PERMISSION_DENIED: &str = "Permission denied";
+/// Category of the other errors reading a file
+const READ_ERRORS: &str = "File read errors";
+
+/// Whether a category of critical errors is about files that could not be read at all
+fn is_read_failure(category: &str) -> bool {
+    category == PERMISSION_DENIED || category == READ_ERRORS
+}
+
 fn extract_warning_type(message: &str) -> String {
-    if message.contains("frontmatter") {
+    if message.contains(PERMISSION_DENIED) {
+        PERMISSION_DENIED.to_string()
+    } else if message.contains("frontmatter") {
         "Frontmatter parsing errors".to_string()
     } else if message.contains("Failed to parse") {
         "File parsing errors".to_string()
     } else if message.contains("Failed to read") {
-        "File read errors".to_string()
+        READ_ERRORS.to_string()
+    } else if message.contains("symlink loop") {
+        "Symlink loops".to_string()
     } else {
         "Other errors".to_string()
     }
@@ -225,6 +716,70 @@ mod tests {
         assert_eq!(logger.get_warning_count(), 0);
     }
 
+    #[test]
+    fn test_write_entries() {
+        let mut logger = Logger::new(false, true).with_log_format(LogFormat::Json);
+        logger.log_warning(
+            "Failed to parse frontmatter: bad indent".to_string(),
+            Some("b.md"),
+        );
+        logger.log_warning(
+            "Used lenient parsing for frontmatter".to_string(),
+            Some("a.md"),
+        );
+        logger.log_info("Found 2 markdown files".to_string(), None::<&Path>);
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("warnings.json");
+        logger.write_entries(&path).unwrap();
+        let entries: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+
+        assert_eq!(
+            entries,
+            serde_json::json!([
+                {
+                    "level": "info",
+                    "category": null,
+                    "message": "Found 2 markdown files",
+                    "file_path": null,
+                },
+                {
+                    "level": "warning",
+                    "category": "Lenient parsing",
+                    "message": "Used lenient parsing for frontmatter",
+                    "file_path": "a.md",
+                },
+                {
+                    "level": "warning",
+                    "category": "Frontmatter parsing errors",
+                    "message": "Failed to parse frontmatter: bad indent",
+                    "file_path": "b.md",
+                },
+            ])
+        );
+        assert!(LogFormat::parse("xml").is_err());
+    }
+
+    #[test]
+    fn test_slowest_files() {
+        let mut logger = Logger::new(false, false).with_timings(Some(2));
+        for (path, millis) in [("a.md", 3), ("b.md", 40), ("c.md", 12)] {
+            logger.record(LogEvent::Timing {
+                file_path: PathBuf::from(path),
+                duration: Duration::from_millis(millis),
+            });
+        }
+
+        assert_eq!(
+            logger.slowest_files(2),
+            vec![
+                ("b.md", Duration::from_millis(40)),
+                ("c.md", Duration::from_millis(12))
+            ]
+        );
+    }
+
     #[test]
     fn test_extract_warning_type() {
         assert_eq!(
@@ -242,6 +797,52 @@ mod tests {
         assert_eq!(extract_warning_type("Unknown error"), "Other errors");
     }
 
+    #[test]
+    fn test_summary_formats() {
+        let mut logger = Logger::new(false, true).with_summary_format(SummaryFormat::Terse);
+        assert_eq!(logger.terse_summary(3, 3), "Parsed 3 notes");
+
+        logger.log_warning(
+            "Used lenient parsing for frontmatter".to_string(),
+            Some("a.md"),
+        );
+        logger.log_warning(
+            "Failed to parse frontmatter: bad indent".to_string(),
+            Some("b.md"),
+        );
+        logger.log_critical(
+            format!("{}, could not read the file", PERMISSION_DENIED),
+            Some("c.md"),
+        );
+        logger.log_critical(
+            "Failed to parse file: expected value at line 1".to_string(),
+            Some("d.canvas"),
+        );
+        assert_eq!(
+            logger.terse_summary(4, 2),
+            "Parsed 2 of 4 files: 1 fixed, 1 unreadable, 1 unparsable, 1 skipped"
+        );
+
+        let summary = logger.summary_json(4, 2);
+        assert_eq!(summary["parsed"], 2);
+        assert_eq!(summary["lenient_parsing"], 1);
+        assert_eq!(summary["unreadable"][PERMISSION_DENIED], 1);
+        assert_eq!(summary["unreadable"].as_object().unwrap().len(), 1);
+        assert_eq!(summary["unparsable"]["File parsing errors"], 1);
+        assert_eq!(summary["parse_errors"]["Frontmatter parsing errors"], 1);
+        assert!(summary.get("warning_files").is_none());
+
+        let summary = logger.with_file_lists(true).summary_json(4, 2);
+        assert_eq!(summary["unreadable_files"][PERMISSION_DENIED][0], "c.md");
+        assert_eq!(
+            summary["unparsable_files"]["File parsing errors"][0],
+            "d.canvas"
+        );
+
+        assert_eq!(SummaryFormat::parse("JSON").unwrap(), SummaryFormat::Json);
+        assert!(SummaryFormat::parse("yaml").is_err());
+    }
+
     #[test]
     fn test_logger_error_counts() {
         let mut logger = Logger::new(false, false);
@@ -295,6 +896,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_files_by_warning_type() {
+        let mut logger = Logger::new(false, false).with_file_lists(true);
+
+        logger.log_warning("Failed to parse frontmatter".to_string(), Some("b.md"));
+        logger.log_warning("Failed to parse frontmatter".to_string(), Some("a.md"));
+        logger.log_warning(
+            "Used lenient parsing for frontmatter in file c.md".to_string(),
+            Some("c.md"),
+        );
+
+        let files = logger.files_by_warning_type();
+        assert_eq!(
+            files.get("Frontmatter parsing errors"),
+            Some(&vec!["a.md".to_string(), "b.md".to_string()])
+        );
+        assert_eq!(files.get(LENIENT_PARSING), Some(&vec!["c.md".to_string()]));
+    }
+
     #[test]
     fn test_silent_mode() {
         let mut logger = Logger::new(true, true); // verbose=true, silent=true
@@ -331,4 +951,47 @@ mod tests {
         // Verify entries are tracked
         assert_eq!(logger.get_warning_count(), 1);
     }
+
+    #[test]
+    fn test_skipped_notice() {
+        let mut logger = Logger::new(false, true);
+        assert_eq!(logger.skipped_notice(10, 10), None);
+
+        logger.log_critical("Failed to parse file".to_string(), Some("a.md"));
+        assert_eq!(
+            logger.skipped_notice(10, 9).as_deref(),
+            Some("Warning: skipped 1 of 10 files, results may be incomplete (use --verbose for details)")
+        );
+
+        logger.log_warning("Failed to parse frontmatter".to_string(), Some("b.md"));
+        assert!(logger
+            .skipped_notice(10, 9)
+            .unwrap()
+            .contains("skipped 1 of 10 files and could not parse the frontmatter of 1 files,"));
+    }
+
+    #[test]
+    fn test_collect_from_parallel_workers() {
+        use rayon::prelude::*;
+
+        let logger = Logger::new(false, true);
+        let (logger, sum) = logger.collect(|log| {
+            (0..100)
+                .into_par_iter()
+                .map(|i| {
+                    if i % 10 == 0 {
+                        log.warning(
+                            format!("Failed to parse frontmatter {}", i),
+                            Some(format!("{}.md", i)),
+                        );
+                    }
+                    i
+                })
+                .sum::<usize>()
+        });
+
+        assert_eq!(sum, 4950);
+        assert_eq!(logger.get_warning_count(), 10);
+        assert_eq!(logger.get_critical_count(), 0);
+    }
 }
@@ -1,16 +1,56 @@
-use clap::{Parser, Subcommand};
-use std::path::PathBuf;
+use anyhow::Context;
+use clap::error::ErrorKind;
+use clap::{Args, CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 
-mod filter;
-mod frontmatter;
-mod logger;
-mod output;
-mod scanner;
-mod yaml_compat;
+mod completions;
+mod config;
+mod query;
+mod tui;
 
-use crate::filter::FilterCriteria;
-use crate::frontmatter::Note;
-use crate::scanner::VaultScanner;
+use crate::completions::FieldCache;
+use crate::config::Config;
+use crate::query::SavedQuery;
+use aktenfux::batch::{Batch, SplitOutput};
+use aktenfux::canvas::CanvasHandler;
+use aktenfux::collation::Collation;
+use aktenfux::columns::{Column, DEFAULT_COLUMNS};
+use aktenfux::created::CreatedSource;
+use aktenfux::dashboard::Dashboard;
+use aktenfux::exec::{self, ExecCommand};
+use aktenfux::filter::{
+    annotate_rules, collect_all_fields, collect_field_values_by_folder,
+    collect_field_values_by_source, diff_results, expand_map_fields, find_duplicates, find_notes,
+    get_field_statistics, group_notes, is_tag_field, matching_rules, parse_path_list,
+    restrict_to_paths, sort_notes_collated, ContentQuery, FieldFilter, FilterCriteria, Rule,
+    ValueSource,
+};
+use aktenfux::frontmatter::{is_redacted, parse_frontmatter_from_file, redact_notes, Fences, Note};
+use aktenfux::hook::{self, HookChecks};
+use aktenfux::join::Join;
+use aktenfux::links::LinkIndex;
+use aktenfux::lint::{KeyCase, LintIssue, Linter};
+use aktenfux::logger::{LogFormat, SummaryFormat};
+use aktenfux::matcher::ValueMatcher;
+use aktenfux::migrate::{Migration, MigrationReport};
+use aktenfux::normalize::{self, Normalization, Normalizer};
+use aktenfux::plan::ChangePlan;
+use aktenfux::policy::{Policy, PolicyRun};
+use aktenfux::scanner::{vault_name, BodyScan, FileHandler, ScanOutcome, VaultScanner};
+use aktenfux::schema::Schema;
+use aktenfux::stats::{self, metrics_by, HealthReport, LenientReport, MetricsReport, Snapshot};
+use aktenfux::titles::TitleIndex;
+use aktenfux::writer::{parse_scalar, plan_changes, plan_repairs, FrontmatterEdit};
+use aktenfux::yaml_compat::MatchMode;
+use aktenfux::{export, output, plan};
 
 #[derive(Parser)]
 #[command(name = "aktenfux")]
@@ -19,81 +59,1559 @@ use crate::scanner::VaultScanner;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// Exit with status 1 when no notes match
+    #[arg(long, global = true)]
+    fail_if_empty: bool,
+}
+
+/// Exit status for successful runs, also when nothing matched without --fail-if-empty
+const EXIT_SUCCESS: u8 = 0;
+/// Exit status when nothing matched and --fail-if-empty was given
+const EXIT_NO_MATCHES: u8 = 1;
+/// Exit status for invalid arguments, the status clap exits with for usage errors
+const EXIT_USAGE: u8 = 2;
+/// Exit status when the scan finished but some files could not be read or parsed
+const EXIT_SCAN_ERRORS: u8 = 3;
+/// Exit status when `lint`, `validate`, `duplicates`, `collisions` or `hook` found problems that
+/// were not fixed, `fix` could not repair frontmatter, an `--exec` command failed, or the name
+/// given to `resolve` is ambiguous
+const EXIT_PROBLEMS: u8 = 4;
+/// Exit status when a command failed: a missing vault, an invalid config, a file that could
+/// not be read or written, a declined confirmation, ...
+const EXIT_FAILURE: u8 = 5;
+
+/// An error in the arguments that clap cannot check itself, reported like clap's own and
+/// exiting with `EXIT_USAGE`
+fn usage_error(kind: ErrorKind, message: &str) -> anyhow::Error {
+    Cli::command().error(kind, message).into()
+}
+
+/// What a command found, used to pick the exit status
+#[derive(Debug, Default)]
+struct RunOutcome {
+    /// Number of matches for commands that search, `None` for the others
+    matches: Option<usize>,
+    critical_errors: usize,
+    /// Lint issues, schema violations, duplicate values and ambiguous names left in the vault,
+    /// unrepairable frontmatter and failed `--exec` commands
+    problems: usize,
+}
+
+impl RunOutcome {
+    /// Remember the critical errors of a scan and hand back its notes
+    fn record(&mut self, scan: ScanOutcome) -> Vec<Note> {
+        self.critical_errors += scan.critical_errors;
+        scan.notes
+    }
+
+    fn exit_status(&self, fail_if_empty: bool) -> u8 {
+        if self.critical_errors > 0 {
+            EXIT_SCAN_ERRORS
+        } else if self.problems > 0 {
+            EXIT_PROBLEMS
+        } else if fail_if_empty && self.matches == Some(0) {
+            EXIT_NO_MATCHES
+        } else {
+            EXIT_SUCCESS
+        }
+    }
+}
+
+#[derive(Args)]
+#[allow(clippy::struct_excessive_bools)]
+struct ScanArgs {
+    /// Paths or configured names of the vaults to scan (defaults to the configured vault or
+    /// current directory), - to read the files to parse from stdin
+    vault_paths: Vec<PathBuf>,
+    /// Parse the markdown files listed on stdin (one path per line, NDJSON or JSON) instead
+    /// of walking the vault
+    #[arg(long)]
+    stdin: bool,
+    /// Enable verbose output with detailed error messages
+    #[arg(short, long)]
+    verbose: bool,
+    /// Suppress all non-essential output (summary and info messages)
+    #[arg(short, long)]
+    silent: bool,
+    /// Read the notes as they were in this git revision (commit, branch, tag or
+    /// `main@{3 months ago}`) instead of from the working tree
+    #[arg(long, value_name = "GIT_REF")]
+    at: Option<String>,
+    /// Use strict YAML parsing (disable lenient parsing for frontmatter with colons)
+    #[arg(long)]
+    strict: bool,
+    /// Use lenient YAML parsing even if the config disables it
+    #[arg(long, conflicts_with = "strict")]
+    lenient: bool,
+    /// Skip files and directories matching a glob, by name or by vault-relative path when
+    /// it contains a `/` (can be used multiple times)
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<String>,
+    /// Also scan what .gitignore and Obsidian's excluded files list
+    #[arg(long)]
+    no_ignore: bool,
+    /// Descend into symlinked folders and parse symlinked notes (links that loop back are
+    /// skipped with a warning)
+    #[arg(long)]
+    follow_symlinks: bool,
+    /// Only scan this many folders deep, 1 being the notes in the vault root
+    #[arg(long, value_name = "N")]
+    max_depth: Option<usize>,
+    /// Don't descend into folders on other file systems, like mounted drives
+    #[arg(long)]
+    one_file_system: bool,
+    /// List the affected files under each warning category in the summary
+    #[arg(long)]
+    warning_files: bool,
+    /// Write warnings and errors to stderr as text or as one JSON object per line
+    #[arg(long, value_name = "FORMAT", default_value = "text", value_parser = parse_log_format)]
+    log_format: LogFormat,
+    /// Scan summary as a single line (terse), several lines (text) or JSON on stderr
+    /// [default: text]
+    #[arg(long, value_name = "FORMAT", value_parser = parse_summary_format)]
+    summary: Option<SummaryFormat>,
+    /// Also write all warnings and errors of the scan to this file as a JSON array
+    #[arg(long, value_name = "FILE")]
+    warnings_out: Option<PathBuf>,
+    /// Also detect frontmatter closed with more dashes (`----`) or with `...`
+    #[arg(long)]
+    tolerant_fences: bool,
+    /// Also index Obsidian .canvas files: the text of their cards, the notes on them and
+    /// the links in their cards
+    #[arg(long)]
+    include_canvas: bool,
+    /// Report the slowest files to parse on stderr (10 unless a count is given)
+    #[arg(long, value_name = "COUNT", num_args = 0..=1, default_missing_value = "10")]
+    timings: Option<usize>,
+    /// Print hints on stderr to speed up scans that take longer than SECONDS (2 unless given)
+    #[arg(long, value_name = "SECONDS", num_args = 0..=1, default_missing_value = "2")]
+    hints: Option<u64>,
+    /// Where to find the created.effective date of notes without `created`, in order:
+    /// filename, git, ctime (default: filename,ctime)
+    #[arg(long, value_delimiter = ',', value_parser = parse_created_source)]
+    created_from: Vec<CreatedSource>,
+    /// Number of threads to parse notes on (default: one per core)
+    #[arg(long, value_name = "N")]
+    threads: Option<NonZeroUsize>,
+    /// Join a CSV or JSON lookup table onto the notes by the value of a field, adding its
+    /// columns as <field>.<column> fields (can be used multiple times)
+    #[arg(long, value_name = "FIELD=FILE", value_parser = parse_join)]
+    join: Vec<(String, PathBuf)>,
+    /// Run this command for each note that is only in the cloud (iCloud, OneDrive) before
+    /// parsing it, with {} replaced by its path, e.g. --download-hook 'brctl download {}'
+    #[arg(long, value_name = "COMMAND")]
+    download_hook: Option<String>,
+    /// Leave out notes that are only in the cloud without reporting them
+    #[arg(long)]
+    skip_placeholders: bool,
+    /// Mask the values of these fields in every output, e.g. --redact email,salary
+    #[arg(long, value_name = "FIELDS", value_delimiter = ',')]
+    redact: Vec<String>,
+    /// Keep note bodies for content searches, set by the commands that need them
+    #[arg(skip)]
+    keep_bodies: bool,
+    /// Which notes to read past the frontmatter, see `Commands::body_scan`
+    #[arg(skip)]
+    body_scan: BodyScan,
+    /// Names and paths of the vaults, resolved from the arguments and the config
+    #[arg(skip)]
+    vaults: Vec<(String, PathBuf)>,
+    /// The files read with --stdin, parsed instead of walking the vaults
+    #[arg(skip)]
+    files: Option<Vec<PathBuf>>,
+    /// Frontmatter keys read under another name, from `[canonical_keys]` in the config
+    #[arg(skip)]
+    canonical_keys: HashMap<String, String>,
+}
+
+impl ScanArgs {
+    /// The first vault, for the commands that work relative to a single vault
+    fn vault(&self) -> &Path {
+        self.vaults
+            .first()
+            .map_or_else(|| Path::new("."), |(_, path)| path.as_path())
+    }
+
+    /// Read the files to parse from stdin for --stdin or a `-` vault path
+    fn read_stdin_files(&mut self) -> anyhow::Result<()> {
+        let dash = self
+            .vault_paths
+            .iter()
+            .position(|path| path.as_os_str() == "-");
+        if let Some(index) = dash {
+            self.vault_paths.remove(index);
+        }
+        if self.stdin || dash.is_some() {
+            let paths = parse_path_list(&std::io::read_to_string(std::io::stdin())?)?;
+            self.files = Some(
+                paths
+                    .into_iter()
+                    .map(PathBuf::from)
+                    .filter(|path| {
+                        hook::is_markdown(path)
+                            || (self.include_canvas && CanvasHandler.handles(path))
+                    })
+                    .collect(),
+            );
+        }
+        Ok(())
+    }
+
+    /// The notes with the `--redact` fields masked, for output
+    fn redacted<'a>(&self, notes: &[&'a Note]) -> Vec<Cow<'a, Note>> {
+        redact_notes(notes, &self.redact)
+    }
+
+    /// The root of the vault a note was found in
+    fn vault_of(&self, note: &Note) -> &Path {
+        note.vault
+            .as_ref()
+            .and_then(|name| self.vaults.iter().find(|(vault, _)| vault == name))
+            .map_or_else(|| self.vault(), |(_, path)| path.as_path())
+    }
+
+    fn apply_config(&mut self, config: &Config, vault_path: PathBuf) -> anyhow::Result<()> {
+        self.vaults = if self.vault_paths.len() > 1 {
+            self.vault_paths
+                .iter()
+                .map(|path| config.resolve_vault(path))
+                .collect()
+        } else {
+            let name = match self.vault_paths.first() {
+                Some(path) => config.resolve_vault(path).0,
+                None => vault_name(&vault_path),
+            };
+            vec![(name, vault_path.clone())]
+        };
+        if !self.strict && !self.lenient {
+            self.strict = config.lenient == Some(false);
+        }
+        self.exclude.extend(config.exclude.iter().cloned());
+        self.tolerant_fences |= config.tolerant_fences == Some(true);
+        self.include_canvas |= config.include_canvas == Some(true);
+        self.follow_symlinks |= config.follow_symlinks == Some(true);
+        self.one_file_system |= config.one_file_system == Some(true);
+        self.skip_placeholders |= config.skip_placeholders == Some(true);
+        self.max_depth = self.max_depth.or(config.max_depth);
+        self.canonical_keys = config.canonical_keys.clone().into_iter().collect();
+        self.redact.extend(config.redact.iter().cloned());
+        if self.download_hook.is_none() {
+            self.download_hook.clone_from(&config.download_hook);
+        }
+        self.threads = self.threads.or(config.threads);
+        if self.summary.is_none() {
+            self.summary = config
+                .summary
+                .as_deref()
+                .map(SummaryFormat::parse)
+                .transpose()?;
+        }
+        self.hints = self.hints.or(config.hints);
+        for (field, path) in &config.joins {
+            if !self.join.iter().any(|(joined, _)| joined == field) {
+                self.join.push((field.clone(), vault_path.join(path)));
+            }
+        }
+        if self.created_from.is_empty() {
+            self.created_from = config
+                .created_from
+                .iter()
+                .map(|source| CreatedSource::parse(source))
+                .collect::<anyhow::Result<_>>()?;
+        }
+        Ok(())
+    }
+
+    fn fences(&self) -> Fences {
+        if self.tolerant_fences {
+            Fences::Tolerant
+        } else {
+            Fences::Strict
+        }
+    }
+
+    fn scanner(&self) -> anyhow::Result<VaultScanner> {
+        let mut scanner = VaultScanner::with_vaults(self.vaults.clone())?;
+        if let Some(git_ref) = &self.at {
+            scanner = scanner.at_revision(git_ref)?;
+        }
+        if self.include_canvas {
+            scanner = scanner.with_handler(Box::new(CanvasHandler));
+        }
+        Ok(scanner
+            .with_warning_files(self.warning_files)
+            .with_excludes(self.exclude.clone())
+            .with_ignore_files(!self.no_ignore)
+            .with_follow_symlinks(self.follow_symlinks)
+            .with_max_depth(self.max_depth)
+            .with_one_file_system(self.one_file_system)
+            .with_bodies(self.keep_bodies)
+            .with_body_scan(self.body_scan.clone())
+            .with_timings(self.timings)
+            .with_hints(self.hints.map(Duration::from_secs))
+            .with_fences(self.fences())
+            .with_created_sources(if self.created_from.is_empty() {
+                CreatedSource::DEFAULT.to_vec()
+            } else {
+                self.created_from.clone()
+            })
+            .with_progress(true)
+            .with_threads(self.threads)
+            .with_log_format(self.log_format)
+            .with_summary_format(self.summary.unwrap_or_default())
+            .with_warnings_out(self.warnings_out.clone())
+            .with_files(self.files.clone())
+            .with_canonical_keys(self.canonical_keys.clone())
+            .with_download_hook(self.download_hook.clone())
+            .with_skip_placeholders(self.skip_placeholders)
+            .with_joins(
+                self.join
+                    .iter()
+                    .map(|(field, path)| Join::from_file(field, path))
+                    .collect::<anyhow::Result<_>>()?,
+            ))
+    }
+
+    fn scan(&self, format: Option<&str>) -> anyhow::Result<ScanOutcome> {
+        let outcome =
+            self.scanner()?
+                .scan_vault(self.verbose, self.silent, !self.strict, format)?;
+        if let ([(_, vault_path)], None, None) = (self.vaults.as_slice(), &self.files, &self.at) {
+            // Completion scans the vault itself when the cache can't be written
+            if let Some(cache) = FieldCache::new() {
+                let _ = cache.write(vault_path, &outcome.notes);
+            }
+        }
+        Ok(outcome)
+    }
+
+    /// Stream notes to `visit` as they are parsed, returning the number of critical errors
+    fn stream<F>(&self, format: Option<&str>, visit: F) -> anyhow::Result<usize>
+    where
+        F: Fn(Note) -> anyhow::Result<()> + Sync,
+    {
+        self.scanner()?
+            .stream_vault(self.verbose, self.silent, !self.strict, format, visit)
+    }
+}
+
+#[derive(Args)]
+#[allow(clippy::struct_excessive_bools)]
+struct FilterArgs {
+    /// Filter by field=value pairs (field==value for whole values, field~=value for substrings),
+    /// exclude with field!=value, or compare with <, <=, >, >= (can be used multiple times)
+    #[arg(long, value_parser = parse_field_filter)]
+    filter: Vec<FieldFilter>,
+    /// Add the filters of a preset from aktenfux.toml (can be used multiple times)
+    #[arg(long)]
+    preset: Vec<String>,
+    /// Only match notes where this field is set (can be used multiple times)
+    #[arg(long, value_name = "FIELD")]
+    has: Vec<String>,
+    /// Only match notes where this field is missing or empty (can be used multiple times)
+    #[arg(long, value_name = "FIELD")]
+    missing: Vec<String>,
+    /// Enable case-insensitive matching for field names and filters
+    #[arg(short = 'i', long)]
+    ignore_case: bool,
+    /// Match case-sensitively even if the config enables ignore_case
+    #[arg(long, conflicts_with = "ignore_case")]
+    case_sensitive: bool,
+    /// Let field=value and field!=value match whole values instead of substrings
+    #[arg(long)]
+    exact: bool,
+    /// Let tag/tags filters also match inline #tags in note bodies
+    #[arg(long)]
+    include_inline_tags: bool,
+    /// Custom comparisons for fields, from the [matchers] of the config
+    #[arg(skip)]
+    matchers: Vec<(String, Arc<dyn ValueMatcher>)>,
+}
+
+impl FilterArgs {
+    fn apply_config(&mut self, config: &Config) -> anyhow::Result<()> {
+        for name in &self.preset {
+            for filter in config.preset(name)? {
+                self.filter.push(
+                    parse_field_filter(filter)
+                        .map_err(|e| anyhow::anyhow!("Preset '{}': {}", name, e))?,
+                );
+            }
+        }
+        if !self.ignore_case && !self.case_sensitive {
+            self.ignore_case = config.ignore_case == Some(true);
+        }
+        self.exact |= config.exact == Some(true);
+        self.matchers = config
+            .matchers
+            .iter()
+            .map(|(field, matcher)| Ok((field.clone(), matcher.matcher(field)?)))
+            .collect::<anyhow::Result<_>>()?;
+        Ok(())
+    }
+
+    fn criteria(&self) -> FilterCriteria {
+        self.criteria_for(self.field_filters())
+    }
+
+    /// The --filter, --has and --missing filters
+    fn field_filters(&self) -> Vec<FieldFilter> {
+        self.filter
+            .iter()
+            .cloned()
+            .chain(self.has.iter().map(|field| FieldFilter::exists(field)))
+            .chain(self.missing.iter().map(|field| FieldFilter::missing(field)))
+            .collect()
+    }
+
+    /// Criteria for other filters, matched with the same case and match mode
+    fn criteria_for(&self, filters: Vec<FieldFilter>) -> FilterCriteria {
+        let criteria = if self.ignore_case {
+            FilterCriteria::new_case_insensitive(filters)
+        } else {
+            FilterCriteria::new(filters)
+        };
+        self.matchers.iter().fold(
+            criteria
+                .with_match_mode(self.match_mode())
+                .with_inline_tags(self.include_inline_tags),
+            |criteria, (field, matcher)| criteria.with_matcher(field, Arc::clone(matcher)),
+        )
+    }
+
+    /// Read the bodies of the notes whose frontmatter passes the filters when matching needs
+    /// them, for a content search or inline tags
+    fn body_scan(&self, content: bool) -> BodyScan {
+        if !content && !self.include_inline_tags {
+            return BodyScan::Frontmatter;
+        }
+        let criteria = self.criteria();
+        BodyScan::Matching(Arc::new(move |note| criteria.may_match(note)))
+    }
+
+    fn match_mode(&self) -> MatchMode {
+        if self.exact {
+            MatchMode::Exact
+        } else {
+            MatchMode::Substring
+        }
+    }
+}
+
+#[derive(Args)]
+struct OutputArgs {
+    /// Output format: table, paths, json, ndjson, csv, markdown, template [default: table]
+    #[arg(short, long)]
+    format: Option<String>,
+    /// Show these columns in table, csv, json and ndjson output: path, title, fields, and
+    /// the days since or until a date with age, due_in, age:<field>, due_in:<field>
+    #[arg(long, value_delimiter = ',', value_parser = parse_column)]
+    columns: Vec<Column>,
+    /// Per-note template for --format=template, e.g. "{{path}}\t{{frontmatter.status}}"
+    #[arg(long)]
+    template: Option<String>,
+    /// Read the --format=template template from a file
+    #[arg(long, conflicts_with = "template")]
+    template_file: Option<PathBuf>,
+    /// Maximum width of table columns before they are truncated or wrapped
+    #[arg(long)]
+    max_col_width: Option<usize>,
+    /// Show table cells in full instead of truncating them
+    #[arg(long, conflicts_with = "max_col_width")]
+    no_truncate: bool,
+    /// Wrap long table cells onto multiple lines instead of truncating them
+    #[arg(long, conflicts_with = "no_truncate")]
+    wrap: bool,
+    /// Sort notes by a field, e.g. file.mtime or due (notes without it come last)
+    #[arg(long)]
+    sort: Option<String>,
+    /// Sort in descending order
+    #[arg(long, requires = "sort")]
+    desc: bool,
+    /// Sort text by the rules of a language (e.g. de, sv), or of each note's lang field
+    /// with auto
+    #[arg(long, value_name = "LANG", requires = "sort")]
+    collate: Option<String>,
+    /// List the rules from aktenfux.toml each note matches, all of them or the ones given
+    /// (--annotate=stale,missing-owner)
+    #[arg(
+        long,
+        value_name = "RULES",
+        num_args = 0..,
+        require_equals = true,
+        value_delimiter = ','
+    )]
+    annotate: Option<Vec<String>>,
+    /// Show the first paragraph of each note's body, cut at N characters
+    #[arg(long, value_name = "N")]
+    excerpt: Option<usize>,
+    #[command(flatten)]
+    quoting: QuotingArgs,
+    /// The filters of the rules picked with --annotate, read from the config
+    #[arg(skip)]
+    rules: Vec<(String, Vec<FieldFilter>)>,
+}
+
+#[derive(Args)]
+struct QuotingArgs {
+    /// Quote paths for the shell in paths and template output
+    #[arg(long)]
+    shell_quote: bool,
+    /// Quote every cell of csv output, not only the ones that need it
+    #[arg(long)]
+    quote_all: bool,
+    /// Write line breaks, tabs and backslashes in paths and values as \n, \t and \\, so
+    /// each note or value stays on one line
+    #[arg(long)]
+    escape_newlines: bool,
+}
+
+impl QuotingArgs {
+    const fn quoting(&self) -> output::Quoting {
+        output::Quoting {
+            shell_quote: self.shell_quote,
+            quote_all: self.quote_all,
+            escape_newlines: self.escape_newlines,
+        }
+    }
+}
+
+impl OutputArgs {
+    fn format(&self) -> &str {
+        self.format.as_deref().unwrap_or("table")
+    }
+
+    fn apply_config(&mut self, config: &Config) -> anyhow::Result<()> {
+        if self.format.is_none() {
+            self.format.clone_from(&config.format);
+        }
+        if let Some(names) = &self.annotate {
+            for (name, rule) in config.rules(names)? {
+                let filters = rule
+                    .filter
+                    .iter()
+                    .map(|filter| FieldFilter::parse(filter))
+                    .chain(rule.has.iter().map(|field| Ok(FieldFilter::exists(field))))
+                    .chain(
+                        rule.missing
+                            .iter()
+                            .map(|field| Ok(FieldFilter::missing(field))),
+                    )
+                    .collect::<anyhow::Result<_>>()
+                    .with_context(|| format!("Rule '{}'", name))?;
+                self.rules.push((name.to_string(), filters));
+            }
+        }
+        Ok(())
+    }
+
+    /// The --annotate rules, matched like the filters of the command
+    fn rules(&self, filters: &FilterArgs) -> Vec<Rule> {
+        self.rules
+            .iter()
+            .map(|(name, rule_filters)| Rule {
+                name: name.clone(),
+                criteria: filters.criteria_for(rule_filters.clone()),
+            })
+            .collect()
+    }
+
+    /// Mark the notes with the rules they match when asked to with --annotate
+    fn annotate(&self, notes: &mut [Note], filters: &FilterArgs) {
+        if self.annotate.is_some() {
+            annotate_rules(notes, &self.rules(filters));
+        }
+    }
+
+    /// Read the excerpts of the notes matching `criteria` when asked to with --excerpt
+    fn add_excerpts(&self, notes: &mut [Note], criteria: &FilterCriteria) {
+        if let Some(max_chars) = self.excerpt {
+            for note in notes {
+                if criteria.matches(note) {
+                    note.load_excerpt(max_chars);
+                }
+            }
+        }
+    }
+
+    fn layout(&self) -> output::TableLayout {
+        output::TableLayout {
+            max_col_width: self.max_col_width,
+            no_truncate: self.no_truncate,
+            wrap: self.wrap,
+        }
+    }
+
+    /// Display notes in the chosen format, sorted by --sort. With filter criteria, the
+    /// table shows the values that matched.
+    fn display(
+        &self,
+        notes: &[&Note],
+        criteria: Option<&FilterCriteria>,
+        silent: bool,
+    ) -> anyhow::Result<()> {
+        self.display_sorted(&self.sorted(notes, criteria), criteria, silent)
+    }
+
+    /// The notes in the order asked for with --sort
+    fn sorted<'a>(&self, notes: &[&'a Note], criteria: Option<&FilterCriteria>) -> Vec<&'a Note> {
+        let mut notes = notes.to_vec();
+        if let Some(field) = &self.sort {
+            let case_sensitive = criteria.is_none_or(FilterCriteria::is_case_sensitive);
+            let collation = self
+                .collate
+                .as_deref()
+                .map(Collation::parse)
+                .unwrap_or_default();
+            sort_notes_collated(&mut notes, field, self.desc, case_sensitive, &collation);
+        }
+        notes
+    }
+
+    /// Display notes in the order given
+    fn display_sorted(
+        &self,
+        notes: &[&Note],
+        criteria: Option<&FilterCriteria>,
+        silent: bool,
+    ) -> anyhow::Result<()> {
+        if self.format().eq_ignore_ascii_case("template") {
+            let template = match (&self.template, &self.template_file) {
+                (Some(template), _) => template.clone(),
+                (None, Some(path)) => std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read template file: {}", path.display()))?,
+                (None, None) => {
+                    return Err(usage_error(
+                        ErrorKind::MissingRequiredArgument,
+                        "--format=template needs --template or --template-file",
+                    ))
+                }
+            };
+            // A trailing newline in a template file would add an empty line per note
+            let template = output::Template::parse(template.trim_end_matches('\n'))?;
+            return output::display_template_format(notes, &template, self.quoting.quoting());
+        }
+
+        let format = self.format();
+        if format.eq_ignore_ascii_case("csv")
+            || !(self.columns.is_empty()
+                || format.eq_ignore_ascii_case("paths")
+                || format.eq_ignore_ascii_case("markdown"))
+        {
+            let columns = if self.columns.is_empty() {
+                DEFAULT_COLUMNS.to_vec()
+            } else {
+                self.columns.clone()
+            };
+            return output::display_columns(
+                notes,
+                &columns,
+                format,
+                &self.layout(),
+                self.quoting.quoting(),
+                criteria.is_none_or(FilterCriteria::is_case_sensitive),
+                silent,
+            );
+        }
+
+        match criteria {
+            Some(criteria) => output::display_filtered_matches(
+                notes,
+                criteria,
+                self.format(),
+                &self.layout(),
+                self.quoting.quoting(),
+                silent,
+            ),
+            None => output::display_filtered_results(
+                notes,
+                self.format(),
+                &self.layout(),
+                self.quoting.quoting(),
+                silent,
+            ),
+        }
+    }
+}
+
+#[derive(Args)]
+struct WriteArgs {
+    /// Show the planned changes without writing any files
+    #[arg(long)]
+    dry_run: bool,
+    /// Apply the changes without asking for confirmation
+    #[arg(short = 'y', long)]
+    yes: bool,
+    /// Ask for confirmation when more than this many files would change [default: 20]
+    #[arg(long)]
+    confirm_threshold: Option<usize>,
+}
+
+impl WriteArgs {
+    fn apply_config(&mut self, config: &Config) {
+        self.confirm_threshold = self.confirm_threshold.or(config.confirm_threshold);
+    }
+
+    fn confirm_threshold(&self) -> usize {
+        self.confirm_threshold
+            .unwrap_or(plan::DEFAULT_CONFIRM_THRESHOLD)
+    }
+
+    fn execute(&self, plan: &ChangePlan, silent: bool) -> anyhow::Result<()> {
+        plan.print_impact_summary(self.dry_run);
+        if self.dry_run || plan.is_empty() {
+            return Ok(());
+        }
+
+        if !plan::confirm_plan(plan, self.yes, self.confirm_threshold())? {
+            return Err(anyhow::anyhow!("Aborted, no files were changed"));
+        }
+
+        let written = plan.apply()?;
+        if !silent {
+            println!("Updated {} files", written);
+        }
+        Ok(())
+    }
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Filter notes by frontmatter fields
     Filter {
-        /// Path to the Obsidian vault (defaults to current directory)
-        #[arg(default_value = ".")]
-        vault_path: PathBuf,
-        /// Filter by field=value pairs (can be used multiple times)
-        #[arg(long, value_parser = parse_filter)]
-        filter: Vec<(String, String)>,
-        /// Enable case-insensitive matching for filters
-        #[arg(short = 'i', long)]
-        ignore_case: bool,
-        /// Output format: table, paths, json
-        #[arg(short, long, default_value = "table")]
-        format: String,
-        /// Enable verbose output with detailed error messages
-        #[arg(short, long)]
-        verbose: bool,
-        /// Suppress all non-essential output (summary and info messages)
-        #[arg(short, long)]
-        silent: bool,
-        /// Use strict YAML parsing (disable lenient parsing for frontmatter with colons)
+        #[command(flatten)]
+        scan: ScanArgs,
+        #[command(flatten)]
+        filters: FilterArgs,
+        #[command(flatten)]
+        output: OutputArgs,
+        /// Only consider notes listed in a previous run's output (paths, NDJSON or JSON; - for stdin)
+        #[arg(long, conflicts_with = "stdin")]
+        from: Option<String>,
+        /// Only match notes whose body contains this text
+        #[arg(long)]
+        content: Option<String>,
+        /// Treat --content as a regular expression
+        #[arg(long, requires = "content")]
+        regex: bool,
+        /// Show at most this many notes (after sorting)
         #[arg(long)]
-        strict: bool,
+        limit: Option<usize>,
+        /// Skip this many notes before showing any (after sorting)
+        #[arg(long, default_value_t = 0)]
+        offset: usize,
+        /// Only print the number of matching notes (a bare number with --silent)
+        #[arg(long, conflicts_with_all = ["limit", "offset"])]
+        count: bool,
+        /// Show which notes entered and left the results since a saved run (paths, NDJSON or
+        /// JSON; - for stdin)
+        #[arg(long, value_name = "FILE", conflicts_with_all = ["limit", "offset", "count"])]
+        diff_against: Option<String>,
+        /// Run a shell command per matching note instead of listing them, with {} replaced
+        /// by the path (appended when missing), e.g. --exec 'touch {}'
+        #[arg(long, value_name = "COMMAND", group = "exec_mode", conflicts_with_all = ["count", "diff_against"])]
+        exec: Option<String>,
+        /// Like --exec, but run the command once with all matching paths
+        #[arg(long, value_name = "COMMAND", group = "exec_mode", conflicts_with_all = ["count", "diff_against"])]
+        exec_batch: Option<String>,
+        /// Print the commands of --exec or --exec-batch without running them
+        #[arg(long, requires = "exec_mode")]
+        dry_run: bool,
+        /// Open the first matching note in Obsidian (after --sort and --offset)
+        #[arg(long, conflicts_with_all = ["count", "diff_against", "exec_mode"])]
+        open: bool,
+        /// Group the matching notes by the values of a field, see --split-output
+        #[arg(long, value_name = "FIELD", requires = "split_output")]
+        group_by: Option<String>,
+        /// Write each group of --group-by to its own file instead of listing the notes,
+        /// e.g. out/{group}.md (paths, json, ndjson or markdown, by default from the extension)
+        #[arg(
+            long,
+            value_name = "PATH",
+            requires = "group_by",
+            conflicts_with_all = ["count", "diff_against", "exec_mode", "open"]
+        )]
+        split_output: Option<String>,
     },
     /// List all available frontmatter fields in the vault
     Fields {
-        /// Path to the Obsidian vault (defaults to current directory)
-        #[arg(default_value = ".")]
-        vault_path: PathBuf,
-        /// Filter by field=value pairs (can be used multiple times)
-        #[arg(long, value_parser = parse_filter)]
-        filter: Vec<(String, String)>,
-        /// Enable case-insensitive matching for filters
-        #[arg(short = 'i', long)]
-        ignore_case: bool,
-        /// Enable verbose output with detailed error messages
-        #[arg(short, long)]
-        verbose: bool,
-        /// Suppress all non-essential output (summary and info messages)
-        #[arg(short, long)]
-        silent: bool,
-        /// Use strict YAML parsing (disable lenient parsing for frontmatter with colons)
+        #[command(flatten)]
+        scan: ScanArgs,
+        #[command(flatten)]
+        filters: FilterArgs,
+        /// Show fields as a tree of namespaces split at this separator, e.g. . or /
         #[arg(long)]
-        strict: bool,
+        namespace_separator: Option<String>,
+        /// Only list the field names, one per line, from the last scan of the vault when
+        /// there was one (for shell completion)
+        #[arg(long, conflicts_with = "namespace_separator")]
+        cached: bool,
+        /// Output format: table, json, csv
+        #[arg(short, long, default_value = "table")]
+        format: String,
+        #[command(flatten)]
+        quoting: QuotingArgs,
     },
     /// List all values for a specific frontmatter field
     Values {
-        /// Path to the Obsidian vault (defaults to current directory)
-        #[arg(default_value = ".")]
-        vault_path: PathBuf,
-        /// The field to list values for
+        #[command(flatten)]
+        scan: ScanArgs,
+        #[command(flatten)]
+        filters: FilterArgs,
+        /// The field to list values for (can be used multiple times)
+        #[arg(short, long, required_unless_present = "all_fields")]
+        field: Vec<String>,
+        /// List the values of every field
+        #[arg(long, conflicts_with = "field")]
+        all_fields: bool,
+        /// Break the value distribution down by top-level folder
+        #[arg(long)]
+        per_folder: bool,
+        /// Label each value with where it comes from: frontmatter, inline (#tags in the
+        /// body, for tags) or virtual (file.*, title, --join); only these sources when given
+        #[arg(
+            long,
+            value_name = "SOURCES",
+            num_args = 0..=1,
+            value_delimiter = ',',
+            value_parser = parse_value_source,
+            conflicts_with = "per_folder"
+        )]
+        show_sources: Option<Vec<ValueSource>>,
+        /// Output format: table, json, csv
+        #[arg(long, default_value = "table")]
+        format: String,
+        #[command(flatten)]
+        quoting: QuotingArgs,
+    },
+    /// Complete a field value from the values already in use, as JSON for editors
+    Complete {
+        #[command(flatten)]
+        scan: ScanArgs,
+        #[command(flatten)]
+        filters: FilterArgs,
+        /// The field to complete
         #[arg(short, long)]
         field: String,
-        /// Enable case-insensitive matching for field names and filters
-        #[arg(short = 'i', long)]
-        ignore_case: bool,
-        /// Filter by field=value pairs (can be used multiple times)
-        #[arg(long, value_parser = parse_filter)]
-        filter: Vec<(String, String)>,
-        /// Enable verbose output with detailed error messages
+        /// What has been typed so far
+        #[arg(short, long, default_value = "")]
+        prefix: String,
+        /// Return at most this many values
         #[arg(short, long)]
-        verbose: bool,
-        /// Suppress all non-essential output (summary and info messages)
+        limit: Option<usize>,
+    },
+    /// Group notes by one or more frontmatter fields and count them
+    Group {
+        #[command(flatten)]
+        scan: ScanArgs,
+        #[command(flatten)]
+        filters: FilterArgs,
+        /// Field to group by (can be used multiple times for nested groups)
+        #[arg(long = "by", required = true)]
+        by: Vec<String>,
+        /// List the notes of each group instead of only counting them
         #[arg(short, long)]
-        silent: bool,
-        /// Use strict YAML parsing (disable lenient parsing for frontmatter with colons)
+        list: bool,
+        /// Output format: table, json
+        #[arg(short, long, default_value = "table")]
+        format: String,
+    },
+    /// Find notes sharing the same value of a field, like copy-pasted ids
+    Duplicates {
+        #[command(flatten)]
+        scan: ScanArgs,
+        #[command(flatten)]
+        filters: FilterArgs,
+        /// Field whose values should be unique
         #[arg(long)]
-        strict: bool,
+        field: String,
+        /// Output format: table, json
+        #[arg(short, long, default_value = "table")]
+        format: String,
+    },
+    /// Print the path of the note going by a file name, title or alias
+    Resolve {
+        /// File name, title or alias of the note (case-insensitive)
+        name: String,
+        #[command(flatten)]
+        scan: ScanArgs,
+        /// Output format: paths, table, json
+        #[arg(short, long, default_value = "paths")]
+        format: String,
+    },
+    /// List file names, titles and aliases that refer to more than one note
+    Collisions {
+        #[command(flatten)]
+        scan: ScanArgs,
+        /// Output format: table, json
+        #[arg(short, long, default_value = "table")]
+        format: String,
+    },
+    /// Show the parsed frontmatter of a single note
+    Show {
+        /// Path, file name or title of the note
+        note: String,
+        #[command(flatten)]
+        scan: ScanArgs,
+        /// Output format: table, json
+        #[arg(short, long, default_value = "table")]
+        format: String,
+    },
+    /// List the notes a note links to
+    Links {
+        /// Path, file name or title of the note
+        note: String,
+        #[command(flatten)]
+        scan: ScanArgs,
+        #[command(flatten)]
+        filters: FilterArgs,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// List the notes linking to a note
+    Backlinks {
+        /// Path, file name or title of the note
+        note: String,
+        #[command(flatten)]
+        scan: ScanArgs,
+        #[command(flatten)]
+        filters: FilterArgs,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// List notes that no other note links to
+    Orphans {
+        #[command(flatten)]
+        scan: ScanArgs,
+        #[command(flatten)]
+        filters: FilterArgs,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// List links pointing at notes or files that don't exist
+    BrokenLinks {
+        #[command(flatten)]
+        scan: ScanArgs,
+        #[command(flatten)]
+        filters: FilterArgs,
+        /// Output format: table, paths, json
+        #[arg(short, long, default_value = "table")]
+        format: String,
+    },
+    /// Browse matching notes interactively in a terminal UI
+    Browse {
+        #[command(flatten)]
+        scan: ScanArgs,
+        #[command(flatten)]
+        filters: FilterArgs,
+    },
+    /// Export matching notes, frontmatter values and links to a database
+    Export {
+        #[command(flatten)]
+        scan: ScanArgs,
+        #[command(flatten)]
+        filters: FilterArgs,
+        /// Export format: sqlite
+        #[arg(short, long, default_value = "sqlite")]
+        format: String,
+        /// File to write, replaced if it exists
+        #[arg(short, long)]
+        out: PathBuf,
+    },
+    /// Write an HTML page with field stats, a tag cloud, value distributions and a
+    /// searchable note table that opens in any browser, without aktenfux or a network
+    Dashboard {
+        #[command(flatten)]
+        scan: ScanArgs,
+        #[command(flatten)]
+        filters: FilterArgs,
+        /// Title of the page (defaults to the vault name)
+        #[arg(long)]
+        title: Option<String>,
+        /// File to write, replaced if it exists
+        #[arg(short, long)]
+        out: PathBuf,
+    },
+    /// Show frontmatter size and nesting metrics to find bloated notes
+    Stats {
+        #[command(flatten)]
+        scan: ScanArgs,
+        #[command(flatten)]
+        filters: FilterArgs,
+        /// Number of notes to list per ranking, or of values per field with --health
+        /// [default: 10, 3 with --health]
+        #[arg(long)]
+        top: Option<usize>,
+        /// Break the metrics down by the values of a field, e.g. lang
+        #[arg(long, value_name = "FIELD")]
+        by: Option<String>,
+        /// Report notes without frontmatter, parse problems and how often each field is
+        /// filled in instead of the metrics
+        #[arg(long, conflicts_with_all = ["by", "record", "trend"])]
+        health: bool,
+        /// Count the notes missing these fields in the health report (can be used multiple
+        /// times)
+        #[arg(long, value_name = "FIELD", value_delimiter = ',', requires = "health")]
+        require: Vec<String>,
+        /// Append a timestamped snapshot of the key metrics to the history file
+        #[arg(long, conflicts_with = "by")]
+        record: bool,
+        /// Show how the recorded metrics evolved instead of the current ones
+        #[arg(long, conflicts_with = "by")]
+        trend: bool,
+        /// History file for --record and --trend [default: .aktenfux/stats-history.jsonl in
+        /// the vault]
+        #[arg(long, value_name = "FILE")]
+        history: Option<PathBuf>,
+        /// Output format: table, json
+        #[arg(short, long, default_value = "table")]
+        format: String,
+    },
+    /// Check frontmatter against the lint rules in aktenfux.toml
+    Lint {
+        #[command(flatten)]
+        scan: ScanArgs,
+        #[command(flatten)]
+        filters: FilterArgs,
+        /// Keys that must come first, in this order (overrides key_order from the config)
+        #[arg(long, value_delimiter = ',')]
+        key_order: Vec<String>,
+        /// Casing of keys: kebab-case, snake_case or lowercase (overrides key_case from the config)
+        #[arg(long, value_parser = parse_key_case)]
+        key_case: Option<KeyCase>,
+        /// Output format: table, paths, json, short
+        #[arg(short, long, default_value = "table")]
+        format: String,
+        /// Rewrite the frontmatter of offending notes to follow the rules
+        #[arg(long)]
+        fix: bool,
+        #[command(flatten)]
+        write: WriteArgs,
+    },
+    /// Check frontmatter against a schema of required fields, types and allowed values
+    Validate {
+        #[command(flatten)]
+        scan: ScanArgs,
+        #[command(flatten)]
+        filters: FilterArgs,
+        /// Schema file (defaults to schema from aktenfux.toml)
+        #[arg(long)]
+        schema: Option<PathBuf>,
+        /// Output format: table, paths, json, short
+        #[arg(short, long, default_value = "table")]
+        format: String,
+    },
+    /// Checks to run from git hooks
+    Hook {
+        #[command(subcommand)]
+        hook: HookCommand,
+    },
+    /// Edit the frontmatter of all notes matching the filters
+    Edit {
+        #[command(flatten)]
+        scan: ScanArgs,
+        #[command(flatten)]
+        filters: FilterArgs,
+        /// Set a field to a value, as field=value (can be used multiple times)
+        #[arg(long, value_parser = parse_filter)]
+        set: Vec<(String, String)>,
+        /// Add a value to a list field, as field=value (can be used multiple times)
+        #[arg(long, value_parser = parse_filter)]
+        add: Vec<(String, String)>,
+        /// Remove a field, or a single list value with field=value (can be used multiple times)
+        #[arg(long)]
+        remove: Vec<String>,
+        #[command(flatten)]
+        write: WriteArgs,
+    },
+    /// Run the named queries of a YAML file against one scan, writing each result to a file
+    Batch {
+        /// YAML file mapping query names to their filters and output files
+        queries: PathBuf,
+        #[command(flatten)]
+        scan: ScanArgs,
+        /// Filters applied to every query in addition to its own
+        #[command(flatten)]
+        filters: FilterArgs,
+    },
+    /// Save filters under a name and run them later
+    Query {
+        #[command(subcommand)]
+        query: QueryCommand,
+    },
+    /// Apply a migration map of field renames, value replacements and deletions
+    Migrate {
+        #[command(flatten)]
+        scan: ScanArgs,
+        #[command(flatten)]
+        filters: FilterArgs,
+        /// YAML file describing the renames, replacements and deletions to apply
+        #[arg(long)]
+        map: Option<PathBuf>,
+        /// Rename a field, as old=new (can be used multiple times)
+        #[arg(long, value_parser = parse_rename)]
+        rename: Vec<(String, String)>,
+        #[command(flatten)]
+        write: WriteArgs,
+    },
+    /// Bring the values of fields into one shape: ISO dates, booleans and lists
+    Normalize {
+        #[command(flatten)]
+        scan: ScanArgs,
+        #[command(flatten)]
+        filters: FilterArgs,
+        /// Field to normalize (can be used multiple times)
+        #[arg(long, required = true)]
+        field: Vec<String>,
+        /// What to normalize: date, bool, list (default: dates and booleans, and lists for
+        /// fields that mostly hold lists)
+        #[arg(long = "as", value_delimiter = ',', value_parser = parse_normalization)]
+        normalizations: Vec<Normalization>,
+        /// Show the values before and after per note without writing any files
+        #[arg(long, conflicts_with = "yes")]
+        preview: bool,
+        /// Output format of --preview: table, paths, json
+        #[arg(short, long, default_value = "table")]
+        format: String,
+        #[command(flatten)]
+        write: WriteArgs,
+    },
+    /// Show which lenient fixes (tabs, wikilinks, colons) each file needed to parse, with
+    /// counts per fix and the lines they changed
+    LenientReport {
+        #[command(flatten)]
+        scan: ScanArgs,
+        #[command(flatten)]
+        filters: FilterArgs,
+        /// Output format: table, paths, json
+        #[arg(short, long, default_value = "table")]
+        format: String,
+    },
+    /// Rewrite frontmatter that only parses leniently into valid YAML: quote values with
+    /// colons and turn `tags: #a #b` into a list
+    Fix {
+        #[command(flatten)]
+        scan: ScanArgs,
+        #[command(flatten)]
+        filters: FilterArgs,
+        /// Only apply the fixes of the lenient parser (see lenient-report) to frontmatter
+        /// that doesn't parse otherwise, leaving tag lists as they are
+        #[arg(long)]
+        lenient_only: bool,
+        #[command(flatten)]
+        write: WriteArgs,
+    },
+    /// Run the [policies] of the config, like archiving finished notes after half a year
+    Policy {
+        #[command(subcommand)]
+        policy: PolicyCommand,
+    },
+    /// Print a completion script for bash, zsh, fish, elvish or powershell, completing field
+    /// names from the vault in bash, zsh and fish (e.g.
+    /// `aktenfux completions bash > ~/.local/share/bash-completion/completions/aktenfux`)
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+}
+
+impl Commands {
+    /// The scan, filter and output arguments of the command, `None` for commands that
+    /// don't scan a vault
+    fn args_mut(
+        &mut self,
+    ) -> Option<(
+        &mut ScanArgs,
+        Option<&mut FilterArgs>,
+        Option<&mut OutputArgs>,
+    )> {
+        Some(match self {
+            Self::Filter {
+                scan,
+                filters,
+                output,
+                ..
+            }
+            | Self::Links {
+                scan,
+                filters,
+                output,
+                ..
+            }
+            | Self::Backlinks {
+                scan,
+                filters,
+                output,
+                ..
+            }
+            | Self::Orphans {
+                scan,
+                filters,
+                output,
+            } => (scan, Some(filters), Some(output)),
+            Self::Fields { scan, filters, .. }
+            | Self::Values { scan, filters, .. }
+            | Self::Complete { scan, filters, .. }
+            | Self::Batch { scan, filters, .. }
+            | Self::Group { scan, filters, .. }
+            | Self::Duplicates { scan, filters, .. }
+            | Self::BrokenLinks { scan, filters, .. }
+            | Self::Browse { scan, filters }
+            | Self::Export { scan, filters, .. }
+            | Self::Dashboard { scan, filters, .. }
+            | Self::Stats { scan, filters, .. }
+            | Self::Lint { scan, filters, .. }
+            | Self::Validate { scan, filters, .. }
+            | Self::Edit { scan, filters, .. }
+            | Self::Migrate { scan, filters, .. }
+            | Self::Normalize { scan, filters, .. }
+            | Self::LenientReport { scan, filters, .. }
+            | Self::Fix { scan, filters, .. }
+            | Self::Policy {
+                policy: PolicyCommand::Run { scan, filters, .. },
+            } => (scan, Some(filters), None),
+            Self::Show { scan, .. }
+            | Self::Resolve { scan, .. }
+            | Self::Collisions { scan, .. }
+            | Self::Hook {
+                hook: HookCommand::PreCommit { scan, .. },
+            } => (scan, None, None),
+            Self::Query { .. } | Self::Completions { .. } => return None,
+        })
+    }
+
+    /// The write options of the commands that change notes
+    fn write_args_mut(&mut self) -> Option<&mut WriteArgs> {
+        match self {
+            Self::Lint { write, .. }
+            | Self::Edit { write, .. }
+            | Self::Migrate { write, .. }
+            | Self::Normalize { write, .. }
+            | Self::Fix { write, .. }
+            | Self::Policy {
+                policy: PolicyCommand::Run { write, .. },
+            } => Some(write),
+            _ => None,
+        }
+    }
+
+    /// Load aktenfux.toml and fill in everything not given on the command line
+    fn apply_config(&mut self) -> anyhow::Result<()> {
+        let Some((scan, filters, output)) = self.args_mut() else {
+            return Ok(());
+        };
+        scan.read_stdin_files()?;
+        let (config, vault_path) = Config::load(scan.vault_paths.first().map(PathBuf::as_path))?;
+        scan.apply_config(&config, vault_path.clone())?;
+        if let Some(filters) = filters {
+            filters.apply_config(&config)?;
+        }
+        if let Some(output) = output {
+            output.apply_config(&config)?;
+        }
+        match self {
+            Self::Fields {
+                namespace_separator,
+                ..
+            } if namespace_separator.is_none() => {
+                namespace_separator.clone_from(&config.namespace_separator);
+            }
+            Self::Lint {
+                key_order,
+                key_case,
+                ..
+            } => {
+                if key_order.is_empty() {
+                    key_order.clone_from(&config.lint.key_order);
+                }
+                if key_case.is_none() {
+                    *key_case = config.lint.key_case()?;
+                }
+            }
+            Self::Validate { schema, .. } if schema.is_none() => {
+                *schema = config.schema.as_ref().map(|path| vault_path.join(path));
+            }
+            Self::Hook {
+                hook:
+                    HookCommand::PreCommit {
+                        schema,
+                        key_order,
+                        key_case,
+                        ..
+                    },
+            } => {
+                if schema.is_none() {
+                    *schema = config.schema.as_ref().map(|path| vault_path.join(path));
+                }
+                if key_order.is_empty() {
+                    key_order.clone_from(&config.lint.key_order);
+                }
+                if key_case.is_none() {
+                    *key_case = config.lint.key_case()?;
+                }
+            }
+            Self::Policy {
+                policy: PolicyCommand::Run { only, policies, .. },
+            } => {
+                *policies = config.policies(only)?;
+            }
+            _ => {}
+        }
+        if let Some(write) = self.write_args_mut() {
+            write.apply_config(&config);
+        }
+        // These read the files again or change them, which a revision can't stand in for
+        let rereads_files = matches!(
+            self,
+            Self::Lint { .. }
+                | Self::Hook { .. }
+                | Self::Edit { .. }
+                | Self::Migrate { .. }
+                | Self::Normalize { .. }
+                | Self::LenientReport { .. }
+                | Self::Fix { .. }
+                | Self::Policy { .. }
+        );
+        if rereads_files
+            && self
+                .args_mut()
+                .is_some_and(|(scan, _, _)| scan.at.is_some())
+        {
+            return Err(usage_error(
+                ErrorKind::ArgumentConflict,
+                "--at only works with commands that read notes, not ones that check or change files",
+            ));
+        }
+        let body_scan = self.body_scan();
+        if let Some((scan, _, _)) = self.args_mut() {
+            scan.body_scan = body_scan;
+        }
+        Ok(())
+    }
+
+    /// Which notes the scan reads past the frontmatter: every note for the commands that
+    /// show links or inline tags, otherwise the notes the filters need the body of
+    fn body_scan(&mut self) -> BodyScan {
+        match self {
+            Self::Show { .. }
+            | Self::Links { .. }
+            | Self::Backlinks { .. }
+            | Self::Orphans { .. }
+            | Self::BrokenLinks { .. }
+            | Self::Browse { .. }
+            | Self::Export { .. }
+            | Self::Dashboard { .. } => BodyScan::All,
+            // The queries of a batch bring their own filters
+            Self::Batch { filters, .. } if filters.include_inline_tags => BodyScan::All,
+            Self::Filter {
+                filters, content, ..
+            } => filters.body_scan(content.is_some()),
+            // Inline tags are listed with the frontmatter tags
+            Self::Values {
+                field,
+                all_fields,
+                show_sources: Some(sources),
+                ..
+            } if (sources.is_empty() || sources.contains(&ValueSource::Inline))
+                && (*all_fields || field.iter().any(|field| is_tag_field(field))) =>
+            {
+                BodyScan::All
+            }
+            _ => self
+                .args_mut()
+                .and_then(|(_, filters, _)| filters)
+                .map_or_else(BodyScan::default, |filters| filters.body_scan(false)),
+        }
+    }
+}
+
+#[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)]
+enum QueryCommand {
+    /// Save filters, sorting and format as a query in the aktenfux.toml of the vault
+    Save {
+        /// Name to run the query by
+        name: String,
+        /// Vault to save the query for (defaults to the configured vault or current directory)
+        vault_path: Option<PathBuf>,
+        #[command(flatten)]
+        filters: FilterArgs,
+        /// Only match notes whose body contains this text
+        #[arg(long)]
+        content: Option<String>,
+        /// Treat --content as a regular expression
+        #[arg(long, requires = "content")]
+        regex: bool,
+        /// Sort notes by a field
+        #[arg(long)]
+        sort: Option<String>,
+        /// Sort in descending order
+        #[arg(long, requires = "sort")]
+        desc: bool,
+        /// Output format: table, paths, json, ndjson, template
+        #[arg(short, long)]
+        format: Option<String>,
+        /// Replace a saved query of the same name
+        #[arg(long)]
+        force: bool,
+    },
+    /// Run a saved query like `aktenfux filter` with its arguments
+    Run {
+        /// Name of the saved query
+        name: String,
+        #[command(flatten)]
+        scan: ScanArgs,
+        /// Overrides the format and sorting of the query
+        #[command(flatten)]
+        output: OutputArgs,
+        /// Show at most this many notes (after sorting)
+        #[arg(long)]
+        limit: Option<usize>,
+        /// Only print the number of matching notes (a bare number with --silent)
+        #[arg(long, conflicts_with = "limit")]
+        count: bool,
+    },
+    /// List the saved queries with their arguments
+    List {
+        /// Vault whose queries to list (defaults to the configured vault or current directory)
+        vault_path: Option<PathBuf>,
+    },
+}
+
+impl QueryCommand {
+    /// The `filter` command a `query run` stands for
+    fn into_filter(self) -> anyhow::Result<Commands> {
+        let Self::Run {
+            name,
+            scan,
+            mut output,
+            limit,
+            count,
+        } = self
+        else {
+            return Err(anyhow::anyhow!("Only `query run` runs a query"));
+        };
+        let vault_path = scan.vault_paths.iter().find(|path| path.as_os_str() != "-");
+        let (config, _) = Config::load(vault_path.map(PathBuf::as_path))?;
+        let query = config.query(&name)?;
+
+        if output.format.is_none() {
+            output.format.clone_from(&query.format);
+        }
+        if output.sort.is_none() {
+            output.sort.clone_from(&query.sort);
+            output.desc = query.desc;
+        }
+        let filters = FilterArgs {
+            filter: query
+                .filter
+                .iter()
+                .map(|filter| FieldFilter::parse(filter))
+                .collect::<anyhow::Result<_>>()
+                .with_context(|| format!("Query '{}'", name))?,
+            preset: query.preset.clone(),
+            has: query.has.clone(),
+            missing: query.missing.clone(),
+            ignore_case: query.ignore_case,
+            case_sensitive: false,
+            exact: query.exact,
+            include_inline_tags: query.include_inline_tags,
+            matchers: Vec::new(),
+        };
+        Ok(Commands::Filter {
+            scan,
+            filters,
+            output,
+            from: None,
+            content: query.content.clone(),
+            regex: query.regex,
+            limit,
+            offset: 0,
+            count,
+            diff_against: None,
+            exec: None,
+            exec_batch: None,
+            dry_run: false,
+            open: false,
+            group_by: None,
+            split_output: None,
+        })
+    }
+}
+
+#[derive(Subcommand)]
+enum PolicyCommand {
+    /// Change and move the notes matching the policies, with a report of what each does
+    Run {
+        #[command(flatten)]
+        scan: ScanArgs,
+        /// Filters a note must also pass, in addition to those of the policy
+        #[command(flatten)]
+        filters: FilterArgs,
+        /// Only run this policy (can be used multiple times, default: all policies)
+        #[arg(long = "policy", value_name = "NAME")]
+        only: Vec<String>,
+        /// Report format: table, json
+        #[arg(short, long, default_value = "table")]
+        format: String,
+        #[command(flatten)]
+        write: WriteArgs,
+        /// The policies to run, from the config
+        #[arg(skip)]
+        policies: Vec<Policy>,
+    },
+}
+
+#[derive(Subcommand)]
+enum HookCommand {
+    /// Check the frontmatter of the staged notes against the schema and lint rules
+    PreCommit {
+        #[command(flatten)]
+        scan: ScanArgs,
+        /// Schema file (defaults to schema from aktenfux.toml)
+        #[arg(long)]
+        schema: Option<PathBuf>,
+        /// Keys that must come first, in this order (defaults to key_order from the config)
+        #[arg(long, value_delimiter = ',')]
+        key_order: Vec<String>,
+        /// Casing of keys: kebab-case, snake_case or lowercase (defaults to key_case from the config)
+        #[arg(long, value_parser = parse_key_case)]
+        key_case: Option<KeyCase>,
     },
 }
 
+fn parse_created_source(s: &str) -> Result<CreatedSource, String> {
+    CreatedSource::parse(s).map_err(|e| e.to_string())
+}
+
+fn parse_key_case(s: &str) -> Result<KeyCase, String> {
+    KeyCase::parse(s).map_err(|e| e.to_string())
+}
+
+fn parse_log_format(s: &str) -> Result<LogFormat, String> {
+    LogFormat::parse(s).map_err(|e| e.to_string())
+}
+
+fn parse_value_source(s: &str) -> Result<ValueSource, String> {
+    ValueSource::parse(s).map_err(|e| e.to_string())
+}
+
+fn parse_summary_format(s: &str) -> Result<SummaryFormat, String> {
+    SummaryFormat::parse(s).map_err(|e| e.to_string())
+}
+
+fn parse_normalization(s: &str) -> Result<Normalization, String> {
+    Normalization::parse(s).map_err(|e| e.to_string())
+}
+
+fn parse_field_filter(s: &str) -> Result<FieldFilter, String> {
+    FieldFilter::parse(s).map_err(|e| e.to_string())
+}
+
+fn parse_column(s: &str) -> Result<Column, String> {
+    Column::parse(s).map_err(|e| e.to_string())
+}
+
 fn parse_filter(s: &str) -> Result<(String, String), String> {
     let parts: Vec<&str> = s.splitn(2, '=').collect();
     if parts.len() != 2 {
@@ -102,81 +1620,1042 @@ fn parse_filter(s: &str) -> Result<(String, String), String> {
     Ok((parts[0].to_string(), parts[1].to_string()))
 }
 
-fn main() -> anyhow::Result<()> {
+fn parse_join(s: &str) -> Result<(String, PathBuf), String> {
+    match s.split_once('=') {
+        Some((field, file)) if !field.is_empty() && !file.is_empty() => {
+            Ok((field.to_string(), PathBuf::from(file)))
+        }
+        _ => Err(format!("Invalid join format: '{}'. Use field=file", s)),
+    }
+}
+
+fn parse_rename(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((old, new)) if !old.is_empty() && !new.is_empty() => {
+            Ok((old.to_string(), new.to_string()))
+        }
+        _ => Err(format!("Invalid rename format: '{}'. Use old=new", s)),
+    }
+}
+
+/// Look up exactly one note by path or title, with a helpful error otherwise
+fn find_single_note<'a>(notes: &'a [Note], query: &str) -> anyhow::Result<&'a Note> {
+    let matches = find_notes(notes, query);
+    match matches.as_slice() {
+        [found] => Ok(found),
+        [] => Err(anyhow::anyhow!("No note found for '{}'", query)),
+        _ => {
+            let paths: Vec<&str> = matches.iter().map(|n| n.path.as_str()).collect();
+            Err(anyhow::anyhow!(
+                "'{}' is ambiguous, it matches:\n  {}",
+                query,
+                paths.join("\n  ")
+            ))
+        }
+    }
+}
+
+/// Read a file given on the command line, or stdin for `-`
+fn read_source(source: &str) -> anyhow::Result<String> {
+    if source == "-" {
+        Ok(std::io::read_to_string(std::io::stdin())?)
+    } else {
+        std::fs::read_to_string(source).with_context(|| format!("Failed to read {}", source))
+    }
+}
+
+fn main() -> ExitCode {
     let cli = Cli::parse();
-    match cli.command {
+    let mut outcome = RunOutcome::default();
+    match run(cli.command, &mut outcome) {
+        Ok(()) => ExitCode::from(outcome.exit_status(cli.fail_if_empty)),
+        Err(e) => match e.downcast_ref::<clap::Error>() {
+            Some(usage) => {
+                // Printing to stderr only fails when there is nowhere to report to
+                let _ = usage.print();
+                ExitCode::from(EXIT_USAGE)
+            }
+            None => {
+                eprintln!("Error: {:?}", e);
+                ExitCode::from(EXIT_FAILURE)
+            }
+        },
+    }
+}
+
+fn run(mut command: Commands, outcome: &mut RunOutcome) -> anyhow::Result<()> {
+    command.apply_config()?;
+    match command {
         Commands::Filter {
-            vault_path,
-            filter,
-            ignore_case,
-            format,
-            verbose,
-            silent,
-            strict,
+            mut scan,
+            filters,
+            output,
+            from,
+            content,
+            regex,
+            limit,
+            offset,
+            count,
+            diff_against,
+            exec,
+            exec_batch,
+            dry_run,
+            open,
+            group_by,
+            split_output,
         } => {
-            let scanner = VaultScanner::new(vault_path)?;
-            let notes = scanner.scan_vault(verbose, silent, !strict, Some(&format))?;
+            let split_output = split_output
+                .map(|pattern| SplitOutput::new(&pattern, output.format.as_deref()))
+                .transpose()?;
+            let mut criteria = filters.criteria();
+            if let Some(content) = content {
+                let query = ContentQuery::new(&content, regex, criteria.is_case_sensitive())?;
+                criteria = criteria.with_content(query);
+                scan.keep_bodies = true;
+            }
+            let paged = limit.is_some() || offset > 0;
+            if from.is_none()
+                && split_output.is_none()
+                && diff_against.is_none()
+                && exec.is_none()
+                && exec_batch.is_none()
+                && !open
+                && (count
+                    || (output.format().eq_ignore_ascii_case("ndjson")
+                        && output.sort.is_none()
+                        && output.columns.is_empty()
+                        && !paged))
+            {
+                // Print matches while scanning instead of holding the whole vault in memory
+                let matches = AtomicUsize::new(0);
+                let stdout = std::io::stdout();
+                let format = if count { None } else { Some(output.format()) };
+                let rules = output.annotate.is_some().then(|| output.rules(&filters));
+                outcome.critical_errors += scan.stream(format, |mut note| {
+                    if criteria.matches(&note) {
+                        if let Some(rules) = &rules {
+                            note.rules = Some(matching_rules(&note, rules));
+                        }
+                        if let Some(max_chars) = output.excerpt {
+                            note.load_excerpt(max_chars);
+                        }
+                        note.redact(&scan.redact);
+                        matches.fetch_add(1, Ordering::Relaxed);
+                        if !count {
+                            output::write_ndjson_line(&mut stdout.lock(), &note)?;
+                        }
+                    }
+                    Ok(())
+                })?;
+                let matches = matches.into_inner();
+                if count {
+                    output::display_match_count(matches, scan.silent);
+                }
+                outcome.matches = Some(matches);
+                return Ok(());
+            }
 
-            let criteria = if ignore_case {
-                FilterCriteria::new_case_insensitive(filter)
-            } else {
-                FilterCriteria::new(filter)
-            };
+            let mut notes = outcome.record(scan.scan(Some(output.format()))?);
+            if let Some(source) = from {
+                notes = restrict_to_paths(notes, &parse_path_list(&read_source(&source)?)?);
+            }
+            output.annotate(&mut notes, &filters);
+            output.add_excerpts(&mut notes, &criteria);
             let filtered_notes = criteria.apply_filters(&notes);
+            outcome.matches = Some(filtered_notes.len());
+            let shown = scan.redacted(&filtered_notes);
+            let filtered_notes: Vec<&Note> = shown.iter().map(|note| &**note).collect();
+
+            let page = || -> Vec<&Note> {
+                output
+                    .sorted(&filtered_notes, Some(&criteria))
+                    .into_iter()
+                    .skip(offset)
+                    .take(limit.unwrap_or(usize::MAX))
+                    .collect()
+            };
 
-            output::display_filtered_results(&filtered_notes, &format, silent)?;
+            if let Some(source) = diff_against {
+                let previous = parse_path_list(&read_source(&source)?)?;
+                let diff = diff_results(&previous, &filtered_notes);
+                output::display_result_diff(&diff, output.format(), scan.silent)?;
+            } else if count {
+                output::display_match_count(filtered_notes.len(), scan.silent);
+            } else if let Some(command) = exec.as_deref().or(exec_batch.as_deref()) {
+                let command = ExecCommand::new(command);
+                let page = page();
+                let paths: Vec<&str> = page.iter().map(|note| note.path.as_str()).collect();
+                let command_lines: Vec<String> = if exec_batch.is_some() {
+                    command.command_lines(&paths)
+                } else {
+                    paths
+                        .iter()
+                        .map(|path| command.command_line(&[path]))
+                        .collect()
+                };
+                for command_line in &command_lines {
+                    if dry_run {
+                        println!("{}", command_line);
+                    } else if !exec::run(command_line)? {
+                        eprintln!("Warning: Command failed: {}", command_line);
+                        outcome.problems += 1;
+                    }
+                }
+            } else if open {
+                match page().first() {
+                    Some(note) => {
+                        tui::open_in_obsidian(note, scan.vault_of(note))?;
+                        if !scan.silent {
+                            println!("Opened {}", note.path);
+                        }
+                    }
+                    None if !scan.silent => eprintln!("No matching note to open"),
+                    None => {}
+                }
+            } else if let (Some(split_output), Some(field)) = (&split_output, &group_by) {
+                let groups = group_notes(
+                    &page(),
+                    std::slice::from_ref(field),
+                    criteria.is_case_sensitive(),
+                );
+                let written = split_output.write(&groups)?;
+                output::display_split_results(&written, scan.silent);
+            } else if paged {
+                output.display_sorted(&page(), Some(&criteria), scan.silent)?;
+            } else {
+                output.display(&filtered_notes, Some(&criteria), scan.silent)?;
+            }
         }
         Commands::Fields {
-            vault_path,
-            filter,
-            ignore_case,
-            verbose,
-            silent,
-            strict,
-        } => {
-            let scanner = VaultScanner::new(vault_path)?;
-            let notes = scanner.scan_vault(verbose, silent, !strict, None)?;
-
-            let criteria = if ignore_case {
-                FilterCriteria::new_case_insensitive(filter)
-            } else {
-                FilterCriteria::new(filter)
-            };
-            let filtered_notes = criteria.apply_filters(&notes);
+            scan,
+            filters,
+            namespace_separator,
+            cached,
+            format,
+            quoting,
+        } => {
+            if cached {
+                let vault_path = &scan.vaults[0].1;
+                let names = match FieldCache::new().and_then(|cache| cache.read(vault_path)) {
+                    Some(names) => names,
+                    None => {
+                        let notes = outcome.record(scan.scan(None)?);
+                        collect_all_fields(&notes)
+                    }
+                };
+                for name in names {
+                    println!("{}", name);
+                }
+                return Ok(());
+            }
+            if namespace_separator.as_deref() == Some("") {
+                return Err(usage_error(
+                    ErrorKind::InvalidValue,
+                    "The namespace separator must not be empty",
+                ));
+            }
+            let notes = outcome.record(scan.scan(Some(&format))?);
+            let filtered_notes = filters.criteria().apply_filters(&notes);
+            outcome.matches = Some(filtered_notes.len());
 
             // Convert Vec<&Note> back to Vec<Note> for display_all_fields
-            let filtered_notes_owned: Vec<Note> = filtered_notes.into_iter().cloned().collect();
+            let mut filtered_notes_owned: Vec<Note> = filtered_notes.into_iter().cloned().collect();
+            for note in &mut filtered_notes_owned {
+                note.redact(&scan.redact);
+            }
 
-            output::display_all_fields(&filtered_notes_owned, silent)?;
+            output::display_all_fields(
+                &filtered_notes_owned,
+                namespace_separator.as_deref(),
+                &format,
+                quoting.quoting(),
+                scan.silent,
+            )?;
         }
         Commands::Values {
-            vault_path,
+            scan,
+            filters,
             field,
-            ignore_case,
-            filter,
-            verbose,
-            silent,
-            strict,
+            all_fields,
+            per_folder,
+            show_sources,
+            format,
+            quoting,
         } => {
-            let scanner = VaultScanner::new(vault_path)?;
-            let notes = scanner.scan_vault(verbose, silent, !strict, None)?;
+            let notes = outcome.record(scan.scan(Some(&format))?);
+            let filtered_notes = filters.criteria().apply_filters(&notes);
+            outcome.matches = Some(filtered_notes.len());
 
-            let criteria = if ignore_case {
-                FilterCriteria::new_case_insensitive(filter)
+            // Convert Vec<&Note> back to Vec<Note> for display_field_values
+            let mut filtered_notes_owned: Vec<Note> =
+                filtered_notes.iter().copied().cloned().collect();
+            for note in &mut filtered_notes_owned {
+                note.redact(&scan.redact);
+            }
+            let filtered_notes: Vec<&Note> = filtered_notes_owned.iter().collect();
+            let requested = if all_fields {
+                collect_all_fields(&filtered_notes_owned)
             } else {
-                FilterCriteria::new(filter)
+                field
             };
+            // Maps have no values of their own, list the values nested in them instead
+            let (fields, maps) = expand_map_fields(
+                &requested,
+                &get_field_statistics(&filtered_notes_owned),
+                !filters.ignore_case,
+            );
+            if !all_fields && !scan.silent {
+                for map in &maps {
+                    let nested: Vec<&str> = fields
+                        .iter()
+                        .filter(|field| field.starts_with(&format!("{}.", map)))
+                        .map(String::as_str)
+                        .collect();
+                    eprintln!(
+                        "'{}' holds maps, listing the values of {}",
+                        map,
+                        nested.join(", ")
+                    );
+                }
+            }
+
+            if let Some(sources) = show_sources {
+                let sources = if sources.is_empty() {
+                    ValueSource::ALL.to_vec()
+                } else {
+                    sources
+                };
+                let counts: Vec<_> = fields
+                    .into_iter()
+                    .map(|field| {
+                        let counts = collect_field_values_by_source(
+                            &filtered_notes,
+                            &field,
+                            !filters.ignore_case,
+                            &sources,
+                        );
+                        (field, counts)
+                    })
+                    .collect();
+                output::display_values_by_source(&counts, &format, quoting.quoting(), scan.silent)?;
+            } else if per_folder {
+                let folders: Vec<_> = fields
+                    .into_iter()
+                    .map(|field| {
+                        let folders = collect_field_values_by_folder(
+                            &filtered_notes,
+                            &field,
+                            scan.vault(),
+                            !filters.ignore_case,
+                        );
+                        (field, folders)
+                    })
+                    .collect();
+                output::display_values_per_folder(
+                    &folders,
+                    &format,
+                    quoting.quoting(),
+                    scan.silent,
+                )?;
+            } else {
+                output::display_values_of_fields(
+                    &filtered_notes_owned,
+                    &fields,
+                    &format,
+                    quoting.quoting(),
+                    !filters.ignore_case,
+                    scan.silent,
+                )?;
+            }
+        }
+        Commands::Complete {
+            scan,
+            filters,
+            field,
+            prefix,
+            limit,
+        } => {
+            let notes = outcome.record(scan.scan(Some("json"))?);
+            let criteria = filters.criteria();
             let filtered_notes = criteria.apply_filters(&notes);
+            let shown = scan.redacted(&filtered_notes);
+            let filtered_notes: Vec<&Note> = shown.iter().map(|note| &**note).collect();
 
-            // Convert Vec<&Note> back to Vec<Note> for display_field_values
-            let filtered_notes_owned: Vec<Note> = filtered_notes.into_iter().cloned().collect();
+            let mut completions = criteria.completions(&filtered_notes, &field, &prefix);
+            if let Some(limit) = limit {
+                completions.truncate(limit);
+            }
+            outcome.matches = Some(completions.len());
+            output::display_completions(&completions)?;
+        }
+        Commands::Batch {
+            queries,
+            scan,
+            filters,
+        } => {
+            let batch = Batch::from_file(&queries)?;
+            let notes = outcome.record(scan.scan(None)?);
 
-            output::display_field_values_with_options(
-                &filtered_notes_owned,
+            let mut results = Vec::new();
+            for query in &batch.queries {
+                let mut query_filters = filters.field_filters();
+                query_filters.extend(query.filters.iter().cloned());
+                let criteria = filters.criteria_for(query_filters);
+                let mut matches = criteria.apply_filters(&notes);
+                if let Some(field) = &query.sort {
+                    sort_notes_collated(
+                        &mut matches,
+                        field,
+                        query.desc,
+                        criteria.is_case_sensitive(),
+                        &Collation::default(),
+                    );
+                }
+                let shown = scan.redacted(&matches);
+                query.write(&shown.iter().map(|note| &**note).collect::<Vec<_>>())?;
+                results.push((query, matches.len()));
+            }
+            outcome.matches = Some(results.iter().map(|(_, count)| count).sum());
+            output::display_batch_results(&results, scan.silent);
+        }
+        Commands::Group {
+            scan,
+            filters,
+            by,
+            list,
+            format,
+        } => {
+            let notes = outcome.record(scan.scan(Some(&format))?);
+            let filtered_notes = filters.criteria().apply_filters(&notes);
+
+            outcome.matches = Some(filtered_notes.len());
+            let shown = scan.redacted(&filtered_notes);
+            let filtered_notes: Vec<&Note> = shown.iter().map(|note| &**note).collect();
+            let groups = group_notes(&filtered_notes, &by, !filters.ignore_case);
+            output::display_groups(&groups, &by, &format, list, scan.silent)?;
+        }
+        Commands::Duplicates {
+            scan,
+            filters,
+            field,
+            format,
+        } => {
+            let notes = outcome.record(scan.scan(Some(&format))?);
+            let filtered_notes = filters.criteria().apply_filters(&notes);
+
+            let duplicates = find_duplicates(&filtered_notes, &field, !filters.ignore_case);
+            output::display_duplicates(
+                &duplicates,
                 &field,
-                !ignore_case,
-                silent,
+                is_redacted(&field, &scan.redact),
+                &format,
+                scan.silent,
             )?;
+            outcome.problems = duplicates.len();
+        }
+        Commands::Resolve { name, scan, format } => {
+            let notes = outcome.record(scan.scan(Some(&format))?);
+            let index = TitleIndex::new(&notes);
+
+            let matches = index.resolve(&name);
+            if matches.is_empty() && !scan.silent {
+                eprintln!("No note goes by '{}'", name);
+            }
+            output::display_name_matches(matches, &format)?;
+            outcome.matches = Some(matches.len());
+            if matches.len() > 1 {
+                outcome.problems = 1;
+            }
+        }
+        Commands::Collisions { scan, format } => {
+            let notes = outcome.record(scan.scan(Some(&format))?);
+            let index = TitleIndex::new(&notes);
+            let collisions = index.collisions();
+
+            output::display_name_collisions(&collisions, &format, scan.silent)?;
+            outcome.problems = collisions.len();
+        }
+        Commands::Show { note, scan, format } => {
+            let notes = outcome.record(scan.scan(Some(&format))?);
+            let found = find_single_note(&notes, &note)?;
+
+            // Parse again to recover the warning for just this note
+            let warning = parse_frontmatter_from_file(
+                &found.path,
+                scan.verbose,
+                !scan.strict,
+                false,
+                scan.fences(),
+            )?
+            .frontmatter_warning;
+            let links = LinkIndex::new(&notes).outgoing(found);
+            let mut found = found.clone();
+            found.redact(&scan.redact);
+            output::display_note_detail(&found, &links, warning.as_deref(), &format)?;
+        }
+        Commands::Links {
+            note,
+            scan,
+            filters,
+            output,
+        } => {
+            let mut notes = outcome.record(scan.scan(Some(output.format()))?);
+            output.annotate(&mut notes, &filters);
+            let found = find_single_note(&notes, &note)?;
+
+            let mut linked: Vec<Note> = Vec::new();
+            let mut unresolved: Vec<String> = Vec::new();
+            for (target, resolved) in LinkIndex::new(&notes).outgoing(found) {
+                match resolved {
+                    Some(target_note) => linked.push(target_note.clone()),
+                    None => unresolved.push(target),
+                }
+            }
+
+            if !unresolved.is_empty() && !scan.silent {
+                eprintln!("Unresolved links: {}", unresolved.join(", "));
+            }
+            let criteria = filters.criteria();
+            output.add_excerpts(&mut linked, &criteria);
+            let filtered_notes = criteria.apply_filters(&linked);
+            outcome.matches = Some(filtered_notes.len());
+            let shown = scan.redacted(&filtered_notes);
+            let filtered_notes: Vec<&Note> = shown.iter().map(|note| &**note).collect();
+            output.display(&filtered_notes, None, scan.silent)?;
+        }
+        Commands::Backlinks {
+            note,
+            scan,
+            filters,
+            output,
+        } => {
+            let mut notes = outcome.record(scan.scan(Some(output.format()))?);
+            output.annotate(&mut notes, &filters);
+            let found = find_single_note(&notes, &note)?;
+
+            let mut linking: Vec<Note> = LinkIndex::new(&notes)
+                .backlinks(&found.path)
+                .into_iter()
+                .cloned()
+                .collect();
+            let criteria = filters.criteria();
+            output.add_excerpts(&mut linking, &criteria);
+            let filtered_notes = criteria.apply_filters(&linking);
+            outcome.matches = Some(filtered_notes.len());
+            let shown = scan.redacted(&filtered_notes);
+            let filtered_notes: Vec<&Note> = shown.iter().map(|note| &**note).collect();
+            output.display(&filtered_notes, None, scan.silent)?;
+        }
+        Commands::Orphans {
+            scan,
+            filters,
+            output,
+        } => {
+            let mut notes = outcome.record(scan.scan(Some(output.format()))?);
+            output.annotate(&mut notes, &filters);
+            let mut orphans: Vec<Note> = LinkIndex::new(&notes)
+                .orphans()
+                .into_iter()
+                .cloned()
+                .collect();
+            let criteria = filters.criteria();
+            output.add_excerpts(&mut orphans, &criteria);
+            let filtered_notes = criteria.apply_filters(&orphans);
+            outcome.matches = Some(filtered_notes.len());
+            let shown = scan.redacted(&filtered_notes);
+            let filtered_notes: Vec<&Note> = shown.iter().map(|note| &**note).collect();
+            output.display(&filtered_notes, None, scan.silent)?;
+        }
+        Commands::BrokenLinks {
+            scan,
+            filters,
+            format,
+        } => {
+            let notes = outcome.record(scan.scan(Some(&format))?);
+            let attachments = scan.scanner()?.attachment_paths();
+            let criteria = filters.criteria();
+            let sources: HashSet<&str> = criteria
+                .apply_filters(&notes)
+                .iter()
+                .map(|note| note.path.as_str())
+                .collect();
+
+            let broken: Vec<_> = LinkIndex::new(&notes)
+                .with_attachments(attachments)
+                .broken_links()
+                .into_iter()
+                .filter(|link| sources.contains(&link.source.path.as_str()))
+                .collect();
+            outcome.matches = Some(broken.len());
+            output::display_broken_links(&broken, &format, scan.silent)?;
+        }
+        Commands::Browse { scan, filters } => {
+            let mut notes = outcome.record(scan.scan(None)?);
+            // The browser only shows notes, so it filters the masked values
+            for note in &mut notes {
+                note.redact(&scan.redact);
+            }
+            let options = tui::BrowseOptions {
+                match_mode: filters.match_mode(),
+                filters: filters.filter,
+                case_sensitive: !filters.ignore_case,
+                include_inline_tags: filters.include_inline_tags,
+                matchers: filters.matchers,
+            };
+            tui::run(&notes, scan.vault(), options)?;
+        }
+        Commands::Export {
+            scan,
+            filters,
+            format,
+            out,
+        } => {
+            if !format.eq_ignore_ascii_case("sqlite") {
+                return Err(usage_error(
+                    ErrorKind::InvalidValue,
+                    &format!("Unknown export format: {}. Supported: sqlite", format),
+                ));
+            }
+            let notes = outcome.record(scan.scan(None)?);
+            let filtered_notes = filters.criteria().apply_filters(&notes);
+            outcome.matches = Some(filtered_notes.len());
+
+            let shown = scan.redacted(&filtered_notes);
+            let filtered_notes: Vec<&Note> = shown.iter().map(|note| &**note).collect();
+            let summary = export::export_sqlite(&filtered_notes, &notes, &out)?;
+            if !scan.silent {
+                println!(
+                    "Exported {} notes, {} frontmatter values and {} links to {}",
+                    summary.notes,
+                    summary.values,
+                    summary.links,
+                    out.display()
+                );
+            }
+        }
+        Commands::Dashboard {
+            scan,
+            filters,
+            title,
+            out,
+        } => {
+            let notes = outcome.record(scan.scan(None)?);
+            let filtered_notes = filters.criteria().apply_filters(&notes);
+            outcome.matches = Some(filtered_notes.len());
+
+            let title = title.unwrap_or_else(|| {
+                scan.vaults
+                    .iter()
+                    .map(|(name, _)| name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            });
+            let generated = chrono::Local::now().format("%Y-%m-%d %H:%M").to_string();
+            let shown = scan.redacted(&filtered_notes);
+            let filtered_notes: Vec<&Note> = shown.iter().map(|note| &**note).collect();
+            Dashboard::new(&filtered_notes, &title, &generated).write(&out)?;
+            if !scan.silent {
+                println!(
+                    "Wrote a dashboard of {} notes to {}",
+                    filtered_notes.len(),
+                    out.display()
+                );
+            }
+        }
+        Commands::Stats {
+            scan,
+            filters,
+            top,
+            by,
+            health,
+            require,
+            record,
+            trend,
+            history,
+            format,
+        } => {
+            let history = history.unwrap_or_else(|| scan.vault().join(stats::HISTORY_FILE));
+            if trend && !record {
+                output::display_trend(&stats::read_history(&history)?, &format)?;
+                return Ok(());
+            }
+
+            let scanned = scan.scan(Some(&format))?;
+            let warnings = scanned.warnings.clone();
+            let notes = outcome.record(scanned);
+            let filtered_notes = filters.criteria().apply_filters(&notes);
+            outcome.matches = Some(filtered_notes.len());
+            let shown = scan.redacted(&filtered_notes);
+            let shown: Vec<&Note> = shown.iter().map(|note| &**note).collect();
+            if health {
+                let missing = require
+                    .iter()
+                    .map(|field| {
+                        let criteria = filters.criteria_for(vec![FieldFilter::missing(field)]);
+                        let count = filtered_notes
+                            .iter()
+                            .filter(|note| criteria.matches(note))
+                            .count();
+                        (field.clone(), count)
+                    })
+                    .collect();
+                let report = HealthReport::new(&shown, top.unwrap_or(3))
+                    .with_scan_problems(outcome.critical_errors, warnings)
+                    .with_missing(missing);
+                output::display_health_report(&report, &format)?;
+            } else if record {
+                let report = MetricsReport::new(&shown, top.unwrap_or(10));
+                let snapshot = Snapshot::new(
+                    &report,
+                    outcome.critical_errors,
+                    chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+                );
+                stats::record_snapshot(&history, &snapshot)?;
+                if !scan.silent {
+                    eprintln!("Recorded snapshot to {}", history.display());
+                }
+                if trend {
+                    output::display_trend(&stats::read_history(&history)?, &format)?;
+                } else {
+                    output::display_metrics_report(&report, &format)?;
+                }
+            } else if let Some(field) = by {
+                let reports = metrics_by(&shown, &field, !filters.ignore_case);
+                output::display_metrics_by(&reports, &field, &format)?;
+            } else {
+                output::display_metrics_report(
+                    &MetricsReport::new(&shown, top.unwrap_or(10)),
+                    &format,
+                )?;
+            }
+        }
+        Commands::Lint {
+            scan,
+            filters,
+            key_order,
+            key_case,
+            format,
+            fix,
+            write,
+        } => {
+            let linter = Linter::new(key_order, key_case)?;
+            let notes = outcome.record(scan.scan(Some(&format))?);
+            let filtered_notes = filters.criteria().apply_filters(&notes);
+
+            let mut issues: Vec<LintIssue> = Vec::new();
+            for note in &filtered_notes {
+                match linter.check_note(note) {
+                    Ok(found) => issues.extend(found),
+                    Err(e) => eprintln!("Warning: {:#}", e),
+                }
+            }
+            issues.sort_by(|a, b| a.path.cmp(&b.path));
+            output::display_issues(&issues, &format, scan.silent)?;
+
+            if fix && !issues.is_empty() {
+                let offenders: HashSet<&str> = issues.iter().map(|i| i.path.as_str()).collect();
+                let offending_notes: Vec<&Note> = filtered_notes
+                    .into_iter()
+                    .filter(|note| offenders.contains(note.path.as_str()))
+                    .collect();
+                let plan = plan_changes(&offending_notes, |_, frontmatter| {
+                    linter.fix(frontmatter);
+                });
+                write.execute(&plan, scan.silent)?;
+                if !write.dry_run {
+                    return Ok(());
+                }
+            }
+            outcome.problems = issues.len();
+        }
+        Commands::Validate {
+            scan,
+            filters,
+            schema,
+            format,
+        } => {
+            let schema_path = schema.ok_or_else(|| {
+                usage_error(
+                    ErrorKind::MissingRequiredArgument,
+                    "No schema given: use --schema or set schema in aktenfux.toml",
+                )
+            })?;
+            let schema = Schema::from_file(schema_path)?;
+            let notes = outcome.record(scan.scan(Some(&format))?);
+            let filtered_notes = filters.criteria().apply_filters(&notes);
+
+            let mut issues: Vec<LintIssue> = filtered_notes
+                .iter()
+                .flat_map(|note| schema.validate(note))
+                .collect();
+            issues.sort_by(|a, b| a.path.cmp(&b.path));
+            output::display_issues(&issues, &format, scan.silent)?;
+            outcome.problems = issues.len();
+        }
+        Commands::Hook {
+            hook:
+                HookCommand::PreCommit {
+                    scan,
+                    schema,
+                    key_order,
+                    key_case,
+                },
+        } => {
+            let schema = schema.map(Schema::from_file).transpose()?;
+            let linter = (!key_order.is_empty() || key_case.is_some())
+                .then(|| Linter::new(key_order, key_case))
+                .transpose()?;
+            let checks = HookChecks::new(schema, linter)?;
+
+            // --stdin reads the paths to check instead of asking git for the staged files
+            let files: Vec<PathBuf> = match &scan.files {
+                Some(files) => files.iter().filter(|path| path.exists()).cloned().collect(),
+                None => hook::staged_files(scan.vault())?,
+            };
+
+            let contents = hook::read_staged(scan.vault(), &files)?;
+
+            let mut issues: Vec<LintIssue> = Vec::new();
+            for (file, content) in files.iter().zip(contents) {
+                let checked = content.and_then(|content| {
+                    checks.check_content(file, &content, !scan.strict, scan.fences())
+                });
+                match checked {
+                    Ok(found) => issues.extend(found),
+                    Err(e) => {
+                        eprintln!("Error: {:#}", e);
+                        outcome.critical_errors += 1;
+                    }
+                }
+            }
+            issues.sort_by(|a, b| a.path.cmp(&b.path));
+            output::display_issues(&issues, "short", true)?;
+            outcome.problems = issues.len();
+        }
+        Commands::Edit {
+            scan,
+            filters,
+            set,
+            add,
+            remove,
+            write,
+        } => {
+            let mut edits: Vec<FrontmatterEdit> = Vec::new();
+            edits.extend(
+                set.into_iter()
+                    .map(|(k, v)| FrontmatterEdit::Set(k, parse_scalar(&v))),
+            );
+            edits.extend(
+                add.into_iter()
+                    .map(|(k, v)| FrontmatterEdit::Add(k, parse_scalar(&v))),
+            );
+            edits.extend(remove.into_iter().map(|r| match r.split_once('=') {
+                Some((k, v)) => FrontmatterEdit::RemoveValue(k.to_string(), parse_scalar(v)),
+                None => FrontmatterEdit::Remove(r),
+            }));
+            if edits.is_empty() {
+                return Err(usage_error(
+                    ErrorKind::MissingRequiredArgument,
+                    "Nothing to do: use --set, --add or --remove",
+                ));
+            }
+
+            let notes = outcome.record(scan.scan(None)?);
+            let filtered_notes = filters.criteria().apply_filters(&notes);
+
+            let plan = plan_changes(&filtered_notes, |_, frontmatter| {
+                for edit in &edits {
+                    edit.apply(frontmatter);
+                }
+            });
+            write.execute(&plan, scan.silent)?;
+        }
+        Commands::Query { query } => match query {
+            QueryCommand::Save {
+                name,
+                vault_path,
+                filters,
+                content,
+                regex,
+                sort,
+                desc,
+                format,
+                force,
+            } => {
+                let (_, vault_path) = Config::load(vault_path.as_deref())?;
+                let query = SavedQuery {
+                    filter: filters.filter.iter().map(ToString::to_string).collect(),
+                    preset: filters.preset,
+                    has: filters.has,
+                    missing: filters.missing,
+                    content,
+                    regex,
+                    ignore_case: filters.ignore_case,
+                    exact: filters.exact,
+                    include_inline_tags: filters.include_inline_tags,
+                    sort,
+                    desc,
+                    format,
+                };
+                let path = query::save(&vault_path, &name, &query, force)?;
+                println!("Saved query '{}' to {}", name, path.display());
+            }
+            QueryCommand::List { vault_path } => {
+                let (config, _) = Config::load(vault_path.as_deref())?;
+                if config.queries.is_empty() {
+                    eprintln!("No saved queries, add one with `aktenfux query save`");
+                }
+                let width = config.queries.keys().map(String::len).max().unwrap_or(0);
+                for (name, query) in &config.queries {
+                    println!("{:<width$}  {}", name, query.arguments(), width = width);
+                }
+            }
+            query @ QueryCommand::Run { .. } => return run(query.into_filter()?, outcome),
+        },
+        Commands::Migrate {
+            scan,
+            filters,
+            map,
+            rename,
+            write,
+        } => {
+            let mut migration = match map {
+                Some(map) => Migration::from_file(map)?,
+                None => Migration::default(),
+            };
+            for (old, new) in rename {
+                migration.add_rename(old, new);
+            }
+            if migration.is_empty() {
+                return Err(usage_error(
+                    ErrorKind::MissingRequiredArgument,
+                    "Nothing to do: use --map or --rename",
+                ));
+            }
+
+            let notes = outcome.record(scan.scan(None)?);
+            let filtered_notes = filters.criteria().apply_filters(&notes);
+
+            let mut report = MigrationReport::default();
+            let plan = plan_changes(&filtered_notes, |_, frontmatter| {
+                migration.apply(frontmatter, &mut report);
+            });
+            report.print();
+            write.execute(&plan, scan.silent)?;
+        }
+        Commands::Normalize {
+            scan,
+            filters,
+            field,
+            normalizations,
+            preview,
+            format,
+            write,
+        } => {
+            let notes = outcome.record(scan.scan(None)?);
+            let filtered_notes = filters.criteria().apply_filters(&notes);
+
+            let normalizers: Vec<Normalizer> = field
+                .into_iter()
+                .map(|field| {
+                    if normalizations.is_empty() {
+                        Normalizer::infer(field, &filtered_notes)
+                    } else {
+                        Normalizer::new(field, normalizations.clone())
+                    }
+                })
+                .collect();
+
+            if preview {
+                let changes = normalize::preview(&filtered_notes, &normalizers);
+                outcome.matches = Some(changes.len());
+                output::display_normalization_preview(&changes, &format, scan.silent)?;
+                return Ok(());
+            }
+
+            let plan = plan_changes(&filtered_notes, |_, frontmatter| {
+                for normalizer in &normalizers {
+                    normalizer.apply(frontmatter);
+                }
+            });
+            write.execute(&plan, scan.silent)?;
+        }
+        Commands::LenientReport {
+            mut scan,
+            filters,
+            format,
+        } => {
+            // A strict scan would report every lenient note as failed
+            scan.strict = false;
+            let notes = outcome.record(scan.scan(Some(&format))?);
+            let filtered_notes = filters.criteria().apply_filters(&notes);
+            let report = LenientReport::new(&filtered_notes);
+            outcome.problems = report.paths().len();
+            output::display_lenient_report(&report, &format, scan.silent)?;
+        }
+        Commands::Fix {
+            mut scan,
+            filters,
+            lenient_only,
+            write,
+        } => {
+            // Notes that only parse leniently are the ones to fix
+            scan.strict = false;
+            let notes = outcome.record(scan.scan(None)?);
+            let filtered_notes = filters.criteria().apply_filters(&notes);
+
+            let (plan, unrepairable) = plan_repairs(&filtered_notes, lenient_only);
+            for (path, reason) in &unrepairable {
+                eprintln!("Warning: Cannot repair frontmatter of {}: {}", path, reason);
+            }
+            outcome.problems += unrepairable.len();
+            write.execute(&plan, scan.silent)?;
+        }
+        Commands::Policy {
+            policy:
+                PolicyCommand::Run {
+                    scan,
+                    filters,
+                    format,
+                    write,
+                    policies,
+                    ..
+                },
+        } => {
+            let notes = outcome.record(scan.scan(None)?);
+            let matches: Vec<(&Policy, Vec<&Note>)> = policies
+                .iter()
+                .map(|policy| {
+                    let mut policy_filters = filters.field_filters();
+                    policy_filters.extend(policy.filters.iter().cloned());
+                    let criteria = filters.criteria_for(policy_filters);
+                    (policy, criteria.apply_filters(&notes))
+                })
+                .collect();
+            let run = PolicyRun::plan(&matches, |note| scan.vault_of(note).to_path_buf());
+            outcome.matches = Some(run.files());
+            match format.as_str() {
+                "json" => println!("{}", run.report_json()?),
+                "table" => run.print_report(&policies),
+                other => {
+                    eprintln!("Unknown format: {}. Using table format.", other);
+                    run.print_report(&policies);
+                }
+            }
+
+            if write.dry_run || run.is_empty() {
+                return Ok(());
+            }
+            if !plan::confirm_changes(run.files(), write.yes, write.confirm_threshold())? {
+                return Err(anyhow::anyhow!("Aborted, no files were changed"));
+            }
+            let (written, moved) = run.apply()?;
+            if !scan.silent {
+                println!("Updated {} files, moved {} notes", written, moved);
+            }
+        }
+        Commands::Completions { shell } => {
+            print!("{}", completions::script(&mut Cli::command(), shell));
         }
     }
 
@@ -0,0 +1,129 @@
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+/// Custom comparison for the values of a field, for values the built-in date, number and
+/// text comparison gets wrong, like durations written as `1h30m`.
+///
+/// Filters on the field (`=`, `!=`, `<`, `<=`, `>`, `>=`) use the matcher for every value
+/// it understands and fall back to the built-in comparison for the others.
+pub trait ValueMatcher: Send + Sync {
+    /// Order a field value against the value of a filter, `None` when either isn't a value
+    /// this matcher understands
+    fn compare(&self, value: &str, other: &str) -> Option<Ordering>;
+}
+
+/// Reads values as quantities with a regular expression: every named group holds a number
+/// that is multiplied by the weight of its unit, and the products are added up.
+///
+/// With the pattern `^(?:(?P<h>\d+)h)?\s*(?:(?P<m>\d+)m)?$` and the units `h = 60` and
+/// `m = 1`, `1h30m` and `90m` are both 90.
+#[derive(Debug, Clone)]
+pub struct QuantityMatcher {
+    pattern: Regex,
+    units: BTreeMap<String, f64>,
+}
+
+impl QuantityMatcher {
+    pub fn new(pattern: &str, units: BTreeMap<String, f64>) -> Result<Self> {
+        let pattern =
+            Regex::new(pattern).map_err(|e| anyhow!("Invalid pattern '{}': {}", pattern, e))?;
+        if units.is_empty() {
+            return Err(anyhow!("A pattern needs units for its named groups"));
+        }
+        let groups: Vec<&str> = pattern.capture_names().flatten().collect();
+        if let Some(unit) = units.keys().find(|unit| !groups.contains(&unit.as_str())) {
+            return Err(anyhow!(
+                "The pattern has no group named '{}' for the unit of the same name",
+                unit
+            ));
+        }
+        Ok(Self { pattern, units })
+    }
+
+    /// The quantity of a value, `None` when the pattern doesn't match it or no unit was found
+    pub fn quantity(&self, value: &str) -> Option<f64> {
+        let captures = self.pattern.captures(value.trim())?;
+        let mut found = false;
+        let mut total = 0.0;
+        for (unit, weight) in &self.units {
+            if let Some(amount) = captures.name(unit) {
+                total += amount.as_str().replace(',', ".").parse::<f64>().ok()? * weight;
+                found = true;
+            }
+        }
+        found.then_some(total)
+    }
+}
+
+impl ValueMatcher for QuantityMatcher {
+    fn compare(&self, value: &str, other: &str) -> Option<Ordering> {
+        Some(self.quantity(value)?.total_cmp(&self.quantity(other)?))
+    }
+}
+
+/// Orders values by their position in a list, like `low`, `medium`, `high`. Case is
+/// ignored.
+#[derive(Debug, Clone)]
+pub struct OrderMatcher {
+    order: Vec<String>,
+}
+
+impl OrderMatcher {
+    pub fn new(order: Vec<String>) -> Self {
+        Self {
+            order: order
+                .iter()
+                .map(|value| value.trim().to_lowercase())
+                .collect(),
+        }
+    }
+
+    fn rank(&self, value: &str) -> Option<usize> {
+        let value = value.trim().to_lowercase();
+        self.order.iter().position(|item| *item == value)
+    }
+}
+
+impl ValueMatcher for OrderMatcher {
+    fn compare(&self, value: &str, other: &str) -> Option<Ordering> {
+        Some(self.rank(value)?.cmp(&self.rank(other)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quantity_matcher() {
+        let units = BTreeMap::from([("h".to_string(), 60.0), ("m".to_string(), 1.0)]);
+        let duration = QuantityMatcher::new(
+            r"^(?:(?P<h>\d+(?:[.,]\d+)?)h)?\s*(?:(?P<m>\d+)m)?$",
+            units.clone(),
+        )
+        .unwrap();
+        assert_eq!(duration.quantity("1h30m"), Some(90.0));
+        assert_eq!(duration.quantity("1,5h"), Some(90.0));
+        assert_eq!(duration.compare("1h30m", "90m"), Some(Ordering::Equal));
+        assert_eq!(duration.compare("45m", "1h"), Some(Ordering::Less));
+        assert_eq!(duration.compare("soon", "1h"), None);
+        assert_eq!(duration.quantity(""), None);
+
+        assert!(QuantityMatcher::new(r"(?P<h>\d+)h", units).is_err());
+        assert!(QuantityMatcher::new(r"(\d+", BTreeMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_order_matcher() {
+        let priority = OrderMatcher::new(vec![
+            "low".to_string(),
+            "medium".to_string(),
+            "High".to_string(),
+        ]);
+        assert_eq!(priority.compare("high", "medium"), Some(Ordering::Greater));
+        assert_eq!(priority.compare("Low", "low"), Some(Ordering::Equal));
+        assert_eq!(priority.compare("urgent", "low"), None);
+    }
+}
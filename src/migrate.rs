@@ -0,0 +1,258 @@
+use crate::writer::FrontmatterEdit;
+use anyhow::{anyhow, Context, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use yaml_rust2::yaml::Hash;
+use yaml_rust2::{Yaml, YamlLoader};
+
+/// A batch of field renames, value replacements and deletions loaded from a YAML file:
+///
+/// ```yaml
+/// rename:
+///   Status: status
+/// replace:
+///   status:
+///     wip: in-progress
+/// delete:
+///   - legacy_id
+/// ```
+#[derive(Debug, Default)]
+pub struct Migration {
+    edits: Vec<FrontmatterEdit>,
+}
+
+impl Migration {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let content = fs::read_to_string(&path).with_context(|| {
+            format!("Failed to read migration map: {}", path.as_ref().display())
+        })?;
+        Self::from_yaml(&content)
+            .with_context(|| format!("Invalid migration map: {}", path.as_ref().display()))
+    }
+
+    pub fn from_yaml(content: &str) -> Result<Self> {
+        let docs =
+            YamlLoader::load_from_str(content).map_err(|e| anyhow!("YAML parsing error: {}", e))?;
+        let Some(Yaml::Hash(root)) = docs.into_iter().next() else {
+            return Err(anyhow!("Expected a mapping with rename, replace or delete"));
+        };
+
+        // Renames run first so replacements and deletions can use the new names, whatever
+        // the order of the sections in the file
+        let mut renames = Vec::new();
+        let mut replacements = Vec::new();
+        let mut deletions = Vec::new();
+        for (section, entries) in &root {
+            match (section.as_str(), entries) {
+                (Some("rename"), Yaml::Hash(fields)) => {
+                    for (old, new) in fields {
+                        renames.push(FrontmatterEdit::Rename(key_string(old)?, key_string(new)?));
+                    }
+                }
+                (Some("replace"), Yaml::Hash(fields)) => {
+                    for (field, values) in fields {
+                        let Yaml::Hash(values) = values else {
+                            return Err(anyhow!(
+                                "Replacements for '{}' must be a mapping of old: new values",
+                                key_string(field)?
+                            ));
+                        };
+                        for (from, to) in values {
+                            replacements.push(FrontmatterEdit::Replace(
+                                key_string(field)?,
+                                from.clone(),
+                                to.clone(),
+                            ));
+                        }
+                    }
+                }
+                (Some("delete"), Yaml::Array(fields)) => {
+                    for field in fields {
+                        deletions.push(FrontmatterEdit::Remove(key_string(field)?));
+                    }
+                }
+                (Some(name), _) => {
+                    return Err(anyhow!("Unknown or malformed migration section: {}", name));
+                }
+                (None, _) => return Err(anyhow!("Migration sections must be named")),
+            }
+        }
+
+        let mut edits = renames;
+        edits.extend(replacements);
+        edits.extend(deletions);
+        Ok(Self { edits })
+    }
+
+    /// Add a rename after the other renames, before any replacement or deletion
+    pub fn add_rename(&mut self, old: String, new: String) {
+        let index = self
+            .edits
+            .iter()
+            .position(|edit| !matches!(edit, FrontmatterEdit::Rename(..)))
+            .unwrap_or(self.edits.len());
+        self.edits.insert(index, FrontmatterEdit::Rename(old, new));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+
+    /// Apply all steps to one note's frontmatter, recording what happened in the report
+    pub fn apply(&self, frontmatter: &mut Hash, report: &mut MigrationReport) {
+        for edit in &self.edits {
+            if let FrontmatterEdit::Rename(old, new) = edit {
+                let old_key = Yaml::String(old.clone());
+                let new_key = Yaml::String(new.clone());
+                if frontmatter.contains_key(&old_key) && frontmatter.contains_key(&new_key) {
+                    report.record_conflict(format!("{} -> {}", old, new));
+                    continue;
+                }
+            }
+            if edit.apply(frontmatter) {
+                report.record(describe(edit));
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct MigrationReport {
+    /// Number of files each migration step changed
+    pub steps: BTreeMap<String, usize>,
+    /// Renames skipped because the note already had the new field
+    pub conflicts: BTreeMap<String, usize>,
+}
+
+impl MigrationReport {
+    fn record(&mut self, step: String) {
+        *self.steps.entry(step).or_insert(0) += 1;
+    }
+
+    fn record_conflict(&mut self, rename: String) {
+        *self.conflicts.entry(rename).or_insert(0) += 1;
+    }
+
+    pub fn print(&self) {
+        println!("Migration report:");
+        if self.steps.is_empty() {
+            println!("  No matching fields or values found");
+        }
+        for (step, count) in &self.steps {
+            println!("  {:<50} {:>6} files", step, count);
+        }
+        if !self.conflicts.is_empty() {
+            println!("Skipped renames (new field already present):");
+            for (rename, count) in &self.conflicts {
+                println!("  {:<50} {:>6} files", rename, count);
+            }
+        }
+    }
+}
+
+fn describe(edit: &FrontmatterEdit) -> String {
+    match edit {
+        FrontmatterEdit::Rename(old, new) => format!("rename {} -> {}", old, new),
+        FrontmatterEdit::Replace(field, from, to) => format!(
+            "replace {}: {} -> {}",
+            field,
+            crate::yaml_compat::yaml_to_string(from),
+            crate::yaml_compat::yaml_to_string(to)
+        ),
+        FrontmatterEdit::Remove(field) => format!("delete {}", field),
+        other => format!("{:?}", other),
+    }
+}
+
+fn key_string(yaml: &Yaml) -> Result<String> {
+    match yaml {
+        Yaml::String(s) => Ok(s.clone()),
+        Yaml::Integer(n) => Ok(n.to_string()),
+        other => Err(anyhow!("Expected a field name, got {:?}", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontmatter::Fences;
+    use crate::writer::FrontmatterDocument;
+
+    const MAP: &str = r#"
+rename:
+  Status: status
+  proj: project
+replace:
+  status:
+    wip: in-progress
+delete:
+  - legacy_id
+"#;
+
+    #[test]
+    fn test_migration_applies_all_steps() {
+        let migration = Migration::from_yaml(MAP).unwrap();
+        let mut doc = FrontmatterDocument::parse(
+            "---\nStatus: wip\nproj: Alpha\nlegacy_id: 7\n---\nBody\n",
+            Fences::Strict,
+        )
+        .unwrap();
+        let mut report = MigrationReport::default();
+        migration.apply(&mut doc.frontmatter, &mut report);
+
+        assert_eq!(
+            doc.frontmatter_text().unwrap(),
+            "status: in-progress\nproject: Alpha\n"
+        );
+        assert_eq!(report.steps.len(), 4);
+        assert_eq!(report.steps.get("delete legacy_id"), Some(&1));
+    }
+
+    #[test]
+    fn test_sections_run_in_fixed_order() {
+        let migration = Migration::from_yaml(
+            "delete:\n  - status\nreplace:\n  state:\n    wip: in-progress\nrename:\n  state: status\n",
+        )
+        .unwrap();
+        let mut doc =
+            FrontmatterDocument::parse("---\nstate: wip\n---\nBody\n", Fences::Strict).unwrap();
+        let mut report = MigrationReport::default();
+        migration.apply(&mut doc.frontmatter, &mut report);
+
+        // Renamed first, so the replacement on the old name finds nothing and the
+        // renamed field is deleted
+        assert_eq!(doc.frontmatter_text().unwrap(), "");
+    }
+
+    #[test]
+    fn test_added_renames_run_before_replacements() {
+        let mut migration =
+            Migration::from_yaml("replace:\n  status:\n    wip: in-progress\n").unwrap();
+        migration.add_rename("Status".to_string(), "status".to_string());
+        let mut doc =
+            FrontmatterDocument::parse("---\nStatus: wip\n---\nBody\n", Fences::Strict).unwrap();
+        let mut report = MigrationReport::default();
+        migration.apply(&mut doc.frontmatter, &mut report);
+
+        assert_eq!(doc.frontmatter_text().unwrap(), "status: in-progress\n");
+    }
+
+    #[test]
+    fn test_rename_conflict_is_reported() {
+        let migration = Migration::from_yaml("rename:\n  Status: status\n").unwrap();
+        let mut doc =
+            FrontmatterDocument::parse("---\nStatus: a\nstatus: b\n---\n", Fences::Strict).unwrap();
+        let mut report = MigrationReport::default();
+        migration.apply(&mut doc.frontmatter, &mut report);
+
+        assert!(!doc.is_modified());
+        assert_eq!(report.conflicts.get("Status -> status"), Some(&1));
+    }
+
+    #[test]
+    fn test_invalid_section() {
+        assert!(Migration::from_yaml("rename: [a, b]\n").is_err());
+        assert!(Migration::from_yaml("move:\n  a: b\n").is_err());
+    }
+}
@@ -0,0 +1,301 @@
+use crate::frontmatter::Note;
+use anyhow::{anyhow, Result};
+use chrono::{NaiveDate, NaiveDateTime};
+use yaml_rust2::yaml::Hash;
+use yaml_rust2::Yaml;
+
+/// Formats of dates without a time, all written as `YYYY-MM-DD`. US style `06/01/2024` is
+/// left out as it can't be told apart from `01/06/2024`.
+const DATE_FORMATS: &[&str] = &[
+    "%Y-%m-%d",
+    "%Y/%m/%d",
+    "%Y.%m.%d",
+    "%d.%m.%Y",
+    "%Y%m%d",
+    "%B %d, %Y",
+    "%b %d, %Y",
+    "%d %B %Y",
+    "%d %b %Y",
+];
+
+/// Formats of dates with a time and the ISO format each is written in, keeping the
+/// seconds only where they were given
+const DATETIME_FORMATS: &[(&str, &str)] = &[
+    ("%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M:%S"),
+    ("%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S"),
+    ("%Y-%m-%dT%H:%M", "%Y-%m-%dT%H:%M"),
+    ("%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M"),
+    ("%Y/%m/%d %H:%M", "%Y-%m-%dT%H:%M"),
+    ("%d.%m.%Y %H:%M", "%Y-%m-%dT%H:%M"),
+];
+
+/// A way the values of a field are brought into one shape
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Normalization {
+    /// Dates in other formats as ISO 8601, like `01.06.2024` to `2024-06-01`
+    Date,
+    /// `yes`, `no`, `on` and `off` and other spellings like `TRUE` as booleans
+    Boolean,
+    /// Single values as a list of one, like `tags: work` to `tags: [work]`
+    List,
+}
+
+impl Normalization {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "date" | "dates" => Ok(Self::Date),
+            "bool" | "boolean" | "booleans" => Ok(Self::Boolean),
+            "list" | "lists" => Ok(Self::List),
+            _ => Err(anyhow!(
+                "Unknown normalization: '{}'. Use date, bool or list",
+                s
+            )),
+        }
+    }
+}
+
+/// A date or date and time in ISO 8601, `None` when the text isn't a date
+pub fn normalize_date(text: &str) -> Option<String> {
+    let text = text.trim();
+    DATE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(text, format).ok())
+        .map(|date| date.format("%Y-%m-%d").to_string())
+        .or_else(|| {
+            DATETIME_FORMATS.iter().find_map(|(input, output)| {
+                NaiveDateTime::parse_from_str(text, input)
+                    .ok()
+                    .map(|datetime| datetime.format(output).to_string())
+            })
+        })
+}
+
+/// The boolean a text spells, `None` for anything else
+pub fn normalize_boolean(text: &str) -> Option<bool> {
+    match text.trim().to_lowercase().as_str() {
+        "true" | "yes" | "on" => Some(true),
+        "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+/// The normalizations to apply to one field
+#[derive(Debug, Clone)]
+pub struct Normalizer {
+    pub field: String,
+    normalizations: Vec<Normalization>,
+}
+
+impl Normalizer {
+    pub fn new(field: String, normalizations: Vec<Normalization>) -> Self {
+        Self {
+            field,
+            normalizations,
+        }
+    }
+
+    /// Pick the normalizations from the values in the notes: dates and booleans always,
+    /// lists when most of the notes with the field hold a list
+    pub fn infer(field: String, notes: &[&Note]) -> Self {
+        let (lists, others) = notes
+            .iter()
+            .filter_map(|note| note.frontmatter.get(&field))
+            .filter(|value| !matches!(value, Yaml::Null))
+            .fold((0, 0), |(lists, others), value| match value {
+                Yaml::Array(_) => (lists + 1, others),
+                _ => (lists, others + 1),
+            });
+        let mut normalizations = vec![Normalization::Date, Normalization::Boolean];
+        if lists > others {
+            normalizations.push(Normalization::List);
+        }
+        Self::new(field, normalizations)
+    }
+
+    fn has(&self, normalization: Normalization) -> bool {
+        self.normalizations.contains(&normalization)
+    }
+
+    /// The normalized value, `None` when it is already normal
+    pub fn normalize(&self, value: &Yaml) -> Option<Yaml> {
+        let normalized = match value {
+            Yaml::Array(items) => Yaml::Array(
+                items
+                    .iter()
+                    .map(|item| self.normalize_scalar(item).unwrap_or_else(|| item.clone()))
+                    .collect(),
+            ),
+            Yaml::Null | Yaml::Hash(_) => return None,
+            scalar => {
+                let scalar = self
+                    .normalize_scalar(scalar)
+                    .unwrap_or_else(|| scalar.clone());
+                if self.has(Normalization::List) {
+                    Yaml::Array(vec![scalar])
+                } else {
+                    scalar
+                }
+            }
+        };
+        (normalized != *value).then_some(normalized)
+    }
+
+    fn normalize_scalar(&self, value: &Yaml) -> Option<Yaml> {
+        let text = match value {
+            Yaml::String(text) => text.clone(),
+            // Compact dates like 20240601 are read as numbers
+            Yaml::Integer(n) if (10_000_000..100_000_000).contains(n) => n.to_string(),
+            _ => return None,
+        };
+        if self.has(Normalization::Date) {
+            if let Some(date) = normalize_date(&text) {
+                return Some(Yaml::String(date));
+            }
+        }
+        if self.has(Normalization::Boolean) && matches!(value, Yaml::String(_)) {
+            if let Some(boolean) = normalize_boolean(&text) {
+                return Some(Yaml::Boolean(boolean));
+            }
+        }
+        None
+    }
+
+    /// Normalize the field in a frontmatter mapping, returning whether it changed
+    pub fn apply(&self, frontmatter: &mut Hash) -> bool {
+        let Some(value) = frontmatter.get_mut(&Yaml::String(self.field.clone())) else {
+            return false;
+        };
+        match self.normalize(value) {
+            Some(normalized) => {
+                *value = normalized;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// A value that normalizing would change
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NormalizedValue {
+    pub path: String,
+    pub field: String,
+    pub before: Yaml,
+    pub after: Yaml,
+}
+
+/// Every value the normalizers would change, note by note
+pub fn preview(notes: &[&Note], normalizers: &[Normalizer]) -> Vec<NormalizedValue> {
+    notes
+        .iter()
+        .flat_map(|note| {
+            normalizers.iter().filter_map(|normalizer| {
+                let before = note.frontmatter.get(&normalizer.field)?;
+                Some(NormalizedValue {
+                    path: note.path.clone(),
+                    field: normalizer.field.clone(),
+                    before: before.clone(),
+                    after: normalizer.normalize(before)?,
+                })
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn string(s: &str) -> Yaml {
+        Yaml::String(s.to_string())
+    }
+
+    #[test]
+    fn test_normalize_date() {
+        assert_eq!(normalize_date("2024-06-01").as_deref(), Some("2024-06-01"));
+        assert_eq!(normalize_date("2024-6-1").as_deref(), Some("2024-06-01"));
+        assert_eq!(normalize_date("2024/06/01").as_deref(), Some("2024-06-01"));
+        assert_eq!(normalize_date("01.06.2024").as_deref(), Some("2024-06-01"));
+        assert_eq!(
+            normalize_date("June 1, 2024").as_deref(),
+            Some("2024-06-01")
+        );
+        assert_eq!(normalize_date("1 Jun 2024").as_deref(), Some("2024-06-01"));
+        assert_eq!(
+            normalize_date("2024-06-01 10:30").as_deref(),
+            Some("2024-06-01T10:30")
+        );
+        assert_eq!(normalize_date("06/01/2024"), None);
+        assert_eq!(normalize_date("soon"), None);
+    }
+
+    #[test]
+    fn test_normalizer() {
+        let all = Normalizer::new(
+            "x".to_string(),
+            vec![
+                Normalization::Date,
+                Normalization::Boolean,
+                Normalization::List,
+            ],
+        );
+        assert_eq!(
+            all.normalize(&string("01.06.2024")),
+            Some(Yaml::Array(vec![string("2024-06-01")]))
+        );
+        assert_eq!(
+            all.normalize(&Yaml::Array(vec![string("2024/06/01"), string("yes")])),
+            Some(Yaml::Array(vec![string("2024-06-01"), Yaml::Boolean(true)]))
+        );
+        assert_eq!(all.normalize(&Yaml::Array(vec![string("done")])), None);
+        assert_eq!(all.normalize(&Yaml::Null), None);
+
+        let dates = Normalizer::new("x".to_string(), vec![Normalization::Date]);
+        assert_eq!(
+            dates.normalize(&Yaml::Integer(20_240_601)),
+            Some(string("2024-06-01"))
+        );
+        assert_eq!(dates.normalize(&Yaml::Integer(3)), None);
+        assert_eq!(dates.normalize(&string("yes")), None);
+        assert_eq!(dates.normalize(&string("2024-06-01")), None);
+
+        let mut frontmatter = Hash::new();
+        frontmatter.insert(string("x"), string("2024/06/01"));
+        assert!(dates.apply(&mut frontmatter));
+        assert_eq!(frontmatter[&string("x")], string("2024-06-01"));
+        assert!(!dates.apply(&mut frontmatter));
+    }
+
+    #[test]
+    fn test_infer_and_preview() {
+        let note = |path: &str, tags: Yaml| {
+            let mut fm = HashMap::new();
+            fm.insert("tags".to_string(), tags);
+            fm.insert("done".to_string(), string("no"));
+            Note::new(path.to_string(), fm)
+        };
+        let notes = [
+            note("a.md", Yaml::Array(vec![string("work")])),
+            note("b.md", Yaml::Array(vec![string("home")])),
+            note("c.md", string("work")),
+        ];
+        let notes: Vec<&Note> = notes.iter().collect();
+
+        let normalizers = [
+            Normalizer::infer("tags".to_string(), &notes),
+            Normalizer::infer("done".to_string(), &notes),
+        ];
+        let changes = preview(&notes, &normalizers);
+        assert_eq!(changes.len(), 4);
+        assert_eq!(changes[3].path, "c.md");
+        assert_eq!(changes[3].field, "done");
+        assert_eq!(
+            changes
+                .iter()
+                .find(|change| change.field == "tags")
+                .map(|change| &change.after),
+            Some(&Yaml::Array(vec![string("work")]))
+        );
+    }
+}
@@ -1,29 +1,513 @@
+use crate::batch::Query;
+use crate::columns::Column;
+use crate::exec::shell_quote;
 use crate::filter::{
     collect_all_fields, collect_field_values, collect_field_values_case_insensitive,
-    get_field_statistics,
+    count_notes_in_namespace, field_tree, get_field_statistics, FieldStats, FieldTreeRow,
+    FolderValueCounts, ValueSource,
 };
-use crate::frontmatter::Note;
-use crate::yaml_compat::yaml_to_json_value;
-use anyhow::Result;
+use crate::filter::{FilterCriteria, FilterOp, ResultDiff};
+use crate::frontmatter::{Note, REDACTED};
+use crate::links::BrokenLink;
+use crate::lint::LintIssue;
+use crate::normalize::NormalizedValue;
+use crate::stats::{
+    sparkline, HealthReport, LenientReport, MetricSummary, MetricsReport, Snapshot,
+};
+use crate::titles::NameMatch;
+use crate::yaml_compat::{trim_block, yaml_to_json_value, yaml_to_string, yaml_type_name};
+use anyhow::{anyhow, Result};
 use colored::*;
+use regex::RegexBuilder;
 use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+use yaml_rust2::Yaml;
+
+/// Default maximum width of the path column in note tables
+const DEFAULT_PATH_WIDTH: usize = 50;
+
+/// Default maximum width of the title column in note tables
+const DEFAULT_TITLE_WIDTH: usize = 30;
+
+/// Default maximum width of values that span several lines, once folded into one
+const DEFAULT_MULTILINE_WIDTH: usize = 60;
+
+/// How cells wider than their column are shown in note tables
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TableLayout {
+    /// Maximum column width, overriding the per-column defaults
+    pub max_col_width: Option<usize>,
+    /// Show cells in full, making columns as wide as their longest cell
+    pub no_truncate: bool,
+    /// Wrap long cells onto continuation lines instead of cutting them
+    pub wrap: bool,
+}
+
+impl TableLayout {
+    fn column_width(&self, content_width: usize, default_max: usize) -> usize {
+        if self.no_truncate {
+            content_width
+        } else {
+            // Leave room for at least one character next to the "..."
+            content_width.min(self.max_col_width.unwrap_or(default_max).max(4))
+        }
+    }
+
+    /// A value on one line: the lines of a multi-line value are joined with `↵` and the
+    /// result is cut like a cell, unless cells are shown in full
+    fn single_line(&self, value: &str) -> String {
+        let value = trim_block(value);
+        if !value.contains('\n') {
+            return value.to_string();
+        }
+        let folded = value
+            .lines()
+            .map(str::trim_end)
+            .collect::<Vec<_>>()
+            .join(" ↵ ");
+        let width = self.column_width(folded.width(), DEFAULT_MULTILINE_WIDTH);
+        let cut = Self {
+            wrap: false,
+            ..*self
+        };
+        cut.fit(&folded, width, false).swap_remove(0)
+    }
+
+    /// Split a cell into the lines shown in a column of `width` terminal columns
+    fn fit(&self, text: &str, width: usize, keep_end: bool) -> Vec<String> {
+        let chars: Vec<char> = text.chars().collect();
+        if text.width() <= width || width == 0 {
+            return vec![text.to_string()];
+        }
+
+        if self.wrap {
+            wrap_chars(&chars, width)
+        } else if keep_end {
+            let reversed: Vec<char> = chars.iter().rev().copied().collect();
+            let tail: String = chars[chars.len() - fitting_chars(&reversed, width - 3)..]
+                .iter()
+                .collect();
+            vec![format!("...{}", tail)]
+        } else {
+            let head: String = chars[..fitting_chars(&chars, width - 3)].iter().collect();
+            vec![format!("{}...", head)]
+        }
+    }
+}
+
+/// Spaces that pad `text` to `width` terminal columns. Tables pad with this instead of
+/// `{:<width$}`, which counts characters, so emoji and other wide characters line up.
+fn padding(text: &str, width: usize) -> String {
+    " ".repeat(width.saturating_sub(text.width()))
+}
+
+/// How many of the leading characters fit into `width` terminal columns
+fn fitting_chars(chars: &[char], width: usize) -> usize {
+    let mut used = 0;
+    chars
+        .iter()
+        .take_while(|c| {
+            used += c.width().unwrap_or(0);
+            used <= width
+        })
+        .count()
+}
+
+/// Wrap text to `width` terminal columns, breaking after spaces or slashes where possible
+fn wrap_chars(chars: &[char], width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut rest = chars;
+    while rest.len() > 1 && rest.iter().map(|c| c.width().unwrap_or(0)).sum::<usize>() > width {
+        // At least one character per line, even if it is wider than the column
+        let fits = fitting_chars(rest, width).max(1);
+        let split = rest[..fits]
+            .iter()
+            .rposition(|c| *c == ' ' || *c == '/')
+            .map_or(fits, |i| i + 1);
+        lines.push(
+            rest[..split]
+                .iter()
+                .collect::<String>()
+                .trim_end()
+                .to_string(),
+        );
+        rest = &rest[split..];
+    }
+    lines.push(rest.iter().collect());
+    lines
+}
+
+/// How paths and values are quoted and escaped in the line-oriented formats: `paths`,
+/// `template` and `csv`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Quoting {
+    /// Quote paths for `sh` when they hold spaces or other special characters
+    pub shell_quote: bool,
+    /// Quote every CSV cell instead of only the ones that need it
+    pub quote_all: bool,
+    /// Write line breaks, tabs and backslashes as `\n`, `\r`, `\t` and `\\`, so every
+    /// note or value stays on one line
+    pub escape_newlines: bool,
+}
+
+impl Quoting {
+    /// A value as it is printed
+    pub fn value(self, value: &str) -> String {
+        if self.escape_newlines {
+            escape_newlines(value)
+        } else {
+            value.to_string()
+        }
+    }
+
+    /// A path as it is printed. With line breaks escaped, shell-quoted paths holding
+    /// them use `$'...'` so the shell turns the escapes back into line breaks.
+    pub fn path(self, path: &str) -> String {
+        if !self.shell_quote {
+            return self.value(path);
+        }
+        if self.escape_newlines && path.contains(['\n', '\r', '\t']) {
+            format!("$'{}'", escape_newlines(path).replace('\'', "\\'"))
+        } else {
+            shell_quote(path)
+        }
+    }
+
+    /// A CSV cell, quoted when it holds a comma, quote or line break or when all cells are
+    fn csv_cell(self, value: &str) -> String {
+        let value = self.value(value);
+        if self.quote_all || value.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value
+        }
+    }
+
+    /// Print one CSV line from its cells
+    fn print_csv_row<S: AsRef<str>>(self, cells: &[S]) {
+        let cells: Vec<String> = cells
+            .iter()
+            .map(|cell| self.csv_cell(cell.as_ref()))
+            .collect();
+        println!("{}", cells.join(","));
+    }
+}
+
+fn escape_newlines(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Print how many notes matched, only the number when silent
+pub fn display_match_count(count: usize, silent: bool) {
+    if silent {
+        println!("{}", count);
+    } else {
+        println!("{} {}", count.to_string().bold(), "matching notes".blue());
+    }
+}
+
+/// List how many notes each query of a batch matched and where they were written
+pub fn display_batch_results(results: &[(&Query, usize)], silent: bool) {
+    let rows: Vec<(&str, &Path, usize)> = results
+        .iter()
+        .map(|(query, count)| (query.name.as_str(), query.output.as_path(), *count))
+        .collect();
+    display_written_files(&format!("Ran {} queries:", results.len()), &rows, silent);
+}
+
+/// Show the file each group of `--split-output` was written to
+pub fn display_split_results(results: &[(String, PathBuf, usize)], silent: bool) {
+    let rows: Vec<(&str, &Path, usize)> = results
+        .iter()
+        .map(|(group, path, count)| (group.as_str(), path.as_path(), *count))
+        .collect();
+    display_written_files(&format!("Wrote {} groups:", results.len()), &rows, silent);
+}
+
+/// Names with the number of notes written for them and the file they went to
+fn display_written_files(heading: &str, rows: &[(&str, &Path, usize)], silent: bool) {
+    if !silent {
+        println!("{}", heading.bold().blue());
+        println!();
+    }
+    let name_width = rows
+        .iter()
+        .map(|(name, _, _)| name.width())
+        .max()
+        .unwrap_or(0)
+        .max(10);
+    for (name, path, count) in rows {
+        println!(
+            "{}{} {:>8}  {}",
+            name.green(),
+            padding(name, name_width),
+            count,
+            path.display().to_string().cyan(),
+        );
+    }
+}
 
-pub fn display_filtered_results(notes: &[&Note], format: &str, silent: bool) -> Result<()> {
+pub fn display_filtered_results(
+    notes: &[&Note],
+    format: &str,
+    layout: &TableLayout,
+    quoting: Quoting,
+    silent: bool,
+) -> Result<()> {
     match format.to_lowercase().as_str() {
-        "table" => display_table_format(notes, silent),
-        "paths" => display_paths_format(notes, silent),
+        "table" => display_table(notes, None, layout, silent),
+        "paths" => display_paths_format(notes, quoting, silent),
         "json" => display_json_format(notes, silent),
+        "ndjson" => display_ndjson_format(notes),
+        "markdown" => write_notes(&mut std::io::stdout().lock(), notes, "markdown"),
         _ => {
             eprintln!("Unknown format: {}. Using table format.", format);
-            display_table_format(notes, silent)
+            display_table(notes, None, layout, silent)
+        }
+    }
+}
+
+/// Like `display_filtered_results`, but the table shows the values that matched each
+/// filter with the matching part highlighted
+pub fn display_filtered_matches(
+    notes: &[&Note],
+    criteria: &FilterCriteria,
+    format: &str,
+    layout: &TableLayout,
+    quoting: Quoting,
+    silent: bool,
+) -> Result<()> {
+    if criteria.filters().is_empty() || !matches!(format.to_lowercase().as_str(), "table") {
+        return display_filtered_results(notes, format, layout, quoting, silent);
+    }
+    display_table(notes, Some(criteria), layout, silent)
+}
+
+/// Show the notes as rows of the given columns in a table, `csv`, `json` or `ndjson`.
+///
+/// Missing values are empty cells, or `null` in JSON. Days in `age` and `due_in` columns are
+/// counted from today.
+pub fn display_columns(
+    notes: &[&Note],
+    columns: &[Column],
+    format: &str,
+    layout: &TableLayout,
+    quoting: Quoting,
+    case_sensitive: bool,
+    silent: bool,
+) -> Result<()> {
+    let today = chrono::Local::now().date_naive();
+    let rows: Vec<Vec<Option<Yaml>>> = notes
+        .iter()
+        .map(|note| {
+            columns
+                .iter()
+                .map(|column| column.value(note, today, case_sensitive))
+                .collect()
+        })
+        .collect();
+    let names: Vec<String> = columns.iter().map(Column::name).collect();
+
+    match format.to_lowercase().as_str() {
+        "json" | "ndjson" => {
+            let objects: Vec<ColumnRow> = rows
+                .iter()
+                .map(|row| ColumnRow {
+                    names: &names,
+                    values: row,
+                })
+                .collect();
+            if format.eq_ignore_ascii_case("json") {
+                println!("{}", serde_json::to_string_pretty(&objects)?);
+            } else {
+                for object in objects {
+                    println!("{}", serde_json::to_string(&object)?);
+                }
+            }
+            return Ok(());
+        }
+        "csv" => {
+            quoting.print_csv_row(&names);
+            for row in &rows {
+                let cells: Vec<String> = row
+                    .iter()
+                    .map(|value| value.as_ref().map(yaml_to_string).unwrap_or_default())
+                    .collect();
+                quoting.print_csv_row(&cells);
+            }
+            return Ok(());
+        }
+        "table" => {}
+        _ => eprintln!("Unknown format: {}. Using table format.", format),
+    }
+
+    if notes.is_empty() {
+        if !silent {
+            println!("{}", "No notes match the specified criteria.".yellow());
+        }
+        return Ok(());
+    }
+    if !silent {
+        println!(
+            "{}",
+            format!("Found {} matching notes:", notes.len())
+                .bold()
+                .blue()
+        );
+        println!();
+    }
+
+    let cells: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|value| {
+                    value.as_ref().map_or_else(
+                        || "-".to_string(),
+                        |value| layout.single_line(&yaml_to_string(value)),
+                    )
+                })
+                .collect()
+        })
+        .collect();
+    let widths: Vec<usize> = columns
+        .iter()
+        .enumerate()
+        .map(|(index, column)| {
+            let content_width = cells
+                .iter()
+                .map(|row| row[index].width())
+                .chain(std::iter::once(names[index].width()))
+                .max()
+                .unwrap_or(0);
+            let default_width = match column {
+                Column::Path => DEFAULT_PATH_WIDTH,
+                _ => DEFAULT_TITLE_WIDTH,
+            };
+            layout.column_width(content_width, default_width)
+        })
+        .collect();
+
+    let header: Vec<String> = names
+        .iter()
+        .zip(&widths)
+        .map(|(name, width)| format!("{}{}", name.bold(), padding(name, *width)))
+        .collect();
+    println!("{}", header.join(" ").trim_end());
+    println!(
+        "{}",
+        "-".repeat(widths.iter().sum::<usize>() + widths.len().saturating_sub(1))
+    );
+    for row in &cells {
+        // Paths keep their end (the file name), everything else its beginning
+        let fitted: Vec<Vec<String>> = row
+            .iter()
+            .zip(columns.iter().zip(&widths))
+            .map(|(cell, (column, width))| layout.fit(cell, *width, *column == Column::Path))
+            .collect();
+        for line in 0..fitted.iter().map(Vec::len).max().unwrap_or(1) {
+            let parts: Vec<String> = fitted
+                .iter()
+                .zip(columns.iter().zip(&widths))
+                .map(|(lines, (column, width))| {
+                    let text = lines.get(line).map_or("", String::as_str);
+                    let colored = match column {
+                        Column::Path => text.cyan(),
+                        Column::Title => text.normal(),
+                        Column::Age(_) | Column::DueIn(_) => text.yellow(),
+                        Column::Field(_) => text.green(),
+                    };
+                    format!("{}{}", colored, padding(text, *width))
+                })
+                .collect();
+            println!("{}", parts.join(" ").trim_end());
+        }
+    }
+    Ok(())
+}
+
+/// A note in `--columns` JSON output, an object with its keys in the order of the columns
+struct ColumnRow<'a> {
+    names: &'a [String],
+    values: &'a [Option<Yaml>],
+}
+
+impl Serialize for ColumnRow<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(Some(self.names.len()))?;
+        for (name, value) in self.names.iter().zip(self.values) {
+            let value = value
+                .as_ref()
+                .map_or(serde_json::Value::Null, yaml_to_json_value);
+            map.serialize_entry(name, &value)?;
         }
+        map.end()
     }
 }
 
-pub fn display_all_fields(notes: &[Note], silent: bool) -> Result<()> {
+/// List all fields with their note and value counts.
+///
+/// With a namespace separator, fields like `review.date` are shown as a tree below their
+/// namespace in the table, `json` and `csv` always list the full field names.
+pub fn display_all_fields(
+    notes: &[Note],
+    separator: Option<&str>,
+    format: &str,
+    quoting: Quoting,
+    silent: bool,
+) -> Result<()> {
     let fields = collect_all_fields(notes);
     let stats = get_field_statistics(notes);
 
+    match format.to_lowercase().as_str() {
+        "json" => {
+            let serializable: Vec<_> = fields
+                .iter()
+                .map(|field| {
+                    let field_stats = &stats[field];
+                    serde_json::json!({
+                        "field": field,
+                        "notes": field_stats.total_count,
+                        "values": field_stats.unique_values.len(),
+                        "type": field_stats.inferred_type(),
+                    })
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&serializable)?);
+            return Ok(());
+        }
+        "csv" => {
+            quoting.print_csv_row(&["field", "notes", "values", "type"]);
+            for field in &fields {
+                let field_stats = &stats[field];
+                quoting.print_csv_row(&[
+                    field.clone(),
+                    field_stats.total_count.to_string(),
+                    field_stats.unique_values.len().to_string(),
+                    field_stats.inferred_type().to_string(),
+                ]);
+            }
+            return Ok(());
+        }
+        "table" => {}
+        _ => eprintln!("Unknown format: {}. Using table format.", format),
+    }
+
     if fields.is_empty() {
         if !silent {
             println!("{}", "No frontmatter fields found in any notes.".yellow());
@@ -36,29 +520,70 @@ pub fn display_all_fields(notes: &[Note], silent: bool) -> Result<()> {
         println!();
     }
 
+    let rows = match separator {
+        Some(separator) => field_tree(&fields, separator),
+        None => fields
+            .iter()
+            .map(|field| FieldTreeRow {
+                depth: 0,
+                label: field.clone(),
+                name: field.clone(),
+                is_namespace: false,
+            })
+            .collect(),
+    };
+    let labels: Vec<String> = rows
+        .iter()
+        .map(|row| {
+            let indent = "  ".repeat(row.depth);
+            match (row.is_namespace, separator) {
+                (true, Some(separator)) => format!("{}{}{}*", indent, row.label, separator),
+                _ => format!("{}{}", indent, row.label),
+            }
+        })
+        .collect();
+
     // Calculate column widths
-    let max_field_width = fields.iter().map(|f| f.len()).max().unwrap_or(0);
+    let max_field_width = labels.iter().map(|l| l.width()).max().unwrap_or(0);
     let field_width = std::cmp::max(max_field_width, 10);
 
     // Header
     println!(
-        "{:<width$} {:>8} {:>8}",
+        "{}{} {:>8} {:>8}  {}",
         "Field".bold(),
+        padding("Field", field_width),
         "Notes".bold(),
         "Values".bold(),
-        width = field_width
+        "Type".bold(),
     );
-    println!("{}", "-".repeat(field_width + 18));
+    println!("{}", "-".repeat(field_width + 26));
 
     // Field data
-    for field in &fields {
-        let field_stats = stats.get(field).unwrap();
+    for (row, label) in rows.iter().zip(&labels) {
+        if row.is_namespace {
+            let separator = separator.unwrap_or_default();
+            println!(
+                "{}{} {:>8} {:>8}",
+                label.cyan().bold(),
+                padding(label, field_width),
+                count_notes_in_namespace(notes, &row.name, separator),
+                "",
+            );
+            continue;
+        }
+        let field_stats = stats.get(&row.name).unwrap();
+        let kind = field_stats.inferred_type();
         println!(
-            "{:<width$} {:>8} {:>8}",
-            field.green(),
+            "{}{} {:>8} {:>8}  {}",
+            label.green(),
+            padding(label, field_width),
             field_stats.total_count,
             field_stats.unique_values.len(),
-            width = field_width
+            if kind == "mixed" {
+                kind.yellow()
+            } else {
+                kind.normal()
+            },
         );
     }
 
@@ -79,6 +604,100 @@ pub fn display_field_values_with_options(
     field: &str,
     case_sensitive: bool,
     silent: bool,
+) -> Result<()> {
+    display_field_value_table(
+        notes,
+        field,
+        &get_field_statistics(notes),
+        case_sensitive,
+        silent,
+    )
+}
+
+/// The values of a field with their counts, most used first
+fn field_value_counts<'a>(
+    notes: &[Note],
+    field: &str,
+    stats: &'a HashMap<String, FieldStats>,
+    case_sensitive: bool,
+) -> Vec<(&'a str, usize)> {
+    let stats_key = if case_sensitive {
+        field.to_string()
+    } else {
+        collect_field_values_case_insensitive(notes, field).1
+    };
+    let mut value_counts: Vec<(&str, usize)> = stats
+        .get(&stats_key)
+        .map(|field_stats| {
+            field_stats
+                .value_counts
+                .iter()
+                .map(|(value, count)| (value.as_str(), *count))
+                .collect()
+        })
+        .unwrap_or_default();
+    value_counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    value_counts
+}
+
+/// Show the value distributions of several fields one after another, collecting the
+/// statistics of the notes only once
+pub fn display_values_of_fields(
+    notes: &[Note],
+    fields: &[String],
+    format: &str,
+    quoting: Quoting,
+    case_sensitive: bool,
+    silent: bool,
+) -> Result<()> {
+    let stats = get_field_statistics(notes);
+    match format.to_lowercase().as_str() {
+        "json" => {
+            let serializable: Vec<_> = fields
+                .iter()
+                .map(|field| {
+                    let values: Vec<_> = field_value_counts(notes, field, &stats, case_sensitive)
+                        .into_iter()
+                        .map(|(value, count)| serde_json::json!({ "value": value, "count": count }))
+                        .collect();
+                    serde_json::json!({ "field": field, "values": values })
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&serializable)?);
+            return Ok(());
+        }
+        "csv" => {
+            quoting.print_csv_row(&["field", "value", "count"]);
+            for field in fields {
+                for (value, count) in field_value_counts(notes, field, &stats, case_sensitive) {
+                    quoting.print_csv_row(&[field.as_str(), value, &count.to_string()]);
+                }
+            }
+            return Ok(());
+        }
+        "table" => {}
+        _ => eprintln!("Unknown format: {}. Using table format.", format),
+    }
+
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        // Without the headings, the tables still need to say which field they are for
+        if silent && fields.len() > 1 {
+            println!("{}", field.bold());
+        }
+        display_field_value_table(notes, field, &stats, case_sensitive, silent)?;
+    }
+    Ok(())
+}
+
+fn display_field_value_table(
+    notes: &[Note],
+    field: &str,
+    stats: &HashMap<String, FieldStats>,
+    case_sensitive: bool,
+    silent: bool,
 ) -> Result<()> {
     let (values, actual_field_name) = if case_sensitive {
         (collect_field_values(notes, field), field.to_string())
@@ -86,8 +705,6 @@ pub fn display_field_values_with_options(
         collect_field_values_case_insensitive(notes, field)
     };
 
-    let stats = get_field_statistics(notes);
-
     if values.is_empty() {
         if !silent {
             if case_sensitive {
@@ -130,17 +747,22 @@ pub fn display_field_values_with_options(
     } else {
         &actual_field_name
     };
+    let layout = TableLayout::default();
     if let Some(field_stats) = stats.get(stats_key) {
         // Calculate column width
-        let max_value_width = values.iter().map(|v| v.len()).max().unwrap_or(0);
+        let max_value_width = values
+            .iter()
+            .map(|v| layout.single_line(v).width())
+            .max()
+            .unwrap_or(0);
         let value_width = std::cmp::max(max_value_width, 10);
 
         // Header
         println!(
-            "{:<width$} {:>8}",
+            "{}{} {:>8}",
             "Value".bold(),
+            padding("Value", value_width),
             "Count".bold(),
-            width = value_width
         );
         println!("{}", "-".repeat(value_width + 10));
 
@@ -149,11 +771,12 @@ pub fn display_field_values_with_options(
         value_counts.sort_by(|a, b| b.1.cmp(a.1));
 
         for (value, count) in value_counts {
+            let value = layout.single_line(value);
             println!(
-                "{:<width$} {:>8}",
+                "{}{} {:>8}",
                 value.green(),
+                padding(&value, value_width),
                 count,
-                width = value_width
             );
         }
 
@@ -168,6 +791,7 @@ pub fn display_field_values_with_options(
     } else {
         // Fallback if stats are not available
         for value in &values {
+            let value = layout.single_line(value);
             if silent {
                 println!("{}", value);
             } else {
@@ -183,8 +807,30 @@ pub fn display_field_values_with_options(
     Ok(())
 }
 
-fn display_table_format(notes: &[&Note], silent: bool) -> Result<()> {
-    if notes.is_empty() {
+pub fn display_groups(
+    groups: &BTreeMap<Vec<String>, Vec<&Note>>,
+    fields: &[String],
+    format: &str,
+    list: bool,
+    silent: bool,
+) -> Result<()> {
+    match format.to_lowercase().as_str() {
+        "json" => display_groups_json(groups, fields, list),
+        "table" => display_groups_table(groups, fields, list, silent),
+        _ => {
+            eprintln!("Unknown format: {}. Using table format.", format);
+            display_groups_table(groups, fields, list, silent)
+        }
+    }
+}
+
+fn display_groups_table(
+    groups: &BTreeMap<Vec<String>, Vec<&Note>>,
+    fields: &[String],
+    list: bool,
+    silent: bool,
+) -> Result<()> {
+    if groups.is_empty() {
         if !silent {
             println!("{}", "No notes match the specified criteria.".yellow());
         }
@@ -194,121 +840,1834 @@ fn display_table_format(notes: &[&Note], silent: bool) -> Result<()> {
     if !silent {
         println!(
             "{}",
-            format!("Found {} matching notes:", notes.len())
+            format!("Notes grouped by {}:", fields.join(", "))
                 .bold()
                 .blue()
         );
         println!();
     }
 
-    // Calculate column widths
-    let max_path_width = notes.iter().map(|n| n.path.len()).max().unwrap_or(0);
-    let max_title_width = notes
-        .iter()
-        .map(|n| n.title.as_ref().map(|t| t.len()).unwrap_or(0))
-        .max()
-        .unwrap_or(0);
+    if list {
+        for (key, notes) in groups {
+            println!(
+                "{} ({})",
+                format_group_key(fields, key).bold().green(),
+                notes.len()
+            );
+            for note in notes {
+                println!("  {}", note.path.cyan());
+            }
+        }
+        return Ok(());
+    }
 
-    let path_width = std::cmp::min(max_path_width, 50);
-    let title_width = std::cmp::min(max_title_width, 30);
+    // One column per grouped field plus the count
+    let widths: Vec<usize> = fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            groups
+                .keys()
+                .map(|key| key[i].width())
+                .chain(std::iter::once(field.width()))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
 
-    // Header
+    let header: Vec<String> = fields
+        .iter()
+        .zip(&widths)
+        .map(|(field, width)| format!("{}{}", field.bold(), padding(field, *width)))
+        .collect();
+    println!("{} {:>8}", header.join(" "), "Count".bold());
     println!(
-        "{:<path_width$} {:<title_width$} {}",
-        "Path".bold(),
-        "Title".bold(),
-        "Frontmatter".bold(),
-        path_width = path_width,
-        title_width = title_width
+        "{}",
+        "-".repeat(widths.iter().sum::<usize>() + widths.len() + 8)
     );
-    println!("{}", "-".repeat(path_width + title_width + 20));
-
-    // Note data
-    for note in notes {
-        let path = if note.path.len() > path_width {
-            format!("...{}", &note.path[note.path.len() - path_width + 3..])
-        } else {
-            note.path.clone()
-        };
-
-        let title = note
-            .title
-            .as_ref()
-            .map(|t| {
-                if t.len() > title_width {
-                    format!("{}...", &t[..title_width - 3])
-                } else {
-                    t.clone()
-                }
-            })
-            .unwrap_or_else(|| "-".to_string());
 
-        let frontmatter_summary = if note.frontmatter.is_empty() {
-            "-".to_string()
-        } else {
-            let keys: Vec<String> = note.frontmatter.keys().cloned().collect();
-            if keys.len() <= 3 {
-                keys.join(", ")
-            } else {
-                format!("{}, ... (+{})", keys[..3].join(", "), keys.len() - 3)
-            }
-        };
+    for (key, notes) in groups {
+        let columns: Vec<String> = key
+            .iter()
+            .zip(&widths)
+            .map(|(value, width)| format!("{}{}", value.green(), padding(value, *width)))
+            .collect();
+        println!("{} {:>8}", columns.join(" "), notes.len());
+    }
 
-        println!(
-            "{:<path_width$} {:<title_width$} {}",
-            path.cyan(),
-            title.green(),
-            frontmatter_summary.dimmed(),
-            path_width = path_width,
-            title_width = title_width
-        );
+    if !silent {
+        println!();
+        println!("Total: {} groups", groups.len());
     }
 
     Ok(())
 }
 
-fn display_paths_format(notes: &[&Note], silent: bool) -> Result<()> {
-    if notes.is_empty() {
-        if !silent {
-            println!("{}", "No notes match the specified criteria.".yellow());
-        }
-        return Ok(());
+fn display_groups_json(
+    groups: &BTreeMap<Vec<String>, Vec<&Note>>,
+    fields: &[String],
+    list: bool,
+) -> Result<()> {
+    #[derive(Serialize)]
+    struct SerializableGroup {
+        group: serde_json::Map<String, serde_json::Value>,
+        count: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        notes: Option<Vec<String>>,
     }
 
-    for note in notes {
-        println!("{}", note.path);
-    }
+    let serializable_groups: Vec<SerializableGroup> = groups
+        .iter()
+        .map(|(key, notes)| SerializableGroup {
+            group: fields
+                .iter()
+                .zip(key)
+                .map(|(field, value)| (field.clone(), serde_json::Value::String(value.clone())))
+                .collect(),
+            count: notes.len(),
+            notes: list.then(|| notes.iter().map(|n| n.path.clone()).collect()),
+        })
+        .collect();
+
+    println!("{}", serde_json::to_string_pretty(&serializable_groups)?);
+    Ok(())
+}
+
+fn format_group_key(fields: &[String], key: &[String]) -> String {
+    fields
+        .iter()
+        .zip(key)
+        .map(|(field, value)| format!("{}={}", field, value))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Show the value distributions of several fields per folder, given as the fields with
+/// their value counts by folder
+pub fn display_values_per_folder(
+    fields: &[(String, FolderValueCounts)],
+    format: &str,
+    quoting: Quoting,
+    silent: bool,
+) -> Result<()> {
+    match format.to_lowercase().as_str() {
+        "json" => {
+            let serializable: Vec<_> = fields
+                .iter()
+                .map(|(field, folders)| serde_json::json!({ "field": field, "folders": folders }))
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&serializable)?);
+        }
+        "csv" => {
+            quoting.print_csv_row(&["field", "folder", "value", "count"]);
+            for (field, folders) in fields {
+                for (folder, counts) in folders {
+                    for (value, count) in counts {
+                        quoting.print_csv_row(&[field, folder, value, &count.to_string()]);
+                    }
+                }
+            }
+        }
+        other => {
+            if other != "table" {
+                eprintln!("Unknown format: {}. Using table format.", format);
+            }
+            for (i, (field, folders)) in fields.iter().enumerate() {
+                if i > 0 {
+                    println!();
+                }
+                display_field_values_per_folder(folders, field, silent)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn display_field_values_per_folder(
+    folders: &FolderValueCounts,
+    field: &str,
+    silent: bool,
+) -> Result<()> {
+    if folders.is_empty() {
+        if !silent {
+            println!(
+                "{}",
+                format!("No values found for field '{}'.", field).yellow()
+            );
+        }
+        return Ok(());
+    }
+
+    if !silent {
+        println!(
+            "{}",
+            format!("Values for field '{}' per folder:", field)
+                .bold()
+                .blue()
+        );
+    }
+
+    let value_width = folders
+        .values()
+        .flat_map(|counts| counts.keys().map(|value| value.width()))
+        .max()
+        .unwrap_or(0)
+        .max(10);
+
+    for (folder, counts) in folders {
+        let total: usize = counts.values().sum();
+        println!();
+        println!("{} ({} occurrences)", folder.bold().cyan(), total);
+
+        // Sort values by count (descending), then alphabetically
+        let mut value_counts: Vec<_> = counts.iter().collect();
+        value_counts.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+
+        for (value, count) in value_counts {
+            println!(
+                "  {}{} {:>8}",
+                value.green(),
+                padding(value, value_width),
+                count,
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Value counts of a field by the source they come from, most used first
+pub type SourceValueCounts = Vec<(String, ValueSource, usize)>;
+
+pub fn display_values_by_source(
+    fields: &[(String, SourceValueCounts)],
+    format: &str,
+    quoting: Quoting,
+    silent: bool,
+) -> Result<()> {
+    match format.to_lowercase().as_str() {
+        "json" => {
+            let serializable: Vec<_> = fields
+                .iter()
+                .map(|(field, counts)| {
+                    let values: Vec<_> = counts
+                        .iter()
+                        .map(|(value, source, count)| {
+                            serde_json::json!({
+                                "value": value,
+                                "source": source.name(),
+                                "count": count,
+                            })
+                        })
+                        .collect();
+                    serde_json::json!({ "field": field, "values": values })
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&serializable)?);
+        }
+        "csv" => {
+            quoting.print_csv_row(&["field", "value", "source", "count"]);
+            for (field, counts) in fields {
+                for (value, source, count) in counts {
+                    quoting.print_csv_row(&[field, value, source.name(), &count.to_string()]);
+                }
+            }
+        }
+        other => {
+            if other != "table" {
+                eprintln!("Unknown format: {}. Using table format.", format);
+            }
+            for (i, (field, counts)) in fields.iter().enumerate() {
+                if i > 0 {
+                    println!();
+                }
+                display_field_values_by_source(counts, field, silent);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn display_field_values_by_source(counts: &SourceValueCounts, field: &str, silent: bool) {
+    if counts.is_empty() {
+        if !silent {
+            println!(
+                "{}",
+                format!("No values found for field '{}'.", field).yellow()
+            );
+        }
+        return;
+    }
+
+    if !silent {
+        println!(
+            "{}",
+            format!("Values for field '{}' by source:", field)
+                .bold()
+                .blue()
+        );
+        println!();
+    }
+
+    let layout = TableLayout::default();
+    let value_width = counts
+        .iter()
+        .map(|(value, _, _)| layout.single_line(value).width())
+        .max()
+        .unwrap_or(0)
+        .max(10);
+    let source_width = "frontmatter".len();
+    println!(
+        "{}{} {}{} {:>8}",
+        "Value".bold(),
+        padding("Value", value_width),
+        "Source".bold(),
+        padding("Source", source_width),
+        "Count".bold(),
+    );
+    println!("{}", "-".repeat(value_width + source_width + 10));
+    for (value, source, count) in counts {
+        let value = layout.single_line(value);
+        println!(
+            "{}{} {}{} {:>8}",
+            value.green(),
+            padding(&value, value_width),
+            source.name(),
+            padding(source.name(), source_width),
+            count,
+        );
+    }
+}
+
+/// Print completions as a JSON array of `{"value", "count"}` objects, most used first
+pub fn display_completions(completions: &[(String, usize)]) -> Result<()> {
+    let serializable: Vec<_> = completions
+        .iter()
+        .map(|(value, count)| serde_json::json!({ "value": value, "count": count }))
+        .collect();
+    println!("{}", serde_json::to_string_pretty(&serializable)?);
+    Ok(())
+}
+
+/// Show the notes that entered and left a filter's results since a saved run
+pub fn display_result_diff(diff: &ResultDiff, format: &str, silent: bool) -> Result<()> {
+    match format.to_lowercase().as_str() {
+        "paths" => {
+            for path in &diff.entered {
+                println!("+ {}", path);
+            }
+            for path in &diff.left {
+                println!("- {}", path);
+            }
+        }
+        "json" => {
+            let serializable = serde_json::json!({
+                "entered": diff.entered,
+                "left": diff.left,
+            });
+            println!("{}", serde_json::to_string_pretty(&serializable)?);
+        }
+        other => {
+            if other != "table" {
+                eprintln!("Unknown format: {}. Using table format.", format);
+            }
+            if diff.entered.is_empty() && diff.left.is_empty() {
+                if !silent {
+                    println!("{}", "No changes since the previous results.".green());
+                }
+                return Ok(());
+            }
+
+            if !silent {
+                println!(
+                    "{}",
+                    format!(
+                        "Since the previous results: {} entered, {} left",
+                        diff.entered.len(),
+                        diff.left.len()
+                    )
+                    .bold()
+                    .blue()
+                );
+                println!();
+            }
+            for path in &diff.entered {
+                println!("{} {}", "+".green().bold(), path);
+            }
+            for path in &diff.left {
+                println!("{} {}", "-".red().bold(), path);
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn display_broken_links(links: &[BrokenLink], format: &str, silent: bool) -> Result<()> {
+    match format.to_lowercase().as_str() {
+        "paths" => {
+            // One line per note so the output can be piped into other tools
+            let mut last = None;
+            for link in links {
+                if last != Some(&link.source.path) {
+                    println!("{}", link.source.path);
+                    last = Some(&link.source.path);
+                }
+            }
+        }
+        "json" => {
+            let serializable: Vec<serde_json::Value> = links
+                .iter()
+                .map(|link| {
+                    serde_json::json!({
+                        "path": link.source.path,
+                        "target": link.target,
+                    })
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&serializable)?);
+        }
+        other => {
+            if other != "table" {
+                eprintln!("Unknown format: {}. Using table format.", format);
+            }
+            if links.is_empty() {
+                if !silent {
+                    println!("{}", "No broken links found.".green());
+                }
+                return Ok(());
+            }
+
+            if !silent {
+                println!(
+                    "{}",
+                    format!("Found {} broken links:", links.len()).bold().blue()
+                );
+                println!();
+            }
+            let path_width = links
+                .iter()
+                .map(|link| link.source.path.width())
+                .max()
+                .unwrap_or(0)
+                .clamp(4, 60);
+            println!(
+                "{}{} {}",
+                "Path".bold(),
+                padding("Path", path_width),
+                "Target".bold(),
+            );
+            println!("{}", "-".repeat(path_width + 20));
+            for link in links {
+                println!(
+                    "{}{} {}",
+                    link.source.path.cyan(),
+                    padding(&link.source.path, path_width),
+                    link.target.red(),
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Print the notes sharing a value of `field`, with `[redacted]` for the values when the
+/// field is redacted
+pub fn display_duplicates(
+    duplicates: &BTreeMap<String, Vec<&Note>>,
+    field: &str,
+    redacted: bool,
+    format: &str,
+    silent: bool,
+) -> Result<()> {
+    let shown = |value: &str| -> String {
+        if redacted {
+            REDACTED.to_string()
+        } else {
+            value.to_string()
+        }
+    };
+    match format.to_lowercase().as_str() {
+        "json" => {
+            let serializable: Vec<serde_json::Value> = duplicates
+                .iter()
+                .map(|(value, notes)| {
+                    serde_json::json!({
+                        "value": shown(value),
+                        "count": notes.len(),
+                        "notes": notes.iter().map(|n| &n.path).collect::<Vec<_>>(),
+                    })
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&serializable)?);
+        }
+        other => {
+            if other != "table" {
+                eprintln!("Unknown format: {}. Using table format.", format);
+            }
+            if duplicates.is_empty() {
+                if !silent {
+                    println!("{}", format!("No duplicate values of '{}'.", field).green());
+                }
+                return Ok(());
+            }
+
+            if !silent {
+                println!(
+                    "{}",
+                    format!(
+                        "Found {} duplicate values of '{}':",
+                        duplicates.len(),
+                        field
+                    )
+                    .bold()
+                    .blue()
+                );
+                println!();
+            }
+            for (value, notes) in duplicates {
+                println!("{} ({})", shown(value).bold().green(), notes.len());
+                for note in notes {
+                    println!("  {}", note.path.cyan());
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Print the notes a name resolved to, the path only for `paths`
+pub fn display_name_matches(matches: &[NameMatch], format: &str) -> Result<()> {
+    match format.to_lowercase().as_str() {
+        "json" => {
+            let serializable: Vec<serde_json::Value> = matches
+                .iter()
+                .map(|m| {
+                    serde_json::json!({
+                        "path": m.note.path,
+                        "matched": m.kind.name(),
+                        "name": m.name,
+                    })
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&serializable)?);
+        }
+        "table" => {
+            for m in matches {
+                println!(
+                    "{} {}",
+                    m.note.path.cyan(),
+                    format!("({} '{}')", m.kind.name(), m.name).dimmed()
+                );
+            }
+        }
+        other => {
+            if other != "paths" {
+                eprintln!("Unknown format: {}. Using paths format.", format);
+            }
+            for m in matches {
+                println!("{}", m.note.path);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Print the names that refer to more than one note
+pub fn display_name_collisions(
+    collisions: &[(&str, &[NameMatch])],
+    format: &str,
+    silent: bool,
+) -> Result<()> {
+    if format.eq_ignore_ascii_case("json") {
+        let serializable: Vec<serde_json::Value> = collisions
+            .iter()
+            .map(|(name, matches)| {
+                serde_json::json!({
+                    "name": name,
+                    "notes": matches
+                        .iter()
+                        .map(|m| serde_json::json!({ "path": m.note.path, "matched": m.kind.name() }))
+                        .collect::<Vec<_>>(),
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&serializable)?);
+        return Ok(());
+    }
+
+    if collisions.is_empty() {
+        if !silent {
+            println!("{}", "Every name refers to a single note.".green());
+        }
+        return Ok(());
+    }
+    if !silent {
+        println!(
+            "{}",
+            format!("Found {} ambiguous names:", collisions.len())
+                .bold()
+                .blue()
+        );
+        println!();
+    }
+    for (name, matches) in collisions {
+        println!("{} ({})", name.bold().green(), matches.len());
+        for m in *matches {
+            println!(
+                "  {} {}",
+                m.note.path.cyan(),
+                format!("({})", m.kind.name()).dimmed()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Show the values normalizing would change, before and after. Values are shown as JSON
+/// so a string `"yes"` can be told apart from the boolean `true`.
+pub fn display_normalization_preview(
+    changes: &[NormalizedValue],
+    format: &str,
+    silent: bool,
+) -> Result<()> {
+    match format.to_lowercase().as_str() {
+        "paths" => {
+            let mut last = None;
+            for change in changes {
+                if last != Some(&change.path) {
+                    println!("{}", change.path);
+                    last = Some(&change.path);
+                }
+            }
+        }
+        "json" => {
+            let serializable: Vec<serde_json::Value> = changes
+                .iter()
+                .map(|change| {
+                    serde_json::json!({
+                        "path": change.path,
+                        "field": change.field,
+                        "before": yaml_to_json_value(&change.before),
+                        "after": yaml_to_json_value(&change.after),
+                    })
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&serializable)?);
+        }
+        other => {
+            if other != "table" {
+                eprintln!("Unknown format: {}. Using table format.", format);
+            }
+            if changes.is_empty() {
+                if !silent {
+                    println!("{}", "All values are already normalized.".green());
+                }
+                return Ok(());
+            }
+
+            let rows: Vec<(&str, &str, String, String)> = changes
+                .iter()
+                .map(|change| {
+                    (
+                        change.path.as_str(),
+                        change.field.as_str(),
+                        yaml_to_json_value(&change.before).to_string(),
+                        yaml_to_json_value(&change.after).to_string(),
+                    )
+                })
+                .collect();
+            let width = |column: fn(&(&str, &str, String, String)) -> usize, header: usize| {
+                rows.iter().map(column).max().unwrap_or(0).clamp(header, 60)
+            };
+            let path_width = width(|row| row.0.width(), 4);
+            let field_width = width(|row| row.1.width(), 5);
+            let before_width = width(|row| row.2.width(), 6);
+
+            if !silent {
+                let notes = changes
+                    .iter()
+                    .map(|change| &change.path)
+                    .collect::<std::collections::HashSet<_>>()
+                    .len();
+                println!(
+                    "{}",
+                    format!("{} values in {} notes would change:", changes.len(), notes)
+                        .bold()
+                        .blue()
+                );
+                println!();
+            }
+            println!(
+                "{}{} {}{} {}{} {}",
+                "Path".bold(),
+                padding("Path", path_width),
+                "Field".bold(),
+                padding("Field", field_width),
+                "Before".bold(),
+                padding("Before", before_width),
+                "After".bold(),
+            );
+            println!(
+                "{}",
+                "-".repeat(path_width + field_width + before_width + 3 + 20)
+            );
+            for (path, field, before, after) in &rows {
+                println!(
+                    "{}{} {}{} {}{} {}",
+                    path.cyan(),
+                    padding(path, path_width),
+                    field,
+                    padding(field, field_width),
+                    before.red(),
+                    padding(before, before_width),
+                    after.green(),
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn display_issues(issues: &[LintIssue], format: &str, silent: bool) -> Result<()> {
+    match format.to_lowercase().as_str() {
+        "short" => {
+            // One uncolored line per issue, for git hooks and editors
+            for issue in issues {
+                println!("{}: [{}] {}", issue.path, issue.rule, issue.message);
+            }
+        }
+        "paths" => {
+            let mut last = None;
+            for issue in issues {
+                if last != Some(&issue.path) {
+                    println!("{}", issue.path);
+                    last = Some(&issue.path);
+                }
+            }
+        }
+        "json" => {
+            let serializable: Vec<serde_json::Value> = issues
+                .iter()
+                .map(|issue| {
+                    serde_json::json!({
+                        "path": issue.path,
+                        "rule": issue.rule,
+                        "message": issue.message,
+                    })
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&serializable)?);
+        }
+        other => {
+            if other != "table" {
+                eprintln!("Unknown format: {}. Using table format.", format);
+            }
+            if issues.is_empty() {
+                if !silent {
+                    println!("{}", "No issues found.".green());
+                }
+                return Ok(());
+            }
+
+            if !silent {
+                println!(
+                    "{}",
+                    format!("Found {} issues:", issues.len()).bold().blue()
+                );
+                println!();
+            }
+            for issue in issues {
+                println!(
+                    "{} {} {}",
+                    issue.path.cyan(),
+                    format!("[{}]", issue.rule).yellow(),
+                    issue.message
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn display_metrics_report(report: &MetricsReport, format: &str) -> Result<()> {
+    if format.eq_ignore_ascii_case("json") {
+        let summary = |metric: &MetricSummary| serde_json::json!({ "average": metric.average, "max": metric.max });
+        let note_metrics = |notes: &[&Note]| {
+            notes
+                .iter()
+                .map(|note| {
+                    serde_json::json!({
+                        "path": note.path,
+                        "lines": note.metrics.lines,
+                        "keys": note.metrics.keys,
+                        "depth": note.metrics.depth,
+                    })
+                })
+                .collect::<Vec<_>>()
+        };
+        let json = serde_json::json!({
+            "notes": report.notes,
+            "with_frontmatter": report.with_frontmatter,
+            "metrics": {
+                "lines": summary(&report.lines),
+                "keys": summary(&report.keys),
+                "depth": summary(&report.depth),
+            },
+            "largest": note_metrics(&report.largest),
+            "deepest": note_metrics(&report.deepest),
+        });
+        println!("{}", serde_json::to_string_pretty(&json)?);
+        return Ok(());
+    }
+    if !format.eq_ignore_ascii_case("table") {
+        eprintln!("Unknown format: {}. Using table format.", format);
+    }
+
+    println!(
+        "{}",
+        format!(
+            "Frontmatter of {} notes ({} with frontmatter):",
+            report.notes, report.with_frontmatter
+        )
+        .bold()
+        .blue()
+    );
+    println!();
+    println!(
+        "{:<8} {:>8} {:>8}",
+        "Metric".bold(),
+        "Average".bold(),
+        "Max".bold()
+    );
+    println!("{}", "-".repeat(26));
+    for (name, metric) in [
+        ("Lines", &report.lines),
+        ("Keys", &report.keys),
+        ("Depth", &report.depth),
+    ] {
+        println!(
+            "{:<8} {:>8.1} {:>8}",
+            name.green(),
+            metric.average,
+            metric.max
+        );
+    }
+
+    for (heading, notes) in [
+        ("Largest frontmatter:", &report.largest),
+        ("Deepest nesting:", &report.deepest),
+    ] {
+        if notes.is_empty() {
+            continue;
+        }
+        println!();
+        println!("{}", heading.bold());
+        for note in notes {
+            println!(
+                "  {:>4} lines {:>3} keys depth {:<2} {}",
+                note.metrics.lines,
+                note.metrics.keys,
+                note.metrics.depth,
+                note.path.cyan()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Print the frontmatter metrics per value of a field
+pub fn display_metrics_by(
+    reports: &[(String, MetricsReport)],
+    field: &str,
+    format: &str,
+) -> Result<()> {
+    if format.eq_ignore_ascii_case("json") {
+        let summary = |metric: &MetricSummary| serde_json::json!({ "average": metric.average, "max": metric.max });
+        let json: Vec<serde_json::Value> = reports
+            .iter()
+            .map(|(value, report)| {
+                serde_json::json!({
+                    "value": value,
+                    "notes": report.notes,
+                    "with_frontmatter": report.with_frontmatter,
+                    "metrics": {
+                        "lines": summary(&report.lines),
+                        "keys": summary(&report.keys),
+                        "depth": summary(&report.depth),
+                    },
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&json)?);
+        return Ok(());
+    }
+    if !format.eq_ignore_ascii_case("table") {
+        eprintln!("Unknown format: {}. Using table format.", format);
+    }
+
+    println!("{}", format!("Frontmatter by {}:", field).bold().blue());
+    println!();
+    let value_width = reports
+        .iter()
+        .map(|(value, _)| value.width())
+        .chain(std::iter::once(field.width()))
+        .max()
+        .unwrap_or(0);
+    println!(
+        "{}{} {:>8} {:>10} {:>10} {:>10}",
+        field.bold(),
+        padding(field, value_width),
+        "Notes".bold(),
+        "Avg lines".bold(),
+        "Avg keys".bold(),
+        "Max depth".bold()
+    );
+    println!("{}", "-".repeat(value_width + 42));
+    for (value, report) in reports {
+        println!(
+            "{}{} {:>8} {:>10.1} {:>10.1} {:>10}",
+            value.green(),
+            padding(value, value_width),
+            report.notes,
+            report.lines.average,
+            report.keys.average,
+            report.depth.max
+        );
+    }
+
+    Ok(())
+}
+
+/// Print the health report of `stats --health`
+pub fn display_health_report(report: &HealthReport, format: &str) -> Result<()> {
+    if format.eq_ignore_ascii_case("json") {
+        let fields: Vec<serde_json::Value> = report
+            .fields
+            .iter()
+            .map(|field| {
+                let values: Vec<_> = field
+                    .top_values
+                    .iter()
+                    .map(|(value, count)| serde_json::json!({ "value": value, "count": count }))
+                    .collect();
+                serde_json::json!({
+                    "field": field.field,
+                    "notes": field.notes,
+                    "fill_rate": field.fill_rate,
+                    "type": field.kind,
+                    "top_values": values,
+                })
+            })
+            .collect();
+        let missing: serde_json::Map<String, serde_json::Value> = report
+            .missing
+            .iter()
+            .map(|(field, count)| (field.clone(), serde_json::json!(count)))
+            .collect();
+        let json = serde_json::json!({
+            "notes": report.notes,
+            "without_frontmatter": report.without_frontmatter,
+            "errors": report.errors,
+            "warnings": report.warnings,
+            "missing": missing,
+            "fields": fields,
+        });
+        println!("{}", serde_json::to_string_pretty(&json)?);
+        return Ok(());
+    }
+    if !format.eq_ignore_ascii_case("table") {
+        eprintln!("Unknown format: {}. Using table format.", format);
+    }
+
+    println!(
+        "{}",
+        format!("Health of {} notes:", report.notes).bold().blue()
+    );
+    println!();
+    let mut problems = vec![
+        (
+            "Without frontmatter".to_string(),
+            report.without_frontmatter,
+        ),
+        ("Unreadable files".to_string(), report.errors),
+    ];
+    problems.extend(
+        report
+            .warnings
+            .iter()
+            .map(|(category, count)| (category.clone(), *count)),
+    );
+    problems.extend(
+        report
+            .missing
+            .iter()
+            .map(|(field, count)| (format!("Missing {}", field), *count)),
+    );
+    let label_width = problems
+        .iter()
+        .map(|(label, _)| label.width())
+        .max()
+        .unwrap_or(0);
+    for (label, count) in &problems {
+        let count = if *count == 0 {
+            count.to_string().green()
+        } else {
+            count.to_string().yellow()
+        };
+        println!("{}{} {:>8}", label, padding(label, label_width), count);
+    }
+
+    if report.fields.is_empty() {
+        return Ok(());
+    }
+    println!();
+    let field_width = report
+        .fields
+        .iter()
+        .map(|field| field.field.width())
+        .chain(std::iter::once("Field".len()))
+        .max()
+        .unwrap_or(0);
+    println!(
+        "{}{} {:>8} {:>7} {:<8} {}",
+        "Field".bold(),
+        padding("Field", field_width),
+        "Notes".bold(),
+        "Filled".bold(),
+        "Type".bold(),
+        "Top values".bold()
+    );
+    println!("{}", "-".repeat(field_width + 38));
+    for field in &report.fields {
+        let values = field
+            .top_values
+            .iter()
+            .map(|(value, count)| {
+                format!("{} ({})", TableLayout::default().single_line(value), count)
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        println!(
+            "{}{} {:>8} {:>6.1}% {:<8} {}",
+            field.field.green(),
+            padding(&field.field, field_width),
+            field.notes,
+            field.fill_rate,
+            field.kind,
+            values
+        );
+    }
+
+    Ok(())
+}
+
+/// Print the lenient fixes per severity with the files and lines that needed them, or just
+/// the files with `paths`
+pub fn display_lenient_report(report: &LenientReport, format: &str, silent: bool) -> Result<()> {
+    if format.eq_ignore_ascii_case("paths") {
+        for path in report.paths() {
+            println!("{}", path);
+        }
+        return Ok(());
+    }
+    if format.eq_ignore_ascii_case("json") {
+        let fixes: Vec<serde_json::Value> = report
+            .fixes
+            .iter()
+            .map(|(fix, files)| {
+                let files: Vec<_> = files
+                    .iter()
+                    .map(|(path, lines)| serde_json::json!({ "path": path, "lines": lines }))
+                    .collect();
+                serde_json::json!({
+                    "fix": fix.name(),
+                    "severity": fix.severity(),
+                    "description": fix.description(),
+                    "files": files,
+                })
+            })
+            .collect();
+        let json = serde_json::json!({
+            "notes": report.notes,
+            "fixes": fixes,
+            "failed": report.failed,
+        });
+        println!("{}", serde_json::to_string_pretty(&json)?);
+        return Ok(());
+    }
+    if !format.eq_ignore_ascii_case("table") {
+        eprintln!("Unknown format: {}. Using table format.", format);
+    }
+
+    let paths = report.paths().len();
+    if paths == 0 {
+        if !silent {
+            println!("All {} notes parsed without lenient fixes.", report.notes);
+        }
+        return Ok(());
+    }
+    println!(
+        "{}",
+        format!(
+            "{} of {} notes needed lenient parsing:",
+            paths, report.notes
+        )
+        .bold()
+        .blue()
+    );
+    println!();
+    println!(
+        "{:<9} {:<10} {:>6} {:>6}  {}",
+        "Severity".bold(),
+        "Fix".bold(),
+        "Files".bold(),
+        "Lines".bold(),
+        "What it does".bold()
+    );
+    println!("{}", "-".repeat(70));
+    for (fix, files) in &report.fixes {
+        let lines: usize = files.iter().map(|(_, lines)| lines.len()).sum();
+        let severity = match fix.severity() {
+            "high" => fix.severity().red(),
+            "medium" => fix.severity().yellow(),
+            _ => fix.severity().normal(),
+        };
+        println!(
+            "{:<9} {:<10} {:>6} {:>6}  {}",
+            severity,
+            fix.name().green(),
+            files.len(),
+            lines,
+            fix.description()
+        );
+    }
+    if !report.failed.is_empty() {
+        println!(
+            "{:<9} {:<10} {:>6} {:>6}  not parsed even with the fixes, see --verbose",
+            "error".red().bold(),
+            "failed".green(),
+            report.failed.len(),
+            "",
+        );
+    }
+
+    for (fix, files) in &report.fixes {
+        println!();
+        println!("{} ({}):", fix.name().bold(), fix.severity());
+        for (path, lines) in files {
+            let lines: Vec<String> = lines.iter().map(ToString::to_string).collect();
+            println!("  {} (line {})", path, lines.join(", "));
+        }
+    }
+    if !report.failed.is_empty() {
+        println!();
+        println!("{}:", "failed".bold());
+        for path in &report.failed {
+            println!("  {}", path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Print recorded stats snapshots, oldest first, with a sparkline per metric
+pub fn display_trend(snapshots: &[Snapshot], format: &str) -> Result<()> {
+    if format.eq_ignore_ascii_case("json") {
+        println!("{}", serde_json::to_string_pretty(snapshots)?);
+        return Ok(());
+    }
+    if !format.eq_ignore_ascii_case("table") {
+        eprintln!("Unknown format: {}. Using table format.", format);
+    }
+
+    println!(
+        "{}",
+        format!("Vault statistics, {} snapshots:", snapshots.len())
+            .bold()
+            .blue()
+    );
+    println!();
+    println!(
+        "{:<16} {:>8} {:>9} {:>10} {:>10} {:>10} {:>7}",
+        "Recorded".bold(),
+        "Notes".bold(),
+        "No fm %".bold(),
+        "Avg lines".bold(),
+        "Avg keys".bold(),
+        "Max depth".bold(),
+        "Errors".bold()
+    );
+    println!("{}", "-".repeat(76));
+    for snapshot in snapshots {
+        let recorded = chrono::DateTime::parse_from_rfc3339(&snapshot.recorded_at).map_or_else(
+            |_| snapshot.recorded_at.clone(),
+            |time| time.format("%Y-%m-%d %H:%M").to_string(),
+        );
+        println!(
+            "{:<16} {:>8} {:>9.1} {:>10.1} {:>10.1} {:>10} {:>7}",
+            recorded.green(),
+            snapshot.notes,
+            snapshot.without_frontmatter(),
+            snapshot.lines_average,
+            snapshot.keys_average,
+            snapshot.depth_max,
+            snapshot.errors
+        );
+    }
+
+    if snapshots.len() < 2 {
+        return Ok(());
+    }
+    println!();
+    println!("{}", "Trend:".bold());
+    let column = |metric: fn(&Snapshot) -> f64| snapshots.iter().map(metric).collect::<Vec<_>>();
+    // Name, values and decimal places of each metric
+    let metrics = [
+        ("Notes", column(|s| s.notes as f64), 0),
+        ("No fm %", column(Snapshot::without_frontmatter), 1),
+        ("Avg lines", column(|s| s.lines_average), 1),
+        ("Avg keys", column(|s| s.keys_average), 1),
+        ("Max depth", column(|s| s.depth_max as f64), 0),
+        ("Errors", column(|s| s.errors as f64), 0),
+    ];
+    for (name, values, decimals) in metrics {
+        let (first, last) = (values[0], values[values.len() - 1]);
+        println!(
+            "  {:<10} {}  {:.*} -> {:.*} ({:+.*})",
+            name.green(),
+            sparkline(&values),
+            decimals,
+            first,
+            decimals,
+            last,
+            decimals,
+            last - first
+        );
+    }
+
+    Ok(())
+}
+
+pub fn display_note_detail(
+    note: &Note,
+    links: &[(String, Option<&Note>)],
+    warning: Option<&str>,
+    format: &str,
+) -> Result<()> {
+    let mut keys: Vec<&String> = note.frontmatter.keys().collect();
+    keys.sort();
+
+    if format.eq_ignore_ascii_case("json") {
+        let frontmatter: serde_json::Map<String, serde_json::Value> = keys
+            .iter()
+            .map(|key| {
+                let value = &note.frontmatter[*key];
+                (
+                    (*key).clone(),
+                    serde_json::json!({
+                        "type": yaml_type_name(value),
+                        "value": yaml_to_json_value(value),
+                    }),
+                )
+            })
+            .collect();
+        let detail = serde_json::json!({
+            "path": note.path,
+            "title": note.title,
+            "frontmatter": frontmatter,
+            "inline_tags": note.inline_tags,
+            "metrics": {
+                "lines": note.metrics.lines,
+                "keys": note.metrics.keys,
+                "depth": note.metrics.depth,
+            },
+            "links": links
+                .iter()
+                .map(|(target, resolved)| serde_json::json!({
+                    "target": target,
+                    "path": resolved.map(|n| n.path.as_str()),
+                }))
+                .collect::<Vec<_>>(),
+            "warning": warning,
+        });
+        println!("{}", serde_json::to_string_pretty(&detail)?);
+        return Ok(());
+    }
+
+    println!("{} {}", "Path: ".bold(), note.path.cyan());
+    println!(
+        "{} {}",
+        "Title:".bold(),
+        note.title.as_deref().unwrap_or("-").green()
+    );
+    if let Some(warning) = warning {
+        println!("{} {}", "Warning:".bold().yellow(), warning);
+    }
+    println!();
+
+    if keys.is_empty() {
+        println!("{}", "No frontmatter fields.".yellow());
+    } else {
+        let field_width = keys.iter().map(|k| k.width()).max().unwrap_or(0).max(5);
+        println!(
+            "{}{} {:<8} {}",
+            "Field".bold(),
+            padding("Field", field_width),
+            "Type".bold(),
+            "Value".bold(),
+        );
+        println!("{}", "-".repeat(field_width + 30));
+        for key in keys {
+            let value = &note.frontmatter[key];
+            let display_value = match value {
+                Yaml::Array(items) => items
+                    .iter()
+                    .map(|item| TableLayout::default().single_line(&yaml_to_string(item)))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                _ => trim_block(&yaml_to_string(value)).to_string(),
+            };
+            // The lines of a multi-line value line up below the first one
+            let mut lines = display_value.lines();
+            println!(
+                "{}{} {:<8} {}",
+                key.green(),
+                padding(key, field_width),
+                yaml_type_name(value).dimmed(),
+                lines.next().unwrap_or_default(),
+            );
+            for line in lines {
+                println!("{} {}", " ".repeat(field_width + 9), line);
+            }
+        }
+    }
+
+    if !note.inline_tags.is_empty() {
+        println!();
+        println!("{} {}", "Inline tags:".bold(), note.inline_tags.join(", "));
+    }
+
+    if note.metrics.keys > 0 {
+        println!();
+        println!(
+            "{} {} lines, {} keys, depth {}",
+            "Frontmatter:".bold(),
+            note.metrics.lines,
+            note.metrics.keys,
+            note.metrics.depth
+        );
+    }
+
+    if !links.is_empty() {
+        println!();
+        println!("{}", "Links:".bold());
+        for (target, resolved) in links {
+            match resolved {
+                Some(target_note) => println!("  {} -> {}", target, target_note.path.cyan()),
+                None => println!("  {} -> {}", target, "(unresolved)".red()),
+            }
+        }
+    }
 
     Ok(())
 }
 
-fn display_json_format(notes: &[&Note], _silent: bool) -> Result<()> {
-    // Create a serde-compatible representation for JSON output
-    #[derive(Serialize)]
-    struct SerializableNote {
-        path: String,
-        frontmatter: serde_json::Map<String, serde_json::Value>,
-        title: Option<String>,
+fn display_table(
+    notes: &[&Note],
+    criteria: Option<&FilterCriteria>,
+    layout: &TableLayout,
+    silent: bool,
+) -> Result<()> {
+    if notes.is_empty() {
+        if !silent {
+            println!("{}", "No notes match the specified criteria.".yellow());
+        }
+        return Ok(());
     }
 
-    let serializable_notes: Vec<SerializableNote> = notes
+    if !silent {
+        println!(
+            "{}",
+            format!("Found {} matching notes:", notes.len())
+                .bold()
+                .blue()
+        );
+        println!();
+    }
+
+    // Calculate column widths
+    let max_path_width = notes.iter().map(|n| n.path.width()).max().unwrap_or(0);
+    let max_title_width = notes
         .iter()
-        .map(|note| {
-            let mut frontmatter_map = serde_json::Map::new();
-            for (key, value) in &note.frontmatter {
-                frontmatter_map.insert(key.clone(), yaml_to_json_value(value));
-            }
+        .map(|n| n.title.as_ref().map(|t| t.width()).unwrap_or(0))
+        .max()
+        .unwrap_or(0);
+
+    let path_width = layout.column_width(max_path_width, DEFAULT_PATH_WIDTH);
+    let title_width = layout.column_width(max_title_width, DEFAULT_TITLE_WIDTH);
+
+    // Notes from several vaults get a column naming their vault
+    let vault_width = notes
+        .iter()
+        .filter_map(|n| n.vault.as_ref())
+        .map(|vault| vault.width().max(5) + 1)
+        .max()
+        .unwrap_or(0);
+
+    // Annotated notes get a column listing the rules they match
+    let rules: Vec<Option<String>> = notes
+        .iter()
+        .map(|n| n.rules.as_ref().map(|rules| rules.join(", ")))
+        .collect();
+    let rules_width = rules
+        .iter()
+        .flatten()
+        .map(|rules| rules.width().max(5) + 1)
+        .max()
+        .unwrap_or(0);
 
-            SerializableNote {
-                path: note.path.clone(),
-                frontmatter: frontmatter_map,
-                title: note.title.clone(),
+    // Which fields each note has; with filters the values that matched follow
+    let summaries: Vec<String> = notes
+        .iter()
+        .map(|n| {
+            let keys: Vec<String> = n.frontmatter.keys().cloned().collect();
+            match keys.len() {
+                0 => "-".to_string(),
+                1..=3 => keys.join(", "),
+                _ => format!("{}, ... (+{})", keys[..3].join(", "), keys.len() - 3),
             }
         })
         .collect();
+    let frontmatter_width = if criteria.is_some() {
+        summaries
+            .iter()
+            .map(|summary| summary.width())
+            .chain(["Frontmatter".width()])
+            .max()
+            .unwrap_or(0)
+            + 1
+    } else {
+        0
+    };
+
+    // Header
+    println!(
+        "{}{}{} {}{} {}{}{}",
+        if vault_width > 0 {
+            format!("{}{}", "Vault".bold(), padding("Vault", vault_width))
+        } else {
+            String::new()
+        },
+        "Path".bold(),
+        padding("Path", path_width),
+        "Title".bold(),
+        padding("Title", title_width),
+        if rules_width > 0 {
+            format!("{}{}", "Rules".bold(), padding("Rules", rules_width))
+        } else {
+            String::new()
+        },
+        "Frontmatter".bold(),
+        if criteria.is_some() {
+            format!(
+                "{}{}",
+                padding("Frontmatter", frontmatter_width),
+                "Matched".bold()
+            )
+        } else {
+            String::new()
+        },
+    );
+    println!(
+        "{}",
+        "-".repeat(vault_width + path_width + title_width + rules_width + frontmatter_width + 20)
+    );
+
+    let title_terms: Vec<&str> = criteria
+        .map(|criteria| {
+            criteria
+                .filters()
+                .iter()
+                .filter(|f| f.op == FilterOp::Contains && f.field.eq_ignore_ascii_case("title"))
+                .map(|f| f.value.as_str())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    // Note data
+    for ((note, note_rules), summary) in notes.iter().zip(&rules).zip(&summaries) {
+        // Paths keep their end (the file name), titles their beginning
+        let path_lines = layout.fit(&note.path, path_width, true);
+        let title_lines = layout.fit(note.title.as_deref().unwrap_or("-"), title_width, false);
+
+        let matched = if let Some(criteria) = criteria {
+            let matched: Vec<String> = criteria
+                .matched_values(note)
+                .iter()
+                .zip(criteria.filters())
+                .map(|((field, values), filter)| {
+                    // Comparisons match whole values, there is no part to highlight
+                    let terms: &[&str] = match filter.op {
+                        FilterOp::Contains => &[filter.value.as_str()],
+                        _ => &[],
+                    };
+                    let values: Vec<String> = values
+                        .iter()
+                        .map(|v| {
+                            highlight_matches(
+                                &layout.single_line(v),
+                                terms,
+                                criteria.is_case_sensitive(),
+                            )
+                        })
+                        .collect();
+                    format!("{}: {}", field.dimmed(), values.join(", "))
+                })
+                .collect();
+            format!(
+                "{}{}",
+                padding(summary, frontmatter_width),
+                matched.join("; ")
+            )
+        } else {
+            String::new()
+        };
+        let last_column = format!("{}{}", summary.dimmed(), matched);
+
+        let case_sensitive = criteria.is_none_or(FilterCriteria::is_case_sensitive);
+        for i in 0..path_lines.len().max(title_lines.len()) {
+            let path = path_lines.get(i).map_or("", String::as_str);
+            let title = title_lines.get(i).map_or("", String::as_str);
+            // Pad before coloring, highlights add escape codes that would break the alignment
+            let path_padding = padding(path, path_width);
+            let title_padding = padding(title, title_width);
+            let vault = match (i, &note.vault) {
+                (0, Some(vault)) => vault.as_str(),
+                _ => "",
+            };
+            let vault_padding = padding(vault, vault_width);
+            let rule_names = match (i, note_rules) {
+                (0, Some(rules)) => rules.as_str(),
+                _ => "",
+            };
+            let rules_padding = padding(rule_names, rules_width);
+
+            let line = format!(
+                "{}{}{}{} {}{} {}{}{}",
+                vault.yellow(),
+                vault_padding,
+                path.cyan(),
+                path_padding,
+                highlight_matches(title, &title_terms, case_sensitive),
+                title_padding,
+                rule_names.magenta(),
+                rules_padding,
+                if i == 0 { last_column.as_str() } else { "" },
+            );
+            println!("{}", line.trim_end());
+        }
+        // Excerpts go on a line of their own below the note
+        if let Some(excerpt) = note
+            .excerpt
+            .as_deref()
+            .filter(|excerpt| !excerpt.is_empty())
+        {
+            println!("{}{}", " ".repeat(vault_width + 2), excerpt.dimmed());
+        }
+    }
+
+    Ok(())
+}
+
+/// Color `text` green with every occurrence of one of `terms` highlighted
+fn highlight_matches(text: &str, terms: &[&str], case_sensitive: bool) -> String {
+    let mut highlighted = String::new();
+    let mut last = 0;
+    for (start, end) in match_ranges(text, terms, case_sensitive) {
+        if start > last {
+            highlighted.push_str(&text[last..start].green().to_string());
+        }
+        highlighted.push_str(&text[start..end].black().on_yellow().bold().to_string());
+        last = end;
+    }
+    highlighted.push_str(&text[last..].green().to_string());
+    highlighted
+}
+
+/// Byte ranges of the occurrences of `terms` in `text`
+fn match_ranges(text: &str, terms: &[&str], case_sensitive: bool) -> Vec<(usize, usize)> {
+    let pattern = terms
+        .iter()
+        .filter(|term| !term.is_empty())
+        .map(|term| regex::escape(term))
+        .collect::<Vec<_>>()
+        .join("|");
+    if pattern.is_empty() {
+        return Vec::new();
+    }
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!case_sensitive)
+        .build()
+        .map(|re| re.find_iter(text).map(|m| (m.start(), m.end())).collect())
+        .unwrap_or_default()
+}
+
+/// A per-note output template such as `{{path}}\t{{frontmatter.status}} {{title}}`.
+///
+/// Placeholders are `path`, `name` (file name without extension), `title`, `rules` (see
+/// `--annotate`), `excerpt` (see `--excerpt`) and `frontmatter.<field>`. Lists are joined with `, ` unless a separator is given with
+/// `{{frontmatter.tags|join:" "}}`. Missing values render as empty strings, and `\t`/`\n`
+/// escapes are expanded so templates can be passed on the command line.
+#[derive(Debug)]
+pub struct Template {
+    parts: Vec<TemplatePart>,
+}
+
+#[derive(Debug, PartialEq)]
+enum TemplatePart {
+    Literal(String),
+    Placeholder { name: String, join: Option<String> },
+}
+
+impl Template {
+    pub fn parse(template: &str) -> Result<Self> {
+        let template = template.replace("\\t", "\t").replace("\\n", "\n");
+        let mut parts = Vec::new();
+        let mut rest = template.as_str();
+
+        while let Some(start) = rest.find("{{") {
+            if start > 0 {
+                parts.push(TemplatePart::Literal(rest[..start].to_string()));
+            }
+            let end = rest[start..]
+                .find("}}")
+                .ok_or_else(|| anyhow!("Unclosed placeholder in template: {}", &rest[start..]))?;
+            let placeholder = &rest[start + 2..start + end];
+            let (name, join) = match placeholder.split_once('|') {
+                Some((name, filter)) => {
+                    let separator = filter
+                        .trim()
+                        .strip_prefix("join:")
+                        .ok_or_else(|| anyhow!("Unknown template filter: {}", filter.trim()))?;
+                    let separator = separator.trim();
+                    let separator = separator
+                        .strip_prefix('"')
+                        .and_then(|s| s.strip_suffix('"'))
+                        .unwrap_or(separator);
+                    (name.trim(), Some(separator.to_string()))
+                }
+                None => (placeholder.trim(), None),
+            };
+            if !matches!(name, "path" | "name" | "title" | "rules" | "excerpt")
+                && !name.starts_with("frontmatter.")
+            {
+                return Err(anyhow!("Unknown template placeholder: {}", name));
+            }
+            parts.push(TemplatePart::Placeholder {
+                name: name.to_string(),
+                join,
+            });
+            rest = &rest[start + end + 2..];
+        }
+        if !rest.is_empty() {
+            parts.push(TemplatePart::Literal(rest.to_string()));
+        }
+
+        Ok(Self { parts })
+    }
+
+    pub fn render(&self, note: &Note) -> String {
+        self.render_quoted(note, Quoting::default())
+    }
+
+    /// Fill in the placeholders for a note, with `{{path}}` quoted and the values escaped
+    /// as `quoting` asks for
+    pub fn render_quoted(&self, note: &Note, quoting: Quoting) -> String {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                TemplatePart::Literal(text) => text.clone(),
+                TemplatePart::Placeholder { name, join } => {
+                    let separator = join.as_deref().unwrap_or(", ");
+                    let value = match name.as_str() {
+                        "path" => return quoting.path(&note.path),
+                        "name" => Path::new(&note.path)
+                            .file_stem()
+                            .map(|stem| stem.to_string_lossy().to_string())
+                            .unwrap_or_default(),
+                        "title" => note.title.clone().unwrap_or_default(),
+                        "excerpt" => note.excerpt.clone().unwrap_or_default(),
+                        "rules" => note
+                            .rules
+                            .as_ref()
+                            .map(|rules| rules.join(separator))
+                            .unwrap_or_default(),
+                        field => field
+                            .strip_prefix("frontmatter.")
+                            .and_then(|field| note.get_frontmatter_value_case_insensitive(field))
+                            .map(|value| template_value(value, separator))
+                            .unwrap_or_default(),
+                    };
+                    quoting.value(&value)
+                }
+            })
+            .collect()
+    }
+}
+
+fn template_value(value: &Yaml, separator: &str) -> String {
+    match value {
+        Yaml::Array(items) => items
+            .iter()
+            .map(|item| template_value(item, separator))
+            .collect::<Vec<_>>()
+            .join(separator),
+        Yaml::Hash(_) => yaml_to_json_value(value).to_string(),
+        Yaml::Null => String::new(),
+        other => yaml_to_string(other),
+    }
+}
+
+/// Print one rendered template line per note
+pub fn display_template_format(
+    notes: &[&Note],
+    template: &Template,
+    quoting: Quoting,
+) -> Result<()> {
+    for note in notes {
+        println!("{}", template.render_quoted(note, quoting));
+    }
+    Ok(())
+}
+
+fn display_paths_format(notes: &[&Note], quoting: Quoting, silent: bool) -> Result<()> {
+    if notes.is_empty() {
+        if !silent {
+            println!("{}", "No notes match the specified criteria.".yellow());
+        }
+        return Ok(());
+    }
+
+    for note in notes {
+        println!("{}", quoting.path(&note.path));
+    }
+
+    Ok(())
+}
+
+/// Serde-compatible representation of a note, the object the `json` and `ndjson` formats
+/// emit per note
+#[derive(Serialize)]
+pub struct SerializableNote<'a> {
+    path: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    vault: Option<&'a str>,
+    frontmatter: serde_json::Map<String, serde_json::Value>,
+    title: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rules: Option<&'a [String]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    excerpt: Option<&'a str>,
+    /// Fields from lookup tables, see `--join`
+    #[serde(skip_serializing_if = "serde_json::Map::is_empty")]
+    joined: serde_json::Map<String, serde_json::Value>,
+}
+
+impl<'a> From<&'a Note> for SerializableNote<'a> {
+    fn from(note: &'a Note) -> Self {
+        let mut frontmatter = serde_json::Map::new();
+        for (key, value) in &note.frontmatter {
+            frontmatter.insert(key.clone(), yaml_to_json_value(value));
+        }
+        let joined = note
+            .joined_fields
+            .iter()
+            .filter_map(|field| {
+                let value = note.get_frontmatter_value(field)?;
+                Some((field.clone(), yaml_to_json_value(value)))
+            })
+            .collect();
+
+        Self {
+            path: &note.path,
+            vault: note.vault.as_deref(),
+            frontmatter,
+            title: note.title.as_deref(),
+            rules: note.rules.as_deref(),
+            excerpt: note.excerpt.as_deref(),
+            joined,
+        }
+    }
+}
+
+fn display_json_format(notes: &[&Note], _silent: bool) -> Result<()> {
+    println!("{}", notes_to_json(notes)?);
+    Ok(())
+}
+
+/// The notes as a pretty-printed JSON array, as printed by the `json` format
+pub fn notes_to_json(notes: &[&Note]) -> Result<String> {
+    let serializable_notes: Vec<SerializableNote> = notes
+        .iter()
+        .map(|note| SerializableNote::from(*note))
+        .collect();
+    Ok(serde_json::to_string_pretty(&serializable_notes)?)
+}
+
+fn display_ndjson_format(notes: &[&Note]) -> Result<()> {
+    let mut out = std::io::stdout().lock();
+    for note in notes {
+        write_ndjson_line(&mut out, note)?;
+    }
+    Ok(())
+}
+
+/// Write notes as `paths`, `json` or `ndjson` to a file or other writer
+pub fn write_notes(out: &mut impl Write, notes: &[&Note], format: &str) -> Result<()> {
+    match format.to_lowercase().as_str() {
+        "paths" => {
+            for note in notes {
+                writeln!(out, "{}", note.path)?;
+            }
+        }
+        "json" => writeln!(out, "{}", notes_to_json(notes)?)?,
+        "ndjson" => {
+            for note in notes {
+                write_ndjson_line(out, note)?;
+            }
+        }
+        "markdown" => {
+            for note in notes {
+                writeln!(out, "- {}", wikilink(note))?;
+            }
+        }
+        _ => {
+            return Err(anyhow!(
+                "Format '{}' can't be written to a file, use paths, json, ndjson or markdown",
+                format
+            ))
+        }
+    }
+    Ok(())
+}
+
+/// A `[[wikilink]]` to a note by its file name, showing its title when that differs
+fn wikilink(note: &Note) -> String {
+    let name = Path::new(&note.path).file_stem().map_or_else(
+        || note.path.clone(),
+        |stem| stem.to_string_lossy().to_string(),
+    );
+    match note.title.as_deref() {
+        Some(title) if title != name => format!("[[{}|{}]]", name, title.replace('|', "-")),
+        _ => format!("[[{}]]", name),
+    }
+}
 
-    let json_output = serde_json::to_string_pretty(&serializable_notes)?;
-    println!("{}", json_output);
+/// Write a note as a single line of JSON, the same object the `json` format emits per note
+pub fn write_ndjson_line(out: &mut impl Write, note: &Note) -> Result<()> {
+    serde_json::to_writer(&mut *out, &SerializableNote::from(note))?;
+    writeln!(out)?;
     Ok(())
 }
 
@@ -316,7 +2675,6 @@ fn display_json_format(notes: &[&Note], _silent: bool) -> Result<()> {
 mod tests {
     use super::*;
     use std::collections::HashMap;
-    use yaml_rust2::Yaml;
 
     fn create_test_note(
         path: &str,
@@ -330,6 +2688,92 @@ mod tests {
         note
     }
 
+    #[test]
+    fn test_csv_cell() {
+        let csv_cell = |value| Quoting::default().csv_cell(value);
+        assert_eq!(csv_cell("work"), "work");
+        assert_eq!(csv_cell("a, b"), "\"a, b\"");
+        assert_eq!(csv_cell("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_cell("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn test_column_row_keeps_column_order() {
+        let names = vec![
+            "title".to_string(),
+            "due_in".to_string(),
+            "owner".to_string(),
+        ];
+        let values = vec![
+            Some(Yaml::String("Site".to_string())),
+            Some(Yaml::Integer(-2)),
+            None,
+        ];
+        let row = ColumnRow {
+            names: &names,
+            values: &values,
+        };
+        assert_eq!(
+            serde_json::to_string(&row).unwrap(),
+            r#"{"title":"Site","due_in":-2,"owner":null}"#
+        );
+    }
+
+    #[test]
+    fn test_quoting() {
+        let quoting = Quoting {
+            shell_quote: true,
+            quote_all: true,
+            escape_newlines: true,
+        };
+        assert_eq!(quoting.csv_cell("work"), "\"work\"");
+        assert_eq!(quoting.csv_cell("two\nlines"), "\"two\\nlines\"");
+        assert_eq!(quoting.value(r"C:\notes"), r"C:\\notes");
+        assert_eq!(quoting.path("My Notes/a.md"), "'My Notes/a.md'");
+        assert_eq!(quoting.path("it's\nodd.md"), r"$'it\'s\nodd.md'");
+
+        let escape_only = Quoting {
+            escape_newlines: true,
+            ..Quoting::default()
+        };
+        assert_eq!(escape_only.path("a\tb.md"), r"a\tb.md");
+
+        let mut fm = HashMap::new();
+        fm.insert(
+            "summary".to_string(),
+            Yaml::String("line one\nline two".to_string()),
+        );
+        let note = create_test_note("My Notes/a.md", None, fm);
+        let template = Template::parse("{{path}}\t{{frontmatter.summary}}").unwrap();
+        assert_eq!(
+            template.render_quoted(&note, quoting),
+            "'My Notes/a.md'\tline one\\nline two"
+        );
+        assert_eq!(template.render(&note), "My Notes/a.md\tline one\nline two");
+    }
+
+    #[test]
+    fn test_field_value_counts() {
+        let note = |path: &str, status: &str| {
+            let mut fm = HashMap::new();
+            fm.insert("Status".to_string(), Yaml::String(status.to_string()));
+            create_test_note(path, None, fm)
+        };
+        let notes = vec![
+            note("a.md", "done"),
+            note("b.md", "active"),
+            note("c.md", "done"),
+            note("d.md", "blocked"),
+        ];
+        let stats = get_field_statistics(&notes);
+
+        assert_eq!(
+            field_value_counts(&notes, "status", &stats, false),
+            vec![("done", 2), ("active", 1), ("blocked", 1)]
+        );
+        assert!(field_value_counts(&notes, "status", &stats, true).is_empty());
+    }
+
     #[test]
     fn test_display_paths_format() {
         let mut fm = HashMap::new();
@@ -344,6 +2788,124 @@ mod tests {
 
         // This would normally print to stdout, but we can't easily test that
         // Just ensure it doesn't panic
-        assert!(display_paths_format(&note_refs, false).is_ok());
+        assert!(display_paths_format(&note_refs, Quoting::default(), false).is_ok());
+    }
+
+    #[test]
+    fn test_table_layout_fit() {
+        let truncate = TableLayout::default();
+        assert_eq!(truncate.fit("short", 10, false), vec!["short"]);
+        assert_eq!(truncate.fit("a long title", 8, false), vec!["a lon..."]);
+        assert_eq!(
+            truncate.fit("notes/ä/file.md", 10, true),
+            vec!["...file.md"]
+        );
+
+        let wrap = TableLayout {
+            wrap: true,
+            ..TableLayout::default()
+        };
+        assert_eq!(wrap.fit("a long title", 7, false), vec!["a long", "title"]);
+        assert_eq!(wrap.fit("abcdefgh", 3, false), vec!["abc", "def", "gh"]);
+
+        let full = TableLayout {
+            no_truncate: true,
+            ..TableLayout::default()
+        };
+        assert_eq!(full.column_width(120, DEFAULT_PATH_WIDTH), 120);
+        assert_eq!(truncate.column_width(120, DEFAULT_PATH_WIDTH), 50);
+    }
+
+    #[test]
+    fn test_single_line() {
+        let layout = TableLayout::default();
+        assert_eq!(layout.single_line("one line\n"), "one line");
+        assert_eq!(layout.single_line("first  \nsecond\n"), "first ↵ second");
+        let paragraph = "word ".repeat(20);
+        let folded = layout.single_line(&format!("{}\n{}", paragraph, paragraph));
+        assert_eq!(folded.width(), DEFAULT_MULTILINE_WIDTH);
+        assert!(folded.ends_with("..."));
+        // Long values on a single line are left to the column they are shown in
+        assert_eq!(layout.single_line(&paragraph), paragraph);
+
+        let full = TableLayout {
+            no_truncate: true,
+            ..TableLayout::default()
+        };
+        assert_eq!(
+            full.single_line(&format!("{}\n{}", paragraph, paragraph))
+                .width(),
+            2 * paragraph.trim_end().width() + 3
+        );
+    }
+
+    #[test]
+    fn test_wide_characters() {
+        // Emoji take two terminal columns
+        assert_eq!(padding("📅 date", 10), "   ");
+        assert_eq!(padding("date", 10), "      ");
+        assert_eq!(padding("too long", 4), "");
+
+        let truncate = TableLayout::default();
+        assert_eq!(truncate.fit("📅📅📅📅 plan", 8, false), vec!["📅📅..."]);
+        assert_eq!(truncate.fit("notes/📅📅.md", 8, true), vec!["...📅.md"]);
+        let wrap = TableLayout {
+            wrap: true,
+            ..TableLayout::default()
+        };
+        assert_eq!(wrap.fit("📅📅📅", 4, false), vec!["📅📅", "📅"]);
+        assert_eq!(wrap.fit("📅📅", 1, false), vec!["📅", "📅"]);
+    }
+
+    #[test]
+    fn test_template_render() {
+        let mut fm = HashMap::new();
+        fm.insert("status".to_string(), Yaml::String("active".to_string()));
+        fm.insert(
+            "tags".to_string(),
+            Yaml::Array(vec![
+                Yaml::String("work".to_string()),
+                Yaml::String("urgent".to_string()),
+            ]),
+        );
+        fm.insert("priority".to_string(), Yaml::Integer(2));
+        let note = create_test_note("notes/alpha.md", Some("Alpha"), fm);
+
+        let template =
+            Template::parse("{{path}}\\t{{frontmatter.status}} {{title}} ({{name}})").unwrap();
+        assert_eq!(
+            template.render(&note),
+            "notes/alpha.md\tactive Alpha (alpha)"
+        );
+
+        let template = Template::parse(
+            "{{frontmatter.tags}}|{{ frontmatter.tags | join:\" \" }}|{{frontmatter.priority}}|{{frontmatter.missing}}",
+        )
+        .unwrap();
+        assert_eq!(template.render(&note), "work, urgent|work urgent|2|");
+
+        let mut note = note;
+        note.rules = Some(vec!["stale".to_string(), "no-owner".to_string()]);
+        let template = Template::parse("{{name}}: {{rules|join:\" \"}}").unwrap();
+        assert_eq!(template.render(&note), "alpha: stale no-owner");
+    }
+
+    #[test]
+    fn test_template_parse_errors() {
+        assert!(Template::parse("{{path").is_err());
+        assert!(Template::parse("{{unknown}}").is_err());
+        assert!(Template::parse("{{frontmatter.tags|upper}}").is_err());
+    }
+
+    #[test]
+    fn test_highlight_matches() {
+        assert_eq!(match_ranges("Project", &["jec"], true), vec![(3, 6)]);
+        assert_eq!(match_ranges("Project", &["JEC"], false), vec![(3, 6)]);
+        assert_eq!(match_ranges("Project", &["JEC"], true), vec![]);
+        assert_eq!(
+            match_ranges("proj projection", &["proj", ""], true),
+            vec![(0, 4), (5, 9)]
+        );
+        assert_eq!(match_ranges("Project", &[""], false), vec![]);
     }
 }
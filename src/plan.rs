@@ -0,0 +1,244 @@
+use anyhow::{Context, Result};
+use colored::*;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::Path;
+
+/// Number of files a write command may touch before it asks for confirmation
+pub const DEFAULT_CONFIRM_THRESHOLD: usize = 20;
+
+/// Number of sample diffs shown in the impact summary
+const SAMPLE_DIFFS: usize = 3;
+
+#[derive(Debug, Clone)]
+pub struct PlannedChange {
+    pub path: String,
+    pub before: String,
+    pub after: String,
+    /// Full file content to write when the plan is applied
+    pub content: Option<String>,
+}
+
+impl PlannedChange {
+    pub fn new(path: String, before: String, after: String) -> Self {
+        Self {
+            path,
+            before,
+            after,
+            content: None,
+        }
+    }
+
+    #[must_use]
+    pub fn with_content(mut self, content: String) -> Self {
+        self.content = Some(content);
+        self
+    }
+
+    /// Line-based diff of the frontmatter, prefixed with `-`/`+` like a unified diff
+    pub fn diff_lines(&self) -> Vec<String> {
+        diff_lines(&self.before, &self.after)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ChangePlan {
+    pub changes: Vec<PlannedChange>,
+}
+
+impl ChangePlan {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, change: PlannedChange) {
+        // Changes that don't alter anything are not worth reporting
+        if change.before != change.after {
+            self.changes.push(change);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn folder_breakdown(&self) -> BTreeMap<String, usize> {
+        let mut folders = BTreeMap::new();
+        for change in &self.changes {
+            let folder = Path::new(&change.path)
+                .parent()
+                .map(|p| p.display().to_string())
+                .filter(|p| !p.is_empty())
+                .unwrap_or_else(|| ".".to_string());
+            *folders.entry(folder).or_insert(0) += 1;
+        }
+        folders
+    }
+
+    /// Write the new content of every planned change, returning the number of files written
+    pub fn apply(&self) -> Result<usize> {
+        let mut written = 0;
+        for change in &self.changes {
+            if let Some(content) = &change.content {
+                fs::write(&change.path, content)
+                    .with_context(|| format!("Failed to write file: {}", change.path))?;
+                written += 1;
+            }
+        }
+        Ok(written)
+    }
+
+    /// Print the number of files to change, a per-folder breakdown and a few sample diffs
+    pub fn print_impact_summary(&self, show_all_diffs: bool) {
+        if self.is_empty() {
+            eprintln!("{}", "No files need to be changed.".yellow());
+            return;
+        }
+
+        eprintln!(
+            "{}",
+            format!("{} files to change:", self.len()).bold().blue()
+        );
+        for (folder, count) in self.folder_breakdown() {
+            eprintln!("  {:<40} {:>6}", folder, count);
+        }
+
+        let shown = if show_all_diffs {
+            self.len()
+        } else {
+            SAMPLE_DIFFS
+        };
+        for change in self.changes.iter().take(shown) {
+            eprintln!();
+            eprintln!("{}", change.path.cyan());
+            for line in change.diff_lines() {
+                if line.starts_with('-') {
+                    eprintln!("  {}", line.red());
+                } else {
+                    eprintln!("  {}", line.green());
+                }
+            }
+        }
+        if self.len() > shown {
+            eprintln!();
+            eprintln!("... and {} more files", self.len() - shown);
+        }
+    }
+}
+
+/// Decide whether a plan may be applied.
+///
+/// Plans at or below `threshold` files are applied directly. Larger plans need `--yes`
+/// or an interactive confirmation; without a terminal they are refused.
+pub fn confirm_plan(plan: &ChangePlan, yes: bool, threshold: usize) -> Result<bool> {
+    confirm_changes(plan.len(), yes, threshold)
+}
+
+/// Like [`confirm_plan`], for changes to `files` files that aren't all in a plan
+pub fn confirm_changes(files: usize, yes: bool, threshold: usize) -> Result<bool> {
+    if yes || files <= threshold {
+        return Ok(true);
+    }
+
+    if !io::stdin().is_terminal() {
+        eprintln!(
+            "{}",
+            format!(
+                "Refusing to modify {} files (threshold {}) without --yes",
+                files, threshold
+            )
+            .red()
+        );
+        return Ok(false);
+    }
+
+    eprint!("Apply changes to {} files? [y/N] ", files);
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+fn diff_lines(before: &str, after: &str) -> Vec<String> {
+    let old: Vec<&str> = before.lines().collect();
+    let new: Vec<&str> = after.lines().collect();
+
+    // Longest common subsequence table, good enough for frontmatter-sized inputs
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut result = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            result.push(format!("-{}", old[i]));
+            i += 1;
+        } else {
+            result.push(format!("+{}", new[j]));
+            j += 1;
+        }
+    }
+    result.extend(old[i..].iter().map(|line| format!("-{}", line)));
+    result.extend(new[j..].iter().map(|line| format!("+{}", line)));
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_lines() {
+        let before = "title: A\nstatus: open\ntags: [x]";
+        let after = "title: A\nstatus: done\ntags: [x]";
+        assert_eq!(
+            diff_lines(before, after),
+            vec!["-status: open", "+status: done"]
+        );
+    }
+
+    #[test]
+    fn test_plan_skips_unchanged_files() {
+        let mut plan = ChangePlan::new();
+        plan.push(PlannedChange::new(
+            "a.md".to_string(),
+            "x: 1".to_string(),
+            "x: 1".to_string(),
+        ));
+        plan.push(PlannedChange::new(
+            "notes/b.md".to_string(),
+            "x: 1".to_string(),
+            "x: 2".to_string(),
+        ));
+        assert_eq!(plan.len(), 1);
+        assert_eq!(plan.folder_breakdown().get("notes"), Some(&1));
+    }
+
+    #[test]
+    fn test_confirm_below_threshold() {
+        let mut plan = ChangePlan::new();
+        plan.push(PlannedChange::new(
+            "a.md".to_string(),
+            "x: 1".to_string(),
+            "x: 2".to_string(),
+        ));
+        assert!(confirm_plan(&plan, false, 5).unwrap());
+        assert!(confirm_plan(&plan, true, 0).unwrap());
+    }
+}
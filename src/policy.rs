@@ -0,0 +1,419 @@
+use crate::filter::{FieldFilter, FilterOp};
+use crate::frontmatter::Note;
+use crate::plan::ChangePlan;
+use crate::writer::{plan_changes, FrontmatterEdit};
+use crate::yaml_compat::yaml_to_string;
+use anyhow::{anyhow, Context, Result};
+use chrono::{NaiveDateTime, TimeDelta};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// What `aktenfux policy run` does with the notes matching a set of filters, like setting
+/// `archived: true` on finished notes and moving them to `Archive/`
+#[derive(Debug, Clone)]
+pub struct Policy {
+    pub name: String,
+    /// Filters a note must pass, including the cutoff of an age like `180d`
+    pub filters: Vec<FieldFilter>,
+    /// Frontmatter changes, applied in order
+    pub edits: Vec<FrontmatterEdit>,
+    /// Folder to move the notes to, relative to the root of their vault
+    pub move_to: Option<PathBuf>,
+}
+
+impl Policy {
+    pub fn new(
+        name: &str,
+        filters: Vec<FieldFilter>,
+        edits: Vec<FrontmatterEdit>,
+        move_to: Option<PathBuf>,
+    ) -> Result<Self> {
+        if edits.is_empty() && move_to.is_none() {
+            return Err(anyhow!(
+                "Policy '{}' does nothing: give it set, add, remove or move_to",
+                name
+            ));
+        }
+        if let Some(folder) = &move_to {
+            // Notes stay inside their vault
+            if !folder
+                .components()
+                .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+            {
+                return Err(anyhow!(
+                    "Policy '{}': move_to must be a folder inside the vault, not {}",
+                    name,
+                    folder.display()
+                ));
+            }
+        }
+        Ok(Self {
+            name: name.to_string(),
+            filters,
+            edits,
+            move_to,
+        })
+    }
+}
+
+/// Parse an age like `180d`, `6w` or `1y` (365 days)
+pub fn parse_age(age: &str) -> Result<TimeDelta> {
+    let age = age.trim();
+    let invalid = || {
+        anyhow!(
+            "Invalid age '{}', expected days, weeks or years like 180d",
+            age
+        )
+    };
+    let split = age
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(invalid)?;
+    let count: i64 = age[..split].parse().map_err(|_| invalid())?;
+    let days = match age[split..].trim() {
+        "d" => count,
+        "w" => count * 7,
+        "y" => count * 365,
+        _ => return Err(invalid()),
+    };
+    TimeDelta::try_days(days).ok_or_else(invalid)
+}
+
+/// A filter passing the notes whose date in `field` is more than `age` before `now`
+pub fn older_than(field: &str, age: &str, now: NaiveDateTime) -> Result<FieldFilter> {
+    let cutoff = now - parse_age(age)?;
+    Ok(FieldFilter {
+        field: field.to_string(),
+        op: FilterOp::Lt,
+        value: cutoff.format("%Y-%m-%dT%H:%M:%S").to_string(),
+        mode: None,
+    })
+}
+
+/// What a policy does to one note
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PolicyAction {
+    pub policy: String,
+    pub path: String,
+    /// The frontmatter changes, like `set archived: true`
+    pub changes: Vec<String>,
+    /// Where the note is moved to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub move_to: Option<String>,
+    /// Why the note isn't moved although the policy moves notes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub not_moved: Option<String>,
+}
+
+impl PolicyAction {
+    fn changes_file(&self) -> bool {
+        !self.changes.is_empty() || self.move_to.is_some()
+    }
+}
+
+/// The changes of a run of policies, to print as a report and apply
+#[derive(Debug, Default)]
+pub struct PolicyRun {
+    pub actions: Vec<PolicyAction>,
+    /// The frontmatter changes, written before the notes are moved
+    pub plan: ChangePlan,
+}
+
+impl PolicyRun {
+    /// Plan the changes of the policies, given with the notes matching each of them.
+    ///
+    /// A note matched by several policies is only changed by the first. Notes already
+    /// below the folder of `move_to` stay where they are, and notes whose destination is
+    /// taken are changed but not moved.
+    pub fn plan<F>(matches: &[(&Policy, Vec<&Note>)], vault_of: F) -> Self
+    where
+        F: Fn(&Note) -> PathBuf,
+    {
+        let mut run = Self::default();
+        let mut handled = HashSet::new();
+        let mut destinations = HashSet::new();
+        for (policy, notes) in matches {
+            let notes: Vec<&Note> = notes
+                .iter()
+                .copied()
+                .filter(|note| handled.insert(note.path.clone()))
+                .collect();
+
+            // Only notes whose frontmatter could be read are changed or moved
+            let mut changes: HashMap<String, Vec<String>> = HashMap::new();
+            let plan = plan_changes(&notes, |note, frontmatter| {
+                let applied = policy
+                    .edits
+                    .iter()
+                    .filter(|edit| edit.apply(frontmatter))
+                    .map(describe)
+                    .collect();
+                changes.insert(note.path.clone(), applied);
+            });
+            run.plan.changes.extend(plan.changes);
+
+            for note in notes {
+                let Some(changes) = changes.remove(&note.path) else {
+                    continue;
+                };
+                let mut action = PolicyAction {
+                    policy: policy.name.clone(),
+                    path: note.path.clone(),
+                    changes,
+                    move_to: None,
+                    not_moved: None,
+                };
+                if let Some(folder) = &policy.move_to {
+                    let folder = vault_of(note).join(folder);
+                    let path = Path::new(&note.path);
+                    if let (false, Some(name)) = (path.starts_with(&folder), path.file_name()) {
+                        let target = folder.join(name);
+                        if target.exists() || !destinations.insert(target.clone()) {
+                            action.not_moved = Some(format!("{} already exists", target.display()));
+                        } else {
+                            action.move_to = Some(target.display().to_string());
+                        }
+                    }
+                }
+                if action.changes_file() || action.not_moved.is_some() {
+                    run.actions.push(action);
+                }
+            }
+        }
+        run
+    }
+
+    /// Number of notes the run changes or moves
+    pub fn files(&self) -> usize {
+        self.actions
+            .iter()
+            .filter(|action| action.changes_file())
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files() == 0
+    }
+
+    /// Write the frontmatter changes, then move the notes, returning the number of files
+    /// written and moved
+    pub fn apply(&self) -> Result<(usize, usize)> {
+        let written = self.plan.apply()?;
+        let mut moved = 0;
+        for action in &self.actions {
+            let Some(target) = &action.move_to else {
+                continue;
+            };
+            let target = Path::new(target);
+            if let Some(folder) = target.parent() {
+                fs::create_dir_all(folder)
+                    .with_context(|| format!("Failed to create directory: {}", folder.display()))?;
+            }
+            // The destination may have been created since the run was planned
+            if target.exists() {
+                return Err(anyhow!(
+                    "Not moving {} to {}, the file already exists",
+                    action.path,
+                    target.display()
+                ));
+            }
+            fs::rename(&action.path, target).with_context(|| {
+                format!("Failed to move {} to {}", action.path, target.display())
+            })?;
+            moved += 1;
+        }
+        Ok((written, moved))
+    }
+
+    /// Print how many notes each policy changes, and what it does to each of them
+    pub fn print_report(&self, policies: &[Policy]) {
+        println!("Policy report:");
+        let width = self
+            .actions
+            .iter()
+            .map(|action| action.path.len())
+            .max()
+            .unwrap_or(0);
+        for policy in policies {
+            let actions: Vec<&PolicyAction> = self
+                .actions
+                .iter()
+                .filter(|action| action.policy == policy.name)
+                .collect();
+            let files = actions
+                .iter()
+                .filter(|action| action.changes_file())
+                .count();
+            println!("  {:<50} {:>6} files", policy.name, files);
+            for action in actions {
+                let mut steps = action.changes.clone();
+                steps.extend(
+                    action
+                        .move_to
+                        .iter()
+                        .map(|target| format!("move to {}", target)),
+                );
+                steps.extend(
+                    action
+                        .not_moved
+                        .iter()
+                        .map(|reason| format!("not moved, {}", reason)),
+                );
+                println!(
+                    "    {:<width$}  {}",
+                    action.path,
+                    steps.join(", "),
+                    width = width
+                );
+            }
+        }
+    }
+
+    pub fn report_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(&self.actions)?)
+    }
+}
+
+fn describe(edit: &FrontmatterEdit) -> String {
+    match edit {
+        FrontmatterEdit::Set(field, value) => format!("set {}: {}", field, yaml_to_string(value)),
+        FrontmatterEdit::Add(field, value) => {
+            format!("add {} to {}", yaml_to_string(value), field)
+        }
+        FrontmatterEdit::Remove(field) => format!("remove {}", field),
+        FrontmatterEdit::RemoveValue(field, value) => {
+            format!("remove {} from {}", yaml_to_string(value), field)
+        }
+        other => format!("{:?}", other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::FilterCriteria;
+    use std::collections::HashMap as Frontmatter;
+    use tempfile::TempDir;
+    use yaml_rust2::Yaml;
+
+    #[test]
+    fn test_parse_age() {
+        assert_eq!(parse_age("180d").unwrap(), TimeDelta::days(180));
+        assert_eq!(parse_age("6w").unwrap(), TimeDelta::days(42));
+        assert_eq!(parse_age(" 1y ").unwrap(), TimeDelta::days(365));
+        assert!(parse_age("d").is_err());
+        assert!(parse_age("3 months").is_err());
+
+        let now =
+            NaiveDateTime::parse_from_str("2024-07-01 12:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        let filter = older_than("file.mtime", "30d", now).unwrap();
+        assert_eq!(filter.op, FilterOp::Lt);
+        assert_eq!(filter.value, "2024-06-01T12:00:00");
+    }
+
+    #[test]
+    fn test_policy_run() {
+        let temp_dir = TempDir::new().unwrap();
+        let vault = temp_dir.path();
+        fs::create_dir_all(vault.join("projects")).unwrap();
+        fs::create_dir_all(vault.join("Archive/2023")).unwrap();
+        for (path, status) in [
+            ("projects/done.md", "done"),
+            ("projects/open.md", "open"),
+            ("projects/taken.md", "done"),
+            ("Archive/taken.md", "done"),
+            ("Archive/2023/old.md", "done"),
+        ] {
+            fs::write(
+                vault.join(path),
+                format!("---\nstatus: {}\n---\nBody\n", status),
+            )
+            .unwrap();
+        }
+        let notes: Vec<Note> = [
+            ("projects/done.md", "done"),
+            ("projects/open.md", "open"),
+            ("projects/taken.md", "done"),
+            ("Archive/taken.md", "done"),
+            ("Archive/2023/old.md", "done"),
+        ]
+        .iter()
+        .map(|(path, status)| {
+            let frontmatter =
+                Frontmatter::from([("status".to_string(), Yaml::String(status.to_string()))]);
+            Note::new(vault.join(path).display().to_string(), frontmatter)
+        })
+        .collect();
+
+        let archive = Policy::new(
+            "archive",
+            vec![FieldFilter::parse("status=done").unwrap()],
+            vec![FrontmatterEdit::Set(
+                "archived".to_string(),
+                Yaml::Boolean(true),
+            )],
+            Some(PathBuf::from("Archive")),
+        )
+        .unwrap();
+        let flag = Policy::new(
+            "flag",
+            Vec::new(),
+            vec![FrontmatterEdit::Add(
+                "tags".to_string(),
+                Yaml::String("review".to_string()),
+            )],
+            None,
+        )
+        .unwrap();
+        let matching = |policy: &Policy| {
+            FilterCriteria::new(policy.filters.clone())
+                .apply_filters(&notes)
+                .into_iter()
+                .collect::<Vec<_>>()
+        };
+        let matches = [(&archive, matching(&archive)), (&flag, matching(&flag))];
+        let run = PolicyRun::plan(&matches, |_| vault.to_path_buf());
+
+        let done = &run.actions[0];
+        assert_eq!(done.changes, vec!["set archived: true".to_string()]);
+        assert_eq!(
+            done.move_to,
+            Some(vault.join("Archive/done.md").display().to_string())
+        );
+        // Changed, but its destination is taken
+        let taken = &run.actions[1];
+        assert!(taken.path.ends_with("projects/taken.md"));
+        assert_eq!(taken.move_to, None);
+        assert!(taken
+            .not_moved
+            .as_ref()
+            .unwrap()
+            .ends_with("already exists"));
+        // Already archived notes stay where they are
+        assert!(run.actions[2..4]
+            .iter()
+            .all(|action| action.move_to.is_none()));
+        // The open note is left to the next policy
+        assert_eq!(run.actions[4].policy, "flag");
+        assert!(run.actions[4].path.ends_with("projects/open.md"));
+        assert_eq!(run.actions.len(), 5);
+        assert_eq!(run.files(), 5);
+
+        assert_eq!(run.apply().unwrap(), (5, 1));
+        assert!(!vault.join("projects/done.md").exists());
+        assert_eq!(
+            fs::read_to_string(vault.join("Archive/done.md")).unwrap(),
+            "---\nstatus: done\narchived: true\n---\nBody\n"
+        );
+
+        assert!(Policy::new("noop", Vec::new(), Vec::new(), None).is_err());
+        assert!(Policy::new(
+            "escape",
+            Vec::new(),
+            Vec::new(),
+            Some(PathBuf::from("../elsewhere"))
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,87 @@
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Width of the bar in characters
+const BAR_WIDTH: usize = 30;
+
+/// A progress bar on stderr for the files parsed so far, updated from any thread.
+///
+/// It stays hidden unless both stdout and stderr are terminals, so piped output and logs
+/// don't fill up with redraws.
+pub struct Progress {
+    total: usize,
+    done: AtomicUsize,
+    /// Last percentage drawn, to redraw at most once per percent
+    drawn: AtomicUsize,
+    visible: bool,
+}
+
+impl Progress {
+    pub fn new(total: usize, visible: bool) -> Self {
+        Self {
+            total,
+            done: AtomicUsize::new(0),
+            drawn: AtomicUsize::new(0),
+            visible: visible
+                && total > 0
+                && io::stdout().is_terminal()
+                && io::stderr().is_terminal(),
+        }
+    }
+
+    /// Count one more file as done
+    pub fn tick(&self) {
+        if !self.visible {
+            return;
+        }
+        let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+        let percent = done * 100 / self.total;
+        if percent > self.drawn.fetch_max(percent, Ordering::Relaxed) {
+            let mut stderr = io::stderr().lock();
+            let _ = write!(stderr, "\r{}", render(done, self.total));
+            let _ = stderr.flush();
+        }
+    }
+
+    /// Clear the bar so the summary starts on an empty line
+    pub fn finish(&self) {
+        if self.visible {
+            let mut stderr = io::stderr().lock();
+            let _ = write!(stderr, "\r\x1b[2K");
+            let _ = stderr.flush();
+        }
+    }
+}
+
+/// The bar for `done` of `total` files, like `[#####     ] 500/1000 files`
+fn render(done: usize, total: usize) -> String {
+    let filled = (done * BAR_WIDTH).checked_div(total).unwrap_or(BAR_WIDTH);
+    format!(
+        "[{}{}] {}/{} files",
+        "#".repeat(filled),
+        " ".repeat(BAR_WIDTH - filled),
+        done,
+        total
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        assert_eq!(render(0, 10), format!("[{}] 0/10 files", " ".repeat(30)));
+        assert_eq!(
+            render(5, 10),
+            format!("[{}{}] 5/10 files", "#".repeat(15), " ".repeat(15))
+        );
+        assert_eq!(render(10, 10), format!("[{}] 10/10 files", "#".repeat(30)));
+
+        // Hidden bars count nothing and print nothing
+        let progress = Progress::new(10, false);
+        progress.tick();
+        progress.finish();
+        assert_eq!(progress.done.load(Ordering::Relaxed), 0);
+    }
+}
@@ -0,0 +1,223 @@
+use crate::config::{Config, CONFIG_FILE_NAME};
+use aktenfux::exec::shell_quote;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml_edit::{DocumentMut, Item, Value};
+
+/// A named filter for `aktenfux query run`, kept as `[queries.<name>]` in aktenfux.toml:
+///
+/// ```toml
+/// [queries.open-tasks]
+/// filter = ["type=task", "status=open"]
+/// sort = "due"
+/// format = "paths"
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct SavedQuery {
+    /// Filters like `--filter`, e.g. `status!=done`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub filter: Vec<String>,
+    /// Presets whose filters are added, like `--preset`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub preset: Vec<String>,
+    /// Fields that must be set, like `--has`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub has: Vec<String>,
+    /// Fields that must be missing or empty, like `--missing`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub missing: Vec<String>,
+    /// Text the body must contain, like `--content`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(skip_serializing_if = "is_false")]
+    pub regex: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub ignore_case: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub exact: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub include_inline_tags: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort: Option<String>,
+    #[serde(skip_serializing_if = "is_false")]
+    pub desc: bool,
+    /// Output format used unless `--format` is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+}
+
+// serde hands `skip_serializing_if` a reference
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+impl SavedQuery {
+    /// The query as `aktenfux filter` arguments, for listing saved queries
+    pub fn arguments(&self) -> String {
+        let lists = [
+            ("--filter", &self.filter),
+            ("--preset", &self.preset),
+            ("--has", &self.has),
+            ("--missing", &self.missing),
+        ];
+        let options = [
+            ("--content", self.content.as_ref()),
+            ("--sort", self.sort.as_ref()),
+            ("--format", self.format.as_ref()),
+        ];
+        let flags = [
+            ("--regex", self.regex),
+            ("--ignore-case", self.ignore_case),
+            ("--exact", self.exact),
+            ("--include-inline-tags", self.include_inline_tags),
+            ("--desc", self.desc),
+        ];
+
+        let mut arguments: Vec<String> = Vec::new();
+        for (option, values) in lists {
+            for value in values {
+                arguments.push(format!("{} {}", option, shell_quote(value)));
+            }
+        }
+        for (option, value) in options {
+            if let Some(value) = value {
+                arguments.push(format!("{} {}", option, shell_quote(value)));
+            }
+        }
+        for (flag, set) in flags {
+            if set {
+                arguments.push(flag.to_string());
+            }
+        }
+        arguments.join(" ")
+    }
+}
+
+/// Add a query to the aktenfux.toml of a vault, creating the file when there is none.
+///
+/// A new query is appended as its own table, so comments and formatting of the file stay as
+/// they are. A query of the same name is an error unless `replace` is set, then its table is
+/// replaced where it is. Returns the path of the file.
+pub fn save(vault_path: &Path, name: &str, query: &SavedQuery, replace: bool) -> Result<PathBuf> {
+    let path = vault_path.join(CONFIG_FILE_NAME);
+    let mut content = if path.is_file() {
+        fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read config: {}", path.display()))?
+    } else {
+        String::new()
+    };
+
+    let mut document: DocumentMut = content
+        .parse()
+        .with_context(|| format!("Failed to parse config: {}", path.display()))?;
+    if let Some(existing) = document
+        .get_mut("queries")
+        .and_then(|queries| queries.get_mut(name))
+    {
+        if !replace {
+            return Err(anyhow!(
+                "Query '{}' already exists in {}, pass --force to replace it",
+                name,
+                path.display()
+            ));
+        }
+        let mut table = toml::to_string(query)?
+            .parse::<DocumentMut>()?
+            .as_table()
+            .clone();
+        if let Some(old) = existing.as_table() {
+            table.set_position(old.position().unwrap_or_default());
+            *table.decor_mut() = old.decor().clone();
+            *existing = Item::Table(table);
+        } else {
+            *existing = Item::Value(Value::InlineTable(table.into_inline_table()));
+        }
+        content = document.to_string();
+    } else {
+        if !content.is_empty() {
+            if !content.ends_with('\n') {
+                content.push('\n');
+            }
+            content.push('\n');
+        }
+        content.push_str(&toml::to_string(&BTreeMap::from([(
+            "queries",
+            BTreeMap::from([(name, query)]),
+        )]))?);
+    }
+
+    // Refuse to write a file the next run couldn't read
+    Config::from_toml(&content)
+        .with_context(|| format!("Cannot add query '{}' to {}", name, path.display()))?;
+    fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_save_query() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join(CONFIG_FILE_NAME),
+            "# Shared settings\nformat = \"table\"",
+        )
+        .unwrap();
+        let query = SavedQuery {
+            filter: vec!["type=task".to_string(), "status=open".to_string()],
+            sort: Some("due".to_string()),
+            desc: true,
+            ..SavedQuery::default()
+        };
+
+        let path = save(temp_dir.path(), "open-tasks", &query, false).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(
+            content.starts_with("# Shared settings\nformat = \"table\"\n\n[queries.open-tasks]\n")
+        );
+        let (config, _) = Config::load(Some(temp_dir.path())).unwrap();
+        assert_eq!(config.queries["open-tasks"], query);
+        assert_eq!(
+            query.arguments(),
+            "--filter 'type=task' --filter 'status=open' --sort due --desc"
+        );
+
+        // Saving a name again needs `replace`
+        let error = save(temp_dir.path(), "open-tasks", &query, false).unwrap_err();
+        assert!(error.to_string().contains("already exists"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+    }
+
+    #[test]
+    fn test_replace_query() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join(CONFIG_FILE_NAME),
+            "# Shared settings\nformat = \"table\"\n\n# Tasks due soon\n[queries.open-tasks]\nfilter = [\"type=task\"]\n\n[queries.drafts]\nfilter = [\"status=draft\"]\n",
+        )
+        .unwrap();
+        let query = SavedQuery {
+            filter: vec!["type=task".to_string(), "status=open".to_string()],
+            sort: Some("due".to_string()),
+            ..SavedQuery::default()
+        };
+
+        let path = save(temp_dir.path(), "open-tasks", &query, true).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# Shared settings\nformat = \"table\"\n\n# Tasks due soon\n[queries.open-tasks]\nfilter = [\"type=task\", \"status=open\"]\nsort = \"due\"\n\n[queries.drafts]\nfilter = [\"status=draft\"]\n"
+        );
+        let (config, _) = Config::load(Some(temp_dir.path())).unwrap();
+        assert_eq!(config.queries["open-tasks"], query);
+        assert_eq!(config.queries.len(), 2);
+    }
+}
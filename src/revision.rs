@@ -0,0 +1,217 @@
+use crate::hook::is_markdown;
+use anyhow::{anyhow, Context, Result};
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+
+/// The notes below a folder as they were in a git revision, read from the repository
+/// instead of the working tree
+#[derive(Debug, Clone)]
+pub struct Revision {
+    /// The commit the revision resolved to
+    pub commit: String,
+    /// The content of every markdown file by its path relative to the folder
+    files: BTreeMap<PathBuf, String>,
+}
+
+impl Revision {
+    /// Read the markdown files below `dir` as they were at `git_ref` (a commit, branch, tag
+    /// or something like `main@{3 months ago}`), like `git show <ref>:<path>` shows them
+    pub fn load(dir: &Path, git_ref: &str) -> Result<Self> {
+        let commit = git(
+            dir,
+            &["rev-parse", "--verify", &format!("{}^{{commit}}", git_ref)],
+        )
+        .map_err(|e| anyhow!("Unknown git revision '{}': {}", git_ref, e))?
+        .trim()
+        .to_string();
+
+        // Without --full-tree, ls-tree lists the files below `dir`, relative to it
+        let paths: Vec<PathBuf> = git(dir, &["ls-tree", "-r", "-z", "--name-only", &commit])?
+            .split('\0')
+            .filter(|path| !path.is_empty() && !path.contains('\n'))
+            .map(PathBuf::from)
+            .filter(|path| is_markdown(path))
+            .collect();
+        let contents = read_blobs(dir, &commit, &paths)?;
+        Ok(Self {
+            commit,
+            files: paths
+                .into_iter()
+                .zip(contents)
+                .filter_map(|(path, content)| Some((path, content?)))
+                .collect(),
+        })
+    }
+
+    /// Paths of the markdown files, relative to the folder
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.files.keys().map(PathBuf::as_path)
+    }
+
+    /// The content of a file in the revision, by its path relative to the folder
+    pub fn read(&self, relative: &Path) -> Result<&str> {
+        self.files.get(relative).map(String::as_str).ok_or_else(|| {
+            anyhow!(
+                "Failed to read file: {} is not in commit {}",
+                relative.display(),
+                &self.commit[..self.commit.len().min(12)]
+            )
+        })
+    }
+}
+
+/// The staged content of files below `dir`, by their paths relative to it, like
+/// `git show :<path>` shows them. Files that aren't in the index are `None`.
+pub fn read_index(dir: &Path, paths: &[PathBuf]) -> Result<Vec<Option<String>>> {
+    read_blobs(dir, "", paths)
+}
+
+/// Whether `dir` is in the work tree of a git repository
+pub fn in_work_tree(dir: &Path) -> bool {
+    git(dir, &["rev-parse", "--is-inside-work-tree"]).is_ok_and(|out| out.trim() == "true")
+}
+
+/// Run git in `dir` and return its output
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// The contents of files at a commit, or in the index for an empty `commit`, in the order
+/// of `paths`, read in a single run of `git cat-file --batch`
+fn read_blobs(dir: &Path, commit: &str, paths: &[PathBuf]) -> Result<Vec<Option<String>>> {
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["cat-file", "--batch"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .context("Failed to run git")?;
+    let mut stdin = child.stdin.take().context("Failed to run git")?;
+    let mut stdout = BufReader::new(child.stdout.take().context("Failed to run git")?);
+
+    // Write the requests while reading the answers, so neither pipe fills up
+    let requests: Vec<String> = paths
+        .iter()
+        .map(|path| format!("{}:./{}\n", commit, path.to_string_lossy()))
+        .collect();
+    let writer = thread::spawn(move || -> std::io::Result<()> {
+        for request in requests {
+            stdin.write_all(request.as_bytes())?;
+        }
+        Ok(())
+    });
+
+    let mut contents = Vec::with_capacity(paths.len());
+    for path in paths {
+        let mut header = String::new();
+        stdout.read_line(&mut header)?;
+        if header.trim_end().ends_with(" missing") {
+            contents.push(None);
+            continue;
+        }
+        let size: usize = header
+            .trim_end()
+            .rsplit(' ')
+            .next()
+            .and_then(|size| size.parse().ok())
+            .ok_or_else(|| {
+                anyhow!(
+                    "Failed to read {} from git: {}",
+                    path.display(),
+                    header.trim()
+                )
+            })?;
+        // The content is followed by a newline
+        let mut content = vec![0; size + 1];
+        stdout.read_exact(&mut content)?;
+        content.pop();
+        contents.push(Some(String::from_utf8_lossy(&content).to_string()));
+    }
+    writer
+        .join()
+        .map_err(|_| anyhow!("Failed to write to git"))??;
+    child.wait()?;
+    Ok(contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn commit(dir: &Path, message: &str) {
+        for args in [
+            vec!["add", "-A"],
+            vec![
+                "-c",
+                "user.name=Test",
+                "-c",
+                "user.email=test@example.com",
+                "commit",
+                "-q",
+                "-m",
+                message,
+            ],
+        ] {
+            assert!(Command::new("git")
+                .arg("-C")
+                .arg(dir)
+                .args(args)
+                .status()
+                .unwrap()
+                .success());
+        }
+    }
+
+    #[test]
+    fn test_load_revision() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = temp_dir.path();
+        assert!(Command::new("git")
+            .args(["init", "-q"])
+            .arg(repo)
+            .status()
+            .unwrap()
+            .success());
+        let vault = repo.join("vault");
+        fs::create_dir_all(vault.join("projects")).unwrap();
+        fs::write(repo.join("outside.md"), "not in the vault\n").unwrap();
+        fs::write(vault.join("projects/plan.md"), "---\nstatus: active\n---\n").unwrap();
+        fs::write(vault.join("image.png"), "png").unwrap();
+        commit(repo, "first");
+        fs::write(vault.join("projects/plan.md"), "---\nstatus: done\n---\n").unwrap();
+        fs::write(vault.join("new.md"), "new\n").unwrap();
+        commit(repo, "second");
+
+        let revision = Revision::load(&vault, "HEAD~1").unwrap();
+        assert_eq!(
+            revision.paths().collect::<Vec<_>>(),
+            vec![Path::new("projects/plan.md")]
+        );
+        assert_eq!(
+            revision.read(Path::new("projects/plan.md")).unwrap(),
+            "---\nstatus: active\n---\n"
+        );
+        assert!(revision.read(Path::new("new.md")).is_err());
+
+        assert_eq!(Revision::load(&vault, "HEAD").unwrap().paths().count(), 2);
+        assert!(Revision::load(&vault, "no-such-branch").is_err());
+    }
+}
@@ -1,34 +1,429 @@
-use crate::frontmatter::{parse_frontmatter_from_file, Note, ParseResult};
-use crate::logger::Logger;
+use crate::created::{first_commit_dates, set_effective_created, CreatedSource};
+use crate::exec::{self, ExecCommand};
+use crate::frontmatter::{
+    parse_frontmatter_from_file, parse_frontmatter_from_str, parse_frontmatter_head, Fences, Note,
+    ParseResult, ParseStatus,
+};
+use crate::hints::ScanPhases;
+use crate::ignore::IgnoreRules;
+use crate::join::Join;
+use crate::logger::{LogFormat, LogSender, Logger, SummaryFormat, NOT_LOCAL, PERMISSION_DENIED};
+use crate::progress::Progress;
+use crate::revision::Revision;
 use anyhow::Result;
+use chrono::{DateTime, Local};
 use rayon::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
 use walkdir::WalkDir;
+use yaml_rust2::Yaml;
 
+/// Notes found by a scan, and how many files could not be read or parsed at all
+#[derive(Debug)]
+pub struct ScanOutcome {
+    pub notes: Vec<Note>,
+    pub critical_errors: usize,
+    /// Number of files per warning category, like the scan summary lists them
+    pub warnings: BTreeMap<String, usize>,
+}
+
+/// A vault root and the name its notes are tagged with
+#[derive(Debug, Clone)]
+struct Vault {
+    name: String,
+    path: PathBuf,
+    /// First commit dates of the files in the vault, read from git when first needed, and
+    /// how long reading them took
+    git_dates: OnceLock<(HashMap<PathBuf, String>, Duration)>,
+    /// The `.gitignore` and Obsidian excluded files of the vault
+    ignore_rules: IgnoreRules,
+    /// The git revision the notes are read from instead of the working tree
+    revision: Option<Revision>,
+}
+
+/// Which notes a scan reads past the frontmatter, for their inline tags, links and body
+#[derive(Clone, Default)]
+pub enum BodyScan {
+    /// None, notes are read up to the end of their frontmatter
+    #[default]
+    Frontmatter,
+    /// Every note
+    All,
+    /// The notes passing a check of their frontmatter and virtual fields, like the
+    /// frontmatter filters of a content search
+    Matching(Arc<dyn Fn(&Note) -> bool + Send + Sync>),
+}
+
+/// Indexes files other than markdown notes, like `.canvas` files, as notes
+pub trait FileHandler: Send + Sync {
+    /// Whether the handler parses this file
+    fn handles(&self, path: &Path) -> bool;
+
+    /// Parse a file into a note, keeping its text as the body when asked to
+    fn parse(&self, path: &Path, keep_body: bool) -> Result<Note>;
+}
+
+#[allow(clippy::struct_excessive_bools)]
 pub struct VaultScanner {
-    vault_path: PathBuf,
+    vaults: Vec<Vault>,
+    list_warning_files: bool,
+    excludes: IgnoreRules,
+    ignore_files: bool,
+    keep_bodies: bool,
+    body_scan: BodyScan,
+    timings: Option<usize>,
+    hints: Option<Duration>,
+    fences: Fences,
+    created_sources: Vec<CreatedSource>,
+    joins: Vec<Join>,
+    files: Option<Vec<PathBuf>>,
+    handlers: Vec<Box<dyn FileHandler>>,
+    progress: bool,
+    threads: Option<NonZeroUsize>,
+    log_format: LogFormat,
+    summary_format: SummaryFormat,
+    warnings_out: Option<PathBuf>,
+    follow_symlinks: bool,
+    max_depth: Option<usize>,
+    one_file_system: bool,
+    canonical_keys: HashMap<String, String>,
+    download_hook: Option<ExecCommand>,
+    skip_placeholders: bool,
 }
 
 impl VaultScanner {
     pub fn new<P: AsRef<Path>>(vault_path: P) -> Result<Self> {
-        let vault_path = vault_path.as_ref().to_path_buf();
+        let vault_path = vault_path.as_ref();
+        Self::with_vaults(vec![(vault_name(vault_path), vault_path.to_path_buf())])
+    }
+
+    /// Scan several vaults as one. When there is more than one, every note is tagged with
+    /// the name of its vault (`Note::vault` and the `file.vault` field).
+    pub fn with_vaults(vaults: Vec<(String, PathBuf)>) -> Result<Self> {
+        for (_, vault_path) in &vaults {
+            if !vault_path.exists() {
+                return Err(anyhow::anyhow!(
+                    "Vault path does not exist: {}",
+                    vault_path.display()
+                ));
+            }
+
+            if !vault_path.is_dir() {
+                return Err(anyhow::anyhow!(
+                    "Vault path is not a directory: {}",
+                    vault_path.display()
+                ));
+            }
+        }
+
+        Ok(Self {
+            vaults: vaults
+                .into_iter()
+                .map(|(name, path)| Vault {
+                    name,
+                    ignore_rules: IgnoreRules::from_vault(&path),
+                    path,
+                    git_dates: OnceLock::new(),
+                    revision: None,
+                })
+                .collect(),
+            list_warning_files: false,
+            excludes: IgnoreRules::default(),
+            ignore_files: true,
+            keep_bodies: false,
+            body_scan: BodyScan::default(),
+            timings: None,
+            hints: None,
+            fences: Fences::default(),
+            created_sources: CreatedSource::DEFAULT.to_vec(),
+            joins: Vec::new(),
+            files: None,
+            handlers: Vec::new(),
+            progress: false,
+            threads: None,
+            log_format: LogFormat::default(),
+            summary_format: SummaryFormat::default(),
+            warnings_out: None,
+            follow_symlinks: false,
+            max_depth: None,
+            one_file_system: false,
+            canonical_keys: HashMap::new(),
+            download_hook: None,
+            skip_placeholders: false,
+        })
+    }
+
+    /// List the files behind each warning category in the scan summary
+    #[must_use]
+    pub fn with_warning_files(mut self, list_warning_files: bool) -> Self {
+        self.list_warning_files = list_warning_files;
+        self
+    }
+
+    /// Skip files and directories matching globs in `.gitignore` syntax: by name at any
+    /// depth (`templates`, `*.excalidraw.md`) or by path relative to the vault (`archive/old`)
+    #[must_use]
+    pub fn with_excludes(mut self, excludes: Vec<String>) -> Self {
+        self.excludes = IgnoreRules::from_globs(&excludes);
+        self
+    }
+
+    /// Skip what the `.gitignore` and the Obsidian excluded files of each vault list (the
+    /// default)
+    #[must_use]
+    pub fn with_ignore_files(mut self, ignore_files: bool) -> Self {
+        self.ignore_files = ignore_files;
+        self
+    }
+
+    /// Keep the body of every note whose body is read for content searches, see
+    /// `with_body_scan`
+    #[must_use]
+    pub fn with_bodies(mut self, keep_bodies: bool) -> Self {
+        self.keep_bodies = keep_bodies;
+        self
+    }
+
+    /// Which notes to read past the frontmatter. By default only the frontmatter is read,
+    /// leaving inline tags, links and bodies empty.
+    #[must_use]
+    pub fn with_body_scan(mut self, body_scan: BodyScan) -> Self {
+        self.body_scan = body_scan;
+        self
+    }
+
+    /// Time every file and report the given number of slowest ones after the scan
+    #[must_use]
+    pub fn with_timings(mut self, timings: Option<usize>) -> Self {
+        self.timings = timings;
+        self
+    }
+
+    /// Read the notes as they were in a git revision instead of from the working tree. Only
+    /// markdown notes are read, and they have no `file.size`, `file.mtime` or `file.ctime`.
+    pub fn at_revision(mut self, git_ref: &str) -> Result<Self> {
+        for vault in &mut self.vaults {
+            vault.revision = Some(Revision::load(&vault.path, git_ref)?);
+        }
+        Ok(self)
+    }
+
+    /// Print hints to speed up scans that take longer than this
+    #[must_use]
+    pub const fn with_hints(mut self, hints: Option<Duration>) -> Self {
+        self.hints = hints;
+        self
+    }
+
+    /// Which lines open and close the frontmatter block
+    #[must_use]
+    pub fn with_fences(mut self, fences: Fences) -> Self {
+        self.fences = fences;
+        self
+    }
+
+    /// Where to look for the creation date of notes without `created`, in this order
+    #[must_use]
+    pub fn with_created_sources(mut self, created_sources: Vec<CreatedSource>) -> Self {
+        self.created_sources = created_sources;
+        self
+    }
+
+    /// Frontmatter keys to read under another name, like `Tag` as `tags`, see
+    /// `Note::canonicalize_keys`
+    #[must_use]
+    pub fn with_canonical_keys(mut self, canonical_keys: HashMap<String, String>) -> Self {
+        self.canonical_keys = canonical_keys;
+        self
+    }
+
+    /// Run a command for every file that is not locally available, like a cloud placeholder,
+    /// before parsing it, with `{}` replaced by its path (appended when missing)
+    #[must_use]
+    pub fn with_download_hook(mut self, download_hook: Option<String>) -> Self {
+        self.download_hook = download_hook.as_deref().map(ExecCommand::new);
+        self
+    }
+
+    /// Leave out the files that are not locally available without reporting them
+    #[must_use]
+    pub fn with_skip_placeholders(mut self, skip_placeholders: bool) -> Self {
+        self.skip_placeholders = skip_placeholders;
+        self
+    }
+
+    /// Lookup tables to join onto every note, see `Join`
+    #[must_use]
+    pub fn with_joins(mut self, joins: Vec<Join>) -> Self {
+        self.joins = joins;
+        self
+    }
+
+    /// Parse exactly these files instead of walking the vaults. Files inside a vault get
+    /// the `file.*` fields relative to it, excludes and ignore files don't apply.
+    #[must_use]
+    pub fn with_files(mut self, files: Option<Vec<PathBuf>>) -> Self {
+        self.files = files;
+        self
+    }
+
+    /// Also index the files a handler parses, next to the markdown notes
+    #[must_use]
+    pub fn with_handler(mut self, handler: Box<dyn FileHandler>) -> Self {
+        self.handlers.push(handler);
+        self
+    }
+
+    fn handler_for(&self, path: &Path) -> Option<&dyn FileHandler> {
+        self.handlers
+            .iter()
+            .find(|handler| handler.handles(path))
+            .map(AsRef::as_ref)
+    }
+
+    /// Show a progress bar on stderr while parsing, unless the scan is silent or verbose or
+    /// the output isn't a terminal
+    #[must_use]
+    pub fn with_progress(mut self, progress: bool) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Parse on this many threads instead of one per core
+    #[must_use]
+    pub fn with_threads(mut self, threads: Option<NonZeroUsize>) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Write the parsing messages to stderr as JSON lines instead of text
+    #[must_use]
+    pub fn with_log_format(mut self, log_format: LogFormat) -> Self {
+        self.log_format = log_format;
+        self
+    }
 
-        if !vault_path.exists() {
-            return Err(anyhow::anyhow!(
-                "Vault path does not exist: {}",
-                vault_path.display()
-            ));
+    /// Print the summary after the scan as a single line, as text or as JSON
+    #[must_use]
+    pub const fn with_summary_format(mut self, summary_format: SummaryFormat) -> Self {
+        self.summary_format = summary_format;
+        self
+    }
+
+    /// Also write all parsing messages to this file as JSON after each scan
+    #[must_use]
+    pub fn with_warnings_out(mut self, warnings_out: Option<PathBuf>) -> Self {
+        self.warnings_out = warnings_out;
+        self
+    }
+
+    /// Descend into symlinked folders and parse symlinked notes. A link back to one of its
+    /// own parent folders is reported as a warning and not followed.
+    #[must_use]
+    pub fn with_follow_symlinks(mut self, follow_symlinks: bool) -> Self {
+        self.follow_symlinks = follow_symlinks;
+        self
+    }
+
+    /// Only find files this many folders deep, 1 being the files in the vault root
+    #[must_use]
+    pub fn with_max_depth(mut self, max_depth: Option<usize>) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Don't descend into folders on another file system than the vault root, like mounts
+    #[must_use]
+    pub fn with_one_file_system(mut self, one_file_system: bool) -> Self {
+        self.one_file_system = one_file_system;
+        self
+    }
+
+    /// A walk over a vault root with the symlink, depth and file system settings
+    fn walk(&self, root: &Path) -> WalkDir {
+        let walk = WalkDir::new(root)
+            .follow_links(self.follow_symlinks)
+            .same_file_system(self.one_file_system);
+        match self.max_depth {
+            Some(max_depth) => walk.max_depth(max_depth),
+            None => walk,
         }
+    }
+
+    /// The notes of a vault in a revision, with the files and folders a walk of the vault
+    /// would skip left out
+    fn revision_files(&self, vault: &Vault, revision: &Revision) -> Vec<PathBuf> {
+        revision
+            .paths()
+            .filter(|relative| {
+                self.max_depth
+                    .is_none_or(|max_depth| relative.components().count() <= max_depth)
+                    && !relative
+                        .file_name()
+                        .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+                    && !relative
+                        .ancestors()
+                        .skip(1)
+                        .filter(|folder| !folder.as_os_str().is_empty())
+                        .any(|folder| self.is_excluded(vault, &vault.path.join(folder), true))
+                    && !self.is_excluded(vault, &vault.path.join(relative), false)
+            })
+            .map(|relative| vault.path.join(relative))
+            .collect()
+    }
 
-        if !vault_path.is_dir() {
-            return Err(anyhow::anyhow!(
-                "Vault path is not a directory: {}",
-                vault_path.display()
-            ));
+    /// Print hints for the phases of a scan that took long, when asked for with `with_hints`
+    fn print_hints(&self, walk: Duration, parse: Duration, files: usize) {
+        let Some(threshold) = self.hints else {
+            return;
+        };
+        ScanPhases {
+            walk,
+            parse,
+            git: self
+                .vaults
+                .iter()
+                .filter_map(|vault| vault.git_dates.get())
+                .map(|(_, duration)| *duration)
+                .sum(),
+            files,
+            listed: self.files.is_some(),
+            filtered_bodies: matches!(self.body_scan, BodyScan::Matching(_)),
+            threads: self
+                .threads
+                .map_or_else(rayon::current_num_threads, NonZeroUsize::get),
+            cores: std::thread::available_parallelism().map_or(1, NonZeroUsize::get),
         }
+        .print_hints(threshold);
+    }
+
+    /// Run parallel work on a pool of the configured size, or on the global pool
+    fn in_pool<R, F>(&self, work: F) -> Result<R>
+    where
+        R: Send,
+        F: FnOnce() -> R + Send,
+    {
+        match self.threads {
+            Some(threads) => Ok(rayon::ThreadPoolBuilder::new()
+                .num_threads(threads.get())
+                .build()?
+                .install(work)),
+            None => Ok(work()),
+        }
+    }
 
-        Ok(Self { vault_path })
+    fn is_excluded(&self, vault: &Vault, path: &Path, is_dir: bool) -> bool {
+        let Ok(relative) = path.strip_prefix(&vault.path) else {
+            return false;
+        };
+        self.excludes.is_ignored(relative, is_dir)
+            || (self.ignore_files && vault.ignore_rules.is_ignored(relative, is_dir))
     }
 
     pub fn scan_vault(
@@ -37,90 +432,570 @@ impl VaultScanner {
         silent: bool,
         lenient: bool,
         format: Option<&str>,
-    ) -> Result<Vec<Note>> {
-        let mut logger = Logger::new(verbose, silent);
+    ) -> Result<ScanOutcome> {
+        let start = Instant::now();
+        let (markdown_files, logger) = self.start_scan(verbose, silent);
+        let walked = start.elapsed();
+        let parse_start = Instant::now();
+        let progress = Progress::new(markdown_files.len(), self.progress && !silent && !verbose);
 
-        logger.log_info(
-            format!("Scanning vault: {}", self.vault_path.display()),
-            None::<&Path>,
-        );
+        // Process files in parallel
+        let (logger, notes) = logger.collect(|log| {
+            self.in_pool(|| {
+                markdown_files
+                    .par_iter()
+                    .filter_map(|(vault, path)| {
+                        let note = self.parse_note(vault, path, verbose, lenient, log);
+                        progress.tick();
+                        note
+                    })
+                    .collect::<Vec<Note>>()
+            })
+        });
+        progress.finish();
+        let notes = notes?;
 
-        // Find all markdown files
-        let markdown_files: Vec<PathBuf> = WalkDir::new(&self.vault_path)
-            .into_iter()
-            .filter_map(|entry| {
-                let entry = entry.ok()?;
-                let path = entry.path();
+        let critical_errors =
+            self.finish_scan(&logger, markdown_files.len(), notes.len(), format)?;
+        if !silent {
+            self.print_hints(walked, parse_start.elapsed(), markdown_files.len());
+        }
+        Ok(ScanOutcome {
+            notes,
+            critical_errors,
+            warnings: logger.warning_counts(),
+        })
+    }
 
-                // Skip hidden files and directories
-                if path.file_name()?.to_str()?.starts_with('.') {
-                    return None;
-                }
+    /// Like `scan_vault`, but hands every note to `visit` as soon as it is parsed instead of
+    /// collecting them, so memory use doesn't grow with the vault. Notes arrive in no
+    /// particular order. Returns the number of critical errors.
+    pub fn stream_vault<F>(
+        &self,
+        verbose: bool,
+        silent: bool,
+        lenient: bool,
+        format: Option<&str>,
+        visit: F,
+    ) -> Result<usize>
+    where
+        F: Fn(Note) -> Result<()> + Sync,
+    {
+        let start = Instant::now();
+        let (markdown_files, logger) = self.start_scan(verbose, silent);
+        let walked = start.elapsed();
+        let parse_start = Instant::now();
+        let progress = Progress::new(markdown_files.len(), self.progress && !silent && !verbose);
 
-                // Only process markdown files
-                if path.extension()?.to_str()? == "md" {
-                    Some(path.to_path_buf())
-                } else {
-                    None
-                }
+        let parsed = AtomicUsize::new(0);
+        let (logger, result) = logger.collect(|log| {
+            self.in_pool(|| {
+                markdown_files
+                    .par_iter()
+                    .filter_map(|(vault, path)| {
+                        let note = self.parse_note(vault, path, verbose, lenient, log);
+                        progress.tick();
+                        note
+                    })
+                    .try_for_each(|note| {
+                        parsed.fetch_add(1, Ordering::Relaxed);
+                        visit(note)
+                    })
             })
-            .collect();
+        });
+        progress.finish();
+        result??;
+
+        let critical_errors =
+            self.finish_scan(&logger, markdown_files.len(), parsed.into_inner(), format)?;
+        if !silent {
+            self.print_hints(walked, parse_start.elapsed(), markdown_files.len());
+        }
+        Ok(critical_errors)
+    }
+
+    /// Find all markdown files and set up the logger collecting the parsing messages
+    fn start_scan(&self, verbose: bool, silent: bool) -> (Vec<(&Vault, PathBuf)>, Logger) {
+        let mut logger = Logger::new(verbose, silent)
+            .with_file_lists(self.list_warning_files)
+            .with_timings(self.timings)
+            .with_log_format(self.log_format)
+            .with_summary_format(self.summary_format);
+
+        if let Some(files) = &self.files {
+            logger.log_info(
+                format!("Parsing {} listed files", files.len()),
+                None::<&Path>,
+            );
+            let markdown_files = files.iter().map(|path| self.locate(path)).collect();
+            return (markdown_files, logger);
+        }
+
+        let mut markdown_files = Vec::new();
+        let mut placeholders = Vec::new();
+        for vault in &self.vaults {
+            if let Some(revision) = &vault.revision {
+                logger.log_info(
+                    format!(
+                        "Scanning vault: {} at commit {}",
+                        vault.path.display(),
+                        revision.commit
+                    ),
+                    None::<&Path>,
+                );
+                markdown_files.extend(
+                    self.revision_files(vault, revision)
+                        .into_iter()
+                        .map(|path| (vault, path)),
+                );
+                continue;
+            }
+            logger.log_info(
+                format!("Scanning vault: {}", vault.path.display()),
+                None::<&Path>,
+            );
+
+            // Find all markdown files
+            markdown_files.extend(
+                self.walk(&vault.path)
+                    .into_iter()
+                    .filter_entry(|entry| {
+                        !self.is_excluded(vault, entry.path(), entry.file_type().is_dir())
+                    })
+                    .filter_map(|entry| {
+                        let entry = match entry {
+                            Ok(entry) => entry,
+                            Err(e) => {
+                                if let (Some(path), Some(ancestor)) = (e.path(), e.loop_ancestor())
+                                {
+                                    logger.log_warning(
+                                        format!(
+                                            "Skipped symlink loop back to {}",
+                                            ancestor.display()
+                                        ),
+                                        Some(path),
+                                    );
+                                } else if let (Some(path), Some(io::ErrorKind::PermissionDenied)) =
+                                    (e.path(), e.io_error().map(io::Error::kind))
+                                {
+                                    logger.log_critical(
+                                        format!("{}, could not read the folder", PERMISSION_DENIED),
+                                        Some(path),
+                                    );
+                                }
+                                return None;
+                            }
+                        };
+                        let path = entry.path();
+
+                        // iCloud keeps notes that are only in the cloud as hidden stubs
+                        if let Some(note_path) = icloud_placeholder_target(path) {
+                            if (note_path.extension().is_some_and(|ext| ext == "md")
+                                || self.handler_for(&note_path).is_some())
+                                && !note_path.exists()
+                            {
+                                placeholders.push((vault, note_path));
+                            }
+                            return None;
+                        }
+
+                        // Skip hidden files and directories
+                        if path.file_name()?.to_str()?.starts_with('.') {
+                            return None;
+                        }
+
+                        // Only process markdown files and files of a handler
+                        if path.extension()?.to_str()? != "md" && self.handler_for(path).is_none() {
+                            return None;
+                        }
+                        // Reading a placeholder would download it, or fail
+                        if is_cloud_placeholder(&entry) {
+                            placeholders.push((vault, path.to_path_buf()));
+                            return None;
+                        }
+                        Some((vault, path.to_path_buf()))
+                    }),
+            );
+        }
+
+        for (vault, note_path) in placeholders {
+            let hook_failure = match &self.download_hook {
+                Some(hook) => match run_download_hook(hook, &note_path) {
+                    None if note_path.is_file() => {
+                        markdown_files.push((vault, note_path));
+                        continue;
+                    }
+                    failure => failure,
+                },
+                None => None,
+            };
+            if !self.skip_placeholders {
+                logger.log_warning(not_local_message(hook_failure), Some(&note_path));
+            }
+        }
 
         logger.log_info(
             format!("Found {} markdown files", markdown_files.len()),
             None::<&Path>,
         );
 
-        // Use Arc<Mutex<Logger>> for thread-safe logging
-        let logger = Arc::new(Mutex::new(logger));
+        (markdown_files, logger)
+    }
 
-        // Process files in parallel
-        let notes: Vec<Note> = markdown_files
-            .par_iter()
-            .filter_map(|path| {
-                match parse_frontmatter_from_file(path, verbose, lenient) {
-                    Ok(ParseResult {
-                        note,
-                        frontmatter_warning,
-                    }) => {
-                        // Log frontmatter warnings if present
-                        if let Some(warning) = frontmatter_warning {
-                            if let Ok(mut logger) = logger.lock() {
-                                logger.log_warning(warning, Some(path));
+    /// The vault a listed file is in and its path below the vault root, like a scan of the
+    /// vault would find it. Files outside all vaults count to the first one, as given.
+    fn locate(&self, path: &Path) -> (&Vault, PathBuf) {
+        fs::canonicalize(path)
+            .ok()
+            .and_then(|canonical| {
+                self.vaults.iter().find_map(|vault| {
+                    let root = fs::canonicalize(&vault.path).ok()?;
+                    let relative = canonical.strip_prefix(root).ok()?;
+                    Some((vault, vault.path.join(relative)))
+                })
+            })
+            .unwrap_or_else(|| (&self.vaults[0], path.to_path_buf()))
+    }
+
+    /// Print the scan summary, write the messages to the warnings file and return the
+    /// number of critical errors
+    fn finish_scan(
+        &self,
+        logger: &Logger,
+        total_files: usize,
+        parsed: usize,
+        format: Option<&str>,
+    ) -> Result<usize> {
+        logger.print_summary(total_files, parsed, format);
+        logger.print_timings();
+        if let Some(path) = &self.warnings_out {
+            logger.write_entries(path)?;
+        }
+        Ok(logger.get_critical_count())
+    }
+
+    fn parse_note(
+        &self,
+        vault: &Vault,
+        path: &Path,
+        verbose: bool,
+        lenient: bool,
+        log: &LogSender,
+    ) -> Option<Note> {
+        let start = Instant::now();
+        let mut result = self.read_note(vault, path, verbose, lenient);
+        let mut hook_failure = None;
+        if let (Err(e), Some(hook)) = (&result, &self.download_hook) {
+            if unreadable(e) == Some(Unreadable::NotLocal) {
+                hook_failure = run_download_hook(hook, path);
+                if hook_failure.is_none() {
+                    result = self.read_note(vault, path, verbose, lenient);
+                }
+            }
+        }
+        if self.timings.is_some() {
+            log.timing(path, start.elapsed());
+        }
+
+        match result {
+            Ok(parsed) => {
+                let ParseResult {
+                    note,
+                    frontmatter_warning,
+                    status,
+                } = parsed;
+                // Log frontmatter warnings if present
+                if let Some(warning) = frontmatter_warning {
+                    log.warning(warning, Some(path));
+                }
+                note.map(|mut note| {
+                    note.set_virtual_field(
+                        "parse.status",
+                        Yaml::String(status.as_str().to_string()),
+                    );
+                    note.canonicalize_keys(&self.canonical_keys);
+                    add_file_fields(&mut note, path, &vault.path);
+                    if vault.revision.is_none() {
+                        add_file_metadata(&mut note, path);
+                    }
+                    set_effective_created(&mut note, &self.created_sources, |note_path| {
+                        let relative = note_path.strip_prefix(&vault.path).ok()?;
+                        vault
+                            .git_dates
+                            .get_or_init(|| {
+                                let start = Instant::now();
+                                (first_commit_dates(&vault.path), start.elapsed())
+                            })
+                            .0
+                            .get(relative)
+                            .cloned()
+                    });
+                    if self.vaults.len() > 1 {
+                        note.set_virtual_field("file.vault", Yaml::String(vault.name.clone()));
+                        note.vault = Some(vault.name.clone());
+                    }
+                    for join in &self.joins {
+                        join.apply(&mut note);
+                    }
+                    if let BodyScan::Matching(check) = &self.body_scan {
+                        if vault.revision.is_none() && check(&note) {
+                            if let Err(e) = note.load_body(self.keep_bodies) {
+                                log.critical(format!("{:#}", e), Some(path));
                             }
                         }
-                        note
                     }
-                    Err(e) => {
-                        if let Ok(mut logger) = logger.lock() {
-                            logger.log_critical(format!("Failed to parse file: {}", e), Some(path));
-                        }
-                        None
+                    note
+                })
+            }
+            Err(e) => {
+                match unreadable(&e) {
+                    Some(Unreadable::PermissionDenied) => log.critical(
+                        format!("{}, could not read the file", PERMISSION_DENIED),
+                        Some(path),
+                    ),
+                    Some(Unreadable::NotLocal) if self.skip_placeholders => {}
+                    Some(Unreadable::NotLocal) => {
+                        log.warning(not_local_message(hook_failure), Some(path));
                     }
+                    None => match e
+                        .chain()
+                        .find_map(|cause| cause.downcast_ref::<io::Error>())
+                    {
+                        Some(error) => {
+                            log.critical(format!("Failed to read file: {}", error), Some(path));
+                        }
+                        None => log.critical(format!("Failed to parse file: {:#}", e), Some(path)),
+                    },
                 }
-            })
-            .collect();
+                None
+            }
+        }
+    }
 
-        // Extract logger from Arc<Mutex<>> for final summary
-        let logger = Arc::try_unwrap(logger)
-            .map_err(|_| anyhow::anyhow!("Failed to unwrap logger"))?
-            .into_inner()
-            .map_err(|_| anyhow::anyhow!("Failed to extract logger from mutex"))?;
+    fn read_note(
+        &self,
+        vault: &Vault,
+        path: &Path,
+        verbose: bool,
+        lenient: bool,
+    ) -> Result<ParseResult> {
+        // The body is kept, as it can't be read from the file later
+        if let Some(revision) = &vault.revision {
+            let relative = path.strip_prefix(&vault.path).unwrap_or(path);
+            return parse_frontmatter_from_str(
+                revision.read(relative)?,
+                path,
+                verbose,
+                lenient,
+                true,
+                self.fences,
+            );
+        }
+        match self.handler_for(path) {
+            Some(handler) => handler
+                .parse(path, self.keep_bodies)
+                .map(|note| ParseResult {
+                    note: Some(note),
+                    frontmatter_warning: None,
+                    status: ParseStatus::Ok,
+                }),
+            None if matches!(self.body_scan, BodyScan::All) => {
+                parse_frontmatter_from_file(path, verbose, lenient, self.keep_bodies, self.fences)
+            }
+            None => parse_frontmatter_head(path, verbose, lenient, self.fences),
+        }
+    }
 
-        logger.print_summary(markdown_files.len(), notes.len(), format);
-        Ok(notes)
+    /// Non-markdown files in the vaults (images, PDFs, ...) that notes may link to or embed
+    pub fn attachment_paths(&self) -> Vec<String> {
+        self.vaults
+            .iter()
+            .flat_map(|vault| {
+                self.walk(&vault.path)
+                    .into_iter()
+                    .filter_entry(|entry| {
+                        entry.depth() == 0
+                            || !(entry.file_name().to_string_lossy().starts_with('.')
+                                || self.is_excluded(
+                                    vault,
+                                    entry.path(),
+                                    entry.file_type().is_dir(),
+                                ))
+                    })
+                    .filter_map(Result::ok)
+                    .filter(|entry| entry.file_type().is_file())
+                    .filter(|entry| entry.path().extension().is_none_or(|ext| ext != "md"))
+                    .filter(|entry| self.handler_for(entry.path()).is_none())
+                    .map(|entry| entry.path().to_string_lossy().to_string())
+            })
+            .collect()
     }
 
     #[cfg(test)]
     pub fn get_vault_path(&self) -> &Path {
-        &self.vault_path
+        &self.vaults[0].path
+    }
+}
+
+/// Why a file could not be read, when it isn't broken
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Unreadable {
+    PermissionDenied,
+    /// A cloud file whose contents are not downloaded
+    NotLocal,
+}
+
+fn unreadable(error: &anyhow::Error) -> Option<Unreadable> {
+    let error = error
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<io::Error>())?;
+    if error.kind() == io::ErrorKind::PermissionDenied {
+        Some(Unreadable::PermissionDenied)
+    } else if is_cloud_error(error) {
+        Some(Unreadable::NotLocal)
+    } else {
+        None
+    }
+}
+
+/// Whether reading failed because the file is only in the cloud: a timeout fetching it,
+/// or one of the `ERROR_CLOUD_FILE_*` codes of Windows (OneDrive, iCloud for Windows)
+fn is_cloud_error(error: &io::Error) -> bool {
+    error.kind() == io::ErrorKind::TimedOut
+        || (cfg!(windows)
+            && error
+                .raw_os_error()
+                .is_some_and(|code| (362..=404).contains(&code)))
+}
+
+/// Whether a file is a placeholder of a cloud file whose contents are not downloaded: on
+/// Windows marked offline or to be recalled from the cloud (OneDrive, iCloud for Windows),
+/// on macOS dataless (File Provider). Elsewhere placeholders show up when they are read.
+///
+/// Empty files are not placeholders, Obsidian creates notes that way.
+#[allow(clippy::missing_const_for_fn)]
+fn is_cloud_placeholder(entry: &walkdir::DirEntry) -> bool {
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        const FILE_ATTRIBUTE_OFFLINE: u32 = 0x1000;
+        const FILE_ATTRIBUTE_RECALL_ON_OPEN: u32 = 0x0004_0000;
+        const FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS: u32 = 0x0040_0000;
+        entry.metadata().is_ok_and(|metadata| {
+            metadata.file_attributes()
+                & (FILE_ATTRIBUTE_OFFLINE
+                    | FILE_ATTRIBUTE_RECALL_ON_OPEN
+                    | FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS)
+                != 0
+        })
+    }
+    #[cfg(target_os = "macos")]
+    {
+        use std::os::macos::fs::MetadataExt;
+        const SF_DATALESS: u32 = 0x4000_0000;
+        entry
+            .metadata()
+            .is_ok_and(|metadata| metadata.st_flags() & SF_DATALESS != 0)
+    }
+    #[cfg(not(any(windows, target_os = "macos")))]
+    {
+        let _ = entry;
+        false
+    }
+}
+
+/// The note an iCloud placeholder stands for: `.Plan.md.icloud` for `Plan.md`
+fn icloud_placeholder_target(path: &Path) -> Option<PathBuf> {
+    let name = path.file_name()?.to_str()?;
+    let note_name = name.strip_prefix('.')?.strip_suffix(".icloud")?;
+    (!note_name.is_empty()).then(|| path.with_file_name(note_name))
+}
+
+/// The warning for a file that is not locally available, with why the download hook
+/// didn't get it when there is one
+fn not_local_message(hook_failure: Option<String>) -> String {
+    let message = format!(
+        "Skipped, the file is {} (cloud placeholder not downloaded)",
+        NOT_LOCAL
+    );
+    match hook_failure {
+        Some(failure) => format!("{}: {}", message, failure),
+        None => message,
+    }
+}
+
+/// Run the download hook for a file, returning why it failed when it did
+fn run_download_hook(hook: &ExecCommand, path: &Path) -> Option<String> {
+    let command_line = hook.command_line(&[&path.to_string_lossy()]);
+    match exec::run_hook(&command_line) {
+        Ok(true) => None,
+        Ok(false) => Some(format!("download hook failed: {}", command_line)),
+        Err(e) => Some(format!("{:#}", e)),
+    }
+}
+
+/// Name of a vault for tagging its notes: its directory name
+pub fn vault_name(vault_path: &Path) -> String {
+    fs::canonicalize(vault_path)
+        .unwrap_or_else(|_| vault_path.to_path_buf())
+        .file_name()
+        .map_or_else(
+            || vault_path.display().to_string(),
+            |name| name.to_string_lossy().to_string(),
+        )
+}
+
+/// Set the `file.*` virtual fields: name without extension, extension, folder relative to
+/// the vault (`/` for the root), size in bytes and modification and creation time
+fn add_file_fields(note: &mut Note, path: &Path, vault_path: &Path) {
+    if let Some(name) = path.file_stem() {
+        note.set_virtual_field(
+            "file.name",
+            Yaml::String(name.to_string_lossy().to_string()),
+        );
+    }
+    if let Some(ext) = path.extension() {
+        note.set_virtual_field("file.ext", Yaml::String(ext.to_string_lossy().to_string()));
+    }
+    let folder = path
+        .parent()
+        .and_then(|parent| parent.strip_prefix(vault_path).ok())
+        .map(|folder| folder.to_string_lossy().to_string())
+        .filter(|folder| !folder.is_empty())
+        .unwrap_or_else(|| "/".to_string());
+    note.set_virtual_field("file.folder", Yaml::String(folder));
+}
+
+/// Set the size and times of the file of a note
+fn add_file_metadata(note: &mut Note, path: &Path) {
+    let Ok(metadata) = fs::metadata(path) else {
+        return;
+    };
+    note.set_virtual_field(
+        "file.size",
+        Yaml::Integer(i64::try_from(metadata.len()).unwrap_or(i64::MAX)),
+    );
+    // Creation time isn't available on every file system
+    for (name, time) in [
+        ("file.mtime", metadata.modified()),
+        ("file.ctime", metadata.created()),
+    ] {
+        if let Ok(time) = time {
+            let time: DateTime<Local> = time.into();
+            note.set_virtual_field(
+                name,
+                Yaml::String(time.format("%Y-%m-%dT%H:%M:%S").to_string()),
+            );
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::yaml_compat::yaml_to_string;
     use std::fs;
+    use std::sync::Mutex;
     use tempfile::TempDir;
 
     #[test]
@@ -140,7 +1015,7 @@ mod tests {
     fn test_scan_empty_vault() {
         let temp_dir = TempDir::new().unwrap();
         let scanner = VaultScanner::new(temp_dir.path()).unwrap();
-        let notes = scanner.scan_vault(false, false, true, None).unwrap();
+        let notes = scanner.scan_vault(false, false, true, None).unwrap().notes;
         assert!(notes.is_empty());
     }
 
@@ -163,9 +1038,427 @@ tags: [test]
         .unwrap();
 
         let scanner = VaultScanner::new(temp_dir.path()).unwrap();
-        let notes = scanner.scan_vault(false, false, true, None).unwrap();
+        let notes = scanner.scan_vault(false, false, true, None).unwrap().notes;
 
         assert_eq!(notes.len(), 1);
         assert_eq!(notes[0].title, Some("Test Note".to_string()));
     }
+
+    #[test]
+    fn test_parse_status() {
+        let temp_dir = TempDir::new().unwrap();
+        for (name, content) in [
+            ("ok.md", "---\nstatus: fine\n---\n"),
+            ("plain.md", "No frontmatter\n"),
+            ("lenient.md", "---\nsource: Book: Chapter 3\n---\n"),
+            ("failed.md", "---\ntags: [unclosed\n---\n"),
+        ] {
+            fs::write(temp_dir.path().join(name), content).unwrap();
+        }
+
+        let statuses = |lenient| {
+            let mut statuses: Vec<(String, String)> = VaultScanner::new(temp_dir.path())
+                .unwrap()
+                .scan_vault(false, true, lenient, None)
+                .unwrap()
+                .notes
+                .iter()
+                .map(|note| {
+                    let status = note.get_frontmatter_value("parse.status").unwrap();
+                    (note.title.clone().unwrap(), yaml_to_string(status))
+                })
+                .collect();
+            statuses.sort();
+            statuses
+        };
+        let expected = |lenient: &str| {
+            vec![
+                ("failed".to_string(), "failed".to_string()),
+                ("lenient".to_string(), lenient.to_string()),
+                ("ok".to_string(), "ok".to_string()),
+                ("plain".to_string(), "ok".to_string()),
+            ]
+        };
+        assert_eq!(statuses(true), expected("lenient"));
+        assert_eq!(statuses(false), expected("failed"));
+    }
+
+    #[test]
+    fn test_scan_vault_with_excludes() {
+        let temp_dir = TempDir::new().unwrap();
+        for dir in ["templates", "archive/old", "archive/new"] {
+            fs::create_dir_all(temp_dir.path().join(dir)).unwrap();
+            fs::write(temp_dir.path().join(dir).join("note.md"), "# Note\n").unwrap();
+        }
+
+        let scanner = VaultScanner::new(temp_dir.path())
+            .unwrap()
+            .with_excludes(vec!["templates".to_string(), "archive/old/".to_string()]);
+        let notes = scanner.scan_vault(false, true, true, None).unwrap().notes;
+
+        assert_eq!(notes.len(), 1);
+        assert!(notes[0].path.contains("new"));
+
+        fs::write(temp_dir.path().join(".gitignore"), "new/\n").unwrap();
+        let scanner = VaultScanner::new(temp_dir.path())
+            .unwrap()
+            .with_excludes(vec!["templates".to_string()]);
+        let notes = scanner.scan_vault(false, true, true, None).unwrap().notes;
+        assert_eq!(notes.len(), 1);
+        assert!(notes[0].path.contains("old"));
+
+        let scanner = scanner.with_ignore_files(false);
+        let notes = scanner.scan_vault(false, true, true, None).unwrap().notes;
+        assert_eq!(notes.len(), 2);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_scan_symlinks_and_depth() {
+        let temp_dir = TempDir::new().unwrap();
+        let shared = TempDir::new().unwrap();
+        fs::write(shared.path().join("shared.md"), "# Shared\n").unwrap();
+        fs::create_dir_all(temp_dir.path().join("a/b")).unwrap();
+        fs::write(temp_dir.path().join("root.md"), "# Root\n").unwrap();
+        fs::write(temp_dir.path().join("a/b/deep.md"), "# Deep\n").unwrap();
+        std::os::unix::fs::symlink(shared.path(), temp_dir.path().join("shared")).unwrap();
+        // A link back to the vault root would be followed forever
+        std::os::unix::fs::symlink(temp_dir.path(), temp_dir.path().join("a/loop")).unwrap();
+
+        let count = |scanner: VaultScanner| {
+            let outcome = scanner.scan_vault(false, true, true, None).unwrap();
+            (outcome.notes.len(), outcome.warnings)
+        };
+        let scanner = || VaultScanner::new(temp_dir.path()).unwrap();
+        assert_eq!(count(scanner()).0, 2);
+        assert_eq!(count(scanner().with_max_depth(Some(1))).0, 1);
+        assert_eq!(count(scanner().with_one_file_system(true)).0, 2);
+
+        let (notes, warnings) = count(scanner().with_follow_symlinks(true));
+        assert_eq!(notes, 3);
+        assert_eq!(warnings.get("Symlink loops"), Some(&1));
+    }
+
+    #[test]
+    fn test_scan_at_revision() {
+        let temp_dir = TempDir::new().unwrap();
+        let vault = temp_dir.path();
+        let git = |args: &[&str]| {
+            assert!(std::process::Command::new("git")
+                .arg("-C")
+                .arg(vault)
+                .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .status()
+                .unwrap()
+                .success());
+        };
+        git(&["init", "-q"]);
+        fs::create_dir(vault.join("templates")).unwrap();
+        fs::write(
+            vault.join("templates/t.md"),
+            "---
+status: active
+---
+",
+        )
+        .unwrap();
+        fs::write(
+            vault.join("plan.md"),
+            "---
+status: active
+---
+See #kickoff
+",
+        )
+        .unwrap();
+        git(&["add", "-A"]);
+        git(&["commit", "-q", "-m", "first"]);
+        fs::write(
+            vault.join("plan.md"),
+            "---
+status: done
+---
+",
+        )
+        .unwrap();
+        fs::write(
+            vault.join("new.md"),
+            "---
+status: active
+---
+",
+        )
+        .unwrap();
+
+        let notes = VaultScanner::new(vault)
+            .unwrap()
+            .with_excludes(vec!["templates".to_string()])
+            .at_revision("HEAD")
+            .unwrap()
+            .scan_vault(false, true, true, None)
+            .unwrap()
+            .notes;
+        assert_eq!(notes.len(), 1);
+        let note = &notes[0];
+        assert_eq!(Path::new(&note.path), vault.join("plan.md"));
+        assert!(note.matches_filter("status", "active"));
+        assert_eq!(note.inline_tags, vec!["kickoff".to_string()]);
+        assert!(note.get_frontmatter_value("file.name").is_some());
+        assert!(note.get_frontmatter_value("file.mtime").is_none());
+
+        assert!(VaultScanner::new(vault)
+            .unwrap()
+            .at_revision("no-such-branch")
+            .is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_cloud_placeholders() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("Local.md"), "# Local\n").unwrap();
+        fs::write(temp_dir.path().join(".Remote.md.icloud"), "bplist").unwrap();
+        fs::write(temp_dir.path().join(".Photo.png.icloud"), "bplist").unwrap();
+
+        let scan = |scanner: VaultScanner| scanner.scan_vault(false, true, true, None).unwrap();
+        let outcome = scan(VaultScanner::new(temp_dir.path()).unwrap());
+        assert_eq!(outcome.notes.len(), 1);
+        assert_eq!(outcome.critical_errors, 0);
+        assert_eq!(outcome.warnings.get("Not locally available"), Some(&1));
+        let skipped = scan(
+            VaultScanner::new(temp_dir.path())
+                .unwrap()
+                .with_skip_placeholders(true),
+        );
+        assert_eq!(skipped.notes.len(), 1);
+        assert!(skipped.warnings.is_empty());
+
+        // The hook fetches the note before it is parsed
+        let outcome = scan(
+            VaultScanner::new(temp_dir.path())
+                .unwrap()
+                .with_download_hook(Some(
+                    "printf -- '---\\nstatus: open\\n---\\n' > {}".to_string(),
+                )),
+        );
+        assert_eq!(outcome.notes.len(), 2);
+        assert!(outcome.warnings.is_empty());
+        assert!(temp_dir.path().join("Remote.md").is_file());
+
+        // A stub next to its downloaded note is left over
+        assert_eq!(
+            scan(VaultScanner::new(temp_dir.path()).unwrap())
+                .notes
+                .len(),
+            2
+        );
+
+        fs::remove_file(temp_dir.path().join("Remote.md")).unwrap();
+        let failing = scan(
+            VaultScanner::new(temp_dir.path())
+                .unwrap()
+                .with_download_hook(Some("false".to_string())),
+        );
+        assert_eq!(failing.notes.len(), 1);
+        assert_eq!(failing.warnings.get("Not locally available"), Some(&1));
+
+        assert_eq!(
+            icloud_placeholder_target(Path::new("notes/.Plan.md.icloud")),
+            Some(PathBuf::from("notes/Plan.md"))
+        );
+        assert_eq!(icloud_placeholder_target(Path::new(".icloud")), None);
+        let denied = anyhow::Error::new(io::Error::from(io::ErrorKind::PermissionDenied))
+            .context("Failed to read file: a.md");
+        assert_eq!(unreadable(&denied), Some(Unreadable::PermissionDenied));
+        let timed_out = anyhow::Error::new(io::Error::from(io::ErrorKind::TimedOut));
+        assert_eq!(unreadable(&timed_out), Some(Unreadable::NotLocal));
+        assert_eq!(unreadable(&anyhow::anyhow!("Invalid canvas JSON")), None);
+    }
+
+    #[test]
+    fn test_scan_listed_files() {
+        let temp_dir = TempDir::new().unwrap();
+        for dir in ["projects", "templates"] {
+            fs::create_dir_all(temp_dir.path().join(dir)).unwrap();
+            fs::write(temp_dir.path().join(dir).join("note.md"), "# Note\n").unwrap();
+        }
+
+        let listed = temp_dir.path().join("projects/../templates/note.md");
+        let outcome = VaultScanner::new(temp_dir.path())
+            .unwrap()
+            .with_excludes(vec!["templates".to_string()])
+            .with_files(Some(vec![listed, temp_dir.path().join("missing.md")]))
+            .scan_vault(false, true, true, None)
+            .unwrap();
+
+        assert_eq!(outcome.notes.len(), 1);
+        assert_eq!(
+            outcome.notes[0].get_frontmatter_value("file.folder"),
+            Some(&Yaml::String("templates".to_string()))
+        );
+        assert_eq!(outcome.critical_errors, 1);
+    }
+
+    #[test]
+    fn test_body_scan() {
+        let temp_dir = TempDir::new().unwrap();
+        for (name, status) in [("open", "open"), ("done", "done")] {
+            fs::write(
+                temp_dir.path().join(format!("{}.md", name)),
+                format!("---\nstatus: {}\n---\nSee [[Plan]]\n", status),
+            )
+            .unwrap();
+        }
+        let linked = |body_scan: BodyScan| {
+            let mut notes = VaultScanner::new(temp_dir.path())
+                .unwrap()
+                .with_bodies(true)
+                .with_body_scan(body_scan)
+                .scan_vault(false, true, true, None)
+                .unwrap()
+                .notes;
+            notes.sort_by(|a, b| a.path.cmp(&b.path));
+            notes
+                .iter()
+                .map(|note| (note.links.len(), note.body.is_some()))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(linked(BodyScan::Frontmatter), vec![(0, false), (0, false)]);
+        assert_eq!(linked(BodyScan::All), vec![(1, true), (1, true)]);
+        let open = BodyScan::Matching(Arc::new(|note: &Note| {
+            note.get_frontmatter_value("status") == Some(&Yaml::String("open".to_string()))
+        }));
+        assert_eq!(linked(open), vec![(0, false), (1, true)]);
+    }
+
+    #[test]
+    fn test_stream_vault() {
+        let temp_dir = TempDir::new().unwrap();
+        for name in ["a", "b", "c"] {
+            fs::write(
+                temp_dir.path().join(format!("{}.md", name)),
+                format!("---\ntitle: {}\n---\n", name),
+            )
+            .unwrap();
+        }
+
+        let scanner = VaultScanner::new(temp_dir.path()).unwrap();
+        let seen = Mutex::new(Vec::new());
+        let critical_errors = scanner
+            .stream_vault(false, true, true, None, |note| {
+                seen.lock().unwrap().push(note.title.unwrap());
+                Ok(())
+            })
+            .unwrap();
+
+        let mut seen = seen.into_inner().unwrap();
+        seen.sort();
+        assert_eq!(seen, vec!["a", "b", "c"]);
+        assert_eq!(critical_errors, 0);
+
+        let result =
+            scanner.stream_vault(false, true, true, None, |_| Err(anyhow::anyhow!("stop")));
+        assert!(result.is_err());
+
+        let scanner = scanner.with_threads(NonZeroUsize::new(1));
+        let notes = scanner.scan_vault(false, true, true, None).unwrap().notes;
+        assert_eq!(notes.len(), 3);
+    }
+
+    #[test]
+    fn test_scan_several_vaults() {
+        let work = TempDir::new().unwrap();
+        let personal = TempDir::new().unwrap();
+        fs::write(work.path().join("a.md"), "---\nstatus: open\n---\n").unwrap();
+        fs::write(personal.path().join("b.md"), "---\nstatus: open\n---\n").unwrap();
+
+        let scanner = VaultScanner::with_vaults(vec![
+            ("work".to_string(), work.path().to_path_buf()),
+            ("personal".to_string(), personal.path().to_path_buf()),
+        ])
+        .unwrap();
+        let mut notes = scanner.scan_vault(false, true, true, None).unwrap().notes;
+        notes.sort_by_key(|note| note.vault.clone());
+
+        let vaults: Vec<Option<&str>> = notes.iter().map(|n| n.vault.as_deref()).collect();
+        assert_eq!(vaults, vec![Some("personal"), Some("work")]);
+        assert_eq!(
+            notes[1].get_frontmatter_value("file.vault"),
+            Some(&Yaml::String("work".to_string()))
+        );
+        assert_eq!(
+            notes[1].get_frontmatter_value("file.folder"),
+            Some(&Yaml::String("/".to_string()))
+        );
+
+        let single = VaultScanner::new(work.path()).unwrap();
+        let notes = single.scan_vault(false, true, true, None).unwrap().notes;
+        assert_eq!(notes[0].vault, None);
+    }
+
+    #[test]
+    fn test_file_fields() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir_all(temp_dir.path().join("projects")).unwrap();
+        fs::write(temp_dir.path().join("projects/plan.md"), "# Plan\n").unwrap();
+        fs::write(temp_dir.path().join("root.md"), "# Root\n").unwrap();
+
+        let scanner = VaultScanner::new(temp_dir.path()).unwrap();
+        let mut notes = scanner.scan_vault(false, true, true, None).unwrap().notes;
+        notes.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let plan = &notes[0];
+        assert_eq!(
+            plan.get_frontmatter_value("file.name"),
+            Some(&Yaml::String("plan".to_string()))
+        );
+        assert_eq!(
+            plan.get_frontmatter_value("file.folder"),
+            Some(&Yaml::String("projects".to_string()))
+        );
+        assert_eq!(
+            plan.get_frontmatter_value("file.size"),
+            Some(&Yaml::Integer(7))
+        );
+        assert!(plan.get_frontmatter_value("file.mtime").is_some());
+        assert_eq!(
+            notes[1].get_frontmatter_value("file.folder"),
+            Some(&Yaml::String("/".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_scan_with_handler() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("note.md"), "# Note\n").unwrap();
+        fs::write(
+            temp_dir.path().join("board.canvas"),
+            r#"{"nodes": [{"id": "1", "type": "file", "file": "note.md"}]}"#,
+        )
+        .unwrap();
+
+        let scanner = VaultScanner::new(temp_dir.path()).unwrap();
+        assert_eq!(
+            scanner
+                .scan_vault(false, true, true, None)
+                .unwrap()
+                .notes
+                .len(),
+            1
+        );
+        assert_eq!(scanner.attachment_paths().len(), 1);
+
+        let scanner = scanner.with_handler(Box::new(crate::canvas::CanvasHandler));
+        let mut notes = scanner.scan_vault(false, true, true, None).unwrap().notes;
+        notes.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(notes.len(), 2);
+        assert_eq!(notes[0].links, vec!["note.md"]);
+        assert_eq!(
+            notes[0].get_frontmatter_value("file.ext"),
+            Some(&Yaml::String("canvas".to_string()))
+        );
+        assert!(scanner.attachment_paths().is_empty());
+    }
 }
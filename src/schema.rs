@@ -0,0 +1,367 @@
+use crate::filter::parse_date;
+use crate::frontmatter::Note;
+use crate::lint::LintIssue;
+use crate::yaml_compat::{collect_yaml_strings, yaml_to_string, yaml_type_name};
+use anyhow::{anyhow, Context, Result};
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use yaml_rust2::{Yaml, YamlLoader};
+
+/// Type a schema expects for a field value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldType {
+    String,
+    Number,
+    Integer,
+    Boolean,
+    /// A string like `2024-06-01` or `2024-06-01T10:30:00`
+    Date,
+    List,
+    Map,
+}
+
+impl FieldType {
+    fn parse(name: &str) -> Result<Self> {
+        match name {
+            "string" => Ok(Self::String),
+            "number" => Ok(Self::Number),
+            "integer" => Ok(Self::Integer),
+            "boolean" => Ok(Self::Boolean),
+            "date" => Ok(Self::Date),
+            "list" => Ok(Self::List),
+            "map" => Ok(Self::Map),
+            other => Err(anyhow!(
+                "Unknown type '{}', expected string, number, integer, boolean, date, list or map",
+                other
+            )),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::String => "string",
+            Self::Number => "number",
+            Self::Integer => "integer",
+            Self::Boolean => "boolean",
+            Self::Date => "date",
+            Self::List => "list",
+            Self::Map => "map",
+        }
+    }
+
+    fn matches(self, value: &Yaml) -> bool {
+        match (self, value) {
+            (Self::String, Yaml::String(_))
+            | (Self::Number, Yaml::Integer(_) | Yaml::Real(_))
+            | (Self::Integer, Yaml::Integer(_))
+            | (Self::Boolean, Yaml::Boolean(_))
+            | (Self::List, Yaml::Array(_))
+            | (Self::Map, Yaml::Hash(_)) => true,
+            (Self::Date, Yaml::String(s)) => parse_date(s).is_some(),
+            _ => false,
+        }
+    }
+}
+
+/// What the schema expects of a single field
+#[derive(Debug, Clone, Default)]
+pub struct FieldRule {
+    pub required: bool,
+    pub field_type: Option<FieldType>,
+    /// Values the field (or every item of a list field) may take, any value if empty
+    pub allowed: Vec<String>,
+    /// Fewest and most characters the value (or every item of a list) may have
+    pub min_length: Option<usize>,
+    pub max_length: Option<usize>,
+    /// Regular expression the whole value (or every item of a list) has to match, as
+    /// written and compiled with anchors
+    pub pattern: Option<(String, Regex)>,
+}
+
+/// Rules for the frontmatter of every note, loaded from a YAML file:
+///
+/// ```yaml
+/// fields:
+///   status:
+///     required: true
+///     allowed: [open, in-progress, done]
+///   due:
+///     type: date
+///   tags:
+///     type: list
+///   id:
+///     pattern: '[A-Z]{2}-\d+'
+///   summary:
+///     max_length: 120
+/// ```
+#[derive(Debug, Default)]
+pub struct Schema {
+    fields: BTreeMap<String, FieldRule>,
+}
+
+impl Schema {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read schema: {}", path.as_ref().display()))?;
+        Self::from_yaml(&content)
+            .with_context(|| format!("Invalid schema: {}", path.as_ref().display()))
+    }
+
+    pub fn from_yaml(content: &str) -> Result<Self> {
+        let docs =
+            YamlLoader::load_from_str(content).map_err(|e| anyhow!("YAML parsing error: {}", e))?;
+        let Some(Yaml::Hash(root)) = docs.into_iter().next() else {
+            return Err(anyhow!("Expected a mapping with a fields section"));
+        };
+
+        let mut schema = Self::default();
+        for (section, entries) in &root {
+            match (section.as_str(), entries) {
+                (Some("fields"), Yaml::Hash(fields)) => {
+                    for (field, rule) in fields {
+                        let field = yaml_to_string(field);
+                        let rule =
+                            parse_rule(rule).with_context(|| format!("Field '{}'", field))?;
+                        schema.fields.insert(field, rule);
+                    }
+                }
+                (Some(name), _) => {
+                    return Err(anyhow!("Unknown or malformed schema section: {}", name));
+                }
+                (None, _) => return Err(anyhow!("Schema sections must be named")),
+            }
+        }
+
+        Ok(schema)
+    }
+
+    /// Check a note's frontmatter, returning one issue per violated rule
+    pub fn validate(&self, note: &Note) -> Vec<LintIssue> {
+        let mut issues = Vec::new();
+        let mut report = |rule: &'static str, message: String| {
+            issues.push(LintIssue {
+                path: note.path.clone(),
+                rule,
+                message,
+            });
+        };
+
+        for (field, rule) in &self.fields {
+            let value = match note.frontmatter.get(field) {
+                None | Some(Yaml::Null) => {
+                    if rule.required {
+                        report("required", format!("missing required field '{}'", field));
+                    }
+                    continue;
+                }
+                Some(value) => value,
+            };
+
+            if let Some(field_type) = rule.field_type {
+                if !field_type.matches(value) {
+                    report(
+                        "type",
+                        format!(
+                            "'{}' should be a {}, found {} '{}'",
+                            field,
+                            field_type.name(),
+                            yaml_type_name(value),
+                            yaml_to_string(value)
+                        ),
+                    );
+                    continue;
+                }
+            }
+
+            if !rule.allowed.is_empty() {
+                for item in collect_yaml_strings(value) {
+                    if !rule.allowed.contains(&item) {
+                        report(
+                            "allowed",
+                            format!(
+                                "'{}' has value '{}', allowed: {}",
+                                field,
+                                item,
+                                rule.allowed.join(", ")
+                            ),
+                        );
+                    }
+                }
+            }
+
+            for item in collect_yaml_strings(value) {
+                let length = item.chars().count();
+                if let Some(min_length) = rule.min_length.filter(|min| length < *min) {
+                    report(
+                        "min_length",
+                        format!(
+                            "'{}' has value '{}' of {} characters, at least {} required",
+                            field, item, length, min_length
+                        ),
+                    );
+                }
+                if let Some(max_length) = rule.max_length.filter(|max| length > *max) {
+                    report(
+                        "max_length",
+                        format!(
+                            "'{}' has value '{}' of {} characters, at most {} allowed",
+                            field, item, length, max_length
+                        ),
+                    );
+                }
+                if let Some((pattern, regex)) = &rule.pattern {
+                    if !regex.is_match(&item) {
+                        report(
+                            "pattern",
+                            format!(
+                                "'{}' has value '{}', which doesn't match {}",
+                                field, item, pattern
+                            ),
+                        );
+                    }
+                }
+            }
+        }
+        issues
+    }
+}
+
+fn parse_rule(rule: &Yaml) -> Result<FieldRule> {
+    let Yaml::Hash(entries) = rule else {
+        return Err(anyhow!("Expected a mapping of rules"));
+    };
+
+    let mut field_rule = FieldRule::default();
+    for (key, value) in entries {
+        match (key.as_str(), value) {
+            (Some("required"), Yaml::Boolean(required)) => field_rule.required = *required,
+            (Some("type"), Yaml::String(name)) => {
+                field_rule.field_type = Some(FieldType::parse(name)?);
+            }
+            (Some("allowed"), Yaml::Array(values)) => {
+                field_rule.allowed = values.iter().map(yaml_to_string).collect();
+            }
+            (Some("min_length"), Yaml::Integer(length)) => {
+                field_rule.min_length = Some(parse_length(*length)?);
+            }
+            (Some("max_length"), Yaml::Integer(length)) => {
+                field_rule.max_length = Some(parse_length(*length)?);
+            }
+            (Some("pattern"), Yaml::String(pattern)) => {
+                // The whole value has to match, not just a part of it
+                let regex = Regex::new(&format!("^(?:{})$", pattern))
+                    .with_context(|| format!("Invalid pattern: {}", pattern))?;
+                field_rule.pattern = Some((pattern.clone(), regex));
+            }
+            (Some(name), _) => return Err(anyhow!("Unknown or malformed rule: {}", name)),
+            (None, _) => return Err(anyhow!("Rule names must be strings")),
+        }
+    }
+    if let (Some(min_length), Some(max_length)) = (field_rule.min_length, field_rule.max_length) {
+        if min_length > max_length {
+            return Err(anyhow!(
+                "min_length {} is larger than max_length {}",
+                min_length,
+                max_length
+            ));
+        }
+    }
+    Ok(field_rule)
+}
+
+fn parse_length(length: i64) -> Result<usize> {
+    usize::try_from(length).map_err(|_| anyhow!("Lengths can't be negative: {}", length))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    const SCHEMA: &str = r#"
+fields:
+  status:
+    required: true
+    allowed: [open, done]
+  due:
+    type: date
+  tags:
+    type: list
+    allowed: [work, home]
+  id:
+    pattern: '[A-Z]{2}-\d+'
+  summary:
+    min_length: 3
+    max_length: 10
+"#;
+
+    #[test]
+    fn test_validate_note() {
+        let schema = Schema::from_yaml(SCHEMA).unwrap();
+
+        let mut fm = HashMap::new();
+        fm.insert("status".to_string(), Yaml::String("open".to_string()));
+        fm.insert("due".to_string(), Yaml::String("2024-06-01".to_string()));
+        let valid = Note::new("valid.md".to_string(), fm);
+        assert!(schema.validate(&valid).is_empty());
+
+        let mut fm = HashMap::new();
+        fm.insert("due".to_string(), Yaml::String("next week".to_string()));
+        fm.insert(
+            "tags".to_string(),
+            Yaml::Array(vec![
+                Yaml::String("work".to_string()),
+                Yaml::String("fun".to_string()),
+            ]),
+        );
+        let invalid = Note::new("invalid.md".to_string(), fm);
+        let rules: Vec<&str> = schema
+            .validate(&invalid)
+            .iter()
+            .map(|issue| issue.rule)
+            .collect();
+        assert_eq!(rules, vec!["type", "required", "allowed"]);
+
+        let mut fm = HashMap::new();
+        fm.insert("status".to_string(), Yaml::String("open".to_string()));
+        fm.insert("id".to_string(), Yaml::String("AB-12x".to_string()));
+        fm.insert("summary".to_string(), Yaml::String("ok".to_string()));
+        let messages: Vec<String> = schema
+            .validate(&Note::new("lengths.md".to_string(), fm))
+            .into_iter()
+            .map(|issue| format!("{}: {}", issue.rule, issue.message))
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                r"pattern: 'id' has value 'AB-12x', which doesn't match [A-Z]{2}-\d+",
+                "min_length: 'summary' has value 'ok' of 2 characters, at least 3 required",
+            ]
+        );
+
+        let mut fm = HashMap::new();
+        fm.insert("status".to_string(), Yaml::String("open".to_string()));
+        fm.insert("id".to_string(), Yaml::String("AB-12".to_string()));
+        fm.insert(
+            "summary".to_string(),
+            Yaml::String("Überlänge!!".to_string()),
+        );
+        let issues = schema.validate(&Note::new("long.md".to_string(), fm));
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].rule, "max_length");
+    }
+
+    #[test]
+    fn test_invalid_schema() {
+        assert!(Schema::from_yaml("fields:\n  due:\n    type: when\n").is_err());
+        assert!(Schema::from_yaml("fields:\n  due:\n    optional: true\n").is_err());
+        assert!(Schema::from_yaml("rules: []\n").is_err());
+        assert!(Schema::from_yaml("fields:\n  id:\n    pattern: '[A-Z'\n").is_err());
+        assert!(Schema::from_yaml("fields:\n  id:\n    max_length: -1\n").is_err());
+        assert!(
+            Schema::from_yaml("fields:\n  id:\n    min_length: 5\n    max_length: 2\n").is_err()
+        );
+    }
+}
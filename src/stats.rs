@@ -0,0 +1,523 @@
+use crate::filter::{get_field_statistics, group_notes};
+use crate::frontmatter::{lenient_fixes, split_frontmatter, FrontmatterMetrics, LenientFix, Note};
+use crate::yaml_compat::yaml_as_str;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+/// Where `stats --record` keeps its snapshots, relative to the vault root
+pub const HISTORY_FILE: &str = ".aktenfux/stats-history.jsonl";
+
+/// Average and maximum of one frontmatter metric
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct MetricSummary {
+    pub average: f64,
+    pub max: usize,
+}
+
+impl MetricSummary {
+    fn new(values: impl Iterator<Item = usize>) -> Self {
+        let (count, total, max) = values.fold((0_usize, 0, 0), |(count, total, max), value| {
+            (count + 1, total + value, max.max(value))
+        });
+        Self {
+            average: if count == 0 {
+                0.0
+            } else {
+                total as f64 / count as f64
+            },
+            max,
+        }
+    }
+}
+
+/// Frontmatter size and complexity across a set of notes
+#[derive(Debug)]
+pub struct MetricsReport<'a> {
+    pub notes: usize,
+    /// Notes with at least one frontmatter field, the averages are taken over these
+    pub with_frontmatter: usize,
+    pub lines: MetricSummary,
+    pub keys: MetricSummary,
+    pub depth: MetricSummary,
+    /// Notes with the most frontmatter lines, largest first
+    pub largest: Vec<&'a Note>,
+    /// Notes with the deepest nested frontmatter, deepest first
+    pub deepest: Vec<&'a Note>,
+}
+
+impl<'a> MetricsReport<'a> {
+    pub fn new(notes: &[&'a Note], top: usize) -> Self {
+        let with_frontmatter: Vec<&FrontmatterMetrics> = notes
+            .iter()
+            .map(|note| &note.metrics)
+            .filter(|metrics| metrics.keys > 0)
+            .collect();
+
+        Self {
+            notes: notes.len(),
+            with_frontmatter: with_frontmatter.len(),
+            lines: MetricSummary::new(with_frontmatter.iter().map(|m| m.lines)),
+            keys: MetricSummary::new(with_frontmatter.iter().map(|m| m.keys)),
+            depth: MetricSummary::new(with_frontmatter.iter().map(|m| m.depth)),
+            largest: top_notes(notes, top, |m| (m.lines, m.keys)),
+            deepest: top_notes(notes, top, |m| (m.depth, m.lines)),
+        }
+    }
+}
+
+/// How completely the notes fill in their frontmatter, the report of `stats --health`
+#[derive(Debug)]
+pub struct HealthReport {
+    pub notes: usize,
+    pub without_frontmatter: usize,
+    /// Files that could not be read or parsed
+    pub errors: usize,
+    /// Number of files per warning category of the scan
+    pub warnings: BTreeMap<String, usize>,
+    /// Required fields with the number of notes where they are missing or empty
+    pub missing: Vec<(String, usize)>,
+    /// Every field, the most used first
+    pub fields: Vec<FieldHealth>,
+}
+
+/// How many notes use a field and its most common values
+#[derive(Debug)]
+pub struct FieldHealth {
+    pub field: String,
+    pub notes: usize,
+    /// Share of the notes with the field, in percent
+    pub fill_rate: f64,
+    pub kind: &'static str,
+    /// The `top` most common values with their counts, most common first
+    pub top_values: Vec<(String, usize)>,
+}
+
+impl HealthReport {
+    pub fn new(notes: &[&Note], top: usize) -> Self {
+        let mut fields: Vec<FieldHealth> = get_field_statistics(notes.iter().copied())
+            .into_iter()
+            .map(|(field, stats)| {
+                let kind = stats.inferred_type();
+                // The values inside maps are listed as fields of their own
+                let mut top_values: Vec<(String, usize)> = if stats.kinds.contains("map") {
+                    Vec::new()
+                } else {
+                    stats.value_counts.into_iter().collect()
+                };
+                top_values.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+                top_values.truncate(top);
+                FieldHealth {
+                    field,
+                    notes: stats.total_count,
+                    fill_rate: percent(stats.total_count, notes.len()),
+                    kind,
+                    top_values,
+                }
+            })
+            .collect();
+        fields.sort_by(|a, b| b.notes.cmp(&a.notes).then_with(|| a.field.cmp(&b.field)));
+
+        Self {
+            notes: notes.len(),
+            without_frontmatter: notes.iter().filter(|note| note.metrics.keys == 0).count(),
+            errors: 0,
+            warnings: BTreeMap::new(),
+            missing: Vec::new(),
+            fields,
+        }
+    }
+
+    /// Add the problems the scan ran into
+    #[must_use]
+    pub fn with_scan_problems(mut self, errors: usize, warnings: BTreeMap<String, usize>) -> Self {
+        self.errors = errors;
+        self.warnings = warnings;
+        self
+    }
+
+    /// Add how many notes miss each of the fields every note should have
+    #[must_use]
+    pub fn with_missing(mut self, missing: Vec<(String, usize)>) -> Self {
+        self.missing = missing;
+        self
+    }
+}
+
+/// Which lenient fixes the notes needed to parse, the report of `aktenfux lenient-report`
+#[derive(Debug, Default)]
+pub struct LenientReport {
+    pub notes: usize,
+    /// The files each fix was needed in, with the lines it changed, sorted by path
+    pub fixes: BTreeMap<LenientFix, Vec<(String, Vec<usize>)>>,
+    /// Files whose frontmatter didn't parse even with the fixes
+    pub failed: Vec<String>,
+}
+
+impl LenientReport {
+    /// Check the frontmatter of the notes scanned with `parse.status` `lenient` again to
+    /// find out which fixes it needed
+    pub fn new(notes: &[&Note]) -> Self {
+        let mut report = Self {
+            notes: notes.len(),
+            ..Self::default()
+        };
+        for note in notes {
+            match note
+                .get_frontmatter_value("parse.status")
+                .and_then(yaml_as_str)
+            {
+                Some("lenient") => {
+                    // The scan just read the file, it only fails when it changed since
+                    let Ok(content) = fs::read_to_string(&note.path) else {
+                        continue;
+                    };
+                    if let Some((frontmatter, _)) = split_frontmatter(&content, note.fences) {
+                        report.add(&note.path, frontmatter);
+                    }
+                }
+                Some("failed") => report.failed.push(note.path.clone()),
+                _ => {}
+            }
+        }
+        for files in report.fixes.values_mut() {
+            files.sort();
+        }
+        report.failed.sort();
+        report
+    }
+
+    fn add(&mut self, path: &str, frontmatter: &str) {
+        let mut lines: BTreeMap<LenientFix, Vec<usize>> = BTreeMap::new();
+        for (line, fix) in lenient_fixes(frontmatter).1 {
+            // Line numbers of the file, below the opening fence
+            lines.entry(fix).or_default().push(line + 1);
+        }
+        for (fix, lines) in lines {
+            self.fixes
+                .entry(fix)
+                .or_default()
+                .push((path.to_string(), lines));
+        }
+    }
+
+    /// Every file that needed a fix or failed, sorted
+    pub fn paths(&self) -> Vec<&str> {
+        let mut paths: Vec<&str> = self
+            .fixes
+            .values()
+            .flatten()
+            .map(|(path, _)| path.as_str())
+            .chain(self.failed.iter().map(String::as_str))
+            .collect();
+        paths.sort_unstable();
+        paths.dedup();
+        paths
+    }
+}
+
+/// `part` as a percentage of `total`, 0 for no notes
+fn percent(part: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 * 100.0 / total as f64
+    }
+}
+
+/// A report per value of a field, like one per language with `lang`. Notes without the
+/// field are reported under `(none)`, notes with several values under each of them.
+pub fn metrics_by<'a>(
+    notes: &[&'a Note],
+    field: &str,
+    case_sensitive: bool,
+) -> Vec<(String, MetricsReport<'a>)> {
+    group_notes(notes, &[field.to_string()], case_sensitive)
+        .into_iter()
+        .map(|(key, notes)| (key.concat(), MetricsReport::new(&notes, 0)))
+        .collect()
+}
+
+/// The key metrics of a vault at one point in time, one line of the history file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// RFC 3339 timestamp of the scan
+    pub recorded_at: String,
+    pub notes: usize,
+    pub with_frontmatter: usize,
+    pub lines_average: f64,
+    pub keys_average: f64,
+    pub depth_max: usize,
+    /// Files that could not be read or parsed
+    #[serde(default)]
+    pub errors: usize,
+}
+
+impl Snapshot {
+    pub fn new(report: &MetricsReport, errors: usize, recorded_at: String) -> Self {
+        Self {
+            recorded_at,
+            notes: report.notes,
+            with_frontmatter: report.with_frontmatter,
+            lines_average: report.lines.average,
+            keys_average: report.keys.average,
+            depth_max: report.depth.max,
+            errors,
+        }
+    }
+
+    /// Share of notes without any frontmatter, in percent
+    pub fn without_frontmatter(&self) -> f64 {
+        percent(self.notes - self.with_frontmatter, self.notes)
+    }
+}
+
+/// Append a snapshot to the history file, creating the file and its directory if needed
+pub fn record_snapshot(path: &Path, snapshot: &Snapshot) -> Result<()> {
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    writeln!(file, "{}", serde_json::to_string(snapshot)?)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// The snapshots of a history file, oldest first
+pub fn read_history(path: &Path) -> Result<Vec<Snapshot>> {
+    let content = fs::read_to_string(path).with_context(|| {
+        format!(
+            "Failed to read stats history: {} (record one with stats --record)",
+            path.display()
+        )
+    })?;
+    let mut snapshots = content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line)
+                .with_context(|| format!("{}:{}: invalid snapshot", path.display(), index + 1))
+        })
+        .collect::<Result<Vec<Snapshot>>>()?;
+    snapshots.sort_by(|a, b| a.recorded_at.cmp(&b.recorded_at));
+    Ok(snapshots)
+}
+
+/// A line of block characters, one per value, scaled between the lowest and highest value
+pub fn sparkline(values: &[f64]) -> String {
+    const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    values
+        .iter()
+        .map(|value| {
+            if max > min {
+                BLOCKS[((value - min) / (max - min) * 7.0).round() as usize]
+            } else {
+                BLOCKS[0]
+            }
+        })
+        .collect()
+}
+
+/// The `top` notes with the highest key, ties broken by path
+fn top_notes<'a>(
+    notes: &[&'a Note],
+    top: usize,
+    key: impl Fn(&FrontmatterMetrics) -> (usize, usize),
+) -> Vec<&'a Note> {
+    let mut sorted: Vec<&Note> = notes
+        .iter()
+        .copied()
+        .filter(|note| note.metrics.keys > 0)
+        .collect();
+    sorted.sort_by(|a, b| {
+        key(&b.metrics)
+            .cmp(&key(&a.metrics))
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    sorted.truncate(top);
+    sorted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn note_with_metrics(path: &str, lines: usize, keys: usize, depth: usize) -> Note {
+        let mut note = Note::new(path.to_string(), HashMap::new());
+        note.set_metrics(FrontmatterMetrics { lines, keys, depth });
+        note
+    }
+
+    #[test]
+    fn test_metrics_report() {
+        let notes = vec![
+            note_with_metrics("a.md", 4, 2, 1),
+            note_with_metrics("b.md", 30, 12, 2),
+            note_with_metrics("c.md", 8, 3, 4),
+            note_with_metrics("empty.md", 0, 0, 0),
+        ];
+        let refs: Vec<&Note> = notes.iter().collect();
+        let report = MetricsReport::new(&refs, 2);
+
+        assert_eq!(report.notes, 4);
+        assert_eq!(report.with_frontmatter, 3);
+        assert_eq!(report.lines.max, 30);
+        assert!((report.keys.average - 17.0 / 3.0).abs() < f64::EPSILON);
+        let largest: Vec<&str> = report.largest.iter().map(|n| n.path.as_str()).collect();
+        assert_eq!(largest, vec!["b.md", "c.md"]);
+        assert_eq!(report.deepest[0].path, "c.md");
+    }
+
+    #[test]
+    fn test_metrics_by() {
+        let mut notes = vec![
+            note_with_metrics("de.md", 4, 2, 1),
+            note_with_metrics("en.md", 10, 6, 2),
+            note_with_metrics("en2.md", 20, 8, 1),
+            note_with_metrics("none.md", 2, 1, 1),
+        ];
+        for (note, lang) in notes.iter_mut().zip(["de", "en", "EN"]) {
+            note.frontmatter.insert(
+                "lang".to_string(),
+                yaml_rust2::Yaml::String(lang.to_string()),
+            );
+        }
+        let refs: Vec<&Note> = notes.iter().collect();
+
+        let by_lang = metrics_by(&refs, "lang", true);
+        let summary: Vec<(&str, usize, usize)> = by_lang
+            .iter()
+            .map(|(lang, report)| (lang.as_str(), report.notes, report.lines.max))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![("(none)", 1, 2), ("EN", 1, 20), ("de", 1, 4), ("en", 1, 10)]
+        );
+        assert!(by_lang[0].1.largest.is_empty());
+    }
+
+    #[test]
+    fn test_health_report() {
+        let mut notes = vec![
+            note_with_metrics("a.md", 2, 1, 1),
+            note_with_metrics("b.md", 2, 1, 1),
+            note_with_metrics("c.md", 3, 2, 1),
+            note_with_metrics("empty.md", 0, 0, 0),
+        ];
+        for (note, status) in notes.iter_mut().zip(["open", "done", "open"]) {
+            note.frontmatter.insert(
+                "status".to_string(),
+                yaml_rust2::Yaml::String(status.to_string()),
+            );
+        }
+        notes[2].frontmatter.insert(
+            "owner".to_string(),
+            yaml_rust2::Yaml::String("kim".to_string()),
+        );
+        let refs: Vec<&Note> = notes.iter().collect();
+
+        let report = HealthReport::new(&refs, 1)
+            .with_scan_problems(2, BTreeMap::from([("Lenient parsing".to_string(), 1)]))
+            .with_missing(vec![("owner".to_string(), 3)]);
+        assert_eq!(report.notes, 4);
+        assert_eq!(report.without_frontmatter, 1);
+        assert_eq!(report.errors, 2);
+        let fields: Vec<(&str, usize, f64)> = report
+            .fields
+            .iter()
+            .map(|field| (field.field.as_str(), field.notes, field.fill_rate))
+            .collect();
+        assert_eq!(fields, vec![("status", 3, 75.0), ("owner", 1, 25.0)]);
+        assert_eq!(report.fields[0].top_values, vec![("open".to_string(), 2)]);
+        assert_eq!(report.fields[0].kind, "string");
+    }
+
+    #[test]
+    fn test_lenient_report() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        for (name, content) in [
+            ("ok.md", "---\nstatus: open\n---\n"),
+            (
+                "colons.md",
+                "---\nsource: Book: Chapter 3\nurl: https://example.com\n---\n",
+            ),
+            (
+                "mixed.md",
+                "---\nproject:\n\tname: Site\nup: [[Home]] and [[Index]]\n---\n",
+            ),
+            ("failed.md", "---\ntags: [unclosed\n---\n"),
+        ] {
+            fs::write(temp_dir.path().join(name), content).unwrap();
+        }
+        let notes = crate::scanner::VaultScanner::new(temp_dir.path())
+            .unwrap()
+            .scan_vault(false, true, true, None)
+            .unwrap()
+            .notes;
+        let refs: Vec<&Note> = notes.iter().collect();
+        let path = |name: &str| temp_dir.path().join(name).to_string_lossy().to_string();
+
+        let report = LenientReport::new(&refs);
+        assert_eq!(report.notes, 4);
+        assert_eq!(
+            report.fixes.keys().copied().collect::<Vec<_>>(),
+            LenientFix::ALL
+        );
+        assert_eq!(
+            report.fixes[&LenientFix::Tabs],
+            vec![(path("mixed.md"), vec![3])]
+        );
+        assert_eq!(
+            report.fixes[&LenientFix::Wikilinks],
+            vec![(path("mixed.md"), vec![4])]
+        );
+        assert_eq!(
+            report.fixes[&LenientFix::Colons],
+            vec![(path("colons.md"), vec![2, 3])]
+        );
+        assert_eq!(report.failed, vec![path("failed.md")]);
+        assert_eq!(report.paths().len(), 3);
+    }
+
+    #[test]
+    fn test_stats_history() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join(HISTORY_FILE);
+        let notes = vec![
+            note_with_metrics("a.md", 4, 2, 1),
+            note_with_metrics("empty.md", 0, 0, 0),
+        ];
+        let refs: Vec<&Note> = notes.iter().collect();
+        let report = MetricsReport::new(&refs, 0);
+
+        let later = Snapshot::new(&report, 1, "2024-02-01T09:00:00+01:00".to_string());
+        let earlier = Snapshot {
+            notes: 1,
+            ..Snapshot::new(&report, 0, "2024-01-01T09:00:00+01:00".to_string())
+        };
+        record_snapshot(&path, &later).unwrap();
+        record_snapshot(&path, &earlier).unwrap();
+
+        let history = read_history(&path).unwrap();
+        assert_eq!(history, vec![earlier, later]);
+        assert!((history[1].without_frontmatter() - 50.0).abs() < f64::EPSILON);
+        assert!(read_history(&temp_dir.path().join("missing.jsonl")).is_err());
+
+        assert_eq!(sparkline(&[1.0, 8.0, 4.5]), "▁█▅");
+        assert_eq!(sparkline(&[3.0, 3.0]), "▁▁");
+    }
+}
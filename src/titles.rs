@@ -0,0 +1,159 @@
+use crate::frontmatter::Note;
+use crate::yaml_compat::collect_yaml_strings;
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+/// How a name refers to a note
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum NameKind {
+    /// The file name without `.md`
+    FileName,
+    /// The `title` field
+    Title,
+    /// An entry of the `aliases` (or `alias`) field
+    Alias,
+}
+
+impl NameKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::FileName => "file name",
+            Self::Title => "title",
+            Self::Alias => "alias",
+        }
+    }
+}
+
+/// A note a name refers to, and how
+#[derive(Debug, Clone)]
+pub struct NameMatch<'a> {
+    pub note: &'a Note,
+    pub kind: NameKind,
+    /// The name as written in the note
+    pub name: String,
+}
+
+/// Index of the names notes go by (file names, titles and aliases), matched ignoring case
+pub struct TitleIndex<'a> {
+    names: BTreeMap<String, Vec<NameMatch<'a>>>,
+}
+
+impl<'a> TitleIndex<'a> {
+    pub fn new(notes: &'a [Note]) -> Self {
+        let mut names: BTreeMap<String, Vec<NameMatch<'a>>> = BTreeMap::new();
+        for note in notes {
+            let file_name = Path::new(&note.path)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string());
+            let title = note
+                .frontmatter
+                .get("title")
+                .map(collect_yaml_strings)
+                .unwrap_or_default();
+            let aliases = note.aliases();
+
+            let entries = file_name
+                .into_iter()
+                .map(|name| (NameKind::FileName, name))
+                .chain(title.into_iter().map(|name| (NameKind::Title, name)))
+                .chain(aliases.into_iter().map(|name| (NameKind::Alias, name)));
+            for (kind, name) in entries {
+                let name = name.trim().to_string();
+                if name.is_empty() {
+                    continue;
+                }
+                let matches = names.entry(name.to_lowercase()).or_default();
+                // A note whose title equals its file name is listed once
+                if !matches.iter().any(|m| std::ptr::eq(m.note, note)) {
+                    matches.push(NameMatch { note, kind, name });
+                }
+            }
+        }
+
+        for matches in names.values_mut() {
+            matches.sort_by(|a, b| {
+                a.kind
+                    .cmp(&b.kind)
+                    .then_with(|| a.note.path.cmp(&b.note.path))
+            });
+        }
+        Self { names }
+    }
+
+    /// The notes going by `name`, file name matches first
+    pub fn resolve(&self, name: &str) -> &[NameMatch<'a>] {
+        self.names
+            .get(&name.trim().to_lowercase())
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Names that refer to more than one note
+    pub fn collisions(&self) -> Vec<(&str, &[NameMatch<'a>])> {
+        self.names
+            .values()
+            .filter(|matches| {
+                let notes: HashSet<&str> = matches.iter().map(|m| m.note.path.as_str()).collect();
+                notes.len() > 1
+            })
+            .map(|matches| (matches[0].name.as_str(), matches.as_slice()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use yaml_rust2::Yaml;
+
+    fn note(path: &str, title: Option<&str>, aliases: &[&str]) -> Note {
+        let mut fm = HashMap::new();
+        if let Some(title) = title {
+            fm.insert("title".to_string(), Yaml::String(title.to_string()));
+        }
+        if !aliases.is_empty() {
+            fm.insert(
+                "aliases".to_string(),
+                Yaml::Array(
+                    aliases
+                        .iter()
+                        .map(|a| Yaml::String(a.to_string()))
+                        .collect(),
+                ),
+            );
+        }
+        Note::new(path.to_string(), fm)
+    }
+
+    #[test]
+    fn test_resolve_names() {
+        let notes = vec![
+            note("people/ada.md", Some("Ada Lovelace"), &["Ada"]),
+            note("projects/engine.md", Some("Analytical Engine"), &[]),
+            note("ideas/Ada.md", None, &[]),
+        ];
+        let index = TitleIndex::new(&notes);
+
+        let found = index.resolve("analytical engine");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].note.path, "projects/engine.md");
+        assert_eq!(found[0].kind, NameKind::Title);
+
+        let kinds: Vec<(&str, NameKind)> = index
+            .resolve("ADA")
+            .iter()
+            .map(|m| (m.note.path.as_str(), m.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("ideas/Ada.md", NameKind::FileName),
+                ("people/ada.md", NameKind::FileName),
+            ]
+        );
+        assert!(index.resolve("Babbage").is_empty());
+
+        let collisions: Vec<&str> = index.collisions().iter().map(|(name, _)| *name).collect();
+        assert_eq!(collisions, vec!["Ada"]);
+    }
+}
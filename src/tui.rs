@@ -0,0 +1,478 @@
+use aktenfux::filter::{FieldFilter, FilterCriteria};
+use aktenfux::frontmatter::Note;
+use aktenfux::matcher::ValueMatcher;
+use aktenfux::yaml_compat::{yaml_to_string, yaml_type_name, MatchMode};
+use anyhow::{anyhow, Context, Result};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use std::path::Path;
+use std::process::Command;
+use std::sync::Arc;
+
+/// Options the browser shares with the `filter` command
+pub struct BrowseOptions {
+    /// Filters given on the command line, always applied before the query
+    pub filters: Vec<FieldFilter>,
+    pub case_sensitive: bool,
+    pub match_mode: MatchMode,
+    pub include_inline_tags: bool,
+    pub matchers: Vec<(String, Arc<dyn ValueMatcher>)>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Mode {
+    Browse,
+    EditQuery,
+}
+
+struct App<'a> {
+    notes: &'a [Note],
+    vault_path: &'a Path,
+    options: BrowseOptions,
+    /// Filters typed in the browser: `field=value` terms and free text
+    query: String,
+    matches: Vec<usize>,
+    list_state: ListState,
+    mode: Mode,
+    status: Option<String>,
+}
+
+impl<'a> App<'a> {
+    fn new(notes: &'a [Note], vault_path: &'a Path, options: BrowseOptions) -> Self {
+        let mut app = Self {
+            notes,
+            vault_path,
+            options,
+            query: String::new(),
+            matches: Vec::new(),
+            list_state: ListState::default(),
+            mode: Mode::Browse,
+            status: None,
+        };
+        app.refresh();
+        app
+    }
+
+    /// Re-apply the command line filters and the query, keeping the selection in range
+    fn refresh(&mut self) {
+        let (query_filters, terms) = parse_query(&self.query);
+        let mut filters = self.options.filters.clone();
+        filters.extend(query_filters);
+
+        let criteria = if self.options.case_sensitive {
+            FilterCriteria::new(filters)
+        } else {
+            FilterCriteria::new_case_insensitive(filters)
+        }
+        .with_match_mode(self.options.match_mode)
+        .with_inline_tags(self.options.include_inline_tags);
+        let criteria = self
+            .options
+            .matchers
+            .iter()
+            .fold(criteria, |criteria, (field, matcher)| {
+                criteria.with_matcher(field, Arc::clone(matcher))
+            });
+
+        self.matches = self
+            .notes
+            .iter()
+            .enumerate()
+            .filter(|(_, note)| criteria.matches(note) && matches_terms(note, &terms))
+            .map(|(i, _)| i)
+            .collect();
+
+        let selected = self.list_state.selected().unwrap_or(0);
+        self.list_state.select(if self.matches.is_empty() {
+            None
+        } else {
+            Some(selected.min(self.matches.len() - 1))
+        });
+    }
+
+    fn selected_note(&self) -> Option<&'a Note> {
+        let notes = self.notes;
+        self.list_state
+            .selected()
+            .and_then(|i| self.matches.get(i))
+            .map(|&i| &notes[i])
+    }
+
+    fn move_selection(&mut self, offset: isize) {
+        if self.matches.is_empty() {
+            return;
+        }
+        let current = self.list_state.selected().unwrap_or(0);
+        let last = self.matches.len() - 1;
+        let next = current.saturating_add_signed(offset).min(last);
+        self.list_state.select(Some(next));
+    }
+
+    fn relative_path(&self, note: &Note) -> String {
+        Path::new(&note.path)
+            .strip_prefix(self.vault_path)
+            .unwrap_or_else(|_| Path::new(&note.path))
+            .to_string_lossy()
+            .to_string()
+    }
+}
+
+/// Open the interactive browser on the scanned notes
+pub fn run(notes: &[Note], vault_path: &Path, options: BrowseOptions) -> Result<()> {
+    let mut app = App::new(notes, vault_path, options);
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, &mut app);
+    ratatui::restore();
+    result
+}
+
+fn event_loop(terminal: &mut DefaultTerminal, app: &mut App) -> Result<()> {
+    loop {
+        terminal.draw(|frame| draw(frame, app))?;
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        app.status = None;
+
+        match app.mode {
+            Mode::EditQuery => match key.code {
+                KeyCode::Enter | KeyCode::Esc => app.mode = Mode::Browse,
+                KeyCode::Backspace => {
+                    app.query.pop();
+                    app.refresh();
+                }
+                KeyCode::Char(c) => {
+                    app.query.push(c);
+                    app.refresh();
+                }
+                _ => {}
+            },
+            Mode::Browse => match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char('/') => app.mode = Mode::EditQuery,
+                KeyCode::Down | KeyCode::Char('j') => app.move_selection(1),
+                KeyCode::Up | KeyCode::Char('k') => app.move_selection(-1),
+                KeyCode::PageDown => app.move_selection(10),
+                KeyCode::PageUp => app.move_selection(-10),
+                KeyCode::Enter => {
+                    if let Some(note) = app.selected_note() {
+                        ratatui::restore();
+                        let opened = open_note(note, app.vault_path);
+                        *terminal = ratatui::init();
+                        app.status = opened.err().map(|e| e.to_string());
+                    }
+                }
+                KeyCode::Char('o') => {
+                    if let Some(note) = app.selected_note() {
+                        app.status = open_in_obsidian(note, app.vault_path)
+                            .err()
+                            .map(|e| e.to_string());
+                    }
+                }
+                _ => {}
+            },
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, app: &mut App) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(1),
+            Constraint::Length(3),
+            Constraint::Length(1),
+        ])
+        .split(frame.area());
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(rows[0]);
+
+    let items: Vec<ListItem> = app
+        .matches
+        .iter()
+        .map(|&i| {
+            let note = &app.notes[i];
+            let mut spans = vec![Span::styled(
+                app.relative_path(note),
+                Style::default().fg(Color::Cyan),
+            )];
+            if let Some(title) = &note.title {
+                spans.push(Span::raw("  "));
+                spans.push(Span::styled(
+                    title.clone(),
+                    Style::default().fg(Color::Green),
+                ));
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(format!(
+            " Notes ({}/{}) ",
+            app.matches.len(),
+            app.notes.len()
+        )))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(list, columns[0], &mut app.list_state);
+
+    let preview = app.selected_note().map(preview_lines).unwrap_or_default();
+    frame.render_widget(
+        Paragraph::new(preview)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(" Frontmatter "),
+            )
+            .wrap(Wrap { trim: false }),
+        columns[1],
+    );
+
+    let query_style = if app.mode == Mode::EditQuery {
+        Style::default().fg(Color::Yellow)
+    } else {
+        Style::default()
+    };
+    frame.render_widget(
+        Paragraph::new(app.query.as_str()).style(query_style).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" Filter (field=value or text) "),
+        ),
+        rows[1],
+    );
+
+    let help = app.status.clone().unwrap_or_else(|| {
+        "/ filter  ↑↓ move  Enter open in $EDITOR  o open in Obsidian  q quit".to_string()
+    });
+    frame.render_widget(
+        Paragraph::new(help).style(Style::default().add_modifier(Modifier::DIM)),
+        rows[2],
+    );
+}
+
+fn preview_lines(note: &Note) -> Vec<Line<'static>> {
+    let mut keys: Vec<&String> = note.frontmatter.keys().collect();
+    keys.sort();
+
+    let mut lines: Vec<Line> = keys
+        .into_iter()
+        .map(|key| {
+            let value = &note.frontmatter[key];
+            let text = match value {
+                yaml_rust2::Yaml::Array(items) => items
+                    .iter()
+                    .map(yaml_to_string)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                other => yaml_to_string(other),
+            };
+            Line::from(vec![
+                Span::styled(
+                    format!("{}: ", key),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(text),
+                Span::styled(
+                    format!("  ({})", yaml_type_name(value)),
+                    Style::default().add_modifier(Modifier::DIM),
+                ),
+            ])
+        })
+        .collect();
+
+    if lines.is_empty() {
+        lines.push(Line::from("No frontmatter"));
+    }
+    if !note.inline_tags.is_empty() {
+        lines.push(Line::from(""));
+        lines.push(Line::from(format!(
+            "Inline tags: {}",
+            note.inline_tags.join(", ")
+        )));
+    }
+    if !note.links.is_empty() {
+        lines.push(Line::from(format!("Links: {}", note.links.join(", "))));
+    }
+    lines
+}
+
+/// Split a browser query into `field=value` filters and free text terms
+fn parse_query(query: &str) -> (Vec<FieldFilter>, Vec<String>) {
+    let mut filters = Vec::new();
+    let mut terms = Vec::new();
+    for word in query.split_whitespace() {
+        match FieldFilter::parse(word) {
+            Ok(filter) if !filter.field.is_empty() => filters.push(filter),
+            _ => terms.push(word.to_lowercase()),
+        }
+    }
+    (filters, terms)
+}
+
+/// Free text terms match the path or title, ignoring case
+fn matches_terms(note: &Note, terms: &[String]) -> bool {
+    let path = note.path.to_lowercase();
+    let title = note.title.as_deref().unwrap_or_default().to_lowercase();
+    terms
+        .iter()
+        .all(|term| path.contains(term) || title.contains(term))
+}
+
+/// Open a note in `$EDITOR`, or in Obsidian when no editor is configured
+fn open_note(note: &Note, vault_path: &Path) -> Result<()> {
+    let Ok(editor) = std::env::var("EDITOR") else {
+        return open_in_obsidian(note, vault_path);
+    };
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().ok_or_else(|| anyhow!("$EDITOR is empty"))?;
+    let status = Command::new(program)
+        .args(parts)
+        .arg(&note.path)
+        .status()
+        .with_context(|| format!("Failed to start editor: {}", editor))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!("Editor exited with {}", status))
+    }
+}
+
+/// Open a note in Obsidian through its `obsidian://open` URI
+pub fn open_in_obsidian(note: &Note, vault_path: &Path) -> Result<()> {
+    let uri = obsidian_uri(note, vault_path)?;
+    // Through `cmd /C start` the `&` between the query parameters would end the command
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("rundll32");
+        command.arg("url.dll,FileProtocolHandler");
+        command
+    } else if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else {
+        Command::new("xdg-open")
+    };
+    let status = command
+        .arg(&uri)
+        .status()
+        .with_context(|| format!("Failed to open {}", uri))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "Failed to open {}: the opener exited with {}",
+            uri,
+            status
+        ))
+    }
+}
+
+/// `obsidian://open` URI for a note, using the vault folder name as the vault name
+fn obsidian_uri(note: &Note, vault_path: &Path) -> Result<String> {
+    let vault_path = vault_path
+        .canonicalize()
+        .unwrap_or_else(|_| vault_path.to_path_buf());
+    let vault_name = vault_path
+        .file_name()
+        .ok_or_else(|| anyhow!("Cannot determine the vault name"))?
+        .to_string_lossy()
+        .to_string();
+    let note_path = Path::new(&note.path);
+    let note_path = note_path
+        .canonicalize()
+        .unwrap_or_else(|_| note_path.to_path_buf());
+    let relative = note_path
+        .strip_prefix(&vault_path)
+        .unwrap_or(&note_path)
+        .to_string_lossy()
+        .to_string();
+
+    Ok(format!(
+        "obsidian://open?vault={}&file={}",
+        percent_encode(&vault_name),
+        percent_encode(&relative)
+    ))
+}
+
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|b| {
+            if b.is_ascii_alphanumeric() || b"-_.~".contains(&b) {
+                (b as char).to_string()
+            } else {
+                format!("%{:02X}", b)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use yaml_rust2::Yaml;
+
+    #[test]
+    fn test_parse_query() {
+        let (filters, terms) = parse_query("status=open Meeting =x");
+        assert_eq!(
+            filters,
+            vec![FieldFilter::from((
+                "status".to_string(),
+                "open".to_string()
+            ))]
+        );
+        assert_eq!(terms, vec!["meeting".to_string(), "=x".to_string()]);
+    }
+
+    #[test]
+    fn test_app_refresh_combines_filters_and_query() {
+        let mut open = HashMap::new();
+        open.insert("status".to_string(), Yaml::String("open".to_string()));
+        let mut done = HashMap::new();
+        done.insert("status".to_string(), Yaml::String("done".to_string()));
+        let notes = vec![
+            Note::new("vault/meeting.md".to_string(), open.clone()),
+            Note::new("vault/plan.md".to_string(), open),
+            Note::new("vault/meeting-old.md".to_string(), done),
+        ];
+        let options = BrowseOptions {
+            filters: vec![("status".to_string(), "open".to_string()).into()],
+            case_sensitive: true,
+            match_mode: MatchMode::Substring,
+            include_inline_tags: false,
+            matchers: Vec::new(),
+        };
+
+        let mut app = App::new(&notes, Path::new("vault"), options);
+        assert_eq!(app.matches, vec![0, 1]);
+
+        app.query = "MEETING".to_string();
+        app.refresh();
+        assert_eq!(app.matches, vec![0]);
+        assert_eq!(
+            app.relative_path(app.selected_note().unwrap()),
+            "meeting.md"
+        );
+    }
+
+    #[test]
+    fn test_obsidian_uri() {
+        let note = Note::new(
+            "My Vault/Daily Notes/2024-01-05.md".to_string(),
+            HashMap::new(),
+        );
+        assert_eq!(
+            obsidian_uri(&note, Path::new("My Vault")).unwrap(),
+            "obsidian://open?vault=My%20Vault&file=Daily%20Notes%2F2024-01-05.md"
+        );
+    }
+}
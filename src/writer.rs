@@ -0,0 +1,521 @@
+use crate::frontmatter::{
+    fix_tag_syntax, fix_yaml_issues, frontmatter_block, split_frontmatter, Fences, Note,
+};
+use crate::plan::{ChangePlan, PlannedChange};
+use anyhow::{anyhow, Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use yaml_rust2::yaml::Hash;
+use yaml_rust2::{Yaml, YamlEmitter, YamlLoader};
+
+/// A note split into its frontmatter mapping and the untouched body
+#[derive(Debug, Clone)]
+pub struct FrontmatterDocument {
+    pub frontmatter: Hash,
+    original: Hash,
+    original_text: Option<String>,
+    /// The opening and closing fence lines as written, kept when the note is rendered
+    fence_lines: Option<(String, String)>,
+    body: String,
+}
+
+impl FrontmatterDocument {
+    pub fn parse(content: &str, fences: Fences) -> Result<Self> {
+        let Some(block) = frontmatter_block(content, fences) else {
+            return Ok(Self {
+                frontmatter: Hash::new(),
+                original: Hash::new(),
+                original_text: None,
+                fence_lines: None,
+                body: content.to_string(),
+            });
+        };
+        let frontmatter_text = block.text;
+
+        let frontmatter = parse_hash(frontmatter_text).or_else(|e| {
+            // Fall back to the same repairs the lenient parser applies
+            parse_hash(&fix_yaml_issues(frontmatter_text)).map_err(|_| e)
+        })?;
+
+        Ok(Self {
+            original: frontmatter.clone(),
+            frontmatter,
+            original_text: Some(frontmatter_text.to_string()),
+            fence_lines: Some((block.opener.to_string(), block.closer.to_string())),
+            body: block.body.to_string(),
+        })
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.frontmatter != self.original
+    }
+
+    /// The frontmatter block as it currently appears in the file
+    pub fn original_text(&self) -> String {
+        self.original_text.clone().unwrap_or_default()
+    }
+
+    /// The frontmatter block as it will be written.
+    ///
+    /// Fields whose value is unchanged keep their original text (formatting, comments),
+    /// only new or modified fields are serialized again.
+    pub fn frontmatter_text(&self) -> Result<String> {
+        let Some(original_text) = &self.original_text else {
+            return emit_hash(&self.frontmatter);
+        };
+
+        let (preamble, chunks) = split_top_level_keys(original_text);
+        let mut text = preamble;
+        for (key, value) in &self.frontmatter {
+            let unchanged = key
+                .as_str()
+                .and_then(|k| chunks.get(k))
+                .filter(|_| self.original.get(key) == Some(value));
+            match unchanged {
+                Some(chunk) => text.push_str(chunk),
+                None => {
+                    let mut entry = Hash::new();
+                    entry.insert(key.clone(), value.clone());
+                    text.push_str(&emit_hash(&entry)?);
+                }
+            }
+        }
+        Ok(text)
+    }
+
+    /// The full note content with the current frontmatter and the original body, between
+    /// the original fences or `---` for a new frontmatter block
+    pub fn render(&self) -> Result<String> {
+        if self.frontmatter.is_empty() && self.original_text.is_none() {
+            return Ok(self.body.clone());
+        }
+        let (opener, closer) = self
+            .fence_lines
+            .as_ref()
+            .map_or(("---\n", "---\n"), |(opener, closer)| {
+                (opener.as_str(), closer.as_str())
+            });
+        Ok(format!(
+            "{}{}{}{}",
+            opener,
+            self.frontmatter_text()?,
+            closer,
+            self.body
+        ))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum FrontmatterEdit {
+    /// Replace the value of a field, creating it if needed
+    Set(String, Yaml),
+    /// Append a value to a list field, turning scalars into lists
+    Add(String, Yaml),
+    /// Remove a field entirely
+    Remove(String),
+    /// Remove a single value from a list field (or the field if it holds just that value)
+    RemoveValue(String, Yaml),
+    /// Rename a field, keeping its position; skipped if the new name is already taken
+    Rename(String, String),
+    /// Replace a value of a field, including occurrences inside list fields
+    Replace(String, Yaml, Yaml),
+}
+
+impl FrontmatterEdit {
+    /// Apply the edit, returning whether the frontmatter changed
+    pub fn apply(&self, frontmatter: &mut Hash) -> bool {
+        let before = frontmatter.clone();
+        match self {
+            Self::Set(key, value) => {
+                let key = Yaml::String(key.clone());
+                // Update in place so the key keeps its position
+                if let Some(existing) = frontmatter.get_mut(&key) {
+                    *existing = value.clone();
+                } else {
+                    frontmatter.insert(key, value.clone());
+                }
+            }
+            Self::Add(key, value) => {
+                let key = Yaml::String(key.clone());
+                match frontmatter.get_mut(&key) {
+                    Some(Yaml::Array(items)) => {
+                        if !items.contains(value) {
+                            items.push(value.clone());
+                        }
+                    }
+                    Some(Yaml::Null) | None => {
+                        frontmatter.insert(key, Yaml::Array(vec![value.clone()]));
+                    }
+                    Some(existing) => {
+                        if existing != value {
+                            *existing = Yaml::Array(vec![existing.clone(), value.clone()]);
+                        }
+                    }
+                }
+            }
+            Self::Remove(key) => {
+                frontmatter.remove(&Yaml::String(key.clone()));
+            }
+            Self::RemoveValue(key, value) => {
+                let key = Yaml::String(key.clone());
+                match frontmatter.get_mut(&key) {
+                    Some(Yaml::Array(items)) => items.retain(|item| item != value),
+                    Some(existing) if existing == value => {
+                        frontmatter.remove(&key);
+                    }
+                    _ => {}
+                }
+            }
+            Self::Rename(old, new) => {
+                let old_key = Yaml::String(old.clone());
+                let new_key = Yaml::String(new.clone());
+                if frontmatter.contains_key(&old_key) && !frontmatter.contains_key(&new_key) {
+                    *frontmatter = std::mem::take(frontmatter)
+                        .into_iter()
+                        .map(|(k, v)| {
+                            if k == old_key {
+                                (new_key.clone(), v)
+                            } else {
+                                (k, v)
+                            }
+                        })
+                        .collect();
+                }
+            }
+            Self::Replace(key, from, to) => match frontmatter.get_mut(&Yaml::String(key.clone())) {
+                Some(Yaml::Array(items)) => {
+                    for item in items.iter_mut().filter(|item| *item == from) {
+                        *item = to.clone();
+                    }
+                }
+                Some(existing) if existing == from => *existing = to.clone(),
+                _ => {}
+            },
+        }
+        *frontmatter != before
+    }
+}
+
+/// Parse a command line value into a typed YAML scalar (`3` becomes a number, `true` a bool)
+pub fn parse_scalar(value: &str) -> Yaml {
+    Yaml::from_str(value)
+}
+
+pub fn read_document<P: AsRef<Path>>(path: P, fences: Fences) -> Result<FrontmatterDocument> {
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read file: {}", path.as_ref().display()))?;
+    FrontmatterDocument::parse(&content, fences)
+        .with_context(|| format!("Cannot edit frontmatter of {}", path.as_ref().display()))
+}
+
+/// Build a change plan by applying `edit` to the frontmatter of every note.
+///
+/// Notes whose frontmatter can't be parsed are reported and left out of the plan.
+pub fn plan_changes<F>(notes: &[&Note], mut edit: F) -> ChangePlan
+where
+    F: FnMut(&Note, &mut Hash),
+{
+    let mut plan = ChangePlan::new();
+    for note in notes {
+        // Frontmatter would break files like canvases, which are JSON
+        if !note.is_markdown() {
+            eprintln!(
+                "Warning: Skipping {}, only markdown notes can be edited",
+                note.path
+            );
+            continue;
+        }
+        let mut doc = match read_document(&note.path, note.fences) {
+            Ok(doc) => doc,
+            Err(e) => {
+                eprintln!("Warning: {:#}", e);
+                continue;
+            }
+        };
+
+        edit(note, &mut doc.frontmatter);
+        if !doc.is_modified() {
+            continue;
+        }
+
+        match (doc.frontmatter_text(), doc.render()) {
+            (Ok(after), Ok(content)) => plan.push(
+                PlannedChange::new(note.path.clone(), doc.original_text(), after)
+                    .with_content(content),
+            ),
+            (Err(e), _) | (_, Err(e)) => eprintln!("Warning: {:#} ({})", e, note.path),
+        }
+    }
+    plan
+}
+
+/// Rewrite a frontmatter block into YAML that parses without the lenient repairs: tag
+/// lists are normalized (unless `lenient_only`) and the lenient fixes are applied when the
+/// block doesn't parse.
+///
+/// `Ok(None)` when the block needs no repair, an error when it can't be repaired.
+pub fn repair_frontmatter(text: &str, lenient_only: bool) -> Result<Option<String>> {
+    let mut repaired = if lenient_only {
+        text.to_string()
+    } else {
+        fix_tag_syntax(text)
+    };
+    if parse_hash(&repaired).is_err() {
+        repaired = fix_yaml_issues(&repaired);
+        if text.ends_with('\n') && !repaired.ends_with('\n') {
+            repaired.push('\n');
+        }
+    }
+    parse_hash(&repaired)?;
+    Ok((repaired != text).then_some(repaired))
+}
+
+/// Build a change plan repairing the frontmatter of every note, see `repair_frontmatter`.
+///
+/// Also returns the notes whose frontmatter can't be repaired, with the reason.
+pub fn plan_repairs(notes: &[&Note], lenient_only: bool) -> (ChangePlan, Vec<(String, String)>) {
+    let mut plan = ChangePlan::new();
+    let mut unrepairable = Vec::new();
+    for note in notes {
+        let content = match fs::read_to_string(&note.path) {
+            Ok(content) => content,
+            Err(e) => {
+                eprintln!("Warning: Failed to read file: {} ({})", note.path, e);
+                continue;
+            }
+        };
+        let Some((text, _)) = split_frontmatter(&content, note.fences) else {
+            continue;
+        };
+
+        match repair_frontmatter(text, lenient_only) {
+            Ok(Some(repaired)) => {
+                // Only the block itself changes, fences and body are kept byte for byte
+                let start = text.as_ptr() as usize - content.as_ptr() as usize;
+                let new_content = format!(
+                    "{}{}{}",
+                    &content[..start],
+                    repaired,
+                    &content[start + text.len()..]
+                );
+                plan.push(
+                    PlannedChange::new(note.path.clone(), text.to_string(), repaired)
+                        .with_content(new_content),
+                );
+            }
+            Ok(None) => {}
+            Err(e) => unrepairable.push((note.path.clone(), format!("{:#}", e))),
+        }
+    }
+    (plan, unrepairable)
+}
+
+/// Split frontmatter text into the lines before the first key and one text chunk per
+/// top-level key (the key line plus its indented or list continuation lines)
+fn split_top_level_keys(text: &str) -> (String, HashMap<String, String>) {
+    let mut preamble = String::new();
+    let mut chunks: HashMap<String, String> = HashMap::new();
+    let mut current: Option<String> = None;
+
+    for line in text.split_inclusive('\n') {
+        let line = if line.ends_with('\n') {
+            line.to_string()
+        } else {
+            format!("{}\n", line)
+        };
+
+        let starts_key =
+            !line.starts_with([' ', '\t', '#', '-']) && line.trim_end() != "" && line.contains(':');
+        if starts_key {
+            let key = line[..line.find(':').unwrap_or(0)]
+                .trim()
+                .trim_matches(|c| c == '"' || c == '\'')
+                .to_string();
+            chunks.insert(key.clone(), line);
+            current = Some(key);
+        } else if let Some(chunk) = current.as_ref().and_then(|k| chunks.get_mut(k)) {
+            chunk.push_str(&line);
+        } else {
+            preamble.push_str(&line);
+        }
+    }
+
+    (preamble, chunks)
+}
+
+fn parse_hash(text: &str) -> Result<Hash> {
+    let docs = YamlLoader::load_from_str(text).map_err(|e| anyhow!("YAML parsing error: {}", e))?;
+    match docs.into_iter().next() {
+        Some(Yaml::Hash(hash)) => Ok(hash),
+        Some(Yaml::Null) | None => Ok(Hash::new()),
+        Some(other) => Err(anyhow!(
+            "Expected hash or null at document root, got {:?}",
+            other
+        )),
+    }
+}
+
+fn emit_hash(hash: &Hash) -> Result<String> {
+    if hash.is_empty() {
+        return Ok(String::new());
+    }
+
+    let mut out = String::new();
+    let mut emitter = YamlEmitter::new(&mut out);
+    emitter.multiline_strings(true);
+    emitter
+        .dump(&Yaml::Hash(hash.clone()))
+        .map_err(|e| anyhow!("Failed to serialize frontmatter: {}", e))?;
+
+    // The emitter always starts a new document with "---"
+    let text = out.strip_prefix("---\n").unwrap_or(&out);
+    Ok(format!("{}\n", text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTE: &str = "---\ntitle: Test\nstatus: open\ntags: [work]\n---\n\n# Body\n\n---\nmore\n";
+
+    #[test]
+    fn test_render_preserves_body() {
+        let mut doc = FrontmatterDocument::parse(NOTE, Fences::Strict).unwrap();
+        FrontmatterEdit::Set("status".to_string(), parse_scalar("done"))
+            .apply(&mut doc.frontmatter);
+
+        assert!(doc.is_modified());
+        let rendered = doc.render().unwrap();
+        assert!(rendered.starts_with("---\ntitle: Test\nstatus: done\ntags: [work]\n---"));
+        assert!(rendered.ends_with("---\n\n# Body\n\n---\nmore\n"));
+    }
+
+    #[test]
+    fn test_render_keeps_fences() {
+        let dotted = "----\ntitle: Imported\n...\nBody\n";
+        let mut doc = FrontmatterDocument::parse(dotted, Fences::Tolerant).unwrap();
+        FrontmatterEdit::Set("status".to_string(), parse_scalar("done"))
+            .apply(&mut doc.frontmatter);
+        assert_eq!(
+            doc.render().unwrap(),
+            "----\ntitle: Imported\nstatus: done\n...\nBody\n"
+        );
+    }
+
+    #[test]
+    fn test_add_and_remove_values() {
+        let mut doc = FrontmatterDocument::parse(NOTE, Fences::Strict).unwrap();
+        FrontmatterEdit::Add("tags".to_string(), parse_scalar("reviewed"))
+            .apply(&mut doc.frontmatter);
+        FrontmatterEdit::Add("tags".to_string(), parse_scalar("work")).apply(&mut doc.frontmatter);
+        FrontmatterEdit::Add("status".to_string(), parse_scalar("late"))
+            .apply(&mut doc.frontmatter);
+        FrontmatterEdit::RemoveValue("tags".to_string(), parse_scalar("work"))
+            .apply(&mut doc.frontmatter);
+        FrontmatterEdit::Remove("title".to_string()).apply(&mut doc.frontmatter);
+
+        let tags = &doc.frontmatter[&Yaml::String("tags".to_string())];
+        assert_eq!(
+            tags,
+            &Yaml::Array(vec![Yaml::String("reviewed".to_string())])
+        );
+        let status = &doc.frontmatter[&Yaml::String("status".to_string())];
+        assert_eq!(status.as_vec().map(Vec::len), Some(2));
+        assert!(!doc
+            .frontmatter
+            .contains_key(&Yaml::String("title".to_string())));
+    }
+
+    #[test]
+    fn test_rename_keeps_position() {
+        let mut doc = FrontmatterDocument::parse(NOTE, Fences::Strict).unwrap();
+        assert!(
+            FrontmatterEdit::Rename("status".to_string(), "state".to_string())
+                .apply(&mut doc.frontmatter)
+        );
+        // Renaming onto an existing key is refused
+        assert!(
+            !FrontmatterEdit::Rename("state".to_string(), "title".to_string())
+                .apply(&mut doc.frontmatter)
+        );
+
+        let keys: Vec<&str> = doc.frontmatter.keys().filter_map(Yaml::as_str).collect();
+        assert_eq!(keys, vec!["title", "state", "tags"]);
+    }
+
+    #[test]
+    fn test_unchanged_fields_keep_formatting() {
+        let content = "---\n# comment\ntitle: Test\ntags:\n- a\n- b\nlinks: [p, q]\n---\nBody\n";
+        let mut doc = FrontmatterDocument::parse(content, Fences::Strict).unwrap();
+        FrontmatterEdit::Add("links".to_string(), parse_scalar("z")).apply(&mut doc.frontmatter);
+
+        assert_eq!(
+            doc.frontmatter_text().unwrap(),
+            "# comment\ntitle: Test\ntags:\n- a\n- b\nlinks:\n  - p\n  - q\n  - z\n"
+        );
+    }
+
+    #[test]
+    fn test_note_without_frontmatter() {
+        let content = "# Just a note\n";
+        let mut doc = FrontmatterDocument::parse(content, Fences::Strict).unwrap();
+        assert_eq!(doc.render().unwrap(), content);
+
+        FrontmatterEdit::Set("status".to_string(), parse_scalar("open"))
+            .apply(&mut doc.frontmatter);
+        assert_eq!(
+            doc.render().unwrap(),
+            "---\nstatus: open\n---\n# Just a note\n"
+        );
+    }
+
+    #[test]
+    fn test_lenient_frontmatter_is_editable() {
+        let content = "---\nsource: Book: Chapter 3\n---\nBody\n";
+        let doc = FrontmatterDocument::parse(content, Fences::Strict).unwrap();
+        assert!(!doc.is_modified());
+        assert_eq!(doc.original_text(), "source: Book: Chapter 3\n");
+    }
+
+    #[test]
+    fn test_repair_frontmatter() {
+        assert_eq!(
+            repair_frontmatter("source: Book: Chapter 3\ntags: #a #b\n", false).unwrap(),
+            Some("source: \"Book: Chapter 3\"\ntags: [a, b]\n".to_string())
+        );
+        assert_eq!(
+            repair_frontmatter("title: Fine\ntags: [a]\n", false).unwrap(),
+            None
+        );
+        assert!(repair_frontmatter("title: [unclosed\n", false).is_err());
+
+        // Only what the lenient parser would fix, Obsidian style tags are valid YAML
+        assert_eq!(
+            repair_frontmatter("source: Book: Chapter 3\ntags: #a #b\n", true).unwrap(),
+            Some("source: \"Book: Chapter 3\"\ntags: #a #b\n".to_string())
+        );
+        assert_eq!(
+            repair_frontmatter("project:\n\tname: Site\n", true).unwrap(),
+            Some("project:\n  name: Site\n".to_string())
+        );
+        assert_eq!(repair_frontmatter("tags: a, b\n", true).unwrap(), None);
+    }
+
+    #[test]
+    fn test_plan_repairs_keeps_body() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("note.md");
+        fs::write(&path, "---\nsource: a: b\n---\nBody: c\n").unwrap();
+        let note = Note::new(path.to_string_lossy().to_string(), HashMap::new());
+
+        let (plan, unrepairable) = plan_repairs(&[&note], false);
+        assert!(unrepairable.is_empty());
+        assert_eq!(plan.len(), 1);
+        plan.apply().unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "---\nsource: \"a: b\"\n---\nBody: c\n"
+        );
+    }
+}
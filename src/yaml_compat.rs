@@ -1,5 +1,6 @@
 use anyhow::{anyhow, Result};
 use std::collections::HashMap;
+use yaml_rust2::yaml::Hash;
 use yaml_rust2::{Yaml, YamlLoader};
 
 /// Compatibility wrapper for yaml-rust2 to match serde_yaml behavior
@@ -11,11 +12,47 @@ pub fn parse_yaml_frontmatter(content: &str) -> Result<HashMap<String, Yaml>> {
         return Ok(HashMap::new());
     }
 
-    // Take the first document (frontmatter is single document)
-    let doc = &docs[0];
+    // Take the first document (frontmatter is single document). Aliases are already
+    // replaced by the values of their anchors, merge keys are left to us.
+    let doc = resolve_merge_keys(docs.into_iter().next().unwrap_or(Yaml::Null));
 
     // Convert to string-keyed HashMap
-    yaml_to_string_map(doc)
+    yaml_to_string_map(&doc)
+}
+
+/// Resolve `<<: *base` merge keys: the entries of the merged map (or maps, the first one
+/// winning) are added unless the mapping sets them itself.
+///
+/// A `<<` whose value isn't a map or a list of maps is kept as an ordinary key.
+fn resolve_merge_keys(yaml: Yaml) -> Yaml {
+    match yaml {
+        Yaml::Array(items) => Yaml::Array(items.into_iter().map(resolve_merge_keys).collect()),
+        Yaml::Hash(hash) => {
+            let mut resolved = Hash::new();
+            for (key, value) in hash {
+                let value = resolve_merge_keys(value);
+                let sources = match (&key, &value) {
+                    (Yaml::String(name), Yaml::Hash(source)) if name == "<<" => vec![source],
+                    (Yaml::String(name), Yaml::Array(items))
+                        if name == "<<" && items.iter().all(|item| item.as_hash().is_some()) =>
+                    {
+                        items.iter().filter_map(Yaml::as_hash).collect()
+                    }
+                    _ => {
+                        resolved.insert(key, value);
+                        continue;
+                    }
+                };
+                for (merged_key, merged_value) in sources.into_iter().flatten() {
+                    if !resolved.contains_key(merged_key) {
+                        resolved.insert(merged_key.clone(), merged_value.clone());
+                    }
+                }
+            }
+            Yaml::Hash(resolved)
+        }
+        other => other,
+    }
 }
 
 /// Convert Yaml::Hash to HashMap<String, Yaml> for string keys only
@@ -47,66 +84,141 @@ pub fn yaml_as_str(yaml: &Yaml) -> Option<&str> {
     }
 }
 
-/// Helper function to check if Yaml contains a string value (replaces serde_yaml pattern matching)
-pub fn yaml_contains_str(yaml: &Yaml, search: &str) -> bool {
-    match yaml {
-        Yaml::String(s) => s.contains(search),
-        Yaml::Array(arr) => arr.iter().any(|item| yaml_contains_str(item, search)),
-        Yaml::Integer(n) => n.to_string().contains(search),
-        Yaml::Real(f) => f.to_string().contains(search),
-        Yaml::Boolean(b) => b.to_string().contains(search),
-        _ => false,
+/// How a string value is compared with a search string
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MatchMode {
+    /// The value contains the search string, so `active` also matches `inactive`
+    #[default]
+    Substring,
+    /// The value equals the search string
+    Exact,
+}
+
+impl MatchMode {
+    pub fn matches(self, value: &str, search: &str, case_sensitive: bool) -> bool {
+        match (self, case_sensitive) {
+            (Self::Substring, true) => value.contains(search),
+            (Self::Substring, false) => value.to_lowercase().contains(&search.to_lowercase()),
+            (Self::Exact, true) => value == search,
+            (Self::Exact, false) => value.to_lowercase() == search.to_lowercase(),
+        }
     }
 }
 
-/// Helper function to check if Yaml contains a string value (case-insensitive)
-pub fn yaml_contains_str_case_insensitive(yaml: &Yaml, search: &str) -> bool {
-    let search_lower = search.to_lowercase();
+/// Check if a scalar Yaml value, or any item of a list, matches a string
+pub fn yaml_matches_str(yaml: &Yaml, search: &str, mode: MatchMode, case_sensitive: bool) -> bool {
     match yaml {
-        Yaml::String(s) => s.to_lowercase().contains(&search_lower),
+        Yaml::String(s) => mode.matches(trim_block(s), search, case_sensitive),
         Yaml::Array(arr) => arr
             .iter()
-            .any(|item| yaml_contains_str_case_insensitive(item, search)),
-        Yaml::Integer(n) => n.to_string().to_lowercase().contains(&search_lower),
-        Yaml::Real(f) => f.to_string().to_lowercase().contains(&search_lower),
-        Yaml::Boolean(b) => b.to_string().to_lowercase().contains(&search_lower),
+            .any(|item| yaml_matches_str(item, search, mode, case_sensitive)),
+        Yaml::Integer(n) => mode.matches(&n.to_string(), search, case_sensitive),
+        Yaml::Real(f) => mode.matches(f, search, case_sensitive),
+        Yaml::Boolean(b) => mode.matches(&b.to_string(), search, case_sensitive),
         _ => false,
     }
 }
 
+/// Helper function to check if Yaml contains a string value (replaces serde_yaml pattern matching)
+pub fn yaml_contains_str(yaml: &Yaml, search: &str) -> bool {
+    yaml_matches_str(yaml, search, MatchMode::Substring, true)
+}
+
+/// Helper function to check if Yaml contains a string value (case-insensitive)
+pub fn yaml_contains_str_case_insensitive(yaml: &Yaml, search: &str) -> bool {
+    yaml_matches_str(yaml, search, MatchMode::Substring, false)
+}
+
 /// Convert Yaml to string representation for display/comparison
 pub fn yaml_to_string(yaml: &Yaml) -> String {
     match yaml {
         Yaml::String(s) => s.clone(),
         Yaml::Integer(n) => n.to_string(),
-        Yaml::Real(f) => f.to_string(),
+        Yaml::Real(f) => f.clone(),
         Yaml::Boolean(b) => b.to_string(),
         Yaml::Null => "null".to_string(),
         _ => format!("{:?}", yaml),
     }
 }
 
+/// Look up a dotted path like `project.name` in nested maps. Keys that contain dots
+/// themselves are found as well.
+pub fn yaml_get_path<'a>(yaml: &'a Yaml, path: &str, case_sensitive: bool) -> Option<&'a Yaml> {
+    let Yaml::Hash(hash) = yaml else {
+        return None;
+    };
+    let lookup = |key: &str| {
+        hash.iter()
+            .find(|(k, _)| match k {
+                Yaml::String(k) if case_sensitive => k == key,
+                Yaml::String(k) => k.to_lowercase() == key.to_lowercase(),
+                _ => false,
+            })
+            .map(|(_, v)| v)
+    };
+    lookup(path).or_else(|| {
+        path.match_indices('.')
+            .find_map(|(i, _)| yaml_get_path(lookup(&path[..i])?, &path[i + 1..], case_sensitive))
+    })
+}
+
+/// The dotted paths of all values nested in a map field, like `project.name` and
+/// `project.phase` for `project: {name: X, phase: 2}`. Empty for other values.
+pub fn yaml_nested_fields<'a>(field: &str, yaml: &'a Yaml) -> Vec<(String, &'a Yaml)> {
+    let Yaml::Hash(hash) = yaml else {
+        return Vec::new();
+    };
+    let mut fields = Vec::new();
+    for (key, value) in hash {
+        if let Yaml::String(key) = key {
+            let path = format!("{}.{}", field, key);
+            fields.extend(yaml_nested_fields(&path, value));
+            fields.push((path, value));
+        }
+    }
+    fields
+}
+
+/// Name of the YAML type of a value, as shown to users
+pub fn yaml_type_name(yaml: &Yaml) -> &'static str {
+    match yaml {
+        Yaml::String(_) => "string",
+        Yaml::Integer(_) => "integer",
+        Yaml::Real(_) => "number",
+        Yaml::Boolean(_) => "boolean",
+        Yaml::Array(_) => "list",
+        Yaml::Hash(_) => "map",
+        Yaml::Null => "null",
+        _ => "other",
+    }
+}
+
 /// Helper to collect string values from Yaml (for arrays and single values)
 pub fn collect_yaml_strings(yaml: &Yaml) -> Vec<String> {
     match yaml {
-        Yaml::String(s) => vec![s.clone()],
+        Yaml::String(s) => vec![trim_block(s).to_string()],
         Yaml::Array(arr) => arr
             .iter()
             .filter_map(|item| {
                 if let Yaml::String(s) = item {
-                    Some(s.clone())
+                    Some(trim_block(s).to_string())
                 } else {
                     None
                 }
             })
             .collect(),
         Yaml::Integer(n) => vec![n.to_string()],
-        Yaml::Real(f) => vec![f.to_string()],
+        Yaml::Real(f) => vec![f.clone()],
         Yaml::Boolean(b) => vec![b.to_string()],
         _ => vec![],
     }
 }
 
+/// A string without the line break that `|` and `>` block scalars end with
+pub fn trim_block(s: &str) -> &str {
+    s.trim_end_matches(['\n', '\r'])
+}
+
 /// Convert Yaml to serde_json::Value for JSON serialization
 pub fn yaml_to_json_value(yaml: &Yaml) -> serde_json::Value {
     match yaml {
@@ -156,6 +268,44 @@ status: active
         assert!(matches!(result.get("status"), Some(Yaml::String(_))));
     }
 
+    #[test]
+    fn test_anchors_and_merge_keys() {
+        let content = r#"
+defaults: &defaults
+  status: draft
+  owner: alice
+reviewer: &reviewer bob
+approvers: [*reviewer, carol]
+project:
+  <<: *defaults
+  status: active
+  name: Website
+task:
+  status: open
+  <<: [{owner: dave, due: 2024-06-01}, *defaults]
+"#;
+        let result = parse_yaml_frontmatter(content).unwrap();
+        // Aliases are replaced by the value of their anchor
+        assert_eq!(
+            collect_yaml_strings(&result["approvers"]),
+            vec!["bob", "carol"]
+        );
+
+        // Keys set next to the merge key win, whether they come before or after it
+        let project = &result["project"];
+        assert_eq!(yaml_to_string(&project["status"]), "active");
+        assert_eq!(yaml_to_string(&project["owner"]), "alice");
+        assert!(project["<<"].is_badvalue());
+        let task = &result["task"];
+        assert_eq!(yaml_to_string(&task["status"]), "open");
+        assert_eq!(yaml_to_string(&task["owner"]), "dave");
+        assert_eq!(yaml_to_string(&task["due"]), "2024-06-01");
+
+        // A merge key that doesn't name a map stays an ordinary field
+        let result = parse_yaml_frontmatter("<<: plain\ntitle: Note").unwrap();
+        assert_eq!(yaml_to_string(&result["<<"]), "plain");
+    }
+
     #[test]
     fn test_yaml_contains_str() {
         let yaml_string = Yaml::String("test value".to_string());
@@ -196,6 +346,103 @@ status: active
         assert!(yaml_contains_str_case_insensitive(&yaml_bool, "true"));
     }
 
+    #[test]
+    fn test_yaml_matches_str_exact() {
+        let status = Yaml::String("inactive".to_string());
+        assert!(yaml_matches_str(
+            &status,
+            "active",
+            MatchMode::Substring,
+            true
+        ));
+        assert!(!yaml_matches_str(&status, "active", MatchMode::Exact, true));
+        assert!(yaml_matches_str(
+            &status,
+            "inactive",
+            MatchMode::Exact,
+            true
+        ));
+        assert!(!yaml_matches_str(
+            &status,
+            "Inactive",
+            MatchMode::Exact,
+            true
+        ));
+        assert!(yaml_matches_str(
+            &status,
+            "Inactive",
+            MatchMode::Exact,
+            false
+        ));
+
+        let tags = Yaml::Array(vec![
+            Yaml::String("work".to_string()),
+            Yaml::String("homework".to_string()),
+        ]);
+        assert!(yaml_matches_str(&tags, "work", MatchMode::Exact, true));
+        assert!(!yaml_matches_str(&tags, "home", MatchMode::Exact, true));
+        assert!(yaml_matches_str(
+            &Yaml::Integer(42),
+            "42",
+            MatchMode::Exact,
+            true
+        ));
+        assert!(!yaml_matches_str(
+            &Yaml::Integer(420),
+            "42",
+            MatchMode::Exact,
+            true
+        ));
+
+        // `description: |` keeps a final line break that isn't part of the value
+        let block = Yaml::String("First line\nsecond line\n".to_string());
+        assert!(yaml_matches_str(
+            &block,
+            "First line\nsecond line",
+            MatchMode::Exact,
+            true
+        ));
+        assert_eq!(
+            collect_yaml_strings(&block),
+            vec!["First line\nsecond line"]
+        );
+    }
+
+    #[test]
+    fn test_yaml_get_path() {
+        let project = parse_yaml_frontmatter(
+            "project:\n  name: X\n  phase: 2\n  meta:\n    owner: ana\n    v1.2: old\n",
+        )
+        .unwrap()
+        .remove("project")
+        .unwrap();
+
+        let get = |path: &str, case_sensitive: bool| {
+            yaml_get_path(&project, path, case_sensitive).map(yaml_to_string)
+        };
+        assert_eq!(get("name", true).as_deref(), Some("X"));
+        assert_eq!(get("meta.owner", true).as_deref(), Some("ana"));
+        assert_eq!(get("meta.v1.2", true).as_deref(), Some("old"));
+        assert_eq!(get("Meta.Owner", true), None);
+        assert_eq!(get("Meta.Owner", false).as_deref(), Some("ana"));
+        assert_eq!(get("name.first", true), None);
+
+        let fields: Vec<String> = yaml_nested_fields("project", &project)
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                "project.name",
+                "project.phase",
+                "project.meta.owner",
+                "project.meta.v1.2",
+                "project.meta"
+            ]
+        );
+    }
+
     #[test]
     fn test_empty_frontmatter() {
         let content = "";